@@ -0,0 +1,155 @@
+//! The `--report-age-histogram` view: how many gone branches last saw a commit within each of
+//! a few age ranges, for deciding on retention policies.
+
+use crate::parse::BranchLine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::hash::BuildHasher;
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// Each bucket's label and the age (in seconds) its branches are younger than
+const BUCKETS: [(&str, i64); 4] = [
+    ("<1 week", 7 * DAY),
+    ("1-4 weeks", 28 * DAY),
+    ("1-6 months", 183 * DAY),
+    (">6 months", i64::MAX),
+];
+
+/// The widest bar, drawn for the fullest bucket
+const BAR_WIDTH: usize = 20;
+
+/// How many branches fall into one age range
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AgeBucket {
+    pub age: String,
+    pub count: usize,
+}
+
+/// Buckets `branches` by the age of their last commit at `now` (Unix seconds), per
+/// `commit_times` (branch name to committer date); branches without a time are left out
+#[must_use]
+pub fn age_histogram<S: BuildHasher>(
+    branches: &[BranchLine],
+    commit_times: &HashMap<String, i64, S>,
+    now: i64,
+) -> Vec<AgeBucket> {
+    let mut counts = [0; BUCKETS.len()];
+    for time in branches
+        .iter()
+        .filter_map(|branch| commit_times.get(&branch.name))
+    {
+        counts[bucket_of(now.saturating_sub(*time))] += 1;
+    }
+    BUCKETS
+        .iter()
+        .zip(counts)
+        .map(|((age, _), count)| AgeBucket {
+            age: (*age).to_string(),
+            count,
+        })
+        .collect()
+}
+
+/// The index into the buckets (youngest first) of a branch whose last commit is `age` seconds
+/// old
+fn bucket_of(age: i64) -> usize {
+    BUCKETS
+        .iter()
+        .position(|(_, limit)| age < *limit)
+        .unwrap_or(BUCKETS.len() - 1)
+}
+
+/// The label of the bucket a branch `age` seconds old is in, e.g. `1-4 weeks`, with its
+/// position among the buckets (youngest first) for ordering
+#[must_use]
+pub fn bucket_label(age: i64) -> (usize, &'static str) {
+    let bucket = bucket_of(age);
+    (bucket, BUCKETS[bucket].0)
+}
+
+/// Formats `buckets` as a small bar chart, e.g. `  1-4 weeks   ####          2`
+#[must_use]
+pub fn format_age_histogram(buckets: &[AgeBucket]) -> String {
+    let most = buckets.iter().map(|bucket| bucket.count).max().unwrap_or(0);
+    let width = buckets
+        .iter()
+        .map(|bucket| bucket.age.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut out = String::from("\nGone branches by age of their last commit:\n");
+    for bucket in buckets {
+        // Any nonempty bucket gets at least one mark
+        let marks = if bucket.count == 0 {
+            0
+        } else {
+            (bucket.count * BAR_WIDTH).div_ceil(most)
+        };
+        let _ = writeln!(
+            out,
+            "  {:width$}  {:BAR_WIDTH$}  {}",
+            bucket.age,
+            "#".repeat(marks),
+            bucket.count
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_branch_lines;
+
+    #[test]
+    fn test_age_histogram() {
+        let branches = parse_branch_lines(
+            r"
+  fresh        abc1234 [origin/fresh: gone] A
+  fortnight    def5678 [origin/fortnight: gone] B
+  quarter-1    ghi9012 [origin/quarter-1: gone] C
+  quarter-2    jkl3456 [origin/quarter-2: gone] D
+  ancient      mno7890 [origin/ancient: gone] E
+  unknown      pqr1234 [origin/unknown: gone] F
+",
+        );
+        let now = 1_000 * DAY;
+        let commit_times = HashMap::from(
+            [
+                ("fresh", now - DAY),
+                ("fortnight", now - 14 * DAY),
+                ("quarter-1", now - 90 * DAY),
+                ("quarter-2", now - 91 * DAY),
+                ("ancient", now - 400 * DAY),
+            ]
+            .map(|(name, time)| (name.to_string(), time)),
+        );
+
+        let buckets = age_histogram(&branches, &commit_times, now);
+        let counts: Vec<(&str, usize)> = buckets
+            .iter()
+            .map(|bucket| (bucket.age.as_str(), bucket.count))
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                ("<1 week", 1),
+                ("1-4 weeks", 1),
+                ("1-6 months", 2),
+                (">6 months", 1)
+            ]
+        );
+        assert_eq!(
+            format_age_histogram(&buckets),
+            "
+Gone branches by age of their last commit:
+  <1 week     ##########            1
+  1-4 weeks   ##########            1
+  1-6 months  ####################  2
+  >6 months   ##########            1
+"
+        );
+    }
+}
@@ -0,0 +1,94 @@
+//! The `--backup-log`: a record of the branches each run deleted, kept for restoring them
+//! long after the run's own output (and its undo commands) is gone.
+//!
+//! Each run that deletes something appends a comment line with its time, then one
+//! `git branch <name> <sha>` per deleted branch, so any line can be pasted to restore that
+//! branch.
+
+use crate::report::{Action, Report};
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::fs::OpenOptions;
+use std::io::Write as _;
+use std::path::Path;
+
+/// The backup log's name inside the git directory, where `--paranoid` keeps it
+pub const BACKUP_FILE: &str = "git-clean-gone-backup.log";
+
+/// Formats the entries for the branches `report` deleted at `timestamp`; empty if it deleted
+/// none
+#[must_use]
+pub fn format_backup_entries(report: &Report, timestamp: &str) -> String {
+    let deleted: Vec<_> = report
+        .branches
+        .iter()
+        .filter(|branch| branch.action == Action::Deleted)
+        .collect();
+    if deleted.is_empty() {
+        return String::new();
+    }
+
+    let mut entries = String::new();
+    let _ = writeln!(
+        entries,
+        "# {timestamp}: {} branch(es) deleted",
+        deleted.len()
+    );
+    for branch in deleted {
+        // Quoted, since characters such as `&` are valid in branch names
+        let _ = writeln!(
+            entries,
+            "git branch {} {}",
+            shell_words::quote(&branch.name),
+            branch.sha
+        );
+    }
+    entries
+}
+
+/// Appends `entries` to the backup log at `path`, creating it if needed
+///
+/// # Errors
+///
+/// Fails if the file can't be written.
+pub fn append(path: &Path, entries: &str) -> Result<()> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(entries.as_bytes()))
+        .with_context(|| format!("Failed to write backup log {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_branch_lines;
+    use crate::report::BranchReport;
+
+    #[test]
+    fn test_format_backup_entries() {
+        let branches = parse_branch_lines(
+            r"
+  feature/a&b  abc1234 [origin/feature/a&b: gone] Deleted
+  wip          def5678 [origin/wip: gone] Kept
+",
+        );
+        let mut report = Report {
+            dry_run: false,
+            branches: vec![
+                BranchReport::new(&branches[0], Action::Deleted),
+                BranchReport::new(&branches[1], Action::Skipped),
+            ],
+            age_histogram: None,
+            remotes_checked: None,
+        };
+        assert_eq!(
+            format_backup_entries(&report, "2026-01-31T12:00:00Z"),
+            "# 2026-01-31T12:00:00Z: 1 branch(es) deleted\ngit branch 'feature/a&b' abc1234\n"
+        );
+
+        report.branches.remove(0);
+        assert_eq!(format_backup_entries(&report, "2026-01-31T12:00:00Z"), "");
+    }
+}
@@ -0,0 +1,162 @@
+//! A cache of the `git branch -vv` listing between runs, so repeated dry runs and `watch`
+//! checks don't re-run git when no ref has changed.
+//!
+//! The listing is stored in `.git/git-clean-gone-branches.cache` along with a fingerprint of
+//! everything it depends on: `HEAD`, `config`, `packed-refs`, and the loose refs under
+//! `refs/heads` and `refs/remotes` (their names, sizes, and modification times). Any change to
+//! those, such as a fetch pruning a remote branch, invalidates it. Repositories laid out in
+//! ways [`crate::refdb`] can't read aren't cached at all.
+
+use crate::git::{branch_listing, branches_from_listing};
+use crate::parse::BranchLine;
+use crate::refdb::{check_layout, find_git_dir};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// The cache file's name inside the git directory
+pub const CACHE_FILE: &str = "git-clean-gone-branches.cache";
+
+#[derive(Serialize, Deserialize)]
+struct CachedListing {
+    fingerprint: String,
+    listing: String,
+}
+
+/// Lists the local branches like [`crate::git::local_branches`], reusing the cached listing if
+/// no ref has changed since it was stored, and refreshing the cache otherwise
+///
+/// # Errors
+///
+/// Fails if `git branch -vv` is needed and can't be run.
+pub fn local_branches(verbose: bool) -> Result<Vec<BranchLine>> {
+    let git_dir = std::env::current_dir()
+        .map_err(anyhow::Error::from)
+        .and_then(|dir| find_git_dir(&dir))
+        .and_then(|git_dir| check_layout(&git_dir).map(|()| git_dir));
+    let Ok(git_dir) = git_dir else {
+        return crate::git::local_branches(verbose);
+    };
+    let path = git_dir.join(CACHE_FILE);
+    let fingerprint = fingerprint(&git_dir)?;
+    if let Some(listing) = load(&path, &fingerprint) {
+        if verbose {
+            println!("Using the cached branch listing (no refs changed)");
+        }
+        return branches_from_listing(&listing, verbose);
+    }
+    let listing = branch_listing(verbose)?;
+    // Taken again so a ref changing while git ran isn't cached under the old state
+    if fingerprint == self::fingerprint(&git_dir)?
+        && let Err(err) = store(&path, &fingerprint, &listing)
+        && verbose
+    {
+        println!("Not caching the branch listing: {err:#}");
+    }
+    branches_from_listing(&listing, verbose)
+}
+
+/// Summarizes the files the branch listing depends on, so any change to them changes the result
+///
+/// # Errors
+///
+/// Fails if a file exists but its metadata can't be read.
+pub fn fingerprint(git_dir: &Path) -> Result<String> {
+    let mut hasher = DefaultHasher::new();
+    for file in ["HEAD", "config", "packed-refs"] {
+        hash_entry(&git_dir.join(file), file, &mut hasher)?;
+    }
+    let mut pending = vec!["refs/heads".to_string(), "refs/remotes".to_string()];
+    while let Some(prefix) = pending.pop() {
+        let dir = git_dir.join(&prefix);
+        let mut entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries.collect::<Result<Vec<_>, _>>()?,
+            Err(err) if err.kind() == ErrorKind::NotFound => continue,
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read {}", dir.display()));
+            }
+        };
+        // `read_dir` order isn't stable across changes to the directory
+        entries.sort_by_key(fs::DirEntry::file_name);
+        for entry in entries {
+            let name = format!("{prefix}/{}", entry.file_name().to_string_lossy());
+            if entry.file_type()?.is_dir() {
+                pending.push(name);
+            } else {
+                hash_entry(&entry.path(), &name, &mut hasher)?;
+            }
+        }
+    }
+    Ok(format!("{:016x}", hasher.finish()))
+}
+
+/// Hashes `path`'s name, size, and modification time, or that it's missing
+fn hash_entry(path: &Path, name: &str, hasher: &mut DefaultHasher) -> Result<()> {
+    name.hash(hasher);
+    match fs::metadata(path) {
+        Ok(metadata) => {
+            metadata.len().hash(hasher);
+            metadata.modified().ok().hash(hasher);
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => "missing".hash(hasher),
+        Err(err) => {
+            return Err(err).with_context(|| format!("Failed to read {}", path.display()));
+        }
+    }
+    Ok(())
+}
+
+/// The listing cached at `path`, if it was stored under `fingerprint`
+fn load(path: &Path, fingerprint: &str) -> Option<String> {
+    let cached: CachedListing = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    (cached.fingerprint == fingerprint).then_some(cached.listing)
+}
+
+/// Caches `listing` at `path` under `fingerprint`
+fn store(path: &Path, fingerprint: &str, listing: &str) -> Result<()> {
+    let cached = CachedListing {
+        fingerprint: fingerprint.to_string(),
+        listing: listing.to_string(),
+    };
+    let json = serde_json::to_string(&cached).context("Failed to serialize branch listing")?;
+    fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_tracks_ref_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let git_dir = dir.path();
+        fs::create_dir_all(git_dir.join("refs/heads/feature")).unwrap();
+        fs::write(git_dir.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        fs::write(git_dir.join("refs/heads/main"), "a\n").unwrap();
+        let initial = fingerprint(git_dir).unwrap();
+        assert_eq!(fingerprint(git_dir).unwrap(), initial);
+
+        fs::write(git_dir.join("refs/heads/feature/x"), "b\n").unwrap();
+        let with_branch = fingerprint(git_dir).unwrap();
+        assert_ne!(with_branch, initial);
+
+        fs::create_dir_all(git_dir.join("refs/remotes/origin")).unwrap();
+        fs::write(git_dir.join("refs/remotes/origin/x"), "b\n").unwrap();
+        assert_ne!(fingerprint(git_dir).unwrap(), with_branch);
+    }
+
+    #[test]
+    fn test_load_requires_matching_fingerprint() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CACHE_FILE);
+        assert_eq!(load(&path, "f1"), None);
+
+        store(&path, "f1", "* main abc1234 Init\n").unwrap();
+        assert_eq!(load(&path, "f1").as_deref(), Some("* main abc1234 Init\n"));
+        assert_eq!(load(&path, "f2"), None);
+    }
+}
@@ -0,0 +1,108 @@
+//! Cancelling a deletion run cleanly on Ctrl-C (SIGINT) or SIGTERM.
+//!
+//! While a [`Cancel`] is armed, the first signal only asks the run to stop: the branch being
+//! deleted is finished, the rest are skipped, and the run reports what it did (and keeps its
+//! checkpoint) instead of dying halfway. A second signal, or one while nothing is armed, exits
+//! right away with [`CANCELLED_EXIT_CODE`], as the signal would have anyway.
+
+use anyhow::{Context, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+/// Exit code of a cancelled run, the shell's 128 + SIGINT
+pub const CANCELLED_EXIT_CODE: i32 = 130;
+
+/// A cancellation token, shared with the signal handler
+#[derive(Debug, Clone, Default)]
+pub struct Cancel {
+    armed: Arc<AtomicBool>,
+    requested: Arc<AtomicBool>,
+}
+
+impl Cancel {
+    /// Installs the process's SIGINT/SIGTERM handler, returning its token; later calls
+    /// return the same token, as a process has only one handler
+    ///
+    /// # Errors
+    ///
+    /// Fails if another handler is already installed.
+    pub fn install() -> Result<Self> {
+        static INSTALLED: OnceLock<Cancel> = OnceLock::new();
+        if let Some(cancel) = INSTALLED.get() {
+            return Ok(cancel.clone());
+        }
+        let cancel = Self::default();
+        let handler = cancel.clone();
+        ctrlc::set_handler(move || {
+            if handler.on_signal() {
+                std::process::exit(CANCELLED_EXIT_CODE);
+            }
+            eprintln!(
+                "\nInterrupted: finishing the current branch, then stopping (interrupt again to \
+                 quit now)"
+            );
+        })
+        .context("Failed to install the interrupt handler")?;
+        Ok(INSTALLED.get_or_init(|| cancel).clone())
+    }
+
+    /// Runs `f` with the token armed, so a signal meanwhile asks it to stop rather than
+    /// exiting
+    pub fn armed<T>(&self, f: impl FnOnce() -> T) -> T {
+        self.armed.store(true, Ordering::SeqCst);
+        let result = f();
+        self.armed.store(false, Ordering::SeqCst);
+        result
+    }
+
+    /// Whether a signal asked the run to stop
+    #[must_use]
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Handles a signal: records the request while armed, and returns whether to exit right
+    /// away instead (when disarmed, or on a second signal)
+    #[must_use]
+    pub fn on_signal(&self) -> bool {
+        !self.armed.load(Ordering::SeqCst) || self.requested.swap(true, Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signal_while_armed_requests_cancellation() {
+        let cancel = Cancel::default();
+        let deleted = cancel.armed(|| {
+            let mut deleted = Vec::new();
+            for branch in ["a", "b", "c"] {
+                if cancel.is_requested() {
+                    break;
+                }
+                deleted.push(branch);
+                if branch == "a" {
+                    // Simulates a Ctrl-C while `a` is being deleted
+                    assert!(!cancel.on_signal());
+                }
+            }
+            deleted
+        });
+        assert_eq!(deleted, ["a"]);
+        assert!(cancel.is_requested());
+    }
+
+    #[test]
+    fn test_signal_exits_when_disarmed_or_repeated() {
+        let cancel = Cancel::default();
+        assert!(cancel.on_signal());
+        assert!(!cancel.is_requested());
+
+        cancel.armed(|| {
+            assert!(!cancel.on_signal());
+            assert!(cancel.on_signal());
+        });
+    }
+}
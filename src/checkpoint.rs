@@ -0,0 +1,163 @@
+//! Checkpoints that record deletion progress, so an interrupted run can be reported on and
+//! resumed.
+//!
+//! The checkpoint is written before the first deletion and rewritten after every one, then
+//! removed once the run completes. Finding one at startup therefore means the previous run
+//! didn't finish.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Progress of a deletion run
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// Branches the run set out to delete, in order
+    pub planned: Vec<String>,
+    /// Branches deleted so far
+    pub deleted: Vec<String>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Checkpoint {
+    /// Loads the checkpoint an interrupted run left at `path`, if any
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file exists but can't be read or parsed.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("Failed to read checkpoint {}", path.display()));
+            }
+        };
+        let checkpoint: Self = serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse checkpoint {}", path.display()))?;
+        Ok(Some(Self {
+            path: path.to_path_buf(),
+            ..checkpoint
+        }))
+    }
+
+    /// Starts a run that will delete `planned`, writing the initial checkpoint
+    ///
+    /// # Errors
+    ///
+    /// Fails if the checkpoint can't be written.
+    pub fn start(path: &Path, planned: Vec<String>) -> Result<Self> {
+        let checkpoint = Self {
+            planned,
+            deleted: Vec::new(),
+            path: path.to_path_buf(),
+        };
+        checkpoint.save()?;
+        Ok(checkpoint)
+    }
+
+    /// Records that `branch` was deleted
+    ///
+    /// # Errors
+    ///
+    /// Fails if the checkpoint can't be written.
+    pub fn record_deleted(&mut self, branch: &str) -> Result<()> {
+        self.deleted.push(branch.to_string());
+        self.save()
+    }
+
+    /// The planned branches that weren't deleted yet
+    #[must_use]
+    pub fn remaining(&self) -> Vec<&str> {
+        self.planned
+            .iter()
+            .filter(|name| !self.deleted.contains(name))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Removes the checkpoint, marking the run as complete
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be removed.
+    pub fn finish(self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err)
+                .with_context(|| format!("Failed to remove checkpoint {}", self.path.display())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes the checkpoint atomically, so an interruption mid-write can't corrupt it
+    fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize checkpoint")?;
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, json + "\n")
+            .with_context(|| format!("Failed to write checkpoint {}", temp_path.display()))?;
+        fs::rename(&temp_path, &self.path)
+            .with_context(|| format!("Failed to write checkpoint {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_start_in_read_only_dir_is_unwritable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o555)).unwrap();
+        // Root ignores permissions, so there's nothing to check there
+        if fs::write(dir.path().join("probe"), "").is_ok() {
+            return;
+        }
+        let path = dir.path().join("git-clean-gone").join("checkpoint.json");
+        let err = Checkpoint::start(&path, names(&["a"])).unwrap_err();
+        assert!(crate::is_unwritable(&err), "{err:#}");
+        fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn test_checkpoint_tracks_progress_on_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("git-clean-gone").join("checkpoint.json");
+        assert_eq!(Checkpoint::load(&path).unwrap(), None);
+
+        let mut checkpoint = Checkpoint::start(&path, names(&["a", "b", "c"])).unwrap();
+        checkpoint.record_deleted("a").unwrap();
+
+        // An interruption here leaves the checkpoint behind
+        let interrupted = Checkpoint::load(&path).unwrap().unwrap();
+        assert_eq!(interrupted.deleted, ["a"]);
+        assert_eq!(interrupted.remaining(), ["b", "c"]);
+
+        checkpoint.record_deleted("b").unwrap();
+        checkpoint.record_deleted("c").unwrap();
+        checkpoint.finish().unwrap();
+        assert_eq!(Checkpoint::load(&path).unwrap(), None);
+    }
+
+    #[test]
+    fn test_checkpoint_rejects_garbage() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("checkpoint.json");
+        fs::write(&path, "not json").unwrap();
+        assert!(Checkpoint::load(&path).is_err());
+    }
+}
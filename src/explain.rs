@@ -0,0 +1,133 @@
+//! The decision trace for `--explain`: one line per local branch, giving the facts a run went
+//! on and the verdict they led to, e.g.
+//! `feature-1: gone upstream origin/feature-1, merged into origin/HEAD, unique commits 0 -> DELETE`.
+
+use crate::parse::BranchLine;
+use crate::risk::Assessment;
+use crate::safety::Held;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::hash::BuildHasher;
+
+/// Explains the verdict on each of `all_branches`: `candidates` names the branches picked out
+/// as gone, of which `deletable` passed the safety checks and `held` didn't, and
+/// `assessments` says how each candidate compares with `base`
+#[must_use]
+pub fn format_explanations<S: BuildHasher>(
+    all_branches: &[BranchLine],
+    candidates: &HashSet<String, S>,
+    deletable: &[BranchLine],
+    held: &[Held],
+    assessments: &[Assessment],
+    base: &str,
+) -> String {
+    let mut out = String::new();
+    for branch in all_branches {
+        let mut facts = vec![upstream_fact(branch)];
+        if branch.is_current {
+            facts.push("checked out".to_string());
+        } else if branch.in_other_worktree {
+            facts.push("checked out in another worktree".to_string());
+        }
+        if let Some(assessment) = assessments.iter().find(|a| a.name == branch.name) {
+            facts.push(merge_fact(assessment, base));
+            facts.push(format!("unique commits {}", assessment.unique_commits));
+        }
+
+        let verdict = if deletable.iter().any(|d| d.name == branch.name) {
+            "DELETE".to_string()
+        } else if let Some(held) = held.iter().find(|h| h.branch.name == branch.name) {
+            format!("KEEP ({})", held.reason)
+        } else if candidates.contains(&branch.name) {
+            "KEEP".to_string()
+        } else if branch.is_checked_out() && branch.tracking.as_deref() == Some("gone") {
+            "KEEP (checked out)".to_string()
+        } else if branch.divergence().is_some_and(|d| d.gone) {
+            "KEEP (not selected as gone)".to_string()
+        } else {
+            "KEEP (not gone)".to_string()
+        };
+        let _ = writeln!(out, "{}: {} -> {verdict}", branch.name, facts.join(", "));
+    }
+    out
+}
+
+/// What the branch tracks, e.g. `gone upstream origin/x` or `upstream origin/main (ahead 1)`
+fn upstream_fact(branch: &BranchLine) -> String {
+    match (&branch.upstream, branch.tracking.as_deref()) {
+        (None, _) => "no upstream".to_string(),
+        (Some(upstream), Some("gone")) => format!("gone upstream {upstream}"),
+        (Some(upstream), Some(tracking)) => format!("upstream {upstream} ({tracking})"),
+        (Some(upstream), None) => format!("upstream {upstream}"),
+    }
+}
+
+fn merge_fact(assessment: &Assessment, base: &str) -> String {
+    if assessment.merged_upstream {
+        "merged upstream".to_string()
+    } else if assessment.unique_commits == 0 {
+        format!(
+            "merged into {}",
+            assessment.merged_into.as_deref().unwrap_or(base)
+        )
+    } else {
+        format!("not merged into {base}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_branch_lines;
+    use crate::safety::HoldKind;
+
+    #[test]
+    fn test_format_explanations() {
+        let all_branches = parse_branch_lines(
+            r"
+* main         abc1234 [origin/main] Init
+  feature-1    def5678 [origin/feature-1: gone] Merged
+  release      aaa1111 [origin/release: gone] Protected
+  wip          bbb2222 [origin/wip: ahead 1] In progress
+  scratch      ccc3333 No upstream
+",
+        );
+        let candidates = HashSet::from(["feature-1".to_string(), "release".to_string()]);
+        let deletable = vec![all_branches[1].clone()];
+        let held = vec![Held {
+            branch: all_branches[2].clone(),
+            kind: HoldKind::Protected,
+            reason: "protected by --protect".to_string(),
+        }];
+        let assessments = vec![
+            Assessment {
+                name: "feature-1".to_string(),
+                unique_commits: 0,
+                merged_into: None,
+                merged_upstream: false,
+            },
+            Assessment {
+                name: "release".to_string(),
+                unique_commits: 2,
+                merged_into: None,
+                merged_upstream: false,
+            },
+        ];
+
+        assert_eq!(
+            format_explanations(
+                &all_branches,
+                &candidates,
+                &deletable,
+                &held,
+                &assessments,
+                "origin/HEAD"
+            ),
+            "main: upstream origin/main, checked out -> KEEP (not gone)\n\
+             feature-1: gone upstream origin/feature-1, merged into origin/HEAD, unique commits 0 -> DELETE\n\
+             release: gone upstream origin/release, not merged into origin/HEAD, unique commits 2 -> KEEP (protected by --protect)\n\
+             wip: upstream origin/wip (ahead 1) -> KEEP (not gone)\n\
+             scratch: no upstream -> KEEP (not gone)\n"
+        );
+    }
+}
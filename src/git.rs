@@ -0,0 +1,1691 @@
+//! Thin wrappers around the `git` subprocesses the tool runs.
+
+use crate::parse::{BranchLine, gone_branches, in_bare_repository, parse_branch_lines};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// A git command that ran but failed, keeping git's own error output apart so callers can
+/// tell why (see [`crate::report::FailureCause`])
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitFailure {
+    /// The command, e.g. `git branch -D feature`
+    pub command: String,
+    pub stderr: String,
+}
+
+impl fmt::Display for GitFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed: {}", self.command, self.stderr)
+    }
+}
+
+impl std::error::Error for GitFailure {}
+
+/// Starts a `git` invocation with `LC_ALL=C` and `LANG=C`, so its output is in English
+/// whatever the user's locale. Parsing relies on that, e.g. `git branch -vv`'s "gone".
+#[must_use]
+pub fn git_command() -> Command {
+    let mut command = Command::new("git");
+    command.env("LC_ALL", "C").env("LANG", "C");
+    #[cfg(test)]
+    fixture::point_at_repo(&mut command);
+    command
+}
+
+/// Throwaway repositories for unit tests. The code under test runs git in the current
+/// directory, which tests running in parallel can't each change, so [`fixture::in_repo`]
+/// points this thread's [`git_command`]s at a fixture instead.
+#[cfg(test)]
+pub(crate) mod fixture {
+    use super::git_command;
+    use std::cell::RefCell;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    thread_local! {
+        static REPO: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+    }
+
+    pub(super) fn point_at_repo(command: &mut Command) {
+        REPO.with_borrow(|repo| {
+            if let Some(repo) = repo {
+                isolate(command.current_dir(repo));
+            }
+        });
+    }
+
+    /// Keeps the user's global and system git config out of a fixture
+    fn isolate(command: &mut Command) -> &mut Command {
+        command
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env_remove("GIT_DIR")
+    }
+
+    /// The repository [`in_repo`] points this thread's git commands at, if any
+    pub(crate) fn current() -> Option<PathBuf> {
+        REPO.with_borrow(Clone::clone)
+    }
+
+    /// Runs `f` with the git commands it starts on this thread running in `dir`
+    pub(crate) fn in_repo<T>(dir: &Path, f: impl FnOnce() -> T) -> T {
+        let previous = REPO.replace(Some(dir.to_path_buf()));
+        let result = f();
+        REPO.set(previous);
+        result
+    }
+
+    /// Runs git in `dir`, asserting it succeeds, and returns its trimmed stdout
+    pub(crate) fn git(dir: &Path, args: &[&str]) -> String {
+        let output = isolate(git_command().current_dir(dir))
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?} failed");
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    /// Makes a repository with one empty commit on `main`
+    pub(crate) fn repo() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        git(dir.path(), &["init", "-q", "-b", "main"]);
+        git(dir.path(), &["commit", "-q", "--allow-empty", "-m", "init"]);
+        dir
+    }
+}
+
+/// Runs `command` with `input` on its stdin, collecting its stdout (and its stderr, if the
+/// caller piped it). The input is written from
+/// a separate thread, so a command that writes output as it reads (like `git cat-file
+/// --batch-check`) can't deadlock on a full pipe.
+///
+/// Lists of revisions go through stdin this way rather than as arguments, which would
+/// overflow the platform's command-line limit with enough branches.
+///
+/// # Errors
+///
+/// Fails if the command can't be started or waited for.
+pub fn output_with_stdin(command: &mut Command, input: String) -> std::io::Result<Output> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take();
+    let writer = std::thread::spawn(move || {
+        if let Some(stdin) = &mut stdin {
+            // A command that exits early closes the pipe; its exit status tells why
+            let _ = stdin.write_all(input.as_bytes());
+        }
+    });
+    let output = child.wait_with_output();
+    let _ = writer.join();
+    output
+}
+
+/// A `git --version` number
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GitVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl std::fmt::Display for GitVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl GitVersion {
+    /// The oldest git whose `git branch -vv` output this tool understands
+    pub const MINIMUM: Self = Self::new(2, 0, 0);
+
+    #[must_use]
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parses `git --version` output, e.g. `git version 2.39.3 (Apple Git-145)` or
+    /// `git version 2.45.1.windows.1`
+    #[must_use]
+    pub fn parse(output: &str) -> Option<Self> {
+        let number = output.trim().strip_prefix("git version ")?;
+        let mut parts = number
+            .split(|c: char| !c.is_ascii_digit())
+            .map(str::parse::<u32>);
+        let major = parts.next()?.ok()?;
+        let minor = parts.next()?.ok()?;
+        let patch = parts.next().and_then(Result::ok).unwrap_or(0);
+        Some(Self::new(major, minor, patch))
+    }
+
+    /// Errors with a friendly message if this version is older than `minimum`, which
+    /// `feature` needs
+    ///
+    /// # Errors
+    ///
+    /// Fails if the version is too old.
+    pub fn require(self, minimum: Self, feature: &str) -> Result<()> {
+        if self < minimum {
+            anyhow::bail!("{feature} requires git >= {minimum}, but git {self} is installed");
+        }
+        Ok(())
+    }
+}
+
+/// Locates git on `PATH` and checks it's at least [`GitVersion::MINIMUM`]
+///
+/// # Errors
+///
+/// Fails if git isn't found, its version can't be read, or it's too old.
+pub fn ensure_git() -> Result<GitVersion> {
+    let output = match git_command().arg("--version").output() {
+        Ok(output) => output,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            anyhow::bail!("git not found on PATH; install git and try again")
+        }
+        Err(err) => return Err(err).context("Failed to execute git --version"),
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let version = GitVersion::parse(&stdout)
+        .with_context(|| format!("Failed to read the git version from {:?}", stdout.trim()))?;
+    version.require(GitVersion::MINIMUM, "git-clean-gone")?;
+
+    Ok(version)
+}
+
+/// Ensures we're inside a git repository
+///
+/// # Errors
+///
+/// Fails if git can't be run or the current directory isn't inside a repository.
+pub fn ensure_git_repo() -> Result<()> {
+    let output = git_command()
+        .args(["rev-parse", "--git-dir"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("Failed to check if in git repository")?;
+
+    if !output.success() {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    Ok(())
+}
+
+/// Fetch options that make git run an arbitrary program, which `--fetch-args` refuses
+const DANGEROUS_FETCH_ARGS: &[&str] = &["--upload-pack", "-u"];
+
+/// Splits a `--fetch-args` string into arguments, honoring shell-style quoting.
+///
+/// The arguments are passed straight to git (never through a shell), so shell
+/// metacharacters are harmless. Options that make git execute another program
+/// (`--upload-pack`/`-u`) are rejected, as are control characters.
+///
+/// # Errors
+///
+/// Fails on unbalanced quotes or a rejected argument.
+pub fn parse_fetch_args(fetch_args: &str) -> Result<Vec<String>> {
+    let args = shell_words::split(fetch_args)
+        .with_context(|| format!("Failed to parse --fetch-args: {fetch_args}"))?;
+
+    for arg in &args {
+        if arg.chars().any(char::is_control) {
+            anyhow::bail!("--fetch-args contains a control character: {arg:?}");
+        }
+        let option = arg.split('=').next().unwrap_or(arg);
+        let is_dangerous = DANGEROUS_FETCH_ARGS.contains(&option)
+            // Short options can be glued to their value, e.g. `-ucmd`
+            || (arg.starts_with("-u") && !arg.starts_with("--"));
+        if is_dangerous {
+            anyhow::bail!("--fetch-args may not contain {option}, which runs an arbitrary program");
+        }
+    }
+
+    Ok(args)
+}
+
+/// Runs `git fetch -ap` to fetch and prune remote branches, from `remote` if given (otherwise
+/// git's default), appending `extra_args`
+///
+/// # Errors
+///
+/// Fails if git can't be run.
+pub fn git_fetch_prune(verbose: bool, remote: Option<&str>, extra_args: &[String]) -> Result<()> {
+    git_fetch_prune_in(Path::new("."), verbose, remote, extra_args)
+}
+
+/// Like [`git_fetch_prune`], but in the repository at `dir` rather than the current one, so
+/// several repositories can be fetched at once
+///
+/// # Errors
+///
+/// Fails if git can't be run.
+pub fn git_fetch_prune_in(
+    dir: &Path,
+    verbose: bool,
+    remote: Option<&str>,
+    extra_args: &[String],
+) -> Result<()> {
+    let mut cmd = git_command();
+    cmd.current_dir(dir)
+        .args(["fetch", "-ap"])
+        .args(remote)
+        .args(extra_args);
+
+    if verbose {
+        cmd.status().context("Failed to execute git fetch -ap")?;
+    } else {
+        cmd.stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .context("Failed to execute git fetch -ap")?;
+    }
+
+    Ok(())
+}
+
+/// A command that runs `command` through the shell: `sh -c`, or `cmd /C` on Windows
+fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
+
+/// Asks the user's `--gone-command` whether `branch` is gone, by running `command` through the
+/// shell with the branch name as its last argument (and in `GIT_CLEAN_GONE_BRANCH`). Exiting 0
+/// means gone; any other exit, including a crash, means keep the branch.
+///
+/// The command's stdout and stderr are passed through, on stderr, for its own diagnostics.
+///
+/// # Errors
+///
+/// Fails if the shell can't be started.
+pub fn run_gone_command(command: &str, branch: &str) -> Result<bool> {
+    run_branch_command(command, branch)
+        .with_context(|| format!("Failed to execute gone command: {command}"))
+}
+
+/// Asks the user's `--open-pr-command` whether `branch` has an open pull request, running it
+/// the same way as [`run_gone_command`]. Exiting 0 means there's an open PR; any other exit,
+/// including a crash, means there isn't.
+///
+/// # Errors
+///
+/// Fails if the shell can't be started.
+pub fn run_open_pr_command(command: &str, branch: &str) -> Result<bool> {
+    run_branch_command(command, branch)
+        .with_context(|| format!("Failed to execute open PR command: {command}"))
+}
+
+/// Runs `command` through the shell with `branch` as its last argument (and in
+/// `GIT_CLEAN_GONE_BRANCH`), passing its output through on stderr, and returns whether it
+/// exited 0
+fn run_branch_command(command: &str, branch: &str) -> std::io::Result<bool> {
+    let mut cmd = if cfg!(windows) {
+        shell_command(&format!("{command} \"{branch}\""))
+    } else {
+        // Passed as `$1` rather than spliced into the command, so it needs no quoting
+        let mut cmd = shell_command(&format!("{command} \"$1\""));
+        cmd.args(["git-clean-gone", branch]);
+        cmd
+    };
+    let output = cmd
+        .env("GIT_CLEAN_GONE_BRANCH", branch)
+        .stdin(Stdio::null())
+        .output()?;
+    eprint!("{}", String::from_utf8_lossy(&output.stdout));
+    eprint!("{}", String::from_utf8_lossy(&output.stderr));
+    Ok(output.status.success())
+}
+
+/// Runs the user's `--branches-from-command` through the shell and returns its stdout, the
+/// branch names to consider. Its stderr is passed through for its own diagnostics.
+///
+/// # Errors
+///
+/// Fails if the shell can't be started, or with the command's exit status if it exits
+/// unsuccessfully.
+pub fn run_branches_command(command: &str) -> Result<String> {
+    let output = shell_command(command)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .with_context(|| format!("Failed to execute branches command: {command}"))?;
+    if !output.status.success() {
+        anyhow::bail!("Branches command `{command}` failed ({})", output.status);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Runs a user-supplied command through the shell in place of `git fetch -ap`.
+///
+/// The command is trusted as-is; it's up to the user that it actually prunes deleted remote
+/// branches, since gone detection relies on that.
+///
+/// # Errors
+///
+/// Fails with the command's stderr if it exits unsuccessfully.
+pub fn run_fetch_command(command: &str, verbose: bool) -> Result<()> {
+    let mut cmd = shell_command(command);
+    if !verbose {
+        cmd.stdout(Stdio::null());
+    }
+    let output = cmd
+        .stderr(Stdio::piped())
+        .output()
+        .with_context(|| format!("Failed to execute fetch command: {command}"))?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        anyhow::bail!(
+            "Fetch command `{command}` failed ({}): {}",
+            output.status,
+            stderr.trim()
+        );
+    }
+    if verbose {
+        eprint!("{stderr}");
+    }
+
+    Ok(())
+}
+
+/// Checks whether `refs/remotes/<remote>/HEAD` points at a ref that no longer exists,
+/// as happens after the remote's default branch is deleted or renamed.
+///
+/// Returns the dangling target, or `None` if the remote HEAD is unset or valid.
+///
+/// # Errors
+///
+/// Fails if git can't be run.
+pub fn dangling_remote_head(remote: &str) -> Result<Option<String>> {
+    let output = git_command()
+        .args(["symbolic-ref", "-q", &format!("refs/remotes/{remote}/HEAD")])
+        .output()
+        .context("Failed to execute git symbolic-ref")?;
+
+    if !output.status.success() {
+        // No remote HEAD is recorded, so there's nothing to be dangling
+        return Ok(None);
+    }
+    let target = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    let exists = git_command()
+        .args(["rev-parse", "--verify", "-q", &target])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("Failed to execute git rev-parse")?
+        .success();
+
+    Ok((!exists).then_some(target))
+}
+
+/// The branch `refs/remotes/<remote>/HEAD` points to (e.g. `main`), if it's recorded
+///
+/// # Errors
+///
+/// Fails if git can't be run.
+pub fn remote_default_branch(remote: &str) -> Result<Option<String>> {
+    let output = git_command()
+        .args(["symbolic-ref", "-q", &format!("refs/remotes/{remote}/HEAD")])
+        .output()
+        .context("Failed to execute git symbolic-ref")?;
+
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .strip_prefix(&format!("refs/remotes/{remote}/"))
+        .map(ToString::to_string))
+}
+
+/// The editor git would use (`$GIT_EDITOR`, `core.editor`, `$VISUAL`, `$EDITOR`, then its
+/// built-in default), via `git var GIT_EDITOR`
+///
+/// # Errors
+///
+/// Fails if git can't be run or no editor is configured on a dumb terminal.
+pub fn git_editor() -> Result<String> {
+    let output = git_command()
+        .args(["var", "GIT_EDITOR"])
+        .output()
+        .context("Failed to execute git var")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("No editor is configured: {}", stderr.trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Re-resolves `refs/remotes/<remote>/HEAD` from the remote via `git remote set-head <remote> -a`
+///
+/// # Errors
+///
+/// Fails if the remote can't be queried.
+pub fn fix_remote_head(remote: &str) -> Result<()> {
+    let output = git_command()
+        .args(["remote", "set-head", remote, "-a"])
+        .output()
+        .context("Failed to execute git remote set-head")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git remote set-head {remote} -a failed: {}", stderr.trim());
+    }
+
+    Ok(())
+}
+
+/// Expires every reflog entry now (`git reflog expire --expire=now --all`), so the commits only
+/// reflogs still reach become unreferenced and the next `git gc` can prune them
+///
+/// # Errors
+///
+/// Fails if `git reflog expire` can't be run or fails.
+pub fn expire_reflogs() -> Result<()> {
+    let output = git_command()
+        .args(["reflog", "expire", "--expire=now", "--all"])
+        .output()
+        .context("Failed to execute git reflog expire")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git reflog expire failed: {}", stderr.trim());
+    }
+
+    Ok(())
+}
+
+/// What `git maintenance run` does after a cleanup, for `--maintenance`
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Maintenance {
+    /// A full `git gc` (default)
+    #[default]
+    Gc,
+    /// The cheaper incremental tasks: pack loose objects, repack incrementally, and update the
+    /// commit-graph
+    Incremental,
+}
+
+impl Maintenance {
+    /// The `git maintenance run --task` names this runs, in order
+    #[must_use]
+    pub fn tasks(self) -> &'static [&'static str] {
+        match self {
+            Self::Gc => &["gc"],
+            Self::Incremental => &["loose-objects", "incremental-repack", "commit-graph"],
+        }
+    }
+
+    /// The oldest git whose `git maintenance` has these tasks
+    #[must_use]
+    pub const fn minimum_git(self) -> GitVersion {
+        match self {
+            Self::Gc => GitVersion::new(2, 29, 0),
+            Self::Incremental => GitVersion::new(2, 30, 0),
+        }
+    }
+}
+
+/// Runs the `maintenance` tasks with `git maintenance run`, returning the tasks that ran
+///
+/// # Errors
+///
+/// Fails if `git maintenance run` can't be run or fails.
+pub fn run_maintenance(maintenance: Maintenance) -> Result<&'static [&'static str]> {
+    let tasks = maintenance.tasks();
+    let output = git_command()
+        .args(["maintenance", "run", "--quiet"])
+        .args(tasks.iter().map(|task| format!("--task={task}")))
+        .output()
+        .context("Failed to execute git maintenance run")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git maintenance run failed: {}", stderr.trim());
+    }
+
+    Ok(tasks)
+}
+
+/// Checks the object database with `git fsck --connectivity-only`, returning fsck's output if
+/// it found problems. Dangling objects, which deleting branches is expected to leave, don't
+/// count.
+///
+/// # Errors
+///
+/// Fails if `git fsck` can't be run.
+pub fn verify_connectivity() -> Result<Option<String>> {
+    let output = git_command()
+        .args([
+            "fsck",
+            "--connectivity-only",
+            "--no-dangling",
+            "--no-progress",
+        ])
+        .output()
+        .context("Failed to execute git fsck")?;
+
+    if output.status.success() {
+        return Ok(None);
+    }
+    let mut problems = String::from_utf8_lossy(&output.stdout).into_owned();
+    problems.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok(Some(problems.trim().to_string()))
+}
+
+/// Splits git output into its valid UTF-8 lines and the lossily decoded lines that aren't, so
+/// one undecodable refname can't spoil the rest of the output
+#[must_use]
+pub fn decode_lines(bytes: &[u8]) -> (String, Vec<String>) {
+    let mut decoded = String::new();
+    let mut undecodable = Vec::new();
+    for line in bytes.split_inclusive(|&byte| byte == b'\n') {
+        match std::str::from_utf8(line) {
+            Ok(line) => decoded.push_str(line),
+            Err(_) => undecodable.push(String::from_utf8_lossy(line).trim_end().to_string()),
+        }
+    }
+    (decoded, undecodable)
+}
+
+/// Runs `git branch -vv` and returns its raw output, minus any lines that aren't valid UTF-8
+/// (which are skipped with a warning)
+///
+/// # Errors
+///
+/// Fails if `git branch -vv` can't be run.
+pub fn branch_listing(verbose: bool) -> Result<String> {
+    let output = git_command()
+        // Colors would get in the way of parsing, even with `color.branch=always`
+        .args(["branch", "--no-color", "-vv"])
+        .output()
+        .context("Failed to execute git branch -vv")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git branch -vv failed");
+    }
+
+    let (stdout, undecodable) = decode_lines(&output.stdout);
+    for line in undecodable {
+        eprintln!("warning: skipping branch listing line that isn't valid UTF-8: {line}");
+    }
+
+    if verbose {
+        println!("\nBranch output:");
+        println!("{stdout}");
+    }
+
+    Ok(stdout)
+}
+
+/// Locates the current repository's git directory (e.g. `.git`)
+///
+/// # Errors
+///
+/// Fails if git can't be run or the current directory isn't inside a repository.
+pub fn git_dir() -> Result<PathBuf> {
+    let output = git_command()
+        .args(["rev-parse", "--git-dir"])
+        .output()
+        .context("Failed to execute git rev-parse --git-dir")?;
+
+    if !output.status.success() {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    Ok(native_path(
+        String::from_utf8_lossy(&output.stdout).trim_end_matches(['\r', '\n']),
+    ))
+}
+
+/// Locates the top of the current working tree, or `None` in a bare repository
+///
+/// # Errors
+///
+/// Fails if git can't be run.
+pub fn work_tree_root() -> Result<Option<PathBuf>> {
+    let output = git_command()
+        .args(["rev-parse", "--show-toplevel"])
+        .stderr(Stdio::null())
+        .output()
+        .context("Failed to execute git rev-parse --show-toplevel")?;
+
+    Ok(output.status.success().then(|| {
+        native_path(String::from_utf8_lossy(&output.stdout).trim_end_matches(['\r', '\n']))
+    }))
+}
+
+/// Lists the initialized submodules under the working tree at `root`, recursively, as their
+/// paths relative to `root` and their absolute locations, superprojects before their own
+/// submodules (`git submodule foreach --recursive` order)
+///
+/// # Errors
+///
+/// Fails if `git submodule foreach` can't be run or fails.
+pub fn submodules(root: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let output = git_command()
+        .args([
+            "submodule",
+            "foreach",
+            "--quiet",
+            "--recursive",
+            r#"printf '%s\t%s/%s\n' "$displaypath" "$toplevel" "$sm_path""#,
+        ])
+        .current_dir(root)
+        .output()
+        .context("Failed to execute git submodule foreach")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git submodule foreach failed: {}", stderr.trim());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (name, path) = line.split_once('\t')?;
+            Some((name.to_string(), native_path(path)))
+        })
+        .collect())
+}
+
+/// Converts a path printed by git to the platform's form. Git for Windows prints paths with
+/// forward slashes (`C:/repo/.git`), which would otherwise end up mixed with backslashes in
+/// joined paths and messages.
+#[must_use]
+pub fn native_path(path: &str) -> PathBuf {
+    if cfg!(windows) {
+        PathBuf::from(path.replace('/', "\\"))
+    } else {
+        PathBuf::from(path)
+    }
+}
+
+/// Resolves a path inside the repository's git directory, e.g. `refs/heads`, the way git does
+/// (accounting for linked worktrees sharing the main repository's refs)
+///
+/// # Errors
+///
+/// Fails if git can't be run or the current directory isn't inside a repository.
+pub fn git_path(path: &str) -> Result<PathBuf> {
+    let output = git_command()
+        .args(["rev-parse", "--git-path", path])
+        .output()
+        .context("Failed to execute git rev-parse --git-path")?;
+
+    if !output.status.success() {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    Ok(native_path(
+        String::from_utf8_lossy(&output.stdout).trim_end_matches(['\r', '\n']),
+    ))
+}
+
+/// Checks whether the current repository is bare, i.e. has no working tree
+///
+/// # Errors
+///
+/// Fails if git can't be run.
+pub fn is_bare_repository() -> Result<bool> {
+    let output = git_command()
+        .args(["rev-parse", "--is-bare-repository"])
+        .output()
+        .context("Failed to execute git rev-parse --is-bare-repository")?;
+
+    Ok(output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "true")
+}
+
+/// The branch `HEAD` names, if it's unborn (there are no commits yet) and no local branch
+/// exists at all, as in a freshly initialized repository. `None` if `HEAD` is detached or
+/// resolves, or some branch exists, e.g. after `git checkout --orphan`.
+///
+/// # Errors
+///
+/// Fails if git can't be run.
+pub fn unborn_branch() -> Result<Option<String>> {
+    let head = git_command()
+        .args(["symbolic-ref", "-q", "--short", "HEAD"])
+        .output()
+        .context("Failed to execute git symbolic-ref")?;
+    if !head.status.success() {
+        return Ok(None);
+    }
+    let resolves = git_command()
+        .args(["rev-parse", "--verify", "-q", "HEAD"])
+        .output()
+        .context("Failed to execute git rev-parse --verify")?;
+    if resolves.status.success() {
+        return Ok(None);
+    }
+    let branches = git_command()
+        .args(["for-each-ref", "--count=1", "refs/heads"])
+        .output()
+        .context("Failed to execute git for-each-ref")?;
+    if !branches.status.success() || !branches.stdout.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(
+        String::from_utf8_lossy(&head.stdout).trim().to_string(),
+    ))
+}
+
+/// Lists the local branches from `git branch -vv`.
+///
+/// In a bare repository no branch counts as checked out (see [`in_bare_repository`]).
+///
+/// # Errors
+///
+/// Fails if git can't be run.
+pub fn local_branches(verbose: bool) -> Result<Vec<BranchLine>> {
+    branches_from_listing(&branch_listing(verbose)?, verbose)
+}
+
+/// Parses a [`branch_listing`] into branches, adjusting them for a bare repository like
+/// [`local_branches`]
+///
+/// # Errors
+///
+/// Fails if git can't be run.
+pub fn branches_from_listing(listing: &str, verbose: bool) -> Result<Vec<BranchLine>> {
+    let branches = parse_branch_lines(listing);
+    if is_bare_repository()? {
+        if verbose {
+            println!("Bare repository: no branch is checked out");
+        }
+        Ok(in_bare_repository(branches))
+    } else {
+        Ok(branches)
+    }
+}
+
+/// Finds branches marked as "gone" (deleted on remote)
+///
+/// # Errors
+///
+/// Fails if `git branch -vv` can't be run.
+pub fn find_gone_branches(verbose: bool) -> Result<Vec<BranchLine>> {
+    Ok(gone_branches(&local_branches(verbose)?))
+}
+
+/// Resolves the full SHA a local branch points to, or `None` if the branch doesn't exist
+///
+/// # Errors
+///
+/// Fails if git can't be run.
+pub fn branch_tip(branch: &str) -> Result<Option<String>> {
+    let output = git_command()
+        .args([
+            "rev-parse",
+            "--verify",
+            "-q",
+            &format!("refs/heads/{branch}"),
+        ])
+        .stderr(Stdio::null())
+        .output()
+        .context("Failed to execute git rev-parse")?;
+
+    Ok(output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string()))
+}
+
+/// Maps every local branch to the full SHA it points to, in one `git for-each-ref`
+///
+/// # Errors
+///
+/// Fails if `git for-each-ref` can't be run.
+pub fn branch_tips() -> Result<HashMap<String, String>> {
+    let output = git_command()
+        .args([
+            "for-each-ref",
+            "--format=%(objectname) %(refname)",
+            "refs/heads",
+        ])
+        .output()
+        .context("Failed to execute git for-each-ref")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git for-each-ref failed");
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (sha, refname) = line.split_once(' ')?;
+            let name = refname.strip_prefix("refs/heads/")?;
+            Some((name.to_string(), sha.to_string()))
+        })
+        .collect())
+}
+
+/// Hashes `text` as git would a blob (`git hash-object --stdin`), without writing it
+///
+/// # Errors
+///
+/// Fails if `git hash-object` can't be run or fails.
+pub fn hash_object(text: &str) -> Result<String> {
+    let output = output_with_stdin(
+        git_command().args(["hash-object", "--stdin"]),
+        text.to_string(),
+    )
+    .context("Failed to execute git hash-object")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git hash-object failed");
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Whether `ancestor` is reachable from `descendant` (or is the same commit)
+///
+/// # Errors
+///
+/// Fails if either doesn't name a commit.
+pub fn is_ancestor(ancestor: &str, descendant: &str) -> Result<bool> {
+    let output = git_command()
+        .args(["merge-base", "--is-ancestor", ancestor, descendant])
+        .output()
+        .context("Failed to execute git merge-base --is-ancestor")?;
+    match output.status.code() {
+        Some(0) => Ok(true),
+        Some(1) => Ok(false),
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("{ancestor} doesn't name a commit: {}", stderr.trim())
+        }
+    }
+}
+
+/// The `git diff --stat` of what `branch` changed since it forked from `base`
+///
+/// # Errors
+///
+/// Fails if `git diff` can't be run, e.g. `base` doesn't name a commit.
+pub fn diffstat(base: &str, branch: &str) -> Result<String> {
+    let output = git_command()
+        .args(["diff", "--stat"])
+        .arg(format!("{base}...refs/heads/{branch}"))
+        .output()
+        .context("Failed to execute git diff --stat")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!(
+            "git diff --stat {base}...{branch} failed: {}",
+            stderr.trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Whether `a` and `b` have any commit in common (a merge base); `false` after a history
+/// rewrite left them unrelated
+///
+/// # Errors
+///
+/// Fails if either doesn't name a commit.
+pub fn shares_history(a: &str, b: &str) -> Result<bool> {
+    let output = git_command()
+        .args(["merge-base", a, b])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .context("Failed to execute git merge-base")?;
+    match output.status.code() {
+        Some(0) => Ok(true),
+        Some(1) => Ok(false),
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git merge-base {a} {b} failed: {}", stderr.trim())
+        }
+    }
+}
+
+/// Reads each local branch's last commit time (committer date, as a Unix timestamp)
+///
+/// # Errors
+///
+/// Fails if `git for-each-ref` can't be run.
+pub fn branch_commit_times() -> Result<HashMap<String, i64>> {
+    let output = git_command()
+        .args([
+            "for-each-ref",
+            "--format=%(committerdate:unix) %(refname:short)",
+            "refs/heads",
+        ])
+        .output()
+        .context("Failed to execute git for-each-ref")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git for-each-ref failed");
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (time, name) = line.split_once(' ')?;
+            Some((name.to_string(), time.parse().ok()?))
+        })
+        .collect())
+}
+
+/// Reads the time of a branch's newest reflog entry (as a Unix timestamp), or `None` if it has
+/// no reflog
+///
+/// # Errors
+///
+/// Fails if `git reflog show` can't be run.
+pub fn last_reflog_time(branch: &str) -> Result<Option<i64>> {
+    let output = git_command()
+        .args(["reflog", "show", "--date=unix", "--format=%gd", "-n1"])
+        .arg(format!("refs/heads/{branch}"))
+        .output()
+        .context("Failed to execute git reflog show")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git reflog show {branch} failed: {}", stderr.trim());
+    }
+
+    Ok(parse_reflog_time(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Reads when the remote-tracking ref for `upstream` (e.g. `origin/feature`) was last updated,
+/// i.e. last seen on the remote, from its reflog, or `None` if no reflog was retained. Pruning
+/// a remote-tracking ref usually deletes its reflog along with it.
+///
+/// # Errors
+///
+/// Fails if `git reflog` can't be run.
+pub fn tracking_last_seen(upstream: &str) -> Result<Option<i64>> {
+    let refname = format!("refs/remotes/{upstream}");
+    let exists = git_command()
+        .args(["reflog", "exists", &refname])
+        .status()
+        .context("Failed to execute git reflog exists")?;
+    if !exists.success() {
+        return Ok(None);
+    }
+
+    let output = git_command()
+        .args([
+            "reflog",
+            "show",
+            "--date=unix",
+            "--format=%gd",
+            "-n1",
+            &refname,
+        ])
+        .output()
+        .context("Failed to execute git reflog show")?;
+    // A reflog whose ref is gone can't be shown by name
+    Ok(output
+        .status
+        .success()
+        .then(|| parse_reflog_time(&String::from_utf8_lossy(&output.stdout)))
+        .flatten())
+}
+
+/// Parses the timestamp out of a `%gd` reflog selector printed with `--date=unix`, such as
+/// `feature@{1700000000}`
+#[must_use]
+pub fn parse_reflog_time(selector: &str) -> Option<i64> {
+    let selector = selector.trim();
+    let start = selector.rfind("@{")? + 2;
+    selector.strip_suffix('}')?.get(start..)?.parse().ok()
+}
+
+/// Lists the commits merged into `base` by the merge commits on its first-parent history,
+/// newest merge first (the second and later parents of each merge)
+///
+/// # Errors
+///
+/// Fails if `git log` can't be run or `base` doesn't resolve.
+pub fn merged_parents(base: &str) -> Result<Vec<String>> {
+    let output = git_command()
+        .args([
+            "log",
+            "--merges",
+            "--first-parent",
+            "--format=%P",
+            base,
+            "--",
+        ])
+        .output()
+        .context("Failed to execute git log --merges")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git log --merges failed for {base}");
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .flat_map(|parents| parents.split_whitespace().skip(1).map(ToString::to_string))
+        .collect())
+}
+
+/// Finds the local branches that are symbolic refs, mapped to the ref each points to
+///
+/// # Errors
+///
+/// Fails if `git for-each-ref` can't be run.
+pub fn symbolic_branches() -> Result<HashMap<String, String>> {
+    let output = git_command()
+        .args([
+            "for-each-ref",
+            "--format=%(refname:short) %(symref)",
+            "refs/heads",
+        ])
+        .output()
+        .context("Failed to execute git for-each-ref")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git for-each-ref failed");
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (name, target) = line.rsplit_once(' ')?;
+            (!target.is_empty()).then(|| (name.to_string(), target.to_string()))
+        })
+        .collect())
+}
+
+/// Reads every value of a multi-valued git config key, e.g. `cleanGone.protect`
+///
+/// # Errors
+///
+/// Fails if `git config` can't be run or reports an error other than the key being unset.
+pub fn config_get_all(key: &str) -> Result<Vec<String>> {
+    let output = git_command()
+        .args(["config", "--get-all", key])
+        .output()
+        .context("Failed to execute git config --get-all")?;
+
+    match output.status.code() {
+        Some(0) => Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(ToString::to_string)
+            .collect()),
+        // Exit code 1 means the key isn't set
+        Some(1) => Ok(Vec::new()),
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git config --get-all {key} failed: {}", stderr.trim());
+        }
+    }
+}
+
+/// Reads each local branch's configured remote (`branch.<name>.remote`)
+///
+/// # Errors
+///
+/// Fails if `git config` can't be run.
+pub fn branch_remotes() -> Result<HashMap<String, String>> {
+    let output = git_command()
+        .args(["config", "--get-regexp", r"^branch\..*\.remote$"])
+        .output()
+        .context("Failed to execute git config --get-regexp")?;
+
+    // Exits with 1 when nothing matches, which just means no branch has a remote
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (key, remote) = line.split_once(' ')?;
+            let branch = key.strip_prefix("branch.")?.strip_suffix(".remote")?;
+            Some((branch.to_string(), remote.to_string()))
+        })
+        .collect())
+}
+
+/// Reads each local branch's description (`branch.<name>.description`, as set by
+/// `git branch --edit-description`)
+///
+/// # Errors
+///
+/// Fails if `git config` can't be run.
+pub fn branch_descriptions() -> Result<HashMap<String, String>> {
+    let output = git_command()
+        .args(["config", "-z", "--get-regexp", r"^branch\..*\.description$"])
+        .output()
+        .context("Failed to execute git config --get-regexp")?;
+
+    // Exits with 1 when nothing matches, which just means no branch has a description
+    Ok(parse_branch_descriptions(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Parses `git config -z --get-regexp` output for descriptions, whose entries (unlike the
+/// lines of the plain output) hold multi-line values intact. Blank descriptions are left out.
+fn parse_branch_descriptions(output: &str) -> HashMap<String, String> {
+    output
+        .split('\0')
+        .filter_map(|entry| {
+            let (key, description) = entry.split_once('\n')?;
+            let branch = key.strip_prefix("branch.")?.strip_suffix(".description")?;
+            let description = description.trim();
+            (!description.is_empty()).then(|| (branch.to_string(), description.to_string()))
+        })
+        .collect()
+}
+
+/// Lists the branches with a `branch.<name>` section in the repository's own config, whether
+/// or not the branch still exists
+///
+/// # Errors
+///
+/// Fails if `git config` can't be run.
+pub fn branch_config_sections() -> Result<HashSet<String>> {
+    let output = git_command()
+        .args(["config", "--local", "--get-regexp", r"^branch\."])
+        .output()
+        .context("Failed to execute git config --get-regexp")?;
+
+    // Exits with 1 when nothing matches, which just means there are no sections
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            // Keys of valueless (boolean) entries come without a value
+            let key = line.split(' ').next()?;
+            let (branch, _) = key.strip_prefix("branch.")?.rsplit_once('.')?;
+            Some(branch.to_string())
+        })
+        .collect())
+}
+
+/// Removes the `branch.<name>` section from the repository's config
+///
+/// # Errors
+///
+/// Fails with git's error message if the section can't be removed.
+pub fn remove_branch_config(branch: &str) -> Result<()> {
+    let section = format!("branch.{branch}");
+    let output = git_command()
+        .args(["config", "--local", "--remove-section", &section])
+        .output()
+        .context("Failed to execute git config --remove-section")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!(
+            "git config --remove-section {section} failed: {}",
+            stderr.trim()
+        );
+    }
+    Ok(())
+}
+
+/// Unsets `branch`'s upstream (`branch.<name>.remote` and `branch.<name>.merge`) in the
+/// repository's config, leaving the rest of its section, such as its description
+///
+/// # Errors
+///
+/// Fails with git's error message if the config can't be changed.
+pub fn unset_upstream_config(branch: &str) -> Result<()> {
+    for key in ["remote", "merge"] {
+        let key = format!("branch.{branch}.{key}");
+        let output = git_command()
+            .args(["config", "--local", "--unset-all", &key])
+            .output()
+            .context("Failed to execute git config --unset-all")?;
+
+        // Exit code 5 means the key isn't set
+        if !output.status.success() && output.status.code() != Some(5) {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git config --unset-all {key} failed: {}", stderr.trim());
+        }
+    }
+    Ok(())
+}
+
+/// Checks whether any remote-tracking branch of `remote` is left (ignoring its `HEAD`)
+///
+/// # Errors
+///
+/// Fails if `git for-each-ref` can't be run.
+pub fn remote_has_branches(remote: &str) -> Result<bool> {
+    let output = git_command()
+        .args([
+            "for-each-ref",
+            "--format=%(refname)",
+            &format!("refs/remotes/{remote}/"),
+        ])
+        .output()
+        .context("Failed to execute git for-each-ref")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git for-each-ref failed");
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .any(|refname| refname != format!("refs/remotes/{remote}/HEAD")))
+}
+
+/// Picks the name [`archive_branch`] renames `branch` to: `archive/<name>`, or
+/// `archive/<name>-2` and so on if that's taken
+///
+/// # Errors
+///
+/// Fails if git can't be run to check which names are taken.
+pub fn archive_name(branch: &str) -> Result<String> {
+    let mut archived = format!("archive/{branch}");
+    let mut counter = 2;
+    while branch_tip(&archived)?.is_some() {
+        archived = format!("archive/{branch}-{counter}");
+        counter += 1;
+    }
+    Ok(archived)
+}
+
+/// Renames `branch` to `archive/<branch>` instead of deleting it, appending `-2`, `-3`, ...
+/// if that name is taken, and returns the new name.
+///
+/// The archived branch's upstream is unset, so it no longer reads as gone.
+///
+/// # Errors
+///
+/// Fails with git's error message if the rename fails.
+pub fn archive_branch(branch: &str) -> Result<String> {
+    let archived = archive_name(branch)?;
+
+    let output = git_command()
+        .args(["branch", "-m", branch, &archived])
+        .output()
+        .context("Failed to execute git branch -m")?;
+    if !output.status.success() {
+        return Err(GitFailure {
+            command: format!("git branch -m {branch} {archived}"),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }
+        .into());
+    }
+
+    let output = git_command()
+        .args(["branch", "--unset-upstream", &archived])
+        .output()
+        .context("Failed to execute git branch --unset-upstream")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!(
+            "Renamed {branch} to {archived}, but unsetting its upstream failed: {}",
+            stderr.trim()
+        );
+    }
+
+    Ok(archived)
+}
+
+/// Deletes a single branch using `git branch -D`, or `-d` without `force`, which git refuses
+/// for a branch that isn't merged into `HEAD`.
+///
+/// When `quiet` is set, git's confirmation line is suppressed so it doesn't mix with a
+/// machine-readable report on stdout.
+///
+/// # Errors
+///
+/// Fails with git's error message if the branch couldn't be deleted.
+pub fn delete_branch(branch: &str, quiet: bool, force: bool) -> Result<()> {
+    let flag = if force { "-D" } else { "-d" };
+    let output = git_command()
+        .args(["branch", flag, branch])
+        .stdout(if quiet {
+            Stdio::null()
+        } else {
+            Stdio::inherit()
+        })
+        .output()
+        .with_context(|| format!("Failed to execute git branch {flag}"))?;
+
+    if !output.status.success() {
+        return Err(GitFailure {
+            command: format!("git branch {flag} {branch}"),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Rejects names with characters no valid branch name has: whitespace at either end and control
+/// characters. Such names can only come from a malformed or crafted listing.
+///
+/// # Errors
+///
+/// Fails, quoting the name with its odd characters escaped, if it's malformed.
+pub fn check_branch_name_chars(branch: &str) -> Result<()> {
+    if branch.is_empty() || branch.trim() != branch || branch.chars().any(char::is_control) {
+        anyhow::bail!("refusing to delete malformed branch name {branch:?}");
+    }
+    Ok(())
+}
+
+/// Validates a branch name before it's handed to git: it must pass
+/// [`check_branch_name_chars`] and `git check-ref-format --branch`, which must also leave it
+/// unchanged (it expands shorthands such as `@{-1}`)
+///
+/// # Errors
+///
+/// Fails if the name is invalid or git can't be run.
+pub fn validate_branch_name(branch: &str) -> Result<()> {
+    check_branch_name_chars(branch)?;
+    let output = git_command()
+        .args(["check-ref-format", "--branch", branch])
+        .stderr(Stdio::null())
+        .output()
+        .context("Failed to execute git check-ref-format")?;
+
+    if !output.status.success() || String::from_utf8_lossy(&output.stdout).trim_end() != branch {
+        anyhow::bail!("refusing to delete invalid branch name {branch:?}");
+    }
+    Ok(())
+}
+
+/// Reads `branch`'s configured upstream as the remote and the branch name on it (from
+/// `branch.<name>.remote` and `branch.<name>.merge`), if it tracks a branch on a remote
+///
+/// # Errors
+///
+/// Fails if `git config` can't be run.
+pub fn upstream_config(branch: &str) -> Result<Option<(String, String)>> {
+    let remote = config_get_all(&format!("branch.{branch}.remote"))?.pop();
+    let merge = config_get_all(&format!("branch.{branch}.merge"))?.pop();
+    Ok(match (remote, merge) {
+        // `.` is the local repository, not a remote
+        (Some(remote), Some(merge)) if remote != "." => merge
+            .strip_prefix("refs/heads/")
+            .map(|name| (remote, name.to_string())),
+        _ => None,
+    })
+}
+
+/// Checks whether `remote` currently has a branch named `branch`, asking the remote itself
+///
+/// # Errors
+///
+/// Fails if the remote can't be queried.
+pub fn remote_branch_exists(remote: &str, branch: &str) -> Result<bool> {
+    let output = git_command()
+        .args([
+            "ls-remote",
+            "--heads",
+            remote,
+            &format!("refs/heads/{branch}"),
+        ])
+        .output()
+        .context("Failed to execute git ls-remote")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git ls-remote {remote} failed: {}", stderr.trim());
+    }
+
+    Ok(!output.stdout.is_empty())
+}
+
+/// Lists the branches on `remote`, asking the remote itself with one `git ls-remote`
+///
+/// # Errors
+///
+/// Fails if the remote can't be queried.
+pub fn remote_heads(remote: &str) -> Result<HashSet<String>> {
+    let output = git_command()
+        .args(["ls-remote", "--heads", remote])
+        .output()
+        .context("Failed to execute git ls-remote")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git ls-remote {remote} failed: {}", stderr.trim());
+    }
+
+    Ok(crate::tracking::parse_ls_remote_heads(
+        &String::from_utf8_lossy(&output.stdout),
+    ))
+}
+
+/// Deletes `branch` on `remote` with `git push <remote> --delete <branch>`
+///
+/// # Errors
+///
+/// Fails with git's error message if the push is rejected.
+pub fn delete_remote_branch(remote: &str, branch: &str) -> Result<()> {
+    let output = git_command()
+        .args(["push", remote, "--delete", branch])
+        .output()
+        .context("Failed to execute git push --delete")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!(
+            "git push {remote} --delete {branch} failed: {}",
+            stderr.trim()
+        );
+    }
+
+    Ok(())
+}
+
+/// Shows the local branches, plus remote-tracking branches when `include_remote` is set
+///
+/// # Errors
+///
+/// Fails if `git branch` can't be run.
+pub fn show_remaining_branches(include_remote: bool) -> Result<()> {
+    let mut cmd = git_command();
+    cmd.arg("branch");
+    if include_remote {
+        cmd.arg("-a");
+    }
+
+    let status = cmd.status().context("Failed to execute git branch")?;
+
+    if !status.success() {
+        anyhow::bail!("git branch failed");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_git_command_forces_c_locale() {
+        let command = git_command();
+        let envs: Vec<_> = command.get_envs().collect();
+        assert!(envs.contains(&("LC_ALL".as_ref(), Some("C".as_ref()))));
+        assert!(envs.contains(&("LANG".as_ref(), Some("C".as_ref()))));
+        assert_eq!(command.get_program(), "git");
+    }
+
+    #[test]
+    fn test_native_path_uses_platform_separators() {
+        let path = native_path("C:/repo/.git/hooks");
+        if cfg!(windows) {
+            assert_eq!(path, PathBuf::from(r"C:\repo\.git\hooks"));
+        } else {
+            assert_eq!(path, PathBuf::from("C:/repo/.git/hooks"));
+        }
+    }
+
+    #[test]
+    fn test_decode_lines_skips_invalid_utf8() {
+        let bytes = b"  feature-1 abc1234 [origin/feature-1: gone] One\n  caf\xe9 def5678 [origin/caf\xe9: gone] Two\n* main      1234567 Three\n";
+        let (decoded, undecodable) = decode_lines(bytes);
+        assert_eq!(
+            decoded,
+            "  feature-1 abc1234 [origin/feature-1: gone] One\n* main      1234567 Three\n"
+        );
+        assert_eq!(
+            undecodable,
+            ["  caf\u{fffd} def5678 [origin/caf\u{fffd}: gone] Two"]
+        );
+
+        let (decoded, undecodable) = decode_lines("  caf\u{e9} abc1234 Sub".as_bytes());
+        assert_eq!(decoded, "  caf\u{e9} abc1234 Sub");
+        assert!(undecodable.is_empty());
+    }
+
+    #[test]
+    fn test_check_branch_name_chars_rejects_odd_names() {
+        assert!(check_branch_name_chars("feature/ok-1").is_ok());
+        for odd in [
+            "",
+            "feature-1 ",
+            " feature-1",
+            "feat\u{1b}[31mure",
+            "a\tb",
+            "a\nb",
+        ] {
+            assert!(check_branch_name_chars(odd).is_err(), "{odd:?}");
+        }
+        let err = check_branch_name_chars("bad\u{7}name").unwrap_err();
+        assert!(err.to_string().contains(r#""bad\u{7}name""#));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_gone_command_passes_branch() {
+        let command = "test \"$GIT_CLEAN_GONE_BRANCH\" = feature-1 && test feature-1 =";
+        assert!(run_gone_command(command, "feature-1").unwrap());
+        assert!(!run_gone_command(command, "it's here").unwrap());
+    }
+
+    #[test]
+    fn test_parse_branch_descriptions() {
+        let descriptions = parse_branch_descriptions(
+            "branch.feature/x.description\nSpike for X\n\nSee the RFC.\n\0\
+             branch.blank.description\n\n\0\
+             branch.feature.remote\norigin\0",
+        );
+        assert_eq!(
+            descriptions,
+            HashMap::from([(
+                "feature/x".to_string(),
+                "Spike for X\n\nSee the RFC.".to_string()
+            )])
+        );
+    }
+
+    #[test]
+    fn test_parse_reflog_time() {
+        assert_eq!(
+            parse_reflog_time("feature@{1700000000}\n"),
+            Some(1_700_000_000)
+        );
+        assert_eq!(parse_reflog_time("a@b@{42}"), Some(42));
+        assert_eq!(parse_reflog_time(""), None);
+        assert_eq!(parse_reflog_time("feature@{0}x"), None);
+    }
+
+    #[test]
+    fn test_parse_git_version() {
+        assert_eq!(
+            GitVersion::parse("git version 2.39.3 (Apple Git-145)\n"),
+            Some(GitVersion::new(2, 39, 3))
+        );
+        assert_eq!(
+            GitVersion::parse("git version 2.45.1.windows.1"),
+            Some(GitVersion::new(2, 45, 1))
+        );
+        assert_eq!(
+            GitVersion::parse("git version 2.7"),
+            Some(GitVersion::new(2, 7, 0))
+        );
+        assert_eq!(GitVersion::parse("hub version 2.14.2"), None);
+    }
+
+    #[test]
+    fn test_git_version_require() {
+        let version = GitVersion::new(2, 8, 6);
+        assert!(version.require(GitVersion::new(2, 8, 0), "x").is_ok());
+        let err = version
+            .require(GitVersion::new(2, 9, 0), "--keep-recent")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--keep-recent requires git >= 2.9.0, but git 2.8.6 is installed"
+        );
+    }
+
+    #[test]
+    fn test_maintenance_is_version_gated() {
+        let version = GitVersion::new(2, 29, 2);
+        assert!(version.require(Maintenance::Gc.minimum_git(), "x").is_ok());
+        assert!(
+            version
+                .require(Maintenance::Incremental.minimum_git(), "x")
+                .is_err()
+        );
+        assert_eq!(
+            Maintenance::Incremental.tasks(),
+            ["loose-objects", "incremental-repack", "commit-graph"]
+        );
+    }
+
+    #[test]
+    fn test_parse_fetch_args_respects_quoting() {
+        let args =
+            parse_fetch_args(r#"--no-tags origin "refs/heads/my branch:refs/x" 'a b'"#).unwrap();
+        assert_eq!(
+            args,
+            ["--no-tags", "origin", "refs/heads/my branch:refs/x", "a b"]
+        );
+    }
+
+    #[test]
+    fn test_parse_fetch_args_empty() {
+        assert!(parse_fetch_args("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_fetch_args_rejects_unbalanced_quotes() {
+        assert!(parse_fetch_args(r#"--no-tags "origin"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_fetch_args_rejects_program_execution() {
+        assert!(parse_fetch_args("--upload-pack=touch /tmp/pwned").is_err());
+        assert!(parse_fetch_args("--upload-pack 'touch /tmp/pwned'").is_err());
+        assert!(parse_fetch_args("-u evil").is_err());
+        assert!(parse_fetch_args("-uevil").is_err());
+        assert!(parse_fetch_args("--update-head-ok").is_ok());
+    }
+
+    #[test]
+    fn test_parse_fetch_args_rejects_control_characters() {
+        assert!(parse_fetch_args("\"--no-tags\nrm\"").is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! `--group-by`: the gone-branch listing grouped by the remote their upstream was on, by the
+//! prefix of their name, or by the age of their last commit.
+
+use crate::age::bucket_label;
+use crate::git::{branch_commit_times, branch_remotes};
+use crate::parse::BranchLine;
+use crate::safety::unix_now;
+use crate::tracking::list_remotes;
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::hash::BuildHasher;
+
+/// What the listing is grouped by (`--group-by`)
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupBy {
+    /// The remote the (now deleted) upstream was on
+    Remote,
+    /// The part of the name before the first `/`, e.g. `feature` for `feature/login`
+    Prefix,
+    /// The age of the last commit, in the `--report-age-histogram` buckets
+    Age,
+}
+
+impl GroupBy {
+    fn heading(self) -> &'static str {
+        match self {
+            Self::Remote => "remote",
+            Self::Prefix => "prefix",
+            Self::Age => "age of their last commit",
+        }
+    }
+}
+
+/// The branches sharing one group key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+    pub key: String,
+    pub branches: Vec<String>,
+}
+
+/// The remote `upstream` (e.g. `origin/feature`) is on: the longest of `remotes` it's under,
+/// since remote names can contain `/`, or else its first component
+#[must_use]
+pub fn upstream_remote<'a>(upstream: &'a str, remotes: &[String]) -> &'a str {
+    remotes
+        .iter()
+        .filter(|remote| {
+            upstream
+                .strip_prefix(remote.as_str())
+                .is_some_and(|rest| rest.starts_with('/'))
+        })
+        .map(String::len)
+        .max()
+        .map_or_else(
+            || upstream.split('/').next().unwrap_or(upstream),
+            |len| &upstream[..len],
+        )
+}
+
+/// The part of `name` before its first `/`, or `(none)` if it has none
+#[must_use]
+pub fn name_prefix(name: &str) -> &str {
+    match name.split_once('/') {
+        Some((prefix, _)) if !prefix.is_empty() => prefix,
+        _ => "(none)",
+    }
+}
+
+/// Groups `branches` under the keys `key_for` gives, each key with a rank to order it by
+/// (then alphabetically); branch order within a group is kept
+#[must_use]
+pub fn group_with<F>(branches: &[BranchLine], mut key_for: F) -> Vec<Group>
+where
+    F: FnMut(&BranchLine) -> (usize, String),
+{
+    let mut groups: BTreeMap<(usize, String), Vec<String>> = BTreeMap::new();
+    for branch in branches {
+        groups
+            .entry(key_for(branch))
+            .or_default()
+            .push(branch.name.clone());
+    }
+    groups
+        .into_iter()
+        .map(|((_, key), branches)| Group { key, branches })
+        .collect()
+}
+
+/// Groups `branches` by remote, per each one's configured remote (`branch_remotes`) or else
+/// its upstream (see [`upstream_remote`])
+#[must_use]
+pub fn group_by_remote<S: BuildHasher>(
+    branches: &[BranchLine],
+    branch_remotes: &HashMap<String, String, S>,
+    remotes: &[String],
+) -> Vec<Group> {
+    group_with(branches, |branch| {
+        let remote = branch_remotes
+            .get(&branch.name)
+            .map(String::as_str)
+            .or_else(|| {
+                branch
+                    .upstream
+                    .as_deref()
+                    .map(|upstream| upstream_remote(upstream, remotes))
+            });
+        match remote {
+            Some(remote) => (0, remote.to_string()),
+            None => (1, "(no upstream)".to_string()),
+        }
+    })
+}
+
+/// Groups `branches` by `by`, reading what it needs from git
+///
+/// # Errors
+///
+/// Fails if the remotes, the branch config, or the commit times can't be read.
+pub fn group_branches(branches: &[BranchLine], by: GroupBy) -> Result<Vec<Group>> {
+    Ok(match by {
+        GroupBy::Remote => group_by_remote(branches, &branch_remotes()?, &list_remotes()?),
+        GroupBy::Prefix => group_with(branches, |branch| {
+            (0, name_prefix(&branch.name).to_string())
+        }),
+        GroupBy::Age => {
+            let (times, now) = (branch_commit_times()?, unix_now());
+            group_with(branches, |branch| match times.get(&branch.name) {
+                Some(time) => {
+                    let (rank, label) = bucket_label(now.saturating_sub(*time));
+                    (rank, label.to_string())
+                }
+                None => (usize::MAX, "unknown".to_string()),
+            })
+        }
+    })
+}
+
+/// Formats `groups` with a header per group, or nothing if there are none
+#[must_use]
+pub fn format_groups(by: GroupBy, groups: &[Group]) -> String {
+    if groups.is_empty() {
+        return String::new();
+    }
+
+    let mut out = format!("\nGone branches by {}:\n", by.heading());
+    for group in groups {
+        let _ = writeln!(out, "  {} ({}):", group.key, group.branches.len());
+        for name in &group.branches {
+            let _ = writeln!(out, "    - {name}");
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_branch_lines;
+
+    #[test]
+    fn test_group_by_remote_and_prefix() {
+        let branches = parse_branch_lines(
+            r"
+  feature/a    abc1234 [origin/feature/a: gone] A
+  fix-b        def5678 [corp/fork/fix-b: gone] B
+  feature/c    ghi9012 [upstream/feature/c: gone] C
+  renamed      jkl3456 [origin/renamed: gone] D
+",
+        );
+        let remotes = ["origin".to_string(), "corp/fork".to_string()];
+        assert_eq!(upstream_remote("corp/fork/fix-b", &remotes), "corp/fork");
+        assert_eq!(upstream_remote("upstream/feature/c", &remotes), "upstream");
+
+        // The configured remote wins over the upstream's name
+        let configured = HashMap::from([("renamed".to_string(), "mirror".to_string())]);
+        let groups = group_by_remote(&branches, &configured, &remotes);
+        let keys: Vec<(&str, usize)> = groups
+            .iter()
+            .map(|group| (group.key.as_str(), group.branches.len()))
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                ("corp/fork", 1),
+                ("mirror", 1),
+                ("origin", 1),
+                ("upstream", 1)
+            ]
+        );
+
+        let groups = group_with(&branches, |branch| {
+            (0, name_prefix(&branch.name).to_string())
+        });
+        assert_eq!(
+            format_groups(GroupBy::Prefix, &groups),
+            "
+Gone branches by prefix:
+  (none) (2):
+    - fix-b
+    - renamed
+  feature (2):
+    - feature/a
+    - feature/c
+"
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! A local history of cleanup runs, one JSON summary per line in
+//! `.git/git-clean-gone-history.jsonl`.
+//!
+//! Only counts and flag names are recorded (never branch names or flag values), and nothing
+//! leaves the machine. `git-clean-gone history` prints the recent runs.
+
+use crate::report::{Action, Report, group_digits};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write as _};
+use std::path::Path;
+
+/// The history file's name inside the git directory
+pub const HISTORY_FILE: &str = "git-clean-gone-history.jsonl";
+
+/// The summary of one run
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RunSummary {
+    /// When the run finished, in RFC 3339 (UTC)
+    pub timestamp: String,
+    pub dry_run: bool,
+    /// Branches deleted (or, in a dry run, that would have been)
+    pub deleted: usize,
+    /// Branches archived (or, in a dry run, that would have been)
+    pub archived: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    /// The command-line flags the run was given, without their values
+    pub flags: Vec<String>,
+}
+
+impl RunSummary {
+    /// Summarizes `report` as finished at `timestamp`, run with the command-line `args`
+    #[must_use]
+    pub fn new<I: IntoIterator<Item = String>>(
+        report: &Report,
+        timestamp: String,
+        args: I,
+    ) -> Self {
+        let (delete, archive) = if report.dry_run {
+            (Action::WouldDelete, Action::WouldArchive)
+        } else {
+            (Action::Deleted, Action::Archived)
+        };
+        Self {
+            timestamp,
+            dry_run: report.dry_run,
+            deleted: report.count(delete),
+            archived: report.count(archive),
+            skipped: report.count(Action::Skipped),
+            failed: report.count(Action::Failed),
+            flags: flag_names(args),
+        }
+    }
+}
+
+/// The flags among `args` (e.g. `--protect=main` and `-n` become `--protect` and `-n`),
+/// leaving out values, branch names, and everything after `--`
+fn flag_names<I: IntoIterator<Item = String>>(args: I) -> Vec<String> {
+    args.into_iter()
+        .take_while(|arg| arg != "--")
+        .filter(|arg| arg.starts_with('-') && arg.len() > 1)
+        .map(|arg| match arg.split_once('=') {
+            Some((flag, _)) => flag.to_string(),
+            None => arg,
+        })
+        .collect()
+}
+
+/// Appends `summary` to the history file at `path`, creating it if needed
+///
+/// # Errors
+///
+/// Fails if the file can't be written.
+pub fn append(path: &Path, summary: &RunSummary) -> Result<()> {
+    let line = serde_json::to_string(summary).context("Failed to serialize run summary")? + "\n";
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .with_context(|| format!("Failed to write history {}", path.display()))
+}
+
+/// Loads the runs recorded at `path`, oldest first; none if there's no history yet
+///
+/// # Errors
+///
+/// Fails if the file can't be read or a line isn't a run summary.
+pub fn load(path: &Path) -> Result<Vec<RunSummary>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("Failed to read history {}", path.display()));
+        }
+    };
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).with_context(|| {
+                format!("Failed to parse line {} of {}", index + 1, path.display())
+            })
+        })
+        .collect()
+}
+
+/// Renders the last `limit` of `runs` one per line, newest last, followed by the total deleted
+/// across every real run
+#[must_use]
+pub fn format_history(runs: &[RunSummary], limit: usize) -> String {
+    if runs.is_empty() {
+        return "No runs recorded yet\n".to_string();
+    }
+    let mut text = String::new();
+    for run in &runs[runs.len().saturating_sub(limit)..] {
+        let _ = write!(
+            text,
+            "{}  {}deleted {}, archived {}, skipped {}, failed {}",
+            run.timestamp,
+            if run.dry_run {
+                "[dry run] would have "
+            } else {
+                ""
+            },
+            group_digits(run.deleted),
+            group_digits(run.archived),
+            group_digits(run.skipped),
+            group_digits(run.failed)
+        );
+        if !run.flags.is_empty() {
+            let _ = write!(text, "  ({})", run.flags.join(" "));
+        }
+        text.push('\n');
+    }
+    let deleted: usize = runs
+        .iter()
+        .filter(|run| !run.dry_run)
+        .map(|run| run.deleted)
+        .sum();
+    let _ = writeln!(
+        text,
+        "\n{} run(s) recorded; {} branch(es) deleted in total",
+        group_digits(runs.len()),
+        group_digits(deleted)
+    );
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(timestamp: &str, dry_run: bool, deleted: usize) -> RunSummary {
+        RunSummary {
+            timestamp: timestamp.to_string(),
+            dry_run,
+            deleted,
+            archived: 0,
+            skipped: 1,
+            failed: 0,
+            flags: vec!["--yes".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_flag_names_drop_values() {
+        let args = ["--protect=main", "-n", "--max-runtime", "5m", "--", "-x"];
+        assert_eq!(
+            flag_names(args.map(String::from)),
+            ["--protect", "-n", "--max-runtime"]
+        );
+    }
+
+    #[test]
+    fn test_append_and_load_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(HISTORY_FILE);
+        assert!(load(&path).unwrap().is_empty());
+
+        let runs = [
+            run("2026-01-01T00:00:00Z", true, 3),
+            run("2026-01-02T00:00:00Z", false, 2),
+        ];
+        for summary in &runs {
+            append(&path, summary).unwrap();
+        }
+        assert_eq!(load(&path).unwrap(), runs);
+
+        fs::write(&path, "not json\n").unwrap();
+        assert!(load(&path).is_err());
+    }
+
+    #[test]
+    fn test_format_history_shows_recent_runs_and_total() {
+        let runs = [
+            run("2026-01-01T00:00:00Z", false, 2_000),
+            run("2026-01-02T00:00:00Z", true, 3),
+            run("2026-01-03T00:00:00Z", false, 2),
+        ];
+        assert_eq!(
+            format_history(&runs, 2),
+            "2026-01-02T00:00:00Z  [dry run] would have deleted 3, archived 0, skipped 1, failed 0  (--yes)\n\
+             2026-01-03T00:00:00Z  deleted 2, archived 0, skipped 1, failed 0  (--yes)\n\
+             \n3 run(s) recorded; 2,002 branch(es) deleted in total\n"
+        );
+        assert_eq!(format_history(&[], 10), "No runs recorded yet\n");
+    }
+}
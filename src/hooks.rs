@@ -0,0 +1,189 @@
+//! The `pre-clean-gone` hook, which lets a repository veto a cleanup, and the `post-merge` hook
+//! that runs a cleanup after every merge.
+//!
+//! The hooks live in the hooks directory (`.git/hooks`, or `core.hooksPath` if set).
+//! `pre-clean-gone` is run like git's own hooks: only if it exists and is executable. It gets the
+//! branches about to be deleted on stdin, one per line, and a non-zero exit aborts the deletion.
+
+use crate::git::{git_path, output_with_stdin};
+use crate::pipeline::Confirmed;
+use crate::script::write_script;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// File name of the hook, inside the hooks directory
+pub const PRE_CLEAN_GONE: &str = "pre-clean-gone";
+
+/// File name of git's hook run after a successful merge, including the one in `git pull`
+pub const POST_MERGE: &str = "post-merge";
+
+/// Marks a `post-merge` hook as written by [`install_post_merge_hook`]
+const INSTALLED_MARKER: &str = "# Installed by git-clean-gone install-hook";
+
+/// The `post-merge` hook [`install_post_merge_hook`] writes
+#[must_use]
+pub fn post_merge_script() -> String {
+    format!("#!/bin/sh\n{INSTALLED_MARKER}\nexec git-clean-gone --hook {POST_MERGE}\n")
+}
+
+/// Writes the `post-merge` hook to `path` (see [`post_merge_script`]). A hook it didn't write is
+/// only replaced with `force`, so an existing one isn't lost.
+///
+/// # Errors
+///
+/// Fails if a foreign hook is in the way, or the hook can't be written.
+pub fn install_post_merge_hook(path: &Path, force: bool) -> Result<()> {
+    if path.symlink_metadata().is_ok() {
+        // Checked at the start, since a symlinked binary contains the marker too
+        let header = format!("#!/bin/sh\n{INSTALLED_MARKER}\n");
+        let ours = fs::read(path).is_ok_and(|existing| existing.starts_with(header.as_bytes()));
+        if !ours && !force {
+            anyhow::bail!(
+                "{} already exists and wasn't installed by git-clean-gone; pass --force to \
+                 replace it",
+                path.display()
+            );
+        }
+        // Removed rather than overwritten, so a symlinked hook's target is left alone
+        fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    write_script(path, &post_merge_script())
+}
+
+/// Finds the `pre-clean-gone` hook, if one is installed and executable
+///
+/// # Errors
+///
+/// Fails if the hooks directory can't be resolved.
+pub fn find_pre_clean_hook() -> Result<Option<PathBuf>> {
+    let path = git_path(&format!("hooks/{PRE_CLEAN_GONE}"))?;
+    Ok(is_executable(&path).then_some(path))
+}
+
+/// Whether `path` is a file git would run as a hook
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+/// Runs the hook at `hook` with `branches` on stdin, one per line. Its stdout is sent to
+/// stderr, so it can't corrupt a machine-readable report.
+///
+/// # Errors
+///
+/// Fails if the hook can't be run or exits non-zero, which vetoes the deletion.
+pub fn run_pre_clean_hook(hook: &Path, branches: &[&str]) -> Result<()> {
+    let mut input = branches.join("\n");
+    input.push('\n');
+    let output = output_with_stdin(&mut Command::new(hook), input)
+        .with_context(|| format!("Failed to run {}", hook.display()))?;
+    eprint!("{}", String::from_utf8_lossy(&output.stdout));
+    let status = output.status;
+    if !status.success() {
+        anyhow::bail!(
+            "{PRE_CLEAN_GONE} hook rejected the cleanup ({status}); no branches were deleted"
+        );
+    }
+    Ok(())
+}
+
+/// Runs the `pre-clean-gone` hook, if one is installed, on the branches `confirmed` clears for
+/// deletion, in listing order. Every frontend that deletes runs it between confirming and
+/// deleting; a dry run or an empty confirmation skips it.
+///
+/// # Errors
+///
+/// Fails if the hook can't be found or run, or vetoes the deletion.
+pub fn run_pre_clean_hook_on(confirmed: &Confirmed, dry_run: bool) -> Result<()> {
+    if dry_run || confirmed.confirmed.is_empty() {
+        return Ok(());
+    }
+    let Some(hook) = find_pre_clean_hook()? else {
+        return Ok(());
+    };
+    let names: Vec<&str> = confirmed
+        .deletable
+        .iter()
+        .filter(|branch| confirmed.confirmed.contains(&branch.name))
+        .map(|branch| branch.name.as_str())
+        .collect();
+    run_pre_clean_hook(&hook, &names)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn write_hook(dir: &Path, script: &str) -> PathBuf {
+        let path = dir.join(PRE_CLEAN_GONE);
+        fs::write(&path, script).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_pre_clean_hook_can_veto() {
+        let dir = tempfile::tempdir().unwrap();
+        let hook = write_hook(
+            dir.path(),
+            "#!/bin/sh\nif grep -qx 'release/1.x'; then exit 1; fi\n",
+        );
+
+        assert!(run_pre_clean_hook(&hook, &["feature-1", "feature-2"]).is_ok());
+        let err = run_pre_clean_hook(&hook, &["feature-1", "release/1.x"]).unwrap_err();
+        assert!(err.to_string().contains("rejected"));
+    }
+
+    #[test]
+    fn test_install_post_merge_hook_keeps_foreign_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hooks").join(POST_MERGE);
+
+        install_post_merge_hook(&path, false).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), post_merge_script());
+        assert!(is_executable(&path));
+        // Reinstalling over its own hook is fine
+        install_post_merge_hook(&path, false).unwrap();
+
+        fs::write(&path, "#!/bin/sh\nmake\n").unwrap();
+        let err = install_post_merge_hook(&path, false).unwrap_err();
+        assert!(err.to_string().contains("--force"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "#!/bin/sh\nmake\n");
+        install_post_merge_hook(&path, true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), post_merge_script());
+
+        // A symlinked hook is replaced, not written through
+        let target = dir.path().join("binary");
+        fs::write(&target, b"\x7fELF\xff").unwrap();
+        fs::remove_file(&path).unwrap();
+        std::os::unix::fs::symlink(&target, &path).unwrap();
+        assert!(install_post_merge_hook(&path, false).is_err());
+        install_post_merge_hook(&path, true).unwrap();
+        assert_eq!(fs::read(&target).unwrap(), b"\x7fELF\xff");
+        assert_eq!(fs::read_to_string(&path).unwrap(), post_merge_script());
+    }
+
+    #[test]
+    fn test_non_executable_hook_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let hook = write_hook(dir.path(), "#!/bin/sh\nexit 1\n");
+        assert!(is_executable(&hook));
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(!is_executable(&hook));
+    }
+}
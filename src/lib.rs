@@ -0,0 +1,1058 @@
+//! Clean up local Git branches whose upstream has been deleted on the remote.
+//!
+//! The `git-clean-gone` binary is a thin frontend over this library. Embedders can drive the
+//! same per-branch deletion loop through [`clean_with`], using its callback to add their own
+//! prompting, logging, or veto logic.
+
+pub mod age;
+pub mod backup;
+pub mod bugreport;
+pub mod cache;
+pub mod cancel;
+pub mod checkpoint;
+pub mod explain;
+pub mod git;
+pub mod grouping;
+pub mod history;
+pub mod hooks;
+pub mod marker;
+pub mod notify;
+pub mod operations;
+pub mod parallel;
+pub mod parse;
+pub mod pattern;
+pub mod pipeline;
+pub mod plan;
+pub mod progress;
+pub mod prompt;
+pub mod refdb;
+pub mod refs;
+pub mod remotes;
+pub mod report;
+pub mod risk;
+pub mod rules;
+pub mod safety;
+pub mod script;
+pub mod tags;
+pub mod tips;
+pub mod tracking;
+pub mod watch;
+pub mod worktrees;
+
+use anyhow::Result;
+use git::GitVersion;
+use parse::BranchLine;
+use pipeline::{Confirmed, Enumerated, Filtered, Phases, State};
+use report::{Action, BranchReport, Report};
+use rules::RuleAction;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::hash::BuildHasher;
+use std::time::Duration;
+
+/// Options controlling a cleanup run
+#[derive(Debug, Clone, Default)]
+#[allow(clippy::struct_excessive_bools)] // Mirrors the CLI flags
+pub struct Options {
+    /// Report what would be deleted without deleting anything
+    pub dry_run: bool,
+    /// Show git's fetch output and the raw branch listing
+    pub verbose: bool,
+    /// Suppress git's own per-branch output, e.g. while printing a machine-readable report
+    pub quiet: bool,
+    /// Remote to fetch and prune, instead of `git fetch`'s default
+    pub fetch_remote: Option<String>,
+    /// Only treat branches whose upstream is on this remote as gone
+    pub gone_remote: Option<String>,
+    /// Shell command that decides which branches are gone (exiting 0 for a gone branch given as
+    /// its last argument), instead of their upstream tracking state
+    pub gone_command: Option<String>,
+    /// Shell command whose output (one branch name per line) lists the candidates, instead of
+    /// the gone branches
+    pub branches_from_command: Option<String>,
+    /// Shell command that says whether a gone branch has an open pull request (exiting 0 if so,
+    /// for the branch given as its last argument), to keep such branches
+    pub open_pr_command: Option<String>,
+    /// Right before deleting, ask the upstreams' remotes whether they're still gone, keeping
+    /// the branches whose upstream was re-created since the fetch
+    pub verify_remote_gone: bool,
+    /// Extra arguments appended to `git fetch -ap`
+    pub fetch_args: Vec<String>,
+    /// Shell command run instead of `git fetch -ap`
+    pub fetch_command: Option<String>,
+    /// Keep this many of the most recently committed gone branches
+    pub keep_recent: Option<usize>,
+    /// Run `git maintenance` after deleting
+    pub maintenance: Option<git::Maintenance>,
+    /// Keep gone branches whose upstream doesn't start with this prefix, e.g. `origin/team-a/`
+    pub upstream_prefix: Option<String>,
+    /// Keep gone branches matching any of these patterns
+    pub protect: Vec<pattern::BranchPattern>,
+    /// Decide whether the branches this maps are gone by whether the remote-tracking branch it
+    /// maps them to exists, instead of by their upstream
+    pub upstream_map: Option<pattern::UpstreamMap>,
+    /// Treat the branches still configured to track this remote, which was removed, as gone
+    /// instead of those whose upstream is gone
+    pub removed_remote: Option<String>,
+    /// Per-branch actions from a rules file, overriding how the branches they match are
+    /// deleted (see [`rules`])
+    pub rules: rules::Rules,
+    /// Keep gone branches that branched off before this commit or date
+    pub since: Option<safety::Since>,
+    /// Keep gone branches whose name still exists on another remote
+    pub skip_if_exists_elsewhere: bool,
+    /// Keep gone branches with reflog activity within this window
+    pub exclude_recent_activity: Option<Duration>,
+    /// Keep gone branches whose upstream was seen on the remote within this window, per its
+    /// remote-tracking ref's reflog
+    pub gone_for: Option<Duration>,
+    /// Keep gone branches whose tip is an ancestor of a tag
+    pub protect_tagged_ancestors: bool,
+    /// Rename gone branches to `archive/<name>` instead of deleting them
+    pub rename_to_archive: bool,
+    /// Delete at most this many branches per run, keeping the rest for later
+    pub limit: Option<usize>,
+    /// Keep gone branches with commits that aren't on the base (see [`risk::resolve_base`])
+    pub merged_only: bool,
+    /// Keep gone branches with commits no other ref reaches, judged across the whole object
+    /// graph instead of against a base
+    pub auto_safe: bool,
+    /// Keep gone branches with commits that aren't on any remote, merged or not
+    pub protect_unpushed: bool,
+    /// Keep candidates whose `git branch -vv` tracking state says they're ahead of their upstream
+    pub protect_ahead: bool,
+    /// Keep gone branches with a description (`git branch --edit-description`)
+    pub protect_described: bool,
+    /// Pick the base with [`risk::fallback_base`] instead of [`risk::resolve_base`]
+    pub remote_head_fallback: bool,
+    /// Delete with `git branch -d` instead of `-D`, so git refuses branches it doesn't
+    /// consider merged into `HEAD`
+    pub safe_delete: bool,
+    /// Keep the gone branch most recently merged into the base by a merge commit
+    pub keep_last_merged: bool,
+    /// Also treat branches as gone when their upstream still exists but shares no history with
+    /// them, e.g. after the remote branch was force-pushed with unrelated history
+    pub treat_unrelated_upstream_as_gone: bool,
+    /// Also clean local branches that never had an upstream, if they're fully merged
+    pub include_no_upstream: bool,
+    /// List branches by reading the ref store directly (see [`refdb`]) instead of running
+    /// `git branch -vv`, falling back to git when that isn't reliable
+    pub prefer_packed_refs: bool,
+    /// Reuse the previous run's `git branch -vv` listing while no ref has changed (see
+    /// [`cache`])
+    pub cache_listing: bool,
+}
+
+impl Options {
+    /// The remote whose default branch is the base for merge checks: [`Options::gone_remote`],
+    /// then [`Options::fetch_remote`], then `origin`
+    #[must_use]
+    pub fn base_remote(&self) -> &str {
+        self.gone_remote
+            .as_deref()
+            .or(self.fetch_remote.as_deref())
+            .unwrap_or("origin")
+    }
+
+    /// What branches are compared against for merge checks, from [`Options::base_remote`]
+    /// (see [`Options::remote_head_fallback`])
+    ///
+    /// # Errors
+    ///
+    /// Fails if the local branches can't be listed for the fallback.
+    pub fn base(&self) -> Result<risk::Base> {
+        if self.remote_head_fallback {
+            Ok(risk::resolve_fallback_base(self.base_remote())?.0)
+        } else {
+            Ok(risk::resolve_base(self.base_remote()))
+        }
+    }
+
+    /// Whether `branch` is archived rather than deleted: per its rule if it has one, otherwise
+    /// per [`Options::rename_to_archive`]
+    #[must_use]
+    pub fn archives(&self, branch: &str) -> bool {
+        match self.rules.action_for(branch) {
+            Some(RuleAction::Archive) => true,
+            Some(RuleAction::SafeDelete | RuleAction::ForceDelete) => false,
+            Some(RuleAction::Keep) | None => self.rename_to_archive,
+        }
+    }
+
+    /// Whether `branch` is deleted with `git branch -D` rather than `-d`: per its rule if it
+    /// has one, otherwise unless [`Options::safe_delete`]
+    #[must_use]
+    pub fn force_deletes(&self, branch: &str) -> bool {
+        match self.rules.action_for(branch) {
+            Some(RuleAction::SafeDelete) => false,
+            Some(RuleAction::ForceDelete) => true,
+            _ => !self.safe_delete,
+        }
+    }
+}
+
+/// Checks that git is installed and new enough for every feature `options` enables.
+///
+/// Runs up front so a missing or outdated git fails with a clear message instead of an
+/// opaque error halfway through.
+///
+/// # Errors
+///
+/// Fails if git isn't on `PATH` or is too old.
+pub fn check_git(options: &Options) -> Result<()> {
+    let version = git::ensure_git()?;
+    if options.keep_recent.is_some() {
+        // `%(committerdate:unix)`
+        version.require(GitVersion::new(2, 9, 0), "--keep-recent")?;
+    }
+    if let Some(maintenance) = options.maintenance {
+        version.require(maintenance.minimum_git(), "--maintenance")?;
+    }
+    if options.verbose {
+        println!("{}", version_note(version, options));
+    }
+    Ok(())
+}
+
+/// A one-line note naming the git version and the caveats of how `options` decide what's
+/// merged with it, for verbose output
+#[must_use]
+pub fn version_note(version: GitVersion, options: &Options) -> String {
+    let mut caveats = Vec::new();
+    if options.merged_only || options.include_no_upstream {
+        caveats.push("merged means reachable from the base, so rebased and squash-merged branches count as unmerged");
+    }
+    if options.safe_delete {
+        caveats.push("git branch -d checks against HEAD, not the base");
+    }
+    if options.prefer_packed_refs && version >= GitVersion::new(2, 45, 0) {
+        caveats
+            .push("refs in the reftable format (git 2.45+) are read with git branch -vv instead");
+    }
+    if caveats.is_empty() {
+        format!("Using git {version}")
+    } else {
+        format!("Using git {version}; {}", caveats.join("; "))
+    }
+}
+
+/// A per-branch event emitted while cleaning
+#[derive(Debug)]
+pub enum Event<'a> {
+    /// The branch is about to be deleted (or reported, in a dry run).
+    ///
+    /// This is the only event whose returned [`Decision`] is used.
+    Considering(&'a BranchLine),
+    /// The branch was left alone because its tip moved to the given SHA since it was listed
+    TipMoved(&'a BranchLine, &'a str),
+    /// The branch was deleted
+    Deleted(&'a BranchLine),
+    /// The branch was renamed to the given archive name instead of deleted
+    Archived(&'a BranchLine, &'a str),
+    /// The branch was left alone because its upstream reappeared on the given remote since
+    /// the fetch (see [`Options::verify_remote_gone`])
+    Reappeared(&'a BranchLine, &'a str),
+    /// Deleting the branch failed; the remaining branches are still attempted
+    Failed(&'a BranchLine, &'a anyhow::Error),
+}
+
+/// The callback's verdict on an [`Event::Considering`] branch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Decision {
+    /// Go ahead and delete the branch
+    #[default]
+    Proceed,
+    /// Keep the branch; it's reported as skipped
+    Skip,
+}
+
+/// Fetches, finds gone branches, and deletes them, consulting `on_event` for each branch.
+///
+/// Branches held back by safety checks are never offered to `on_event`; they're reported
+/// as skipped with the reason.
+///
+/// # Errors
+///
+/// Fails if the current directory isn't a git repository or git is missing or too old. Failures to
+/// delete individual branches are reported in the returned [`Report`] instead.
+pub fn clean_with<F>(options: &Options, on_event: F) -> Result<Report>
+where
+    F: FnMut(Event<'_>) -> Decision,
+{
+    check_git(options)?;
+    git::ensure_git_repo()?;
+    pipeline::run(&mut LibraryPhases { options, on_event }, State::Start)
+}
+
+/// The phases of [`clean_with`]: every branch the safety checks let through is confirmed, the
+/// `pre-clean-gone` hook may veto the lot, and `on_event` gets the final say on each as it's
+/// deleted
+struct LibraryPhases<'a, F> {
+    options: &'a Options,
+    on_event: F,
+}
+
+impl<F> Phases for LibraryPhases<'_, F>
+where
+    F: FnMut(Event<'_>) -> Decision,
+{
+    fn fetch(&mut self) -> Result<()> {
+        let options = self.options;
+        match &options.fetch_command {
+            Some(command) => git::run_fetch_command(command, options.verbose),
+            None => git::git_fetch_prune(
+                options.verbose,
+                options.fetch_remote.as_deref(),
+                &options.fetch_args,
+            ),
+        }
+    }
+
+    fn enumerate(&mut self) -> Result<Enumerated> {
+        let all_branches = list_local_branches(self.options)?;
+        Ok(Enumerated {
+            gone: find_gone_branches(&all_branches, self.options)?,
+            all_branches,
+        })
+    }
+
+    fn filter(&mut self, enumerated: Enumerated) -> Result<Filtered> {
+        let (deletable, held) =
+            safety::apply_safety_checks(enumerated.gone, &enumerated.all_branches, self.options)?;
+        Ok(Filtered {
+            all_branches: enumerated.all_branches,
+            deletable,
+            held,
+        })
+    }
+
+    fn delete(&mut self, confirmed: Confirmed) -> Result<Report> {
+        hooks::run_pre_clean_hook_on(&confirmed, self.options.dry_run)?;
+        let on_event = &mut self.on_event;
+        let mut branches =
+            delete_branches(&confirmed.deletable, self.options, |event| match event {
+                Event::Considering(branch) if !confirmed.confirmed.contains(&branch.name) => {
+                    Decision::Skip
+                }
+                event => on_event(event),
+            });
+        branches.extend(confirmed.held.iter().map(BranchReport::held));
+        Ok(Report {
+            dry_run: self.options.dry_run,
+            branches,
+            age_histogram: None,
+            remotes_checked: None,
+        })
+    }
+}
+
+/// Lists the local branches, from the ref store with [`Options::prefer_packed_refs`] unless
+/// it holds something only git can interpret, otherwise from `git branch -vv` (or its cached
+/// output, with [`Options::cache_listing`])
+///
+/// # Errors
+///
+/// Fails if `git branch -vv` is needed and can't be run.
+pub fn list_local_branches(options: &Options) -> Result<Vec<BranchLine>> {
+    if options.prefer_packed_refs {
+        match std::env::current_dir()
+            .map_err(anyhow::Error::from)
+            .and_then(|dir| refdb::read_branches(&dir))
+        {
+            Ok(branches) => {
+                if options.verbose {
+                    println!("Read {} branch(es) from the ref store", branches.len());
+                }
+                return Ok(branches);
+            }
+            Err(err) if options.verbose => {
+                println!("Falling back to git branch -vv: {err:#}");
+            }
+            Err(_) => {}
+        }
+    }
+    if options.cache_listing {
+        cache::local_branches(options.verbose)
+    } else {
+        git::local_branches(options.verbose)
+    }
+}
+
+/// Selects the gone branches among `all_branches`, restricted to those tracking
+/// [`Options::gone_remote`] if it's set, plus the merged branches without an upstream with
+/// [`Options::include_no_upstream`] and those with an unrelated upstream with
+/// [`Options::treat_unrelated_upstream_as_gone`]. With [`Options::gone_command`], the command's verdicts are
+/// used instead.
+///
+/// # Errors
+///
+/// Fails if the branches' remotes can't be read, or the gone command can't be run.
+pub fn find_gone_branches(
+    all_branches: &[BranchLine],
+    options: &Options,
+) -> Result<Vec<BranchLine>> {
+    if let Some(command) = &options.branches_from_command {
+        return find_listed_by_command(all_branches, command);
+    }
+    if let Some(command) = &options.gone_command {
+        return find_gone_by_command(all_branches, command, options.verbose);
+    }
+    if let Some(remote) = &options.removed_remote {
+        // Without its remote, git shows a branch as having no upstream at all, so these are
+        // found by the remote their config still names
+        let branches = all_branches
+            .iter()
+            .filter(|branch| !branch.is_checked_out())
+            .cloned()
+            .collect();
+        return Ok(parse::tracking_remote(
+            branches,
+            remote,
+            &git::branch_remotes()?,
+        ));
+    }
+    if options.verbose {
+        for branch in all_branches.iter().filter(|branch| !branch.is_current) {
+            if branch.in_other_worktree {
+                if branch.tracking.as_deref() == Some("gone") {
+                    println!(
+                        "Not deleting {}: checked out in another worktree",
+                        branch.name
+                    );
+                }
+            } else if let Some(divergence) =
+                branch.divergence().filter(|d| d.gone && d.has_counts())
+            {
+                println!(
+                    "Not treating {} as gone: {} its deleted upstream",
+                    branch.name,
+                    divergence.summary()
+                );
+            }
+        }
+    }
+    let gone = parse::gone_branches(all_branches);
+    let mut gone = match &options.gone_remote {
+        Some(remote) => parse::tracking_remote(gone, remote, &git::branch_remotes()?),
+        None => gone,
+    };
+    if let Some(map) = &options.upstream_map {
+        let tracking: HashSet<String> = tracking::list_all_tracking_branches()?
+            .into_iter()
+            .map(|(remote, branch)| format!("{remote}/{branch}"))
+            .collect();
+        gone = apply_upstream_map(all_branches, &gone, map, &tracking);
+    }
+    if options.treat_unrelated_upstream_as_gone {
+        gone.extend(find_unrelated_upstream_branches(all_branches)?);
+    }
+    if options.include_no_upstream {
+        gone.extend(find_merged_upstreamless_branches(
+            all_branches,
+            options.base_remote(),
+            &options.base()?,
+        )?);
+    }
+    Ok(gone)
+}
+
+/// Re-decides which of `all_branches` are gone for [`Options::upstream_map`]: a branch `map`
+/// maps is gone if the remote-tracking branch it maps to isn't among `tracking` (and it isn't
+/// checked out), whatever its upstream says; the rest stay as in `gone`
+#[must_use]
+pub fn apply_upstream_map<S: BuildHasher>(
+    all_branches: &[BranchLine],
+    gone: &[BranchLine],
+    map: &pattern::UpstreamMap,
+    tracking: &HashSet<String, S>,
+) -> Vec<BranchLine> {
+    all_branches
+        .iter()
+        .filter(|branch| match map.map(&branch.name) {
+            Some(remote_branch) => !branch.is_checked_out() && !tracking.contains(&remote_branch),
+            None => gone.iter().any(|gone| gone.name == branch.name),
+        })
+        .cloned()
+        .collect()
+}
+
+/// Selects the branches among `all_branches`, other than checked-out ones, that `command`
+/// says are gone (see [`git::run_gone_command`]), running it once per branch
+///
+/// # Errors
+///
+/// Fails if the command can't be run.
+pub fn find_gone_by_command(
+    all_branches: &[BranchLine],
+    command: &str,
+    verbose: bool,
+) -> Result<Vec<BranchLine>> {
+    let mut gone = Vec::new();
+    for branch in all_branches
+        .iter()
+        .filter(|branch| !branch.is_checked_out())
+    {
+        if git::run_gone_command(command, &branch.name)? {
+            if verbose {
+                println!("--gone-command: {} is gone", branch.name);
+            }
+            gone.push(branch.clone());
+        }
+    }
+    Ok(gone)
+}
+
+/// Selects the branches among `all_branches`, other than checked-out ones, that the
+/// `--branches-from-command` output lists, warning about the listed names that aren't local
+/// branches
+///
+/// # Errors
+///
+/// Fails if the command can't be run or exits unsuccessfully.
+pub fn find_listed_by_command(
+    all_branches: &[BranchLine],
+    command: &str,
+) -> Result<Vec<BranchLine>> {
+    let (listed, missing) =
+        parse::select_listed(all_branches, &git::run_branches_command(command)?);
+    for name in missing {
+        eprintln!(
+            "warning: branch {name} from --branches-from-command doesn't exist locally; skipping"
+        );
+    }
+    Ok(listed
+        .into_iter()
+        .filter(|branch| !branch.is_checked_out())
+        .collect())
+}
+
+/// Selects the branches among `all_branches`, other than checked-out ones, whose upstream
+/// still exists but shares no history with them, for
+/// [`Options::treat_unrelated_upstream_as_gone`]
+///
+/// # Errors
+///
+/// Fails if a branch can't be compared with its upstream.
+pub fn find_unrelated_upstream_branches(all_branches: &[BranchLine]) -> Result<Vec<BranchLine>> {
+    let mut unrelated = Vec::new();
+    for branch in all_branches {
+        if branch.is_checked_out()
+            || branch.upstream.is_none()
+            || branch.divergence().is_some_and(|d| d.gone)
+        {
+            continue;
+        }
+        let upstream = format!("{}@{{upstream}}", branch.name);
+        if !git::shares_history(&format!("refs/heads/{}", branch.name), &upstream)? {
+            unrelated.push(branch.clone());
+        }
+    }
+    Ok(unrelated)
+}
+
+/// Selects the branches among `all_branches` that have no upstream and no commits beyond
+/// `base` (see [`Options::base`]). `remote`'s default branch itself is never selected, even if
+/// it lost its upstream.
+///
+/// # Errors
+///
+/// Fails if a branch can't be compared against the base.
+pub fn find_merged_upstreamless_branches(
+    all_branches: &[BranchLine],
+    remote: &str,
+    base: &risk::Base,
+) -> Result<Vec<BranchLine>> {
+    let default_branch = git::remote_default_branch(remote)?;
+    let mut merged = Vec::new();
+    for branch in parse::upstreamless_branches(all_branches) {
+        if default_branch.as_ref() != Some(&branch.name)
+            && risk::unique_commit_count(&branch.name, base)? == 0
+        {
+            merged.push(branch);
+        }
+    }
+    Ok(merged)
+}
+
+/// Like [`clean_with`], deleting every gone branch without intervention.
+///
+/// # Errors
+///
+/// See [`clean_with`].
+pub fn clean(options: &Options) -> Result<Report> {
+    clean_with(options, |_| Decision::Proceed)
+}
+
+/// Deletes each branch in turn, emitting events to `on_event` around every deletion. Each
+/// gets its own `git branch -D`, so no command line grows with the number of branches.
+///
+/// Names that aren't valid branch names (e.g. with control characters, from a crafted listing)
+/// are reported as [`Event::Failed`] without being handed to git, even in a dry run.
+///
+/// Right before deleting, each branch's tip is checked against the SHA it was listed with, and
+/// a branch that moved in the meantime (e.g. a commit made during the confirmation prompt) is
+/// skipped with [`Event::TipMoved`]. With [`Options::rename_to_archive`] (or an `archive`
+/// rule), branches are archived instead. A dry run goes through the same checks, offering branches to the callback as
+/// [`Event::Considering`], but the ones that pass are only reported as
+/// [`Action::WouldDelete`] (or [`Action::WouldArchive`]). With [`Options::verify_remote_gone`],
+/// the upstreams' remotes are asked for their branches before the first deletion, and a branch
+/// whose upstream is back on its remote is skipped with [`Event::Reappeared`].
+///
+/// A branch git refuses to delete is reported as [`Action::Failed`] with the
+/// [`report::FailureCause`] and git's stderr, and the rest are still attempted.
+pub fn delete_branches<F>(
+    branches: &[BranchLine],
+    options: &Options,
+    mut on_event: F,
+) -> Vec<BranchReport>
+where
+    F: FnMut(Event<'_>) -> Decision,
+{
+    let mut reappeared = if options.verify_remote_gone {
+        reappeared_upstreams(branches)
+    } else {
+        HashMap::new()
+    };
+    branches
+        .iter()
+        .map(|branch| {
+            if on_event(Event::Considering(branch)) == Decision::Skip {
+                return BranchReport::new(branch, Action::Skipped);
+            }
+            if let Err(err) = git::validate_branch_name(&branch.name) {
+                on_event(Event::Failed(branch, &err));
+                return BranchReport::failed(branch, &err);
+            }
+            match git::branch_tip(&branch.name) {
+                Ok(Some(tip)) if tip.starts_with(&branch.sha) => {}
+                Ok(Some(tip)) => {
+                    on_event(Event::TipMoved(branch, &tip));
+                    return BranchReport::skipped(
+                        branch,
+                        safety::HoldKind::TipMismatch,
+                        format!("tip moved from {} to {tip} since it was listed", branch.sha),
+                    );
+                }
+                Ok(None) => {
+                    return BranchReport::skipped(
+                        branch,
+                        safety::HoldKind::TipMismatch,
+                        "deleted since it was listed".to_string(),
+                    );
+                }
+                Err(err) => {
+                    on_event(Event::Failed(branch, &err));
+                    return BranchReport::failed(branch, &err);
+                }
+            }
+            if options.verify_remote_gone {
+                match reappeared.remove(&branch.name).unwrap_or(Ok(None)) {
+                    Ok(Some(remote)) => {
+                        on_event(Event::Reappeared(branch, &remote));
+                        return BranchReport::skipped(
+                            branch,
+                            safety::HoldKind::Reappeared,
+                            format!("upstream reappeared on {remote} since the fetch"),
+                        );
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        on_event(Event::Failed(branch, &err));
+                        return BranchReport::failed(branch, &err);
+                    }
+                }
+            }
+            // Every check above runs in a dry run too, so it reports what a real run would do
+            let archive = options.archives(&branch.name);
+            if options.dry_run && archive {
+                return BranchReport::new(branch, Action::WouldArchive);
+            }
+            if options.dry_run {
+                return BranchReport::new(branch, Action::WouldDelete);
+            }
+
+            let result = if archive {
+                git::archive_branch(&branch.name).map(Some)
+            } else {
+                let force = options.force_deletes(&branch.name);
+                git::delete_branch(&branch.name, options.quiet, force).map(|()| None)
+            };
+            match result {
+                Ok(Some(archived_as)) => {
+                    on_event(Event::Archived(branch, &archived_as));
+                    BranchReport::archived(branch, archived_as)
+                }
+                Ok(None) => {
+                    on_event(Event::Deleted(branch));
+                    BranchReport::new(branch, Action::Deleted)
+                }
+                Err(err) => {
+                    on_event(Event::Failed(branch, &err));
+                    BranchReport::failed(branch, &err)
+                }
+            }
+        })
+        .collect()
+}
+
+/// The remote each of `branches`' upstreams is back on, if it is, for `--verify-remote-gone`.
+/// Each remote lists its branches with one `git ls-remote`, the remotes in parallel (see
+/// [`parallel`]).
+fn reappeared_upstreams(branches: &[BranchLine]) -> HashMap<String, Result<Option<String>>> {
+    let upstreams: Vec<_> = branches
+        .iter()
+        .map(|branch| (branch.name.as_str(), git::upstream_config(&branch.name)))
+        .collect();
+    let remotes: BTreeSet<String> = upstreams
+        .iter()
+        .filter_map(|(_, upstream)| upstream.as_ref().ok()?.as_ref())
+        .map(|(remote, _)| remote.clone())
+        .collect();
+    let remotes: Vec<String> = remotes.into_iter().collect();
+    let heads: HashMap<String, Result<HashSet<String>>> =
+        parallel::map(&remotes, |remote| git::remote_heads(remote))
+            .into_iter()
+            .zip(&remotes)
+            .map(|(heads, remote)| (remote.clone(), heads))
+            .collect();
+
+    upstreams
+        .into_iter()
+        .map(|(name, upstream)| {
+            let reappeared = upstream.and_then(|upstream| match upstream {
+                None => Ok(None),
+                Some((remote, upstream)) => match &heads[&remote] {
+                    Ok(heads) => Ok(heads.contains(&upstream).then_some(remote)),
+                    // Every branch on a remote that couldn't be asked fails the same way
+                    Err(err) => Err(anyhow::anyhow!("{err:#}")),
+                },
+            });
+            (name.to_string(), reappeared)
+        })
+        .collect()
+}
+
+/// Whether `err` comes from lacking permission to write a file, as on a read-only mount or a
+/// `.git` owned by another user, so an auxiliary file (a checkpoint, plan, or history) can be
+/// given up on without failing the run
+#[must_use]
+pub fn is_unwritable(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<std::io::Error>())
+        .any(|err| {
+            matches!(
+                err.kind(),
+                std::io::ErrorKind::PermissionDenied | std::io::ErrorKind::ReadOnlyFilesystem
+            )
+        })
+}
+
+/// The environment variables that mark a CI environment, checked in order by [`detect_ci`]:
+/// the generic `CI`, then the ones particular CI services set
+pub const CI_VARIABLES: &[&str] = &[
+    "CI",
+    "GITHUB_ACTIONS",
+    "GITLAB_CI",
+    "CIRCLECI",
+    "TRAVIS",
+    "BUILDKITE",
+    "JENKINS_URL",
+    "TF_BUILD",
+    "TEAMCITY_VERSION",
+    "BITBUCKET_BUILD_NUMBER",
+    "APPVEYOR",
+    "DRONE",
+];
+
+/// The first of [`CI_VARIABLES`] that `var` (e.g. `std::env::var`) says is set, or `None` when
+/// not running in CI. `CI` counts only when `true` or `1`, since some tools set `CI=false`;
+/// the rest count when set to anything but the empty string.
+pub fn detect_ci<F>(mut var: F) -> Option<&'static str>
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    CI_VARIABLES.iter().copied().find(|&name| match var(name) {
+        Some(value) if name == "CI" => value.eq_ignore_ascii_case("true") || value == "1",
+        Some(value) => !value.is_empty(),
+        None => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fixture::{self, git};
+    use crate::parse::parse_gone_branches;
+
+    #[test]
+    fn test_detect_ci() {
+        use std::collections::HashMap;
+
+        let env = |vars: &[(&str, &str)]| {
+            let vars: HashMap<String, String> = vars
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            detect_ci(|name| vars.get(name).cloned())
+        };
+        assert_eq!(env(&[]), None);
+        assert_eq!(env(&[("CI", "true")]), Some("CI"));
+        assert_eq!(env(&[("CI", "1")]), Some("CI"));
+        assert_eq!(env(&[("CI", "false")]), None);
+        assert_eq!(
+            env(&[("CI", "false"), ("GITHUB_ACTIONS", "true")]),
+            Some("GITHUB_ACTIONS")
+        );
+        assert_eq!(env(&[("JENKINS_URL", "")]), None);
+        assert_eq!(
+            env(&[("JENKINS_URL", "https://ci.example.com/")]),
+            Some("JENKINS_URL")
+        );
+    }
+
+    #[test]
+    fn test_apply_upstream_map() {
+        let all_branches = parse::parse_branch_lines(
+            r"
+  user/merged    abc1234 Its origin/merged is gone
+  user/live      def5678 Its origin/live still exists
+  user/stale     aaa1111 [origin/user/stale: gone] Mapped, so the upstream doesn't count
+  other          bbb2222 [origin/other: gone] Not mapped, so the upstream counts
+* user/current   ccc3333 Checked out
+",
+        );
+        let gone = parse::gone_branches(&all_branches);
+        let map = pattern::UpstreamMap::new("^user/(.+)$", "origin/$1").unwrap();
+        let tracking = HashSet::from(["origin/live".to_string(), "origin/stale".to_string()]);
+
+        let names: Vec<String> = apply_upstream_map(&all_branches, &gone, &map, &tracking)
+            .into_iter()
+            .map(|branch| branch.name)
+            .collect();
+        assert_eq!(names, ["user/merged", "other"]);
+    }
+
+    #[test]
+    fn test_is_unwritable_looks_through_context() {
+        use anyhow::Context as _;
+        use std::io::{Error, ErrorKind};
+
+        let denied: Result<()> =
+            Err(Error::from(ErrorKind::PermissionDenied)).context("Failed to write checkpoint");
+        assert!(is_unwritable(&denied.unwrap_err()));
+        let read_only = Error::from(ErrorKind::ReadOnlyFilesystem);
+        assert!(is_unwritable(&read_only.into()));
+        let missing = Error::from(ErrorKind::NotFound);
+        assert!(!is_unwritable(&missing.into()));
+        assert!(!is_unwritable(&anyhow::anyhow!("Permission denied")));
+    }
+
+    #[test]
+    fn test_version_note_lists_caveats_for_the_flags() {
+        let version = GitVersion::new(2, 45, 1);
+        assert_eq!(
+            version_note(version, &Options::default()),
+            "Using git 2.45.1"
+        );
+
+        let options = Options {
+            safe_delete: true,
+            prefer_packed_refs: true,
+            ..Options::default()
+        };
+        assert_eq!(
+            version_note(version, &options),
+            "Using git 2.45.1; git branch -d checks against HEAD, not the base; refs in the \
+             reftable format (git 2.45+) are read with git branch -vv instead"
+        );
+        assert_eq!(
+            version_note(GitVersion::new(2, 44, 0), &options),
+            "Using git 2.44.0; git branch -d checks against HEAD, not the base"
+        );
+    }
+
+    #[test]
+    fn test_merged_upstreamless_branches_ignore_same_named_tags() {
+        let repo = fixture::repo();
+        let dir = repo.path();
+        git(dir, &["branch", "done"]);
+        // A tag on `main` named like a branch with work of its own
+        git(dir, &["tag", "v1"]);
+        git(dir, &["checkout", "-q", "-b", "v1"]);
+        git(dir, &["commit", "-q", "--allow-empty", "-m", "unmerged"]);
+        git(dir, &["checkout", "-q", "main"]);
+
+        let merged = fixture::in_repo(dir, || {
+            let branches = git::local_branches(true).unwrap();
+            find_merged_upstreamless_branches(
+                &branches,
+                "origin",
+                &risk::Base::Ref("main".to_string()),
+            )
+            .unwrap()
+        });
+        let names: Vec<&str> = merged.iter().map(|branch| branch.name.as_str()).collect();
+        assert_eq!(names, ["done"]);
+    }
+
+    #[test]
+    fn test_delete_branches_callback_can_veto() {
+        let repo = fixture::repo();
+        git(repo.path(), &["branch", "feature-1"]);
+        git(repo.path(), &["branch", "keep-me"]);
+        let sha = git(repo.path(), &["rev-parse", "--short", "HEAD"]);
+        let branches = parse_gone_branches(&format!(
+            "
+  feature-1    {sha} [origin/feature-1: gone] Some commit
+  keep-me      {sha} [origin/keep-me: gone] Another commit
+"
+        ))
+        .unwrap();
+        let options = Options {
+            dry_run: true,
+            ..Options::default()
+        };
+
+        let mut considered = Vec::new();
+        let results = fixture::in_repo(repo.path(), || {
+            delete_branches(&branches, &options, |event| match event {
+                Event::Considering(branch) => {
+                    considered.push(branch.name.clone());
+                    if branch.name == "keep-me" {
+                        Decision::Skip
+                    } else {
+                        Decision::Proceed
+                    }
+                }
+                Event::TipMoved(..)
+                | Event::Reappeared(..)
+                | Event::Deleted(_)
+                | Event::Archived(..)
+                | Event::Failed(..) => {
+                    panic!("dry run must not delete")
+                }
+            })
+        });
+
+        assert_eq!(considered, ["feature-1", "keep-me"]);
+        assert_eq!(results[0].action, Action::WouldDelete);
+        assert_eq!(results[1].action, Action::Skipped);
+    }
+
+    /// Makes a fixture repository whose `origin` is a new bare repository at `remote`, with
+    /// `main` pushed there and tracking it
+    fn repo_with_origin(remote: &std::path::Path) -> tempfile::TempDir {
+        git(remote, &["init", "-q", "--bare", "-b", "main"]);
+        let repo = fixture::repo();
+        git(
+            repo.path(),
+            &["remote", "add", "origin", remote.to_str().unwrap()],
+        );
+        git(repo.path(), &["push", "-q", "-u", "origin", "main"]);
+        repo
+    }
+
+    #[test]
+    fn test_delete_branches_skips_upstreams_pushed_again_since_the_fetch() {
+        let remote = tempfile::tempdir().unwrap();
+        let repo = repo_with_origin(remote.path());
+        let dir = repo.path();
+        for branch in ["reappeared", "gone"] {
+            git(dir, &["branch", branch]);
+            git(dir, &["push", "-q", "-u", "origin", branch]);
+            git(dir, &["push", "-q", "origin", "--delete", branch]);
+        }
+        // Pushed to the URL, so the remote-tracking branch stays gone as if fetched before
+        let url = remote.path().to_str().unwrap();
+        git(
+            dir,
+            &["push", "-q", url, "reappeared:refs/heads/reappeared"],
+        );
+
+        let options = Options {
+            verify_remote_gone: true,
+            quiet: true,
+            ..Options::default()
+        };
+        let mut reappeared = Vec::new();
+        let (results, remaining) = fixture::in_repo(dir, || {
+            let branches = git::find_gone_branches(true).unwrap();
+            let results = delete_branches(&branches, &options, |event| {
+                if let Event::Reappeared(branch, remote) = event {
+                    reappeared.push((branch.name.clone(), remote.to_string()));
+                }
+                Decision::Proceed
+            });
+            (results, git::local_branches(true).unwrap())
+        });
+
+        let actions: Vec<(&str, Action)> = results
+            .iter()
+            .map(|result| (result.name.as_str(), result.action))
+            .collect();
+        assert_eq!(
+            actions,
+            [("gone", Action::Deleted), ("reappeared", Action::Skipped)]
+        );
+        assert_eq!(results[1].hold, Some(safety::HoldKind::Reappeared));
+        assert_eq!(
+            reappeared,
+            [("reappeared".to_string(), "origin".to_string())]
+        );
+        let remaining: Vec<&str> = remaining
+            .iter()
+            .map(|branch| branch.name.as_str())
+            .collect();
+        assert_eq!(remaining, ["main", "reappeared"]);
+    }
+
+    #[test]
+    fn test_unrelated_upstreams_are_found_and_related_ones_kept() {
+        let remote = tempfile::tempdir().unwrap();
+        let repo = repo_with_origin(remote.path());
+        let dir = repo.path();
+        for branch in ["rewritten", "related"] {
+            git(dir, &["branch", branch]);
+            git(dir, &["push", "-q", "-u", "origin", branch]);
+        }
+        // `rewritten` is force-pushed with history of its own, `related` just moves on
+        git(dir, &["checkout", "-q", "--orphan", "unrelated"]);
+        git(dir, &["commit", "-q", "--allow-empty", "-m", "unrelated"]);
+        git(dir, &["push", "-q", "-f", "origin", "unrelated:rewritten"]);
+        git(dir, &["checkout", "-q", "-b", "ahead", "main"]);
+        git(dir, &["commit", "-q", "--allow-empty", "-m", "ahead"]);
+        git(dir, &["push", "-q", "origin", "ahead:related"]);
+        git(dir, &["checkout", "-q", "main"]);
+        git(dir, &["branch", "-q", "-D", "unrelated", "ahead"]);
+
+        let unrelated = fixture::in_repo(dir, || {
+            find_unrelated_upstream_branches(&git::local_branches(true).unwrap()).unwrap()
+        });
+        let names: Vec<&str> = unrelated
+            .iter()
+            .map(|branch| branch.name.as_str())
+            .collect();
+        assert_eq!(names, ["rewritten"]);
+    }
+
+    #[test]
+    fn test_delete_branches_skips_branches_deleted_since_listing() {
+        let repo = fixture::repo();
+        let branches =
+            parse_gone_branches("  feature-1    abc1234 [origin/feature-1: gone] Some commit\n")
+                .unwrap();
+
+        let results = fixture::in_repo(repo.path(), || {
+            delete_branches(&branches, &Options::default(), |event| match event {
+                Event::Considering(_) => Decision::Proceed,
+                _ => panic!("unexpected event"),
+            })
+        });
+
+        assert_eq!(results[0].action, Action::Skipped);
+        assert_eq!(results[0].hold, Some(safety::HoldKind::TipMismatch));
+        assert_eq!(
+            results[0].reason.as_deref(),
+            Some("deleted since it was listed")
+        );
+    }
+}
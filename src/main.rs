@@ -1,251 +1,3939 @@
 use anyhow::{Context, Result};
-use clap::Parser;
-use regex::Regex;
-use std::process::{Command, Stdio};
+use clap::builder::BoolishValueParser;
+use clap::{Parser, Subcommand};
+use git_clean_gone::age::{AgeBucket, age_histogram, format_age_histogram};
+use git_clean_gone::backup::{self, BACKUP_FILE, format_backup_entries};
+use git_clean_gone::bugreport::{BugReport, format_bug_report};
+use git_clean_gone::cancel::{CANCELLED_EXIT_CODE, Cancel};
+use git_clean_gone::checkpoint::Checkpoint;
+use git_clean_gone::explain::format_explanations;
+use git_clean_gone::git::{
+    Maintenance, branch_commit_times, branch_config_sections, branch_descriptions, branch_remotes,
+    branch_tip, branch_tips, config_get_all, dangling_remote_head, decode_lines,
+    delete_remote_branch, diffstat, ensure_git_repo, expire_reflogs, fix_remote_head, git_dir,
+    git_editor, git_fetch_prune, git_fetch_prune_in, git_path, local_branches, parse_fetch_args,
+    remote_branch_exists, remove_branch_config, run_fetch_command, run_maintenance,
+    show_remaining_branches, submodules, unborn_branch, unset_upstream_config, verify_connectivity,
+    work_tree_root,
+};
+use git_clean_gone::grouping::{GroupBy, format_groups, group_branches};
+use git_clean_gone::history::{self, HISTORY_FILE, RunSummary, format_history};
+use git_clean_gone::hooks::{POST_MERGE, install_post_merge_hook, run_pre_clean_hook_on};
+use git_clean_gone::marker::{self, MARKER_FILE, cleaned_within};
+use git_clean_gone::notify;
+use git_clean_gone::parallel;
+use git_clean_gone::parse::{BranchLine, gone_branches, parse_branch_lines, simulate_gone};
+use git_clean_gone::pattern::{
+    BranchPattern, UpstreamMap, global_protect_path, load_pattern_list, matches_any,
+    user_config_dir,
+};
+use git_clean_gone::pipeline::{self, Confirmed, Enumerated, Filtered, Phases, State};
+use git_clean_gone::plan::{
+    Drift, Plan, expected_sha_mismatch, format_drift, plan_hash, read_branch_list,
+    read_expected_shas,
+};
+use git_clean_gone::progress::{self, Progress};
+use git_clean_gone::prompt::{
+    PromptStyle, TimedInput, confirm, confirm_with_style, confirm_word, edit_in_editor,
+    format_edit_list, select_from_edit_list,
+};
+use git_clean_gone::refs::prune_empty_dirs;
+use git_clean_gone::remotes::{
+    Fetched, RemoteCheck, VanishedRemote, branches_configured_for, fetched_remotes,
+    find_orphaned_tracking, find_vanished_remotes, format_orphaned_tracking, format_remote_checks,
+    format_vanished_remotes, remote_checks,
+};
+use git_clean_gone::report::{
+    Action, BranchReport, OutputFormat, RemoteDeletion, RemoteOutcome, Report, group_digits,
+    truncate_name, write_report,
+};
+use git_clean_gone::risk::{
+    Assessment, Base, Risk, SUMMARY_GLYPH, SortOrder, apply_merged_upstream, assess_against_bases,
+    assess_against_bases_in_graph, assess_branches, assess_branches_in_graph,
+    format_compact_summary, format_identical_tips, format_risk_ranking, format_risk_summary,
+    format_shared_upstreams, format_upstreamless_summary, graph_unique_commit_count,
+    resolve_fallback_base, sort_by_unique_commits,
+};
+use git_clean_gone::rules::{RULES_FILE, Rules};
+use git_clean_gone::safety::{
+    DEFAULT_PROTECTED, Held, HoldKind, Since, apply_safety_checks, existing_elsewhere_warnings,
+    format_described, hold_unlisted_risky, unix_now,
+};
+use git_clean_gone::script::{format_script, script_commands, write_script};
+use git_clean_gone::tags::{delete_tag, find_gone_tags};
+use git_clean_gone::tips::{self, ALIAS_TIP, TIPS_MARKER};
+use git_clean_gone::tracking::{
+    UpstreamState, delete_ref, find_stale_pr_refs, find_stale_tracking_refs,
+    format_upstream_states, list_refs_under, list_remotes, upstream_state,
+};
+use git_clean_gone::watch::Seen;
+use git_clean_gone::worktrees::{
+    blocker as worktree_blocker, list_worktrees, orphan_worktrees, remove_worktree,
+};
+use git_clean_gone::{
+    Decision, Event, Options, check_git, clean_with, delete_branches, detect_ci,
+    find_gone_branches, is_unwritable, list_local_branches,
+};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime};
 
 #[derive(Parser, Debug)]
+#[allow(clippy::struct_excessive_bools)] // CLI flags are naturally booleans
+#[allow(clippy::struct_field_names)] // Field names mirror the flag names
 #[command(name = "git-clean-gone")]
 #[command(about = "Clean up local Git branches that have been deleted on the remote", long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
     /// Perform a dry run without actually deleting branches
-    #[arg(short, long)]
+    #[arg(
+        short,
+        long,
+        env = "GIT_CLEAN_GONE_DRY_RUN",
+        value_parser = BoolishValueParser::new()
+    )]
     dry_run: bool,
 
-    /// Verbose output
-    #[arg(short, long)]
-    verbose: bool,
+    /// Verbose output
+    #[arg(
+        short,
+        long,
+        env = "GIT_CLEAN_GONE_VERBOSE",
+        value_parser = BoolishValueParser::new()
+    )]
+    verbose: bool,
+
+    /// Output format; machine-readable formats print only the final report to stdout
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Human,
+        env = "GIT_CLEAN_GONE_FORMAT"
+    )]
+    format: OutputFormat,
+
+    /// Exit with status 1 if any gone branches were found, like `git diff --exit-code`
+    #[arg(long, env = "GIT_CLEAN_GONE_EXIT_CODE", value_parser = BoolishValueParser::new())]
+    exit_code: bool,
+
+    /// Only check: do a dry run (its report still goes to stdout), then exit with status 4 if
+    /// it would delete or archive any branch, or 0 if not
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "edit", "tags", "remote_tracking", "prune_stale_pr_refs", "removed_remote"
+        ],
+        env = "GIT_CLEAN_GONE_CHECK",
+        value_parser = BoolishValueParser::new()
+    )]
+    check: bool,
+
+    /// Print the JSON report on a single line instead of pretty-printed (with --format json or
+    /// --output-format json)
+    #[arg(long, env = "GIT_CLEAN_GONE_JSON_COMPACT", value_parser = BoolishValueParser::new())]
+    json_compact: bool,
+
+    /// Also write the report in the chosen --format to FILE (plain text for the human format)
+    #[arg(long, value_name = "FILE", env = "GIT_CLEAN_GONE_OUTPUT")]
+    output: Option<PathBuf>,
+
+    /// Write the --output file in this format instead of --format's, e.g. JSON to the file
+    /// while the terminal gets the human-readable output
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        requires = "output",
+        env = "GIT_CLEAN_GONE_OUTPUT_FORMAT"
+    )]
+    output_format: Option<OutputFormat>,
+
+    /// Write the report only to the --output file, not to stdout
+    #[arg(
+        long,
+        requires = "output",
+        env = "GIT_CLEAN_GONE_OUTPUT_ONLY",
+        value_parser = BoolishValueParser::new()
+    )]
+    output_only: bool,
+
+    /// TESTING AID: treat BRANCH as gone from its remote regardless of its real upstream, e.g.
+    /// in CI fixtures (repeatable)
+    #[arg(
+        long,
+        value_name = "BRANCH",
+        value_delimiter = ',',
+        conflicts_with = "plan_in",
+        env = "GIT_CLEAN_GONE_SIMULATE_REMOTE_DELETION"
+    )]
+    simulate_remote_deletion: Vec<String>,
+
+    /// Abort cleanly once the run has taken this long (e.g. "120s"), finishing the current
+    /// branch first and exiting with code 124
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = humantime::parse_duration,
+        env = "GIT_CLEAN_GONE_MAX_RUNTIME"
+    )]
+    max_runtime: Option<Duration>,
+
+    /// When the run started, for --max-runtime
+    #[arg(skip = Instant::now())]
+    started: Instant,
+
+    /// Branch names known to be merged upstream (e.g. exported from the hosting API), one per
+    /// line; with it, gone branches with unmerged commits that aren't listed are kept
+    #[arg(long, value_name = "FILE", env = "GIT_CLEAN_GONE_MERGED_PRS_FILE")]
+    merged_prs_file: Option<PathBuf>,
+
+    /// The names read from --merged-prs-file
+    #[arg(skip)]
+    merged_prs: HashSet<String>,
+
+    /// Before confirming, show a diffstat of what each risky branch's unique commits changed
+    /// since it forked from the base (one extra git call per risky branch)
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_SHOW_DIFFSTAT",
+        value_parser = BoolishValueParser::new()
+    )]
+    show_diffstat: bool,
+
+    /// Shorten branch names longer than N characters in the human-readable listings, ending
+    /// them with "…" (display only: branches are still deleted by their full names, and the
+    /// machine-readable formats always carry them in full); at least 1
+    #[arg(long, value_name = "N", env = "GIT_CLEAN_GONE_TRUNCATE_NAMES")]
+    truncate_names: Option<NonZeroUsize>,
+
+    /// Map local branch names matching REGEX to the remote-tracking branch REPLACEMENT names
+    /// (with $1, ${name}, ... for the capture groups), and treat a mapped branch as gone when
+    /// that remote branch doesn't exist, e.g. '^user/(.+)$' 'origin/$1' (space-separated in the
+    /// environment variable)
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["REGEX", "REPLACEMENT"],
+        conflicts_with_all = ["gone_command", "stdin", "plan_in"],
+        env = "GIT_CLEAN_GONE_UPSTREAM_MAP"
+    )]
+    upstream_map: Vec<String>,
+
+    /// Clean up after removing REMOTE (e.g. an abandoned fork): delete the local branches still
+    /// configured to track it, then the refs/remotes/REMOTE/* refs and branch.*.remote=REMOTE
+    /// config left behind, confirming each step
+    #[arg(
+        long,
+        value_name = "REMOTE",
+        conflicts_with_all = [
+            "remote", "format", "gone_command", "upstream_map", "stdin", "plan_in", "tags",
+            "remote_tracking", "branch_type", "prune_stale_pr_refs"
+        ],
+        env = "GIT_CLEAN_GONE_REMOVED_REMOTE"
+    )]
+    removed_remote: Option<String>,
+
+    /// After cleaning up, list the linked worktrees whose branch is gone (git won't delete a
+    /// branch checked out in one), with the command to remove each
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_INCLUDE_ORPHAN_WORKTREES",
+        value_parser = BoolishValueParser::new()
+    )]
+    include_orphan_worktrees: bool,
+
+    /// Like --include-orphan-worktrees, but remove the listed worktrees (after asking, unless
+    /// --yes), except locked ones and ones with uncommitted changes or untracked files
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_REMOVE_ORPHAN_WORKTREES",
+        value_parser = BoolishValueParser::new()
+    )]
+    remove_orphan_worktrees: bool,
+
+    /// Skip listing the remaining branches at the end
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_NO_SHOW_REMAINING",
+        value_parser = BoolishValueParser::new()
+    )]
+    no_show_remaining: bool,
+
+    /// Include remote-tracking branches in the remaining branch listing
+    #[arg(
+        long,
+        conflicts_with = "no_show_remaining",
+        env = "GIT_CLEAN_GONE_SHOW_REMOTE",
+        value_parser = BoolishValueParser::new()
+    )]
+    show_remote: bool,
+
+    /// Extra arguments for `git fetch -ap`, e.g. "--no-tags origin" (shell-style quoting)
+    #[arg(
+        long,
+        value_name = "ARGS",
+        allow_hyphen_values = true,
+        env = "GIT_CLEAN_GONE_FETCH_ARGS"
+    )]
+    fetch_args: Option<String>,
+
+    /// Shell command to run instead of `git fetch -ap`; it must prune deleted remote branches itself
+    #[arg(
+        long,
+        value_name = "CMD",
+        conflicts_with = "fetch_args",
+        env = "GIT_CLEAN_GONE_FETCH_COMMAND"
+    )]
+    fetch_command: Option<String>,
+
+    /// Write the branches a dry run would delete, with their tip SHAs, to a plan file
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "dry_run",
+        env = "GIT_CLEAN_GONE_PLAN_OUT"
+    )]
+    plan_out: Option<PathBuf>,
+
+    /// Write the git commands a dry run would otherwise leave to a real one (deletions,
+    /// archive renames, --delete-remote pushes) to a shell script, for running by hand
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "dry_run",
+        env = "GIT_CLEAN_GONE_EMIT_SCRIPT"
+    )]
+    emit_script: Option<PathBuf>,
+
+    /// Delete exactly the branches in a plan file, refusing any whose tip has since changed
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["plan_out", "tags"],
+        env = "GIT_CLEAN_GONE_PLAN_IN"
+    )]
+    plan_in: Option<PathBuf>,
+
+    /// Only delete branches whose tip matches the SHA listed for them in this file, one
+    /// `<branch> <sha>` per line; any other branch is skipped with a warning
+    #[arg(long, value_name = "FILE", env = "GIT_CLEAN_GONE_EXPECT_SHAS")]
+    expect_shas: Option<PathBuf>,
+
+    /// Refuse to delete anything unless this run's plan hash (printed by every run, including
+    /// dry runs) is HASH, proving it targets the same branches at the same tips as the run
+    /// that printed it
+    #[arg(long, value_name = "HASH", env = "GIT_CLEAN_GONE_EXPECT_PLAN_HASH")]
+    expect_plan_hash: Option<String>,
+
+    /// Compare gone branches against these refs (e.g. main,release/2.x) instead of the remote's
+    /// default branch; a branch is safe if it's merged into at least one of them
+    #[arg(
+        long,
+        value_name = "REF",
+        value_delimiter = ',',
+        env = "GIT_CLEAN_GONE_MERGED_BASE"
+    )]
+    merged_base: Vec<String>,
+
+    /// Check which gone branches are merged by loading the commit graph once, instead of
+    /// running git once per branch (faster with many gone branches)
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_COMMIT_GRAPH_REACHABLE",
+        value_parser = BoolishValueParser::new()
+    )]
+    commit_graph_reachable: bool,
+
+    /// Run at most N git processes at once where git runs for several remotes, branches, or
+    /// submodules in parallel (default: the available parallelism)
+    #[arg(long, value_name = "N", env = "GIT_CLEAN_GONE_MAX_PARALLEL_GIT")]
+    max_parallel_git: Option<NonZeroUsize>,
+
+    /// Keep the N most recently committed gone branches instead of deleting them
+    #[arg(long, value_name = "N", env = "GIT_CLEAN_GONE_KEEP_RECENT")]
+    keep_recent: Option<usize>,
+
+    /// Delete at most N gone branches this run; the rest are kept (and reported) for later runs
+    #[arg(long, value_name = "N", env = "GIT_CLEAN_GONE_LIMIT")]
+    limit: Option<usize>,
+
+    /// Order the gone branches by name, or by the unique commits deleting them would lose (most
+    /// first, with the count next to each); with --limit, the riskiest are taken first
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = SortOrder::Name,
+        env = "GIT_CLEAN_GONE_SORT"
+    )]
+    sort: SortOrder,
+
+    /// Also list the gone branches grouped, with a header per group: by the remote their
+    /// upstream was on, by the prefix of their name, or by the age of their last commit
+    #[arg(long, value_enum, env = "GIT_CLEAN_GONE_GROUP_BY")]
+    group_by: Option<GroupBy>,
+
+    /// Shorthand for --group-by remote
+    #[arg(
+        long,
+        conflicts_with = "group_by",
+        env = "GIT_CLEAN_GONE_GROUP_OUTPUT_BY_REMOTE",
+        value_parser = BoolishValueParser::new()
+    )]
+    group_output_by_remote: bool,
+
+    /// Warn prominently when a dry run would delete more than N branches (0 to never warn)
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 50,
+        env = "GIT_CLEAN_GONE_WARN_LARGE"
+    )]
+    warn_large: usize,
+
+    /// Keep gone branches whose name still exists as a branch on another remote, instead of
+    /// only warning about them
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_SKIP_IF_EXISTS_ELSEWHERE",
+        value_parser = BoolishValueParser::new()
+    )]
+    skip_if_exists_elsewhere: bool,
+
+    /// Keep gone branches whose reflog shows activity within this long (e.g. "1h"), even if
+    /// their commits look old
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = humantime::parse_duration,
+        env = "GIT_CLEAN_GONE_EXCLUDE_RECENT_ACTIVITY"
+    )]
+    exclude_recent_activity: Option<Duration>,
+
+    /// Only delete gone branches that have been gone at least this long (e.g. "7d"), per their
+    /// remote-tracking ref's reflog; branches without one aren't held back, with a note
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = humantime::parse_duration,
+        env = "GIT_CLEAN_GONE_GONE_FOR"
+    )]
+    gone_for: Option<Duration>,
+
+    /// Do nothing if the last successful cleanup (not a dry run) finished within this long
+    /// (e.g. "1h"), so frequent calls, such as from a shell prompt hook, stay cheap
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = humantime::parse_duration,
+        env = "GIT_CLEAN_GONE_SKIP_IF_CLEANED_WITHIN"
+    )]
+    skip_if_cleaned_within: Option<Duration>,
+
+    /// Keep gone branches whose tip is an ancestor of any tag (e.g. part of a release)
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_PROTECT_TAGGED_ANCESTORS",
+        value_parser = BoolishValueParser::new()
+    )]
+    protect_tagged_ancestors: bool,
+
+    /// After deleting, remove directories under refs/heads left empty (e.g. refs/heads/feature/)
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_PRUNE_EMPTY_DIRS",
+        value_parser = BoolishValueParser::new()
+    )]
+    prune_empty_dirs: bool,
+
+    /// Do nothing (and exit 0, with a note) unless running in CI, as detected from `CI=true`
+    /// or a CI service's own environment variable, e.g. for a script meant only for CI
+    #[arg(
+        long,
+        conflicts_with = "only_if_not_ci",
+        env = "GIT_CLEAN_GONE_ONLY_IF_CI",
+        value_parser = BoolishValueParser::new()
+    )]
+    only_if_ci: bool,
+
+    /// Do nothing (and exit 0, with a note) when running in CI, e.g. for a developer-only
+    /// script that shouldn't run in pipelines
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_ONLY_IF_NOT_CI",
+        value_parser = BoolishValueParser::new()
+    )]
+    only_if_not_ci: bool,
+
+    /// Also clean up the gone branches in each initialized submodule, recursively, each with
+    /// its own confirmation, then list what happened per repository
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "format", "tags", "remote_tracking", "plan_in", "plan_out", "stdin", "expect_shas",
+            "expect_plan_hash", "emit_script", "output",
+        ],
+        env = "GIT_CLEAN_GONE_RECURSE_SUBMODULES",
+        value_parser = BoolishValueParser::new()
+    )]
+    recurse_submodules: bool,
+
+    /// After deleting, remove any `branch.<name>` config sections the deleted branches left
+    /// behind
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_PRUNE_CONFIG_BRANCHES",
+        value_parser = BoolishValueParser::new()
+    )]
+    prune_config_branches: bool,
+
+    /// After deleting, expire every reflog right away (git reflog expire --expire=now --all),
+    /// so `git gc` can reclaim the deleted branches' commits; this defeats restoring them.
+    /// Asks you to type `expire` first, which --yes doesn't answer
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_REFLOG_EXPIRE",
+        value_parser = BoolishValueParser::new()
+    )]
+    reflog_expire: bool,
+
+    /// Skip --reflog-expire's typed confirmation, e.g. in scripts
+    #[arg(
+        long,
+        requires = "reflog_expire",
+        env = "GIT_CLEAN_GONE_REFLOG_EXPIRE_YES",
+        value_parser = BoolishValueParser::new()
+    )]
+    reflog_expire_yes: bool,
+
+    /// After deleting, run `git maintenance` to keep the repository healthy: `gc` (the default
+    /// with no value) or the cheaper `incremental` tasks. Needs git 2.29 (2.30 for incremental)
+    #[arg(
+        long,
+        value_enum,
+        value_name = "TASKS",
+        num_args = 0..=1,
+        default_missing_value = "gc",
+        env = "GIT_CLEAN_GONE_MAINTENANCE"
+    )]
+    maintenance: Option<Maintenance>,
+
+    /// After deleting, check the object database with `git fsck --connectivity-only`, failing
+    /// the run if it reports problems (off by default, as it reads every object)
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_VERIFY_CLEAN_AFTER",
+        value_parser = BoolishValueParser::new()
+    )]
+    verify_clean_after: bool,
+
+    /// Always run `git branch -vv` afresh, instead of reusing the previous run's listing while
+    /// no ref has changed
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_NO_CACHE",
+        value_parser = BoolishValueParser::new()
+    )]
+    no_cache: bool,
+
+    /// How to ask before deleting: a [y/N] question, a numbered menu, or typing the number of
+    /// branches (or tags or refs) about to be deleted [default: simple, or count with
+    /// --paranoid]
+    #[arg(long, value_enum, env = "GIT_CLEAN_GONE_PROMPT_STYLE")]
+    prompt_style: Option<PromptStyle>,
+
+    /// Stop waiting for an answer at a prompt after this many seconds and take the default
+    /// (no), for runs that only sometimes have someone at the terminal. --yes skips the prompts
+    /// altogether
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        value_parser = |text: &str| text.parse().map(Duration::from_secs),
+        env = "GIT_CLEAN_GONE_CONFIRM_TIMEOUT"
+    )]
+    confirm_timeout: Option<Duration>,
+
+    /// Use the branch listing as it is, without fetching and pruning first
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_NO_FETCH",
+        value_parser = BoolishValueParser::new()
+    )]
+    no_fetch: bool,
+
+    /// Only print a one-line summary for shell prompts, e.g. `⎇ 3 gone (2 safe, 1 risky)`, or
+    /// nothing if no branch is gone, and exit without deleting anything
+    #[arg(
+        long,
+        conflicts_with_all = ["format", "tags"],
+        env = "GIT_CLEAN_GONE_COMPACT_SUMMARY_LINE",
+        value_parser = BoolishValueParser::new()
+    )]
+    compact_summary_line: bool,
+
+    /// The glyph --compact-summary-line starts with
+    #[arg(
+        long,
+        value_name = "GLYPH",
+        default_value = SUMMARY_GLYPH,
+        env = "GIT_CLEAN_GONE_SUMMARY_GLYPH"
+    )]
+    summary_glyph: String,
+
+    /// When to color --compact-summary-line's glyph: green when every gone branch is safe,
+    /// yellow otherwise
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ColorChoice::Auto,
+        env = "GIT_CLEAN_GONE_COLOR"
+    )]
+    color: ColorChoice,
+
+    /// Don't show the one-time tip on running this as `git clean-gone`
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_NO_TIPS",
+        value_parser = BoolishValueParser::new()
+    )]
+    no_tips: bool,
+
+    /// Don't append this run's summary to the local history (see the `history` subcommand)
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_NO_HISTORY",
+        value_parser = BoolishValueParser::new()
+    )]
+    no_history: bool,
+
+    /// Append a `git branch <name> <sha>` line for each deleted branch to FILE, for restoring
+    /// them long after the run
+    #[arg(long, value_name = "FILE", env = "GIT_CLEAN_GONE_BACKUP_LOG")]
+    backup_log: Option<PathBuf>,
+
+    /// Show a desktop notification with the deleted and kept counts when the run finishes
+    /// (in `watch`, whenever a check finds newly gone branches)
+    #[arg(long, env = "GIT_CLEAN_GONE_NOTIFY", value_parser = BoolishValueParser::new())]
+    notify: bool,
+
+    /// Record deletion progress in .git/git-clean-gone/checkpoint.json, so an interrupted run
+    /// can be reported on and resumed
+    #[arg(long, env = "GIT_CLEAN_GONE_CHECKPOINT", value_parser = BoolishValueParser::new())]
+    checkpoint: bool,
+
+    /// Rename gone branches to archive/<branch> instead of deleting them
+    #[arg(
+        long,
+        conflicts_with = "tags",
+        env = "GIT_CLEAN_GONE_RENAME_TO_ARCHIVE",
+        value_parser = BoolishValueParser::new()
+    )]
+    rename_to_archive: bool,
+
+    /// List branches by reading packed-refs, loose refs, and .git/config directly instead of
+    /// running git branch -vv (faster with many refs); falls back to git if that's unreliable
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_PREFER_PACKED_REFS",
+        value_parser = BoolishValueParser::new()
+    )]
+    prefer_packed_refs: bool,
+
+    /// Also delete local branches that never had an upstream, if they're fully merged into the
+    /// remote's default branch
+    #[arg(
+        long,
+        conflicts_with_all = ["stdin", "plan_in"],
+        env = "GIT_CLEAN_GONE_INCLUDE_NO_UPSTREAM",
+        value_parser = BoolishValueParser::new()
+    )]
+    include_no_upstream: bool,
+
+    /// Only delete gone branches whose commits are all merged into the remote's default branch,
+    /// and delete them with `git branch -d` so git double-checks each one
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_PRUNE_MERGED_REMOTE_TRACKING",
+        value_parser = BoolishValueParser::new()
+    )]
+    prune_merged_remote_tracking: bool,
+
+    /// The recommended everyday mode: --prune-merged-remote-tracking, protecting main, master,
+    /// develop, release/*, and the cleanGone.protect patterns from git config
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_SAFE",
+        value_parser = BoolishValueParser::new()
+    )]
+    safe: bool,
+
+    /// The strongest guard against losing work: everything --safe does, plus
+    /// --protect-unpushed, --prompt-style count, and a --backup-log in
+    /// .git/git-clean-gone-backup.log (--yes, --prompt-style, and --backup-log still override
+    /// those)
+    #[arg(
+        long,
+        conflicts_with = "yolo",
+        env = "GIT_CLEAN_GONE_PARANOID",
+        value_parser = BoolishValueParser::new()
+    )]
+    paranoid: bool,
+
+    /// For those who know what they're doing: --yes, --force with --auto-safe, and no
+    /// protection from the global protect file (--protect and --safe still protect)
+    #[arg(
+        long,
+        conflicts_with = "edit",
+        env = "GIT_CLEAN_GONE_YOLO",
+        value_parser = BoolishValueParser::new()
+    )]
+    yolo: bool,
+
+    /// Keep gone branches with commits that aren't on any remote-tracking branch, of any
+    /// remote, even if they're merged (e.g. squash-merged work never pushed anywhere else)
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_PROTECT_UNPUSHED",
+        value_parser = BoolishValueParser::new()
+    )]
+    protect_unpushed: bool,
+
+    /// Keep candidates that `git branch -vv` shows as ahead of their upstream (e.g.
+    /// `[origin/x: ahead 2, gone]`), reporting the count. Cheaper than --protect-unpushed, since
+    /// it only reads the listing
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_PROTECT_AHEAD",
+        value_parser = BoolishValueParser::new()
+    )]
+    protect_ahead: bool,
+
+    /// Keep gone branches with a description (set with `git branch --edit-description`), on
+    /// the theory that describing a branch was deliberate
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_PROTECT_DESCRIBED",
+        value_parser = BoolishValueParser::new()
+    )]
+    protect_described: bool,
+
+    /// Compare against the current branch, else the remote's default branch, else main or
+    /// master, else the only local branch that isn't gone, noting which one was picked
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_REMOTE_HEAD_FALLBACK",
+        value_parser = BoolishValueParser::new()
+    )]
+    remote_head_fallback: bool,
+
+    /// Only delete gone branches with no commits that another ref (branch, tag, or
+    /// remote-tracking branch) doesn't reach, so nothing is lost whatever the base, and delete
+    /// them with `git branch -d`
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_AUTO_SAFE",
+        value_parser = BoolishValueParser::new()
+    )]
+    auto_safe: bool,
+
+    /// With --auto-safe, also delete the gone branches with unique commits (with
+    /// `git branch -D`), reporting how many each has
+    #[arg(
+        long,
+        requires = "auto_safe",
+        env = "GIT_CLEAN_GONE_FORCE",
+        value_parser = BoolishValueParser::new()
+    )]
+    force: bool,
+
+    /// Pick the branches to delete by editing the list in your editor (as for git commit
+    /// messages): remove a line to keep that branch
+    #[arg(
+        long,
+        conflicts_with_all = ["dry_run", "yes"],
+        env = "GIT_CLEAN_GONE_EDIT",
+        value_parser = BoolishValueParser::new()
+    )]
+    edit: bool,
+
+    /// With --edit, list the branches to keep instead: remove a line to delete that branch
+    #[arg(
+        long,
+        requires = "edit",
+        env = "GIT_CLEAN_GONE_SELECT_INVERT",
+        value_parser = BoolishValueParser::new()
+    )]
+    select_invert: bool,
+
+    /// Exit with code 3, deleting nothing, if any gone branch has unmerged commits
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_FAIL_ON_UNMERGED",
+        value_parser = BoolishValueParser::new()
+    )]
+    fail_on_unmerged: bool,
+
+    /// Print, for every local branch, the facts behind its verdict and the verdict itself
+    /// (e.g. "feature-1: gone upstream origin/feature-1, merged into origin/HEAD, unique
+    /// commits 0 -> DELETE"); implies --dry-run, so nothing is deleted
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_EXPLAIN",
+        value_parser = BoolishValueParser::new()
+    )]
+    explain: bool,
+
+    /// Also list the groups of gone branches that point at the same commit
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_DEDUPE_IDENTICAL_TIPS",
+        value_parser = BoolishValueParser::new()
+    )]
+    dedupe_identical_tips: bool,
+
+    /// Warn about gone branches that track the same upstream, before deleting them, as one of
+    /// them may be a mistaken duplicate
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_WARN_SHARED_UPSTREAM",
+        value_parser = BoolishValueParser::new()
+    )]
+    warn_shared_upstream: bool,
+
+    /// Also show how many gone branches last saw a commit under a week, 1-4 weeks, 1-6 months,
+    /// and over 6 months ago (included in the JSON report)
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_REPORT_AGE_HISTOGRAM",
+        value_parser = BoolishValueParser::new()
+    )]
+    report_age_histogram: bool,
+
+    /// Also show which remotes were fetched and pruned, and which remote decided each local
+    /// branch's gone state, for working out why a branch wasn't found gone (shown with
+    /// --verbose too, and always included in the JSON report)
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_LIST_REMOTES_CHECKED",
+        value_parser = BoolishValueParser::new()
+    )]
+    list_remotes_checked: bool,
+
+    /// Also show what's still known about each gone branch's upstream: how far the branch is
+    /// behind it and its last known tip, when a remote-tracking ref or reflog remains
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_DIFF_UPSTREAM",
+        value_parser = BoolishValueParser::new()
+    )]
+    diff_upstream: bool,
+
+    /// Keep the gone branch most recently merged into the default branch by a merge commit
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_KEEP_LAST_MERGED",
+        value_parser = BoolishValueParser::new()
+    )]
+    keep_last_merged: bool,
+
+    /// List every gone branch that wasn't deleted with the reason it was kept, and give each one a
+    /// reason in machine-readable reports
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_REPORT_BRANCHES_KEPT_REASONS",
+        value_parser = BoolishValueParser::new()
+    )]
+    report_branches_kept_reasons: bool,
+
+    /// Also delete each locally deleted branch on this remote (e.g. your fork), after confirming
+    #[arg(
+        long,
+        value_name = "REMOTE",
+        conflicts_with = "tags",
+        env = "GIT_CLEAN_GONE_DELETE_REMOTE"
+    )]
+    delete_remote: Option<String>,
+
+    /// Read `git branch -vv` output from stdin instead of fetching and listing branches
+    #[arg(
+        long,
+        conflicts_with_all = ["plan_in", "fetch_args", "fetch_command", "tags", "fix_head"],
+        env = "GIT_CLEAN_GONE_STDIN",
+        value_parser = BoolishValueParser::new()
+    )]
+    stdin: bool,
+
+    /// Clean up local tags that no longer exist on the remote instead of branches
+    #[arg(
+        long,
+        conflicts_with = "format",
+        env = "GIT_CLEAN_GONE_TAGS",
+        value_parser = BoolishValueParser::new()
+    )]
+    tags: bool,
+
+    /// Which branches to clean up: local branches whose upstream is gone, remote-tracking refs
+    /// (refs/remotes/*) whose branch is gone from the remote, or both in turn, each with its
+    /// own confirmation and counts
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = BranchType::Local,
+        conflicts_with = "tags",
+        env = "GIT_CLEAN_GONE_BRANCH_TYPE"
+    )]
+    branch_type: BranchType,
+
+    /// Shorthand for --branch-type tracking
+    #[arg(
+        long,
+        conflicts_with_all = ["format", "tags", "plan_in", "stdin", "branch_type"],
+        env = "GIT_CLEAN_GONE_REMOTE_TRACKING",
+        value_parser = BoolishValueParser::new()
+    )]
+    remote_tracking: bool,
+
+    /// Delete refs under NAMESPACE (e.g. refs/remotes/origin/pr/) whose pull or merge request
+    /// ref (refs/pull/<N>/* or refs/merge-requests/<N>/*) is gone from --remote
+    #[arg(
+        long,
+        value_name = "NAMESPACE",
+        conflicts_with_all = ["format", "tags", "remote_tracking", "plan_in", "stdin"],
+        env = "GIT_CLEAN_GONE_PRUNE_STALE_PR_REFS"
+    )]
+    prune_stale_pr_refs: Option<String>,
+
+    /// Remote to check [default: origin]: its HEAD is validated and used as the merge base, and
+    /// `--tags` compares against it. When given, only branches tracking it count as gone
+    #[arg(long, env = "GIT_CLEAN_GONE_REMOTE")]
+    remote: Option<String>,
+
+    /// Only clean gone branches that branched off after this point, a commit (branches that
+    /// contain it) or a date (e.g. 2026-01-31, or 30days for 30 days ago, compared with when
+    /// the branch forked from the remote's default branch); older ones are kept and reported
+    #[arg(
+        long,
+        value_name = "REF_OR_DATE",
+        value_parser = |text: &str| Ok::<_, String>(Since::parse(text)),
+        env = "GIT_CLEAN_GONE_SINCE"
+    )]
+    since: Option<Since>,
+
+    /// Decide which branches are gone with this shell command instead of their upstream
+    /// tracking state: it's run once per local branch, with the branch name as its last
+    /// argument, and exiting 0 marks the branch gone
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        conflicts_with_all = ["stdin", "plan_in", "include_no_upstream", "verify_remote_gone"],
+        env = "GIT_CLEAN_GONE_GONE_COMMAND"
+    )]
+    gone_command: Option<String>,
+
+    /// Take the candidates from this shell command's output instead of the gone branches: it's
+    /// run once, and prints the branch names to consider, one per line. The names still go
+    /// through the protections and safety checks, and ones that don't exist locally are reported
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        conflicts_with_all = [
+            "stdin", "plan_in", "gone_command", "upstream_map", "removed_remote",
+            "include_no_upstream", "treat_detached_upstream_as_gone", "verify_remote_gone", "tags",
+            "remote_tracking", "branch_type"
+        ],
+        env = "GIT_CLEAN_GONE_BRANCHES_FROM_COMMAND"
+    )]
+    branches_from_command: Option<String>,
+
+    /// Keep gone branches that still have an open pull request, as decided by this shell
+    /// command: it's run once per branch about to be deleted, with the branch name as its last
+    /// argument, and exiting 0 means the branch has an open PR (e.g. a script around
+    /// `gh pr list --state open --head <branch>`)
+    #[arg(long, value_name = "COMMAND", env = "GIT_CLEAN_GONE_OPEN_PR_COMMAND")]
+    open_pr_command: Option<String>,
+
+    /// Also treat a branch as gone when its upstream still exists but shares no history with
+    /// it (the remote branch was rewritten with unrelated history). Such branches are listed
+    /// separately before anything is deleted
+    #[arg(
+        long,
+        conflicts_with_all = ["stdin", "plan_in", "gone_command", "verify_remote_gone"],
+        env = "GIT_CLEAN_GONE_TREAT_DETACHED_UPSTREAM_AS_GONE",
+        value_parser = BoolishValueParser::new()
+    )]
+    treat_detached_upstream_as_gone: bool,
+
+    /// Right before deleting, check with one `git ls-remote` per remote that the upstreams are
+    /// still gone, and keep the branches whose upstream was re-created since the fetch
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_VERIFY_REMOTE_GONE",
+        value_parser = BoolishValueParser::new()
+    )]
+    verify_remote_gone: bool,
+
+    /// Only delete gone branches whose upstream starts with this prefix (e.g. origin/team-a/);
+    /// the others are kept and reported
+    #[arg(
+        long,
+        value_name = "PREFIX",
+        env = "GIT_CLEAN_GONE_MATCH_UPSTREAM_PREFIX"
+    )]
+    match_upstream_prefix: Option<String>,
+
+    /// Remote to fetch and prune, instead of `git fetch`'s default; independent of --remote
+    #[arg(long, value_name = "NAME", env = "GIT_CLEAN_GONE_FETCH_REMOTE")]
+    fetch_remote: Option<String>,
+
+    /// Report branches whose configured remote no longer exists (git shows them with no
+    /// upstream), and unset their leftover `branch.<name>.remote` and `.merge`
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_CLEAN_ORPHAN_TRACKING",
+        value_parser = BoolishValueParser::new()
+    )]
+    clean_orphan_tracking: bool,
+
+    /// Re-resolve a dangling remote HEAD with `git remote set-head <remote> -a`
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_FIX_HEAD",
+        value_parser = BoolishValueParser::new()
+    )]
+    fix_head: bool,
+
+    /// Don't ask for confirmation before deleting
+    #[arg(
+        short,
+        long,
+        global = true,
+        env = "GIT_CLEAN_GONE_YES",
+        value_parser = BoolishValueParser::new()
+    )]
+    yes: bool,
+
+    /// Never delete branches matching this glob (e.g. 'release/*'); repeatable
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        env = "GIT_CLEAN_GONE_PROTECT",
+        value_delimiter = ','
+    )]
+    protect: Vec<BranchPattern>,
+
+    /// Read per-branch rules (keep, safe-delete, force-delete, or archive by glob) from this
+    /// file instead of .git-clean-gone-rules at the top of the working tree
+    #[arg(long, value_name = "FILE", env = "GIT_CLEAN_GONE_RULES_FILE")]
+    rules_file: Option<PathBuf>,
+
+    /// Also protect the patterns listed in the multi-valued git config key cleanGone.protect
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_EXCLUDE_PROTECTED_FROM_GITCONFIG",
+        value_parser = BoolishValueParser::new()
+    )]
+    exclude_protected_from_gitconfig: bool,
+
+    /// Delete gone branches matching this glob (e.g. 'dependabot/*') without asking; repeatable
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        env = "GIT_CLEAN_GONE_AUTO_YES",
+        value_delimiter = ','
+    )]
+    auto_yes: Vec<BranchPattern>,
+
+    /// Delete the gone branches with no unique commits without asking, still asking about the
+    /// risky ones
+    #[arg(
+        long,
+        conflicts_with = "edit",
+        env = "GIT_CLEAN_GONE_ASSUME_YES_FOR_EMPTY_BRANCHES",
+        value_parser = BoolishValueParser::new()
+    )]
+    assume_yes_for_empty_branches: bool,
+
+    /// Print versions, relevant git config, and a sample of the branch listing (without commit
+    /// messages or URL credentials) to paste into a bug report, then exit
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_BUG_REPORT",
+        value_parser = BoolishValueParser::new()
+    )]
+    bug_report: bool,
+
+    /// Run as the given git hook, as the script `install-hook` writes does
+    #[arg(long, value_enum, hide = true, env = "GIT_CLEAN_GONE_HOOK")]
+    hook: Option<HookContext>,
+
+    /// Print how long the fetch, enumeration, and deletion phases took (to stderr)
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_TIMINGS",
+        value_parser = BoolishValueParser::new()
+    )]
+    timings: bool,
+
+    /// Only report branches that became gone since the last run
+    /// (remembered in .git/git-clean-gone/seen.json, which dry runs don't update)
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_REPORT_ONLY_NEW",
+        value_parser = BoolishValueParser::new()
+    )]
+    report_only_new: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Periodically fetch, prune, and report gone branches until interrupted
+    Watch(WatchArgs),
+    /// Install a post-merge hook that checks for gone branches after every merge or pull
+    InstallHook(InstallHookArgs),
+    /// Show the summaries of recent runs recorded in .git/git-clean-gone-history.jsonl
+    History(HistoryArgs),
+    /// Write the branches a run would delete, with their tips, to a plan file, deleting
+    /// nothing (like --dry-run --plan-out)
+    Plan(PlanArgs),
+    /// Delete the branches in a plan file, refusing to delete anything if one of them moved
+    /// or disappeared since the plan was made
+    Apply(ApplyArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct PlanArgs {
+    /// Where to write the plan
+    #[arg(value_name = "FILE")]
+    file: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+struct ApplyArgs {
+    /// The plan file `plan` wrote
+    #[arg(value_name = "PLAN")]
+    plan: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+struct HistoryArgs {
+    /// How many of the most recent runs to show
+    #[arg(long, default_value_t = 10, env = "GIT_CLEAN_GONE_HISTORY_LIMIT")]
+    limit: usize,
+}
+
+#[derive(clap::Args, Debug)]
+struct InstallHookArgs {
+    /// Replace an existing post-merge hook that git-clean-gone didn't install
+    #[arg(long, value_parser = BoolishValueParser::new())]
+    force: bool,
+}
+
+/// The kinds of branches `--branch-type` cleans up
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum BranchType {
+    /// Local branches whose upstream is gone
+    Local,
+    /// Remote-tracking refs whose branch is gone from the remote
+    Tracking,
+    /// Local branches, then remote-tracking refs
+    All,
+}
+
+/// When to color output, for `--color`
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ColorChoice {
+    /// When stdout is a terminal and `NO_COLOR` isn't set
+    Auto,
+    Always,
+    Never,
+}
+
+/// A git hook git-clean-gone can run as
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum HookContext {
+    PostMerge,
+}
+
+#[derive(clap::Args, Debug)]
+struct WatchArgs {
+    /// Time between checks, e.g. 30s, 15m, 1h
+    #[arg(
+        long,
+        default_value = "15m",
+        value_parser = humantime::parse_duration,
+        env = "GIT_CLEAN_GONE_WATCH_INTERVAL"
+    )]
+    interval: Duration,
+
+    /// Delete gone branches on every check instead of only reporting them (requires --yes)
+    #[arg(
+        long,
+        env = "GIT_CLEAN_GONE_WATCH_EXECUTE",
+        value_parser = BoolishValueParser::new()
+    )]
+    execute: bool,
+}
+
+impl Args {
+    /// Asks on stdin whether to delete `count` items, in the `--prompt-style`
+    fn confirm<W: Write>(&self, out: &mut W, question: &str, count: usize) -> Result<bool> {
+        confirm_with_style(
+            &mut self.prompt_input(),
+            out,
+            question,
+            self.prompt_style(),
+            count,
+        )
+    }
+
+    /// Stdin for answering prompts, giving up after the `--confirm-timeout`
+    fn prompt_input(&self) -> Box<dyn BufRead> {
+        match self.confirm_timeout {
+            Some(timeout) => Box::new(TimedInput::stdin(timeout)),
+            None => Box::new(io::stdin().lock()),
+        }
+    }
+
+    /// Whether the report is rendered in `format`, to stdout or to the `--output` file
+    fn renders(&self, format: OutputFormat) -> bool {
+        self.format == format || (self.output.is_some() && self.output_format == Some(format))
+    }
+
+    /// The `--branch-type` to clean up, with `--remote-tracking` as `tracking`
+    fn branch_type(&self) -> BranchType {
+        if self.remote_tracking {
+            BranchType::Tracking
+        } else {
+            self.branch_type
+        }
+    }
+
+    /// Turns the `plan` and `apply` subcommands into the `--dry-run --plan-out` and `--plan-in`
+    /// runs they stand for
+    fn expand_command(&mut self) -> Result<()> {
+        if matches!(self.command, Some(Commands::Plan(_) | Commands::Apply(_)))
+            && (self.plan_in.is_some() || self.plan_out.is_some())
+        {
+            anyhow::bail!("--plan-in and --plan-out can't be combined with `plan` or `apply`");
+        }
+        match &self.command {
+            Some(Commands::Plan(plan_args)) => {
+                self.dry_run = true;
+                self.plan_out = Some(plan_args.file.clone());
+            }
+            Some(Commands::Apply(apply_args)) => self.plan_in = Some(apply_args.plan.clone()),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The `--group-by`, with `--group-output-by-remote` as `remote`
+    fn group_by(&self) -> Option<GroupBy> {
+        self.group_by
+            .or(self.group_output_by_remote.then_some(GroupBy::Remote))
+    }
+
+    /// The `--prompt-style`, defaulting to counting with `--paranoid`
+    fn prompt_style(&self) -> PromptStyle {
+        self.prompt_style.unwrap_or(if self.paranoid {
+            PromptStyle::Count
+        } else {
+            PromptStyle::Simple
+        })
+    }
+
+    /// The `--backup-log`, defaulting to one in the git directory with `--paranoid`
+    fn backup_log(&self) -> Result<Option<PathBuf>> {
+        match &self.backup_log {
+            Some(path) => Ok(Some(path.clone())),
+            None if self.paranoid => git_path(BACKUP_FILE).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Whether to color output, per `--color`
+    fn colors(&self) -> bool {
+        match self.color {
+            ColorChoice::Auto => io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+
+    /// The `--remote` to check, defaulting to `origin`
+    fn remote(&self) -> &str {
+        self.remote.as_deref().unwrap_or("origin")
+    }
+
+    /// Whether `--max-runtime` has run out
+    fn deadline_passed(&self) -> bool {
+        self.max_runtime
+            .is_some_and(|max_runtime| self.started.elapsed() > max_runtime)
+    }
+
+    /// Fails with [`DeadlineExceeded`] if `--max-runtime` ran out by the end of `phase`
+    fn check_deadline(&self, phase: &str) -> Result<()> {
+        match self.max_runtime {
+            Some(max_runtime) if self.deadline_passed() => Err(DeadlineExceeded(format!(
+                "exceeded --max-runtime of {} during {phase}; nothing was deleted",
+                humantime::format_duration(max_runtime)
+            ))
+            .into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Exit code when `--max-runtime` is exceeded, matching `timeout(1)`
+const DEADLINE_EXIT_CODE: i32 = 124;
+
+/// Reason given for branches left alone because `--max-runtime` ran out
+const DEADLINE_REASON: &str = "not attempted: --max-runtime exceeded";
+
+/// The run was cut short by `--max-runtime`
+#[derive(Debug)]
+struct DeadlineExceeded(String);
+
+impl std::fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for DeadlineExceeded {}
+
+/// Reason given for branches left alone because the run was interrupted
+const INTERRUPTED_REASON: &str = "not attempted: interrupted";
+
+/// The run was interrupted mid-deletion, and stopped after the branch being deleted
+#[derive(Debug)]
+struct Interrupted(usize);
+
+impl std::fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "interrupted; {} branch(es) were not attempted",
+            group_digits(self.0)
+        )
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// `--exit-code` found gone branches, `git diff --exit-code` style: the run itself went fine,
+/// and it exits with status 1
+#[derive(Debug)]
+struct BranchesFound(usize);
+
+impl std::fmt::Display for BranchesFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} gone branch(es) found (--exit-code)",
+            group_digits(self.0)
+        )
+    }
+}
+
+impl std::error::Error for BranchesFound {}
+
+/// Exit code when `--check` finds the plan isn't empty (1 is any other error)
+const PLAN_NOT_EMPTY_EXIT_CODE: i32 = 4;
+
+/// `--check` found branches the run would delete or archive
+#[derive(Debug)]
+struct PlanNotEmpty(usize);
+
+impl std::fmt::Display for PlanNotEmpty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} gone branch(es) would be cleaned up (--check)",
+            group_digits(self.0)
+        )
+    }
+}
+
+impl std::error::Error for PlanNotEmpty {}
+
+/// Exit code when `--fail-on-unmerged` finds unmerged gone branches (2 is clap's usage error)
+const UNMERGED_EXIT_CODE: i32 = 3;
+
+/// `--fail-on-unmerged` found gone branches with unmerged work
+#[derive(Debug)]
+struct UnmergedBranches(Vec<String>);
+
+impl std::fmt::Display for UnmergedBranches {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} gone branch(es) have unmerged commits (--fail-on-unmerged): {}; nothing was deleted",
+            group_digits(self.0.len()),
+            self.0.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UnmergedBranches {}
+
+fn main() -> Result<()> {
+    match run() {
+        Err(err) if err.is::<DeadlineExceeded>() => {
+            eprintln!("Error: {err}");
+            std::process::exit(DEADLINE_EXIT_CODE);
+        }
+        Err(err) if err.is::<UnmergedBranches>() => {
+            eprintln!("Error: {err}");
+            std::process::exit(UNMERGED_EXIT_CODE);
+        }
+        Err(err) if err.is::<Interrupted>() => {
+            eprintln!("Error: {err}");
+            std::process::exit(CANCELLED_EXIT_CODE);
+        }
+        // Also just an outcome, and silent like `git diff --exit-code`
+        Err(err) if err.is::<BranchesFound>() => std::process::exit(1),
+        // Not an error, just the outcome of the check
+        Err(err) if err.is::<PlanNotEmpty>() => {
+            eprintln!("{err}");
+            std::process::exit(PLAN_NOT_EMPTY_EXIT_CODE);
+        }
+        result => result,
+    }
+}
+
+fn run() -> Result<()> {
+    // Installed as the hook itself (e.g. a symlink), git passes the hook's own arguments, so
+    // they're replaced with the hook mode
+    let invoked_as_post_merge = env::args_os()
+        .next()
+        .and_then(|arg0| Path::new(&arg0).file_name().map(|name| name == POST_MERGE))
+        .unwrap_or(false);
+    let mut args = if invoked_as_post_merge {
+        Args::parse_from(["git-clean-gone", "--hook", POST_MERGE])
+    } else {
+        Args::parse()
+    };
+    if args.bug_report {
+        print!("{}", format_bug_report(&BugReport::collect()));
+        return Ok(());
+    }
+    if let Some(limit) = args.max_parallel_git {
+        parallel::set_limit(limit);
+    }
+    if let Some(path) = &args.merged_prs_file {
+        args.merged_prs = read_branch_list(path)?;
+    }
+    // Explaining and checking are read-only
+    args.dry_run |= args.explain || args.check;
+    args.expand_command()?;
+    // The presets' flags; see `Args::prompt_style` and `Args::backup_log` for their defaults
+    args.safe |= args.paranoid;
+    args.protect_unpushed |= args.paranoid;
+    args.yes |= args.yolo;
+    args.force |= args.yolo && args.auto_safe;
+    if args.json_compact && !args.renders(OutputFormat::Json) {
+        anyhow::bail!("--json-compact only applies to --format json or --output-format json");
+    }
+    if let Some(note) = ci_guard_note(&args, detect_ci(|name| env::var(name).ok())) {
+        eprintln!("{note}");
+        return Ok(());
+    }
+    let machine = args.format.is_machine();
+
+    let mut options = Options {
+        dry_run: args.dry_run,
+        // Verbose diagnostics would interleave with the report in machine-readable formats
+        verbose: args.verbose && !machine,
+        // The progress bar prints its own deletion lines (see `delete_confirmed`), as does
+        // --truncate-names, since git's would have the full names
+        quiet: machine || progress::wanted(machine, args.verbose) || args.truncate_names.is_some(),
+        fetch_args: match &args.fetch_args {
+            Some(fetch_args) => parse_fetch_args(fetch_args)?,
+            None => Vec::new(),
+        },
+        fetch_command: args.fetch_command.clone(),
+        fetch_remote: args.fetch_remote.clone(),
+        gone_remote: args.remote.clone(),
+        upstream_prefix: args.match_upstream_prefix.clone(),
+        gone_command: args.gone_command.clone(),
+        branches_from_command: args.branches_from_command.clone(),
+        open_pr_command: args.open_pr_command.clone(),
+        since: args.since.clone(),
+        treat_unrelated_upstream_as_gone: args.treat_detached_upstream_as_gone,
+        verify_remote_gone: args.verify_remote_gone,
+        keep_recent: args.keep_recent,
+        upstream_map: match args.upstream_map.as_slice() {
+            [] => None,
+            [regex, replacement] => Some(UpstreamMap::new(regex, replacement)?),
+            // From the environment variable, which holds both in one value
+            [both] => match both.split_once(char::is_whitespace) {
+                Some((regex, replacement)) => Some(UpstreamMap::new(regex, replacement.trim())?),
+                None => anyhow::bail!("--upstream-map needs a REGEX and a REPLACEMENT"),
+            },
+            _ => anyhow::bail!("--upstream-map needs a REGEX and a REPLACEMENT"),
+        },
+        removed_remote: args.removed_remote.clone(),
+        maintenance: args.maintenance,
+        protect: args.protect.clone(),
+        // Loaded once the repository is known to exist
+        rules: Rules::default(),
+        exclude_recent_activity: args.exclude_recent_activity,
+        gone_for: args.gone_for,
+        skip_if_exists_elsewhere: args.skip_if_exists_elsewhere,
+        protect_tagged_ancestors: args.protect_tagged_ancestors,
+        rename_to_archive: args.rename_to_archive,
+        prefer_packed_refs: args.prefer_packed_refs,
+        cache_listing: !args.no_cache,
+        include_no_upstream: args.include_no_upstream,
+        keep_last_merged: args.keep_last_merged,
+        limit: args.limit,
+        merged_only: args.prune_merged_remote_tracking || args.safe,
+        auto_safe: args.auto_safe && !args.force,
+        protect_unpushed: args.protect_unpushed,
+        protect_ahead: args.protect_ahead,
+        protect_described: args.protect_described,
+        remote_head_fallback: args.remote_head_fallback,
+        safe_delete: args.prune_merged_remote_tracking
+            || args.safe
+            || (args.auto_safe && !args.force),
+    };
+
+    check_git(&options)?;
+    // Kept apart so each submodule can load its own settings on top (--recurse-submodules)
+    let base_options = options.clone();
+    load_repo_settings(&args, &mut options)?;
+    dispatch(&args, options, &base_options)
+}
+
+/// Runs the subcommand, or else the cleanup `args` asks for
+fn dispatch(args: &Args, options: Options, base_options: &Options) -> Result<()> {
+    match &args.command {
+        Some(Commands::Watch(watch_args)) => watch(args, watch_args, options),
+        Some(Commands::InstallHook(install_args)) => {
+            let path = git_path(&format!("hooks/{POST_MERGE}"))?;
+            install_post_merge_hook(&path, install_args.force)?;
+            println!("Installed the post-merge hook at {}", path.display());
+            Ok(())
+        }
+        Some(Commands::History(history_args)) => {
+            let runs = history::load(&git_path(HISTORY_FILE)?)?;
+            print!("{}", format_history(&runs, history_args.limit));
+            Ok(())
+        }
+        Some(Commands::Apply(apply_args)) => {
+            check_plan_drift(&apply_args.plan)?;
+            clean(args, &options)
+        }
+        None if args.hook == Some(HookContext::PostMerge) => post_merge(options),
+        None if args.tags => clean_tags(args),
+        None if args.recurse_submodules => clean_with_submodules(args, &options, base_options),
+        // `plan` only sets flags (see `Args::expand_command`)
+        Some(Commands::Plan(_)) | None => clean(args, &options),
+    }
+}
+
+/// Why `--only-if-ci` or `--only-if-not-ci` makes this run a no-op, if one does, given the CI
+/// variable [`detect_ci`] found
+fn ci_guard_note(args: &Args, ci: Option<&str>) -> Option<String> {
+    match ci {
+        None if args.only_if_ci => {
+            Some("Not running in CI, so doing nothing (--only-if-ci).".to_string())
+        }
+        Some(variable) if args.only_if_not_ci => Some(format!(
+            "Running in CI ({variable} is set), so doing nothing (--only-if-not-ci)."
+        )),
+        _ => None,
+    }
+}
+
+/// Checks that we're in a git repository, then adds the protection and rules it configures
+/// to `options`
+fn load_repo_settings(args: &Args, options: &mut Options) -> Result<()> {
+    ensure_git_repo()?;
+
+    if args.safe {
+        for glob in DEFAULT_PROTECTED {
+            options.protect.push(BranchPattern::new(glob)?);
+        }
+    }
+    if args.exclude_protected_from_gitconfig || args.safe {
+        for glob in config_get_all("cleanGone.protect")? {
+            let pattern = BranchPattern::new(&glob)
+                .with_context(|| format!("Invalid cleanGone.protect pattern {glob:?}"))?;
+            options.protect.push(pattern);
+        }
+    }
+    let global_protect = global_protect_path(env::var_os("XDG_CONFIG_HOME"), env::var_os("HOME"));
+    if let Some(path) = global_protect.filter(|_| !args.yolo)
+        && let Some(patterns) = load_pattern_list(&path)?
+    {
+        options.protect.extend(patterns);
+    }
+
+    options.rules = load_rules(args.rules_file.as_deref())?;
+    Ok(())
+}
+
+/// Cleans up the gone branches of the repository, then of each initialized submodule in turn
+/// (`--recurse-submodules`), each with its own settings and confirmation, and ends with a
+/// per-repository breakdown. A repository whose cleanup fails doesn't stop the rest.
+fn clean_with_submodules(args: &Args, options: &Options, base_options: &Options) -> Result<()> {
+    if args.branch_type() != BranchType::Local {
+        anyhow::bail!("--recurse-submodules only works with --branch-type local");
+    }
+    let mut breakdown = vec![(".".to_string(), clean_branches(args, options, false))];
+
+    // A bare repository has no working tree, so no submodules
+    if let Some(root) = work_tree_root()? {
+        let original_dir = env::current_dir().context("Failed to read the current directory")?;
+        let submodules = submodules(&root)?;
+        let prefetched = prefetch_submodules(&submodules, args, base_options);
+        for ((name, path), fetched) in submodules.into_iter().zip(prefetched) {
+            println!("\nSubmodule {name}:");
+            // git runs in the current directory, so this points every git command at the
+            // submodule
+            env::set_current_dir(&path)
+                .with_context(|| format!("Failed to enter submodule {}", path.display()))?;
+            let mut options = base_options.clone();
+            let result = load_repo_settings(args, &mut options)
+                .and_then(|()| clean_branches(args, &options, fetched));
+            breakdown.push((name, result));
+        }
+        env::set_current_dir(&original_dir)
+            .with_context(|| format!("Failed to return to {}", original_dir.display()))?;
+    }
+
+    print!("{}", format_submodule_breakdown(&breakdown));
+    let (mut failed, mut found) = (0, 0);
+    for (_, result) in &breakdown {
+        if let Err(err) = result {
+            match err.downcast_ref::<BranchesFound>() {
+                Some(BranchesFound(count)) => found += count,
+                None => failed += 1,
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!(
+            "Cleanup failed in {} of {} repositories",
+            group_digits(failed),
+            group_digits(breakdown.len())
+        );
+    }
+    if found > 0 {
+        return Err(BranchesFound(found).into());
+    }
+    Ok(())
+}
+
+/// Fetches and prunes every submodule up front, several at once (`--max-parallel-git`), before
+/// they're cleaned up one by one, returning whether each was fetched. The rest, and every
+/// submodule with `--fetch-command`, fetch in their own run as usual.
+fn prefetch_submodules(
+    submodules: &[(String, PathBuf)],
+    args: &Args,
+    options: &Options,
+) -> Vec<bool> {
+    if args.no_fetch || options.fetch_command.is_some() || submodules.len() < 2 {
+        return vec![false; submodules.len()];
+    }
+    if !args.format.is_machine() {
+        println!(
+            "\nFetching and pruning {} submodules...",
+            group_digits(submodules.len())
+        );
+    }
+    parallel::map(submodules, |(_, path)| {
+        git_fetch_prune_in(
+            path,
+            options.verbose,
+            options.fetch_remote.as_deref(),
+            &options.fetch_args,
+        )
+        .is_ok()
+    })
+}
+
+/// The per-repository lines ending `--recurse-submodules`: each repository's summary (see
+/// [`notify::summary`]), or the error its cleanup failed with
+fn format_submodule_breakdown(breakdown: &[(String, Result<String>)]) -> String {
+    let mut text = String::from("\nPer repository:\n");
+    let width = breakdown
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    for (name, result) in breakdown {
+        let _ = match result {
+            Ok(summary) => writeln!(text, "  {name:<width$}  {summary}"),
+            // The error's context is the repository's summary (see `clean_branches`)
+            Err(err) if err.is::<BranchesFound>() => {
+                writeln!(text, "  {name:<width$}  {err} (gone branches found)")
+            }
+            Err(err) => writeln!(text, "  {name:<width$}  error: {err:#}"),
+        };
+    }
+    text
+}
+
+/// Cleans up the `--branch-type` branches (or `--prune-stale-pr-refs` refs)
+fn clean(args: &Args, options: &Options) -> Result<()> {
+    let marker = git_path(MARKER_FILE)?;
+    if let Some(window) = args.skip_if_cleaned_within
+        && let Some(ago) = cleaned_within(marker::last_clean(&marker), window, unix_now())
+    {
+        eprintln!(
+            "note: skipping; the last cleanup was {} ago, within --skip-if-cleaned-within {}",
+            humantime::format_duration(ago),
+            humantime::format_duration(window)
+        );
+        return Ok(());
+    }
+    if args.compact_summary_line {
+        return print_compact_summary(args, options);
+    }
+    // Piped-in listings don't come from this repository
+    if args.branch_type() == BranchType::Local
+        && !args.stdin
+        && let Some(branch) = unborn_branch()?
+    {
+        return report_unborn(&branch, args);
+    }
+    let cleaned = clean_by_type(args, options);
+    // Finding branches with --exit-code still completed the cleanup
+    let completed = match &cleaned {
+        Ok(()) => true,
+        Err(err) => err.is::<BranchesFound>(),
+    };
+    if completed
+        && !args.dry_run
+        && let Err(err) = marker::record(&marker, unix_now())
+    {
+        eprintln!("warning: {err:#}");
+    }
+    cleaned
+}
+
+/// Prints the `--compact-summary-line` for the branches a run would delete, fetching first
+/// unless `--no-fetch` is given. Nothing else is printed, so a prompt can embed the output.
+fn print_compact_summary(args: &Args, options: &Options) -> Result<()> {
+    if !args.no_fetch {
+        match &options.fetch_command {
+            Some(command) => run_fetch_command(command, false)?,
+            None => git_fetch_prune(false, options.fetch_remote.as_deref(), &options.fetch_args)?,
+        }
+    }
+    let all_branches = list_local_branches(options)?;
+    let gone = find_gone_branches(&all_branches, options)?;
+    // The library's checks, as the CLI's own would also warn about each branch they keep
+    let (deletable, _) = apply_safety_checks(gone, &all_branches, options)?;
+    let (assessments, _) = if deletable.is_empty() {
+        (Vec::new(), String::new())
+    } else {
+        assess_risk(&deletable, args, options)?
+    };
+    print!(
+        "{}",
+        format_compact_summary(&assessments, &args.summary_glyph, args.colors())
+    );
+    Ok(())
+}
+
+/// Ends a run in a repository with no commits yet, which has no branches to clean up. Machine
+/// formats still get their (empty) report.
+fn report_unborn(branch: &str, args: &Args) -> Result<()> {
+    let message = format!(
+        "No commits yet (HEAD is the unborn branch {branch}), so there are no branches to clean \
+         up."
+    );
+    if !args.format.is_machine() {
+        println!("{message}");
+        return Ok(());
+    }
+    eprintln!("note: {message}");
+    let report = Report {
+        dry_run: args.dry_run,
+        branches: Vec::new(),
+        age_histogram: None,
+        remotes_checked: None,
+    };
+    if let Some(rendered) = report.render(args.format)? {
+        print!("{rendered}");
+    }
+    Ok(())
+}
+
+/// Cleans up the `--branch-type` (or `--prune-stale-pr-refs`) the run is for
+fn clean_by_type(args: &Args, options: &Options) -> Result<()> {
+    match (args.branch_type(), &args.prune_stale_pr_refs) {
+        (BranchType::Local, Some(namespace)) => clean_pr_refs(args, namespace),
+        (BranchType::Local, None) => {
+            match &args.removed_remote {
+                Some(remote) => clean_removed_remote(args, options, remote)?,
+                None => drop(clean_branches(args, options, false)?),
+            }
+            if args.include_orphan_worktrees || args.remove_orphan_worktrees {
+                clean_orphan_worktrees(args)?;
+            }
+            Ok(())
+        }
+        (branch_type, _) => {
+            check_tracking_args(args)?;
+            if branch_type == BranchType::Tracking {
+                return clean_remote_tracking(args);
+            }
+            // Each type is confirmed and reported separately, and one failing doesn't
+            // stop the other
+            let local = clean_branches(args, options, false).map(drop);
+            println!();
+            let tracking = clean_remote_tracking(args);
+            local.and(tracking)
+        }
+    }
+}
+
+/// Rejects the options remote-tracking cleanup can't honor, for `--branch-type tracking` and
+/// `all` (`--remote-tracking` conflicts with them outright)
+fn check_tracking_args(args: &Args) -> Result<()> {
+    let unsupported = [
+        (args.format != OutputFormat::Human, "--format"),
+        (args.plan_in.is_some(), "--plan-in"),
+        (args.stdin, "--stdin"),
+        (args.explain, "--explain"),
+        (args.check, "--check"),
+        (args.prune_stale_pr_refs.is_some(), "--prune-stale-pr-refs"),
+    ];
+    match unsupported.iter().find(|(given, _)| *given) {
+        Some((_, flag)) => anyhow::bail!("{flag} only works with --branch-type local"),
+        None => Ok(()),
+    }
+}
+
+/// Loads the rules from `path`, which must exist, or else from the working tree's rules file,
+/// if it has one
+fn load_rules(path: Option<&Path>) -> Result<Rules> {
+    if let Some(path) = path {
+        return Rules::load(path)?
+            .with_context(|| format!("Rules file {} doesn't exist", path.display()));
+    }
+    match work_tree_root()? {
+        Some(root) => Ok(Rules::load(&root.join(RULES_FILE))?.unwrap_or_default()),
+        None => Ok(Rules::default()),
+    }
+}
+
+/// Runs the main cleanup: find gone branches, confirm, delete, and report, phase by phase
+/// (see [`CliPhases`]), returning the run's summary (see [`notify::summary`])
+fn clean_branches(args: &Args, options: &Options, fetched: bool) -> Result<String> {
+    clean_branches_planned(args, options, fetched).map(|(summary, _)| summary)
+}
+
+/// Like [`clean_branches`], also returning the names of the branches a dry run would delete
+fn clean_branches_planned(
+    args: &Args,
+    options: &Options,
+    fetched: bool,
+) -> Result<(String, HashSet<String>)> {
+    if args.stdin && !args.dry_run && !args.yes {
+        // The piped listing uses up stdin, so there's nothing left to answer a prompt
+        anyhow::bail!(
+            "--stdin reads the branch listing from stdin, so deleting also requires --yes"
+        );
+    }
+
+    let plan = args.plan_in.as_deref().map(Plan::read).transpose()?;
+    let expected_shas = args
+        .expect_shas
+        .as_deref()
+        .map(read_expected_shas)
+        .transpose()?;
+    let checkpoint_path = if args.checkpoint && !args.dry_run {
+        Some(git_dir()?.join("git-clean-gone").join("checkpoint.json"))
+    } else {
+        None
+    };
+    let resumed = match &checkpoint_path {
+        Some(path) => check_interrupted_run(path, args)?,
+        None => None,
+    };
+
+    let mut phases = CliPhases {
+        args,
+        options,
+        plan,
+        expected_shas,
+        checkpoint_path,
+        resumed,
+        timings: Vec::new(),
+        sarif_assessments: Vec::new(),
+        age_histogram: None,
+        remotes_checked: None,
+        descriptions: HashMap::new(),
+        any_confirmed: false,
+        fetched,
+    };
+    let report = pipeline::run(&mut phases, State::Start)?;
+    let summary = notify::summary(&report);
+    let would_delete = report
+        .branches
+        .iter()
+        .filter(|branch| branch.action == Action::WouldDelete)
+        .map(|branch| branch.name.clone())
+        .collect();
+    match finish(args, options, report, &phases.timings) {
+        // Carries the summary along for the --recurse-submodules breakdown
+        Err(err) if err.is::<BranchesFound>() => Err(err.context(summary)),
+        result => result.map(|()| (summary, would_delete)),
+    }
+}
+
+/// The main cleanup's phases, with the state the CLI threads between them on top of the
+/// [`State`]s themselves
+struct CliPhases<'a> {
+    args: &'a Args,
+    options: &'a Options,
+    /// The `--plan-in` plan, which pins the branches to delete
+    plan: Option<Plan>,
+    expected_shas: Option<HashMap<String, String>>,
+    checkpoint_path: Option<PathBuf>,
+    /// The branches an interrupted run left to delete, when resuming it
+    resumed: Option<Vec<String>>,
+    /// How long each phase took, for `--timings`
+    timings: Vec<(&'static str, Duration)>,
+    /// The candidates' risk for `--format sarif` and `--format junit`, assessed while they still exist
+    sarif_assessments: Vec<Assessment>,
+    /// The `--report-age-histogram` buckets, for the report
+    age_histogram: Option<Vec<AgeBucket>>,
+    /// What the fetch covered, for the report with `--format json`
+    remotes_checked: Option<Vec<RemoteCheck>>,
+    /// The branch descriptions, for the report
+    descriptions: HashMap<String, String>,
+    any_confirmed: bool,
+    /// Fetched already, along with the other submodules (see `prefetch_submodules`)
+    fetched: bool,
+}
+
+impl Phases for CliPhases<'_> {
+    /// Fetches and prunes, unless a plan or `--stdin` supplies the branches, or `--no-fetch`
+    /// is given
+    fn fetch(&mut self) -> Result<()> {
+        let (args, options) = (self.args, self.options);
+        // A plan pins the exact branches to delete, and with --stdin the caller supplies the
+        // listing, so either way there's nothing to fetch
+        if self.plan.is_some() || args.stdin || args.no_fetch {
+            return Ok(());
+        }
+        if !self.fetched {
+            if !args.format.is_machine() {
+                println!("Fetching and pruning remote branches...");
+            }
+            let phase_start = Instant::now();
+            let spinner = Progress::spinner(show_progress(args), "Fetching");
+            let fetched = match &options.fetch_command {
+                Some(command) => run_fetch_command(command, options.verbose),
+                None => git_fetch_prune(
+                    options.verbose,
+                    options.fetch_remote.as_deref(),
+                    &options.fetch_args,
+                ),
+            };
+            spinner.finish();
+            fetched?;
+            self.timings.push(("fetch", phase_start.elapsed()));
+            args.check_deadline("fetch")?;
+        }
+
+        check_remote_head(args.remote(), args.fix_head)?;
+        if args.clean_orphan_tracking {
+            clean_orphan_tracking(args.dry_run, args.format.is_machine())?;
+        }
+        Ok(())
+    }
+
+    /// Finds the gone branches and all local branches, from a plan, piped-in `git branch -vv`
+    /// output, or the fresh fetch, narrowed to the rest of an interrupted run when resuming
+    fn enumerate(&mut self) -> Result<Enumerated> {
+        let (args, options) = (self.args, self.options);
+        let phase_start = Instant::now();
+        let (mut gone, all_branches) = if let Some(plan) = &self.plan {
+            let all_branches = list_local_branches(options)?;
+            (planned_branches(plan, &all_branches), all_branches)
+        } else if args.stdin {
+            let mut bytes = Vec::new();
+            io::stdin()
+                .read_to_end(&mut bytes)
+                .context("Failed to read stdin")?;
+            let (listing, undecodable) = decode_lines(&bytes);
+            for line in undecodable {
+                eprintln!("warning: skipping input line that isn't valid UTF-8: {line}");
+            }
+            let all_branches = simulate_remote_deletion(parse_branch_lines(&listing), args);
+            (gone_branches(&all_branches), all_branches)
+        } else {
+            let all_branches = simulate_remote_deletion(list_local_branches(options)?, args);
+            (find_gone_branches(&all_branches, options)?, all_branches)
+        };
+        if args.branches_from_command.is_some() {
+            eprintln!(
+                "note: {} candidate(s) come from --branches-from-command, not from gone detection",
+                group_digits(gone.len())
+            );
+        }
+        self.timings.push(("enumeration", phase_start.elapsed()));
+        let listing = args.list_remotes_checked || args.verbose;
+        if listing || args.renders(OutputFormat::Json) {
+            let checks = remotes_checked(&all_branches, &gone, args, options, self.plan.is_some())?;
+            if listing && args.format.is_machine() {
+                eprint!("{}", format_remote_checks(&checks));
+            } else if listing {
+                print!("{}", format_remote_checks(&checks));
+            }
+            self.remotes_checked = args.renders(OutputFormat::Json).then_some(checks);
+        }
+        if options.treat_unrelated_upstream_as_gone && self.plan.is_none() && !args.stdin {
+            warn_unrelated_upstreams(&gone);
+        }
+        if let Some(remaining) = &self.resumed {
+            gone.retain(|branch| remaining.contains(&branch.name));
+        }
+        Ok(Enumerated { all_branches, gone })
+    }
+
+    /// Runs the safety checks, then everything that looks at the candidates before anyone
+    /// confirms: `--fail-on-unmerged`, the informational listings, and `--plan-out`
+    fn filter(&mut self, enumerated: Enumerated) -> Result<Filtered> {
+        let args = self.args;
+        let candidates: HashSet<String> = enumerated
+            .gone
+            .iter()
+            .map(|branch| branch.name.clone())
+            .collect();
+        if args.report_age_histogram {
+            let buckets = age_histogram(&enumerated.gone, &branch_commit_times()?, unix_now());
+            let histogram = format_age_histogram(&buckets);
+            if args.format.is_machine() {
+                eprint!("{histogram}");
+            } else {
+                print!("{histogram}");
+            }
+            self.age_histogram = Some(buckets);
+        }
+        // Sorted before the safety checks, so --limit takes the riskiest first
+        let gone = match args.sort {
+            SortOrder::UniqueCommits if !enumerated.gone.is_empty() => {
+                let (assessments, _) = assess_risk(&enumerated.gone, args, self.options)?;
+                sort_by_unique_commits(enumerated.gone, &assessments)
+            }
+            _ => enumerated.gone,
+        };
+        let (deletable, held) = check_safety(gone, &enumerated.all_branches, args, self.options)?;
+        args.check_deadline("enumeration")?;
+        if args.explain {
+            explain(
+                &enumerated.all_branches,
+                &candidates,
+                &deletable,
+                &held,
+                args,
+                self.options,
+            )?;
+        }
+        self.descriptions = branch_descriptions()?;
+        inspect_candidates(&deletable, &self.descriptions, args, self.options)?;
+        check_plan_hash(&deletable, args)?;
+        // Assessed now, as deleted branches can't be assessed afterwards
+        self.sarif_assessments = assess_for_sarif(&deletable, &held, args, self.options)?;
+
+        if let Some(path) = &args.plan_out {
+            skip_if_unwritable(write_plan(path, &deletable), "the plan")?;
+        }
+        Ok(Filtered {
+            all_branches: enumerated.all_branches,
+            deletable,
+            held,
+        })
+    }
+
+    /// Asks which branches to delete, then runs the pre-clean hook on them
+    fn confirm(&mut self, filtered: Filtered) -> Result<Confirmed> {
+        let args = self.args;
+        let deletable = filtered.deletable;
+        let confirmed = if deletable.is_empty() {
+            if !args.format.is_machine() {
+                println!("No gone branches found.");
+            }
+            HashSet::new()
+        } else {
+            confirm_deletion(&deletable, args, self.options)?
+        };
+        warn_if_large(deletable.len(), args);
+        let confirmed = Confirmed {
+            deletable,
+            held: filtered.held,
+            confirmed,
+        };
+        run_pre_clean_hook_on(&confirmed, args.dry_run)?;
+        self.any_confirmed = !confirmed.confirmed.is_empty();
+        Ok(confirmed)
+    }
+
+    /// Deletes the confirmed branches, recording progress in the checkpoint with
+    /// `--checkpoint`
+    fn delete(&mut self, confirmed: Confirmed) -> Result<Report> {
+        let args = self.args;
+        let Confirmed {
+            deletable,
+            held,
+            confirmed,
+        } = confirmed;
+        let mut checkpoint = match &self.checkpoint_path {
+            Some(path) if !confirmed.is_empty() => skip_if_unwritable(
+                start_checkpoint(path, &deletable, &confirmed),
+                "the checkpoint",
+            )?,
+            _ => None,
+        };
+
+        let phase_start = Instant::now();
+        let results = delete_confirmed(
+            &deletable,
+            args,
+            self.options,
+            &confirmed,
+            self.plan.as_ref(),
+            self.expected_shas.as_ref(),
+            checkpoint.as_mut(),
+        );
+        let timed_out = results.iter().any(|result| {
+            matches!(
+                result.reason.as_deref(),
+                Some(DEADLINE_REASON | INTERRUPTED_REASON)
+            )
+        });
+        // A run cut short by --max-runtime or an interruption keeps its checkpoint, so the next
+        // run can resume
+        if let Some(checkpoint) = checkpoint
+            && !timed_out
+        {
+            skip_if_unwritable(checkpoint.finish(), "the checkpoint")?;
+        }
+        if !args.dry_run && !deletable.is_empty() {
+            self.timings.push(("deletion", phase_start.elapsed()));
+        }
+        let mut report = Report {
+            dry_run: args.dry_run,
+            branches: results
+                .into_iter()
+                .chain(held.iter().map(BranchReport::held))
+                .collect(),
+            age_histogram: self.age_histogram.take(),
+            remotes_checked: self.remotes_checked.take(),
+        };
+        report.fill_unmerged_commits(&self.sarif_assessments);
+        report.fill_descriptions(&self.descriptions);
+        if let Some(by) = args.group_by() {
+            let listed: Vec<BranchLine> = deletable
+                .iter()
+                .cloned()
+                .chain(held.into_iter().map(|held| held.branch))
+                .collect();
+            report.fill_groups(&group_branches(&listed, by)?);
+        }
+        Ok(report)
+    }
+
+    /// Checks that the deleted branches are gone, then follows up with `--delete-remote` and
+    /// `--emit-script`
+    fn verify(&mut self, mut report: Report) -> Result<Report> {
+        let args = self.args;
+        if !args.dry_run && self.any_confirmed {
+            verify_removed(&mut report, self.options, args.format.is_machine())?;
+            verify_archived(&mut report, args.format.is_machine())?;
+        }
+        if let Some(remote) = &args.delete_remote {
+            delete_on_remote(remote, &mut report, args)?;
+        }
+        if let Some(path) = &args.emit_script {
+            emit_script(path, &report, args, self.options)?;
+        }
+        Ok(report)
+    }
+}
+
+/// Removes the config sections of the branches `report` says were deleted, for
+/// `--prune-config-branches`, and says how many there were
+fn prune_config_sections(report: &Report, machine: bool) -> Result<()> {
+    let sections = branch_config_sections()?;
+    let mut removed = 0;
+    for branch in report
+        .branches
+        .iter()
+        .filter(|branch| branch.action == Action::Deleted && sections.contains(&branch.name))
+    {
+        match remove_branch_config(&branch.name) {
+            Ok(()) => removed += 1,
+            Err(err) => eprintln!("warning: {err:#}"),
+        }
+    }
+    say(
+        machine,
+        &format!(
+            "Removed {} orphaned branch config section(s)",
+            group_digits(removed)
+        ),
+    );
+    Ok(())
+}
+
+/// Gives up on writing an auxiliary file (such as the checkpoint or the plan) with a warning
+/// when `result` failed for lack of permission, e.g. on a read-only mount, so the cleanup
+/// itself still runs; any other failure is returned
+fn skip_if_unwritable<T>(result: Result<T>, what: &str) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(err) if is_unwritable(&err) => {
+            eprintln!("warning: can't write {what}, so continuing without it: {err:#}");
+            Ok(None)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Writes the branches about to be deleted, with their tips, to a plan file at `path`
+fn write_plan(path: &Path, gone_branches: &[BranchLine]) -> Result<()> {
+    Plan::from_branches(gone_branches)?.write(path)?;
+    eprintln!(
+        "Wrote plan for {} branch(es) to {}",
+        group_digits(gone_branches.len()),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Writes the git commands for what the dry run in `report` would do to a script at `path`
+fn emit_script(path: &Path, report: &Report, args: &Args, options: &Options) -> Result<()> {
+    let commands = script_commands(report, options, args.delete_remote.as_deref())?;
+    write_script(path, &format_script(&commands))?;
+    eprintln!(
+        "Wrote {} git command(s) to {}",
+        group_digits(commands.len()),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Deletes the confirmed gone branches (skipping plan and `--expect-shas` mismatches, with the
+/// reason), printing progress and
+/// recording each deletion in the checkpoint. An interruption meanwhile stops it after the
+/// branch being deleted (see [`Cancel`])
+fn delete_confirmed(
+    gone_branches: &[BranchLine],
+    args: &Args,
+    options: &Options,
+    confirmed: &HashSet<String>,
+    plan: Option<&Plan>,
+    expected_shas: Option<&HashMap<String, String>>,
+    mut checkpoint: Option<&mut Checkpoint>,
+) -> Vec<BranchReport> {
+    let machine = args.format.is_machine();
+    let show_progress = show_progress(args) && !args.dry_run;
+    let progress = Progress::bar(show_progress, confirmed.len(), "Deleting");
+    let own_lines = show_progress || (args.truncate_names.is_some() && !machine);
+    let cancel = interrupt_handler(args, confirmed);
+    let mut timed_out = Vec::new();
+    let mut interrupted = Vec::new();
+    let mut mismatched = HashMap::new();
+    let mut results = cancel.armed(|| {
+        delete_branches(gone_branches, options, |event| match event {
+            Event::Considering(branch) if !confirmed.contains(&branch.name) => Decision::Skip,
+            Event::Considering(branch) if cancel.is_requested() => {
+                interrupted.push(branch.name.clone());
+                Decision::Skip
+            }
+            Event::Considering(branch) if args.deadline_passed() => {
+                timed_out.push(branch.name.clone());
+                Decision::Skip
+            }
+            Event::Considering(branch) => match plan {
+                Some(plan) if !tip_matches_plan(plan, branch) => {
+                    mismatched.insert(
+                        branch.name.clone(),
+                        "tip changed since the plan was made".to_string(),
+                    );
+                    Decision::Skip
+                }
+                _ => match expected_shas.and_then(|expected| expected_sha_reason(expected, branch))
+                {
+                    Some(reason) => {
+                        progress.suspend(|| {
+                            eprintln!("warning: not deleting {}: {reason}", branch.name);
+                        });
+                        mismatched.insert(branch.name.clone(), reason);
+                        Decision::Skip
+                    }
+                    None => Decision::Proceed,
+                },
+            },
+            Event::TipMoved(branch, tip) => {
+                progress.inc();
+                let reason = format!(
+                    "its tip moved from {} to {tip} since it was listed",
+                    branch.sha
+                );
+                progress.suspend(|| eprintln!("warning: not deleting {}: {reason}", branch.name));
+                Decision::Proceed
+            }
+            Event::Reappeared(branch, remote) => {
+                progress.inc();
+                let reason = format!("its upstream reappeared on {remote} since the fetch");
+                progress.suspend(|| eprintln!("warning: not deleting {}: {reason}", branch.name));
+                Decision::Proceed
+            }
+            Event::Failed(_, err) => {
+                progress.inc();
+                progress.suspend(|| eprintln!("error: {err:#}"));
+                Decision::Proceed
+            }
+            Event::Deleted(branch) | Event::Archived(branch, _) => {
+                progress.inc();
+                if let Some(line) = deletion_line(&event, args, own_lines) {
+                    progress.suspend(|| println!("{line}"));
+                }
+                if let Some(checkpoint) = checkpoint.as_deref_mut()
+                    && let Err(err) = checkpoint.record_deleted(&branch.name)
+                {
+                    progress.suspend(|| eprintln!("warning: {err:#}"));
+                }
+                Decision::Proceed
+            }
+        })
+    });
+    progress.finish();
+    for result in &mut results {
+        let (reason, hold) = if timed_out.contains(&result.name) {
+            (DEADLINE_REASON.to_string(), HoldKind::Deadline)
+        } else if interrupted.contains(&result.name) {
+            (INTERRUPTED_REASON.to_string(), HoldKind::Interrupted)
+        } else if let Some(reason) = mismatched.remove(&result.name) {
+            (reason, HoldKind::TipMismatch)
+        } else {
+            continue;
+        };
+        result.reason = Some(reason);
+        result.hold = Some(hold);
+    }
+    results
+}
+
+/// The line announcing a deletion or archive `event`, if one is printed: archives always are
+/// (outside the machine formats), and deletions when git's own line is suppressed (with
+/// `own_lines`) so it can't tear the progress bar, or show the full name with
+/// `--truncate-names`
+fn deletion_line(event: &Event<'_>, args: &Args, own_lines: bool) -> Option<String> {
+    let shown = |branch: &BranchLine| truncate_name(&branch.name, args.truncate_names).into_owned();
+    match event {
+        Event::Archived(branch, archived_as) if !args.format.is_machine() => Some(format!(
+            "Archived branch {} as {archived_as}",
+            shown(branch)
+        )),
+        Event::Deleted(branch) if own_lines => Some(format!(
+            "Deleted branch {} (was {}).",
+            shown(branch),
+            branch.sha
+        )),
+        _ => None,
+    }
+}
+
+/// The token that lets an interruption stop a real deletion run cleanly; a dry run keeps the
+/// default handling, as it has nothing to leave half done
+fn interrupt_handler(args: &Args, confirmed: &HashSet<String>) -> Cancel {
+    if args.dry_run || confirmed.is_empty() {
+        return Cancel::default();
+    }
+    Cancel::install().unwrap_or_else(|err| {
+        eprintln!("warning: {err:#}; an interruption will stop the run mid-deletion");
+        Cancel::default()
+    })
+}
+
+/// Whether to draw progress indicators (see [`progress::wanted`])
+fn show_progress(args: &Args) -> bool {
+    progress::wanted(args.format.is_machine(), args.verbose)
+}
+
+/// Writes the initial checkpoint for deleting the confirmed branches, in listing order
+fn start_checkpoint(
+    path: &Path,
+    gone_branches: &[BranchLine],
+    confirmed: &HashSet<String>,
+) -> Result<Checkpoint> {
+    let planned = gone_branches
+        .iter()
+        .filter(|branch| confirmed.contains(&branch.name))
+        .map(|branch| branch.name.clone())
+        .collect();
+    Checkpoint::start(path, planned)
+}
+
+/// Runs the safety checks, warning about each branch they keep and about branches that still
+/// exist on another remote
+fn check_safety(
+    gone_branches: Vec<BranchLine>,
+    all_branches: &[BranchLine],
+    args: &Args,
+    options: &Options,
+) -> Result<(Vec<BranchLine>, Vec<Held>)> {
+    let (gone_branches, mut held) = apply_safety_checks(gone_branches, all_branches, options)?;
+    let gone_branches = match &args.merged_prs_file {
+        Some(path) if !gone_branches.is_empty() => {
+            let (assessments, _) = assess_risk(&gone_branches, args, options)?;
+            let listed: Vec<&str> = gone_branches
+                .iter()
+                .filter(|branch| args.merged_prs.contains(&branch.name))
+                .map(|branch| branch.name.as_str())
+                .collect();
+            eprintln!(
+                "{} gone branch(es) are listed as merged in {}{}{}",
+                group_digits(listed.len()),
+                path.display(),
+                if listed.is_empty() { "" } else { ": " },
+                listed.join(", ")
+            );
+            let (gone_branches, unlisted) = hold_unlisted_risky(gone_branches, &assessments);
+            held.extend(unlisted);
+            gone_branches
+        }
+        _ => gone_branches,
+    };
+    for held in &held {
+        eprintln!("warning: keeping {}: {}", held.branch.name, held.reason);
+    }
+    if !args.skip_if_exists_elsewhere {
+        for warning in existing_elsewhere_warnings(&gone_branches)? {
+            eprintln!("warning: {warning}; pass --skip-if-exists-elsewhere to keep it");
+        }
+    }
+    Ok((gone_branches, held))
+}
+
+/// Appends `report`'s summary to the run history, warning rather than failing the run if it
+/// can't be written
+fn record_history(report: &Report) {
+    let timestamp = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+    let summary = RunSummary::new(report, timestamp, env::args().skip(1));
+    if let Err(err) = git_path(HISTORY_FILE).and_then(|path| history::append(&path, &summary)) {
+        eprintln!("warning: {err:#}");
+    }
+}
+
+/// Appends the deleted branches to the `--backup-log`, warning rather than failing the run if
+/// it can't be written, since the branches are already deleted and the undo commands still
+/// need printing
+fn write_backup_log(path: &Path, report: &Report) {
+    let timestamp = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+    let entries = format_backup_entries(report, &timestamp);
+    if !entries.is_empty()
+        && let Err(err) = backup::append(path, &entries)
+    {
+        eprintln!("warning: {err:#}");
+    }
+}
+
+/// Shows a desktop notification for `--notify`, warning rather than failing the run if it
+/// can't be shown
+fn send_notification(body: &str) {
+    if let Err(err) = notify::send(body) {
+        eprintln!("warning: {err:#}");
+    }
+}
+
+/// Expires the reflogs for `--reflog-expire`, once warned and confirmed
+fn reflog_expire(args: &Args) -> Result<()> {
+    eprintln!(
+        "warning: expiring every reflog lets the next git gc prune the deleted branches' \
+         commits, after which restoring them (e.g. with the undo commands) is impossible; \
+         this also expires the reflogs of every other ref, including HEAD"
+    );
+    let mut out: Box<dyn Write> = if args.format.is_machine() {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    };
+    let confirmed = args.reflog_expire_yes
+        || confirm_word(
+            &mut args.prompt_input(),
+            &mut out,
+            "Expire all reflogs now?",
+            "expire",
+        )?;
+    if !confirmed {
+        writeln!(out, "Not expiring the reflogs.")?;
+        return Ok(());
+    }
+    expire_reflogs()?;
+    writeln!(
+        out,
+        "Expired all reflogs; run `git gc --prune=now` to reclaim the space."
+    )?;
+    Ok(())
+}
+
+/// Prints `message` as a line of its own: on stderr with a `machine` format, so it stays out
+/// of the report, and on stdout otherwise
+fn say(machine: bool, message: &str) {
+    if machine {
+        eprintln!("{message}");
+    } else {
+        println!("{message}");
+    }
+}
+
+/// Runs `git maintenance` for `--maintenance`, saying which tasks ran
+fn maintain(maintenance: Maintenance, machine: bool) -> Result<()> {
+    say(machine, "\nRunning git maintenance...");
+    let tasks = run_maintenance(maintenance)?;
+    say(
+        machine,
+        &format!("Ran maintenance task(s): {}", tasks.join(", ")),
+    );
+    Ok(())
+}
+
+/// Runs `git fsck --connectivity-only` for `--verify-clean-after`, failing with fsck's output
+/// if it finds problems
+fn verify_integrity(machine: bool) -> Result<()> {
+    say(
+        machine,
+        "\nVerifying the repository with git fsck --connectivity-only...",
+    );
+    if let Some(problems) = verify_connectivity()? {
+        anyhow::bail!("git fsck --connectivity-only reported problems:\n{problems}");
+    }
+    say(machine, "Repository verified: no problems found");
+    Ok(())
+}
+
+/// Tidies up after deleting, then prints the timings and the report (or the remaining
+/// branches), failing if any deletion failed
+fn finish(
+    args: &Args,
+    options: &Options,
+    report: Report,
+    timings: &[(&str, Duration)],
+) -> Result<()> {
+    tidy_up(args, options, &report, timings)?;
+
+    let has_failures = report.has_failures();
+    let deleted = report.count(Action::Deleted);
+    // Counted before --report-only-new narrows the report, so --check covers the whole plan
+    let planned = report.count(Action::WouldDelete) + report.count(Action::WouldArchive);
+    let not_attempted = report
+        .branches
+        .iter()
+        .filter(|branch| branch.reason.as_deref() == Some(DEADLINE_REASON))
+        .count();
+    let interrupted = report
+        .branches
+        .iter()
+        .filter(|branch| branch.reason.as_deref() == Some(INTERRUPTED_REASON))
+        .count();
+    let mut report = if args.report_only_new {
+        only_newly_gone(report, args.format.is_machine())?
+    } else {
+        report
+    };
+    if args.report_branches_kept_reasons {
+        report.fill_kept_reasons();
+    }
+    if report.dry_run {
+        report.fill_planned_actions();
+    }
+
+    let render = |format| {
+        if args.json_compact && format == OutputFormat::Json {
+            report.to_compact_json().map(Some)
+        } else {
+            report.render(format)
+        }
+    };
+    let rendered = render(args.format)?;
+    if let Some(path) = &args.output {
+        // Rendered from the same report as stdout, so both reflect the same decisions
+        let contents = match args.output_format {
+            Some(format) if format != args.format => render(format)?,
+            _ => rendered.clone(),
+        };
+        write_report(path, &contents.unwrap_or_else(|| report.to_text()))?;
+    }
+    if let Some(rendered) = rendered {
+        if !args.output_only {
+            print!("{rendered}");
+        }
+    } else {
+        if !args.no_show_remaining {
+            // Show remaining branches
+            println!("\nRemaining branches:");
+            show_remaining_branches(args.show_remote)?;
+        }
+        if args.report_branches_kept_reasons {
+            print!("{}", report.kept_reasons());
+        }
+        print!("{}", report.failures());
+        print!("{}", report.undo_commands());
+    }
+    if args.reflog_expire && deleted > 0 {
+        reflog_expire(args)?;
+    }
+    if let Some(maintenance) = args.maintenance
+        && deleted > 0
+    {
+        maintain(maintenance, args.format.is_machine())?;
+    }
+    if args.verify_clean_after && deleted > 0 {
+        verify_integrity(args.format.is_machine())?;
+    }
+
+    if not_attempted > 0 {
+        let max_runtime = args.max_runtime.unwrap_or_default();
+        return Err(DeadlineExceeded(format!(
+            "exceeded --max-runtime of {}; {} branch(es) were not attempted",
+            humantime::format_duration(max_runtime),
+            group_digits(not_attempted)
+        ))
+        .into());
+    }
+    if interrupted > 0 {
+        return Err(Interrupted(interrupted).into());
+    }
+    if has_failures {
+        anyhow::bail!("Failed to delete some branches");
+    }
+    if args.exit_code && !report.branches.is_empty() {
+        return Err(BranchesFound(report.branches.len()).into());
+    }
+    if args.check && planned > 0 {
+        return Err(PlanNotEmpty(planned).into());
+    }
+
+    Ok(())
+}
+
+/// The tidying up after deleting: `--prune-empty-dirs`, `--prune-config-branches`, the
+/// timings, the history, and the notification
+fn tidy_up(
+    args: &Args,
+    options: &Options,
+    report: &Report,
+    timings: &[(&str, Duration)],
+) -> Result<()> {
+    if args.prune_empty_dirs && !args.dry_run {
+        for dir in prune_empty_dirs(&git_path("refs/heads")?)? {
+            if options.verbose {
+                println!("Removed empty ref directory {}", dir.display());
+            }
+        }
+    }
+    if args.prune_config_branches && !args.dry_run {
+        prune_config_sections(report, args.format.is_machine())?;
+    }
+
+    if args.timings {
+        print_timings(timings);
+    }
+    if !args.no_history {
+        record_history(report);
+    }
+    if !args.dry_run
+        && let Some(path) = args.backup_log()?
+    {
+        write_backup_log(&path, report);
+    }
+    if args.notify {
+        send_notification(&notify::summary(report));
+    }
+    if !args.no_tips {
+        show_tip_once(args);
+    }
+    Ok(())
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+/// Prints the alias tip, the first time a run finishes for this user, in interactive use
+fn show_tip_once(args: &Args) {
+    // Scripts, hooks, and machine-readable output have no use for it
+    if args.format.is_machine() || args.hook.is_some() || !io::stdout().is_terminal() {
+        return;
+    }
+    let Some(config_dir) = user_config_dir(env::var_os("XDG_CONFIG_HOME"), env::var_os("HOME"))
+    else {
+        return;
+    };
+    if tips::claim(&config_dir.join(TIPS_MARKER)) {
+        println!("\n{ALIAS_TIP}");
+    }
+}
 
-    // Ensure we're in a git repository
-    ensure_git_repo()?;
+/// Assesses the gone branches against `--merged-base`, or else the resolved default base,
+/// taking `--merged-prs-file` into account, and returns the assessments and a description of
+/// what they were compared against
+fn assess_risk(
+    gone_branches: &[BranchLine],
+    args: &Args,
+    options: &Options,
+) -> Result<(Vec<Assessment>, String)> {
+    let fast = args.commit_graph_reachable;
+    let (mut assessments, base) = if args.merged_base.is_empty() {
+        let base = options.base()?;
+        let assessments = if fast {
+            assess_branches_in_graph(gone_branches, &base)?
+        } else {
+            assess_branches(gone_branches, &base)?
+        };
+        (assessments, base.to_string())
+    } else {
+        let bases: Vec<Base> = args.merged_base.iter().cloned().map(Base::Ref).collect();
+        let assessments = if fast {
+            assess_against_bases_in_graph(gone_branches, &bases)?
+        } else {
+            assess_against_bases(gone_branches, &bases)?
+        };
+        (assessments, args.merged_base.join(", "))
+    };
+    apply_merged_upstream(&mut assessments, &args.merged_prs);
+    Ok((assessments, base))
+}
+
+/// With `--format sarif` or `--format junit`, assesses every candidate, whether about to be
+/// deleted or held back, so each unmerged one can be reported; otherwise assesses nothing
+fn assess_for_sarif(
+    gone_branches: &[BranchLine],
+    held: &[Held],
+    args: &Args,
+    options: &Options,
+) -> Result<Vec<Assessment>> {
+    let candidates: Vec<BranchLine> = gone_branches
+        .iter()
+        .cloned()
+        .chain(held.iter().map(|held| held.branch.clone()))
+        .collect();
+    let reports_risk = args.renders(OutputFormat::Sarif) || args.renders(OutputFormat::Junit);
+    if !reports_risk || candidates.is_empty() {
+        return Ok(Vec::new());
+    }
+    assess_risk(&candidates, args, options).map(|(assessments, _)| assessments)
+}
 
-    // Fetch and prune
-    println!("Fetching and pruning remote branches...");
-    git_fetch_prune(args.verbose)?;
+/// Warns when a dry run would delete more than `--warn-large` branches, which usually
+/// means something (such as a removed remote) made everything look gone
+fn warn_if_large(count: usize, args: &Args) {
+    if args.dry_run && args.warn_large > 0 && count > args.warn_large {
+        eprintln!(
+            "\nwarning: this would delete {} branches, more than --warn-large {}. Review \
+             the list before a real run (did a remote vanish?), and consider --limit to delete \
+             in batches.",
+            group_digits(count),
+            group_digits(args.warn_large)
+        );
+    }
+}
 
-    // Find gone branches
-    let gone_branches = find_gone_branches(args.verbose)?;
+/// Applies `--fail-on-unmerged` and prints `--dedupe-identical-tips`, `--diff-upstream`,
+/// `--warn-shared-upstream`, and the base `--remote-head-fallback` picked, before anything is
+/// confirmed or deleted
+fn inspect_candidates(
+    gone_branches: &[BranchLine],
+    descriptions: &HashMap<String, String>,
+    args: &Args,
+    options: &Options,
+) -> Result<()> {
+    if args.fail_on_unmerged {
+        check_unmerged(gone_branches, args, options)?;
+    }
+    let print = |text: &str| {
+        if args.format.is_machine() {
+            eprint!("{text}");
+        } else {
+            print!("{text}");
+        }
+    };
+    print(&format_described(gone_branches, descriptions));
+    if args.dedupe_identical_tips {
+        print(&format_identical_tips(gone_branches));
+    }
+    if args.diff_upstream {
+        print(&format_upstream_states(&upstream_states(gone_branches)));
+    }
+    if args.warn_shared_upstream {
+        eprint!("{}", format_shared_upstreams(gone_branches));
+    }
+    if args.remote_head_fallback && args.merged_base.is_empty() {
+        let (base, source) = resolve_fallback_base(options.base_remote())?;
+        eprintln!("note: comparing against {base} ({source}, per --remote-head-fallback)");
+    }
+    if args.auto_safe && args.force {
+        warn_unique_commits(gone_branches)?;
+    }
+    Ok(())
+}
 
-    if gone_branches.is_empty() {
-        println!("No gone branches found.");
+/// Prints the `--explain` line of every local branch, assessing each candidate's merge state
+fn explain(
+    all_branches: &[BranchLine],
+    candidates: &HashSet<String>,
+    deletable: &[BranchLine],
+    held: &[Held],
+    args: &Args,
+    options: &Options,
+) -> Result<()> {
+    let assessed: Vec<BranchLine> = deletable
+        .iter()
+        .cloned()
+        .chain(held.iter().map(|held| held.branch.clone()))
+        .collect();
+    let (assessments, base) = if assessed.is_empty() {
+        (Vec::new(), String::new())
     } else {
-        println!("\nFound {} gone branch(es):", gone_branches.len());
-        for branch in &gone_branches {
-            println!("  - {branch}");
+        assess_risk(&assessed, args, options)?
+    };
+    let text = format_explanations(
+        all_branches,
+        candidates,
+        deletable,
+        held,
+        &assessments,
+        &base,
+    );
+    if args.format.is_machine() {
+        eprint!("\n{text}");
+    } else {
+        print!("\n{text}");
+    }
+    Ok(())
+}
+
+/// Warns about each gone branch with commits no other ref reaches, for `--auto-safe --force`,
+/// which deletes them anyway
+fn warn_unique_commits(gone_branches: &[BranchLine]) -> Result<()> {
+    for branch in gone_branches {
+        let unique = graph_unique_commit_count(&branch.name)?;
+        if unique > 0 {
+            eprintln!(
+                "warning: {} has {unique} commit(s) on no other ref, which deleting it loses (--force)",
+                branch.name
+            );
         }
+    }
+    Ok(())
+}
 
-        if args.dry_run {
-            println!(
-                "\n[DRY RUN] Would delete {} branch(es)",
-                gone_branches.len()
+/// Prints the plan hash of deleting `deletable`, failing if it isn't `--expect-plan-hash`
+fn check_plan_hash(deletable: &[BranchLine], args: &Args) -> Result<()> {
+    if deletable.is_empty() && args.expect_plan_hash.is_none() {
+        return Ok(());
+    }
+    let hash = plan_hash(deletable)?;
+    if args.format.is_machine() {
+        eprintln!("Plan hash: {hash}");
+    } else {
+        println!("Plan hash: {hash}");
+    }
+    match &args.expect_plan_hash {
+        // Nothing left to delete (e.g. a retry of a run that already deleted them), so there's
+        // nothing for the hash to guard
+        Some(expected) if deletable.is_empty() && !expected.eq_ignore_ascii_case(&hash) => {
+            eprintln!(
+                "note: nothing is left to delete, so the expected plan hash {expected} doesn't \
+                 apply"
             );
-        } else {
-            println!("\nDeleting gone branches...");
-            delete_branches(&gone_branches, args.verbose)?;
+            Ok(())
         }
+        Some(expected) if !expected.eq_ignore_ascii_case(&hash) => anyhow::bail!(
+            "This run's plan hash is {hash}, not the expected {expected} (--expect-plan-hash): \
+             the branches it would delete, or their tips, changed since that run; nothing was \
+             deleted. Review a new dry run, or use --plan-out and --plan-in to see which \
+             branches differ"
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// What's known about each gone branch's upstream, for `--diff-upstream`. It's only
+/// informational, so a branch that can't be looked up is warned about and left out.
+fn upstream_states(gone_branches: &[BranchLine]) -> Vec<UpstreamState> {
+    gone_branches
+        .iter()
+        .filter_map(|branch| match upstream_state(branch) {
+            Ok(state) => state,
+            Err(err) => {
+                eprintln!("warning: can't look up {}'s upstream: {err:#}", branch.name);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Fails with [`UnmergedBranches`] if any gone branch has commits its base doesn't
+fn check_unmerged(gone_branches: &[BranchLine], args: &Args, options: &Options) -> Result<()> {
+    let (assessments, _) = assess_risk(gone_branches, args, options)?;
+    let unmerged: Vec<String> = assessments
+        .into_iter()
+        .filter(|assessment| assessment.risk() == Risk::Risky)
+        .map(|assessment| assessment.name)
+        .collect();
+    if unmerged.is_empty() {
+        Ok(())
+    } else {
+        Err(UnmergedBranches(unmerged).into())
     }
+}
 
-    // Show remaining branches
-    println!("\nRemaining branches:");
-    show_all_branches()?;
+/// Re-lists the local branches to confirm that the deleted ones are really gone, marking any
+/// survivor (e.g. one held by a worktree) as failed
+fn verify_removed(report: &mut Report, options: &Options, machine: bool) -> Result<()> {
+    let remaining = local_branches(options.verbose)?;
+    for survivor in report.verify_removed(&remaining) {
+        eprintln!("error: {survivor} still exists after deletion");
+    }
+    let verified = report
+        .branches
+        .iter()
+        .filter(|branch| matches!(branch.action, Action::Deleted | Action::Archived))
+        .count();
+    if verified > 0 {
+        say(
+            machine,
+            &format!("Verified {} branch(es) removed", group_digits(verified)),
+        );
+    }
+    Ok(())
+}
 
+/// Checks that each archived branch's archive exists at the tip the branch had, listing the
+/// verified ones with their tips and marking any other as failed
+fn verify_archived(report: &mut Report, machine: bool) -> Result<()> {
+    if !report
+        .branches
+        .iter()
+        .any(|branch| branch.action == Action::Archived)
+    {
+        return Ok(());
+    }
+    let (verified, invalid) = report.verify_archived(&branch_tips()?);
+    for error in invalid {
+        eprintln!("error: {error}");
+    }
+    if !verified.is_empty() {
+        let mut message = format!("Verified {} archive(s):\n", group_digits(verified.len()));
+        for (archive, tip) in &verified {
+            let _ = writeln!(message, "  {archive} {tip}");
+        }
+        if machine {
+            eprint!("{message}");
+        } else {
+            print!("{message}");
+        }
+    }
     Ok(())
 }
 
-/// Ensures we're inside a git repository
-fn ensure_git_repo() -> Result<()> {
-    let output = Command::new("git")
-        .args(["rev-parse", "--git-dir"])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
-        .context("Failed to check if in git repository")?;
+/// Deletes the branches that were just deleted locally on `remote` too, after asking
+/// (`--yes` skips the question), recording each outcome in the report
+fn delete_on_remote(remote: &str, report: &mut Report, args: &Args) -> Result<()> {
+    let deleted: Vec<&mut BranchReport> = report
+        .branches
+        .iter_mut()
+        .filter(|branch| branch.action == Action::Deleted)
+        .collect();
+    if deleted.is_empty() {
+        return Ok(());
+    }
+
+    let machine = args.format.is_machine();
+    let question = format!(
+        "\nAlso delete {} branch(es) on remote '{remote}'?",
+        group_digits(deleted.len())
+    );
+    let confirmed = args.yes
+        || if machine {
+            args.confirm(&mut io::stderr(), &question, deleted.len())?
+        } else {
+            args.confirm(&mut io::stdout(), &question, deleted.len())?
+        };
 
-    if !output.success() {
-        anyhow::bail!("Not in a git repository");
+    // The branches are deleted on the remote in parallel, then reported in order
+    let results = if confirmed {
+        let names: Vec<&str> = deleted.iter().map(|branch| branch.name.as_str()).collect();
+        parallel::map(&names, |name| {
+            remote_branch_exists(remote, name).and_then(|exists| {
+                if exists {
+                    delete_remote_branch(remote, name).map(|()| RemoteOutcome::Deleted)
+                } else {
+                    Ok(RemoteOutcome::Missing)
+                }
+            })
+        })
+    } else {
+        deleted
+            .iter()
+            .map(|_| Ok(RemoteOutcome::Declined))
+            .collect()
+    };
+    for (branch, result) in deleted.into_iter().zip(results) {
+        let (outcome, error) = match result {
+            Ok(outcome) => (outcome, None),
+            Err(err) => {
+                eprintln!("error: {err:#}");
+                (RemoteOutcome::Failed, Some(format!("{err:#}")))
+            }
+        };
+        if !machine {
+            match outcome {
+                RemoteOutcome::Deleted => println!("Deleted {remote}/{}", branch.name),
+                RemoteOutcome::Missing => {
+                    println!("No {} on {remote}; nothing to delete there", branch.name);
+                }
+                RemoteOutcome::Declined | RemoteOutcome::Failed => {}
+            }
+        }
+        branch.remote_deletion = Some(RemoteDeletion {
+            remote: remote.to_string(),
+            outcome,
+            error,
+        });
     }
 
     Ok(())
 }
 
-/// Runs `git fetch -ap` to fetch and prune remote branches
-fn git_fetch_prune(verbose: bool) -> Result<()> {
-    let mut cmd = Command::new("git");
-    cmd.args(["fetch", "-ap"]);
+/// Reports on a run that a leftover checkpoint shows was interrupted, and asks whether to
+/// resume it (`--yes` resumes without asking).
+///
+/// Returns the branches the interrupted run hadn't deleted yet when resuming; when not,
+/// the checkpoint is discarded and `None` is returned.
+fn check_interrupted_run(path: &Path, args: &Args) -> Result<Option<Vec<String>>> {
+    let Some(checkpoint) = Checkpoint::load(path)? else {
+        return Ok(None);
+    };
+
+    let remaining: Vec<String> = checkpoint
+        .remaining()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let mut out = io::stderr();
+    writeln!(
+        out,
+        "A previous run was interrupted after deleting {} of {} branch(es).",
+        group_digits(checkpoint.deleted.len()),
+        group_digits(checkpoint.planned.len())
+    )?;
+    for name in &checkpoint.deleted {
+        writeln!(out, "  deleted: {name}")?;
+    }
+    for name in &remaining {
+        writeln!(out, "  not deleted: {name}")?;
+    }
 
-    if verbose {
-        cmd.status().context("Failed to execute git fetch -ap")?;
+    let resume = args.yes
+        || confirm(
+            &mut args.prompt_input(),
+            &mut out,
+            "Resume deleting the rest?",
+        )?;
+    if resume {
+        Ok(Some(remaining))
     } else {
-        cmd.stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .context("Failed to execute git fetch -ap")?;
+        skip_if_unwritable(checkpoint.finish(), "the checkpoint")?;
+        Ok(None)
+    }
+}
+
+/// Narrows `report` to the branches that weren't gone in the previous run, and remembers
+/// the current ones for the next run unless this is a dry run
+fn only_newly_gone(report: Report, machine: bool) -> Result<Report> {
+    let state_path = git_dir()?.join("git-clean-gone").join("seen.json");
+    let mut seen = Seen::load(&state_path)?;
+    let new: Vec<String> = seen
+        .newly_gone(&report)
+        .into_iter()
+        .map(|branch| branch.name.clone())
+        .collect();
+    if !report.dry_run {
+        skip_if_unwritable(seen.save(&state_path), "the --report-only-new state")?;
+    }
+
+    if !machine {
+        if new.is_empty() {
+            println!("\nNo newly gone branches since the last run.");
+        } else {
+            println!(
+                "\nNewly gone since the last run: {}",
+                group_digits(new.len())
+            );
+            for name in &new {
+                println!("  + {name}");
+            }
+        }
+    }
+
+    Ok(Report {
+        branches: report
+            .branches
+            .into_iter()
+            .filter(|branch| new.contains(&branch.name))
+            .collect(),
+        ..report
+    })
+}
+
+/// Lists the candidates `--treat-detached-upstream-as-gone` added, those whose upstream still
+/// exists, apart from the truly gone ones
+fn warn_unrelated_upstreams(gone_branches: &[BranchLine]) {
+    let unrelated: Vec<&BranchLine> = gone_branches
+        .iter()
+        .filter(|branch| branch.upstream.is_some() && !branch.divergence().is_some_and(|d| d.gone))
+        .collect();
+    if unrelated.is_empty() {
+        return;
+    }
+    eprintln!(
+        "warning: {} branch(es) share no history with their upstream and count as gone \
+         (--treat-detached-upstream-as-gone):",
+        group_digits(unrelated.len())
+    );
+    for branch in unrelated {
+        eprintln!(
+            "  - {} ({})",
+            branch.name,
+            branch.upstream.as_deref().unwrap_or_default()
+        );
+    }
+}
+
+/// Applies `--simulate-remote-deletion`, warning about named branches that don't exist
+fn simulate_remote_deletion(branches: Vec<BranchLine>, args: &Args) -> Vec<BranchLine> {
+    let names = &args.simulate_remote_deletion;
+    for name in names {
+        if !branches.iter().any(|branch| &branch.name == name) {
+            eprintln!("warning: can't simulate remote deletion of {name}: no such branch");
+        }
     }
+    simulate_gone(branches, names)
+}
 
+/// Runs as git's `post-merge` hook, without prompting. By default it only says how many gone
+/// branches there are; with `git config cleanGone.postMerge delete`, it deletes them.
+fn post_merge(options: Options) -> Result<()> {
+    let delete = match config_get_all("cleanGone.postMerge")?.pop().as_deref() {
+        None | Some("notify") => false,
+        Some("delete") => true,
+        Some(other) => {
+            anyhow::bail!("cleanGone.postMerge must be \"notify\" or \"delete\", not {other:?}")
+        }
+    };
+    let options = Options {
+        dry_run: !delete,
+        verbose: false,
+        quiet: true,
+        ..options
+    };
+    let report = clean_with(&options, |event| {
+        if let Event::Failed(_, err) = event {
+            eprintln!("git-clean-gone: error: {err:#}");
+        }
+        Decision::Proceed
+    })?;
+
+    let count = |action| {
+        report
+            .branches
+            .iter()
+            .filter(|branch| branch.action == action)
+            .count()
+    };
+    if delete {
+        let deleted = count(Action::Deleted);
+        if deleted > 0 {
+            println!(
+                "git-clean-gone: deleted {} gone branch(es)",
+                group_digits(deleted)
+            );
+        }
+    } else {
+        let gone = count(Action::WouldDelete);
+        if gone > 0 {
+            println!(
+                "git-clean-gone: {} gone branch(es) can be deleted; run `git clean-gone` to review them",
+                group_digits(gone)
+            );
+        }
+    }
     Ok(())
 }
 
-/// Finds branches marked as "gone" (deleted on remote)
-fn find_gone_branches(verbose: bool) -> Result<Vec<String>> {
-    let output = Command::new("git")
-        .args(["branch", "-vv"])
-        .output()
-        .context("Failed to execute git branch -vv")?;
+/// Re-checks for gone branches every interval, announcing newly gone ones, until Ctrl-C.
+///
+/// Checks are dry runs unless `--execute` is given, which also requires `--yes` since
+/// nobody is around to confirm each deletion.
+fn watch(args: &Args, watch_args: &WatchArgs, options: Options) -> Result<()> {
+    if watch_args.execute && !args.yes {
+        anyhow::bail!("watch --execute deletes branches unattended, so it also requires --yes");
+    }
+
+    let (stop_tx, stop_rx) = mpsc::channel();
+    ctrlc::set_handler(move || {
+        // The receiver only disappears once the loop has already stopped
+        let _ = stop_tx.send(());
+    })
+    .context("Failed to install the Ctrl-C handler")?;
+
+    let options = Options {
+        dry_run: !watch_args.execute,
+        verbose: args.verbose,
+        quiet: false,
+        ..options
+    };
+    println!(
+        "Watching for gone branches every {}{}; press Ctrl-C to stop.",
+        humantime::format_duration(watch_args.interval),
+        if options.dry_run { " (dry run)" } else { "" }
+    );
+
+    let mut seen = Seen::default();
+    loop {
+        let now = humantime::format_rfc3339_seconds(SystemTime::now());
+        let result = clean_with(&options, |event| {
+            if let Event::Failed(_, err) = event {
+                eprintln!("error: {err:#}");
+            }
+            Decision::Proceed
+        });
+        match result {
+            Ok(report) => {
+                let new = seen.newly_gone(&report);
+                if new.is_empty() {
+                    if args.verbose {
+                        println!("[{now}] No new gone branches");
+                    }
+                } else {
+                    // The bell makes terminals flag the tab, as a lightweight notification
+                    println!(
+                        "\x07[{now}] {} new gone branch(es):",
+                        group_digits(new.len())
+                    );
+                    for branch in &new {
+                        println!("  - {} ({})", branch.name, branch.action);
+                    }
+                    if args.notify {
+                        send_notification(&format!(
+                            "{} new gone branch(es). {}",
+                            group_digits(new.len()),
+                            notify::summary(&report)
+                        ));
+                    }
+                }
+            }
+            Err(err) => eprintln!("[{now}] warning: check failed: {err:#}"),
+        }
+
+        match stop_rx.recv_timeout(watch_args.interval) {
+            Err(RecvTimeoutError::Timeout) => {}
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    println!("\nStopped watching.");
+    Ok(())
+}
 
-    if !output.status.success() {
-        anyhow::bail!("git branch -vv failed");
+/// Refuses to `apply` a plan if any of its branches moved since it was made, showing what
+/// changed. Planned branches that no longer exist are only noted, since deleting them is what
+/// the plan was for, so applying a plan again (e.g. a retry after it succeeded) is a no-op.
+fn check_plan_drift(path: &Path) -> Result<()> {
+    let (missing, drift): (Vec<Drift>, Vec<Drift>) = Plan::read(path)?
+        .drift(&branch_tips()?)
+        .into_iter()
+        .partition(|drift| matches!(drift, Drift::Missing { .. }));
+    if !missing.is_empty() {
+        eprintln!(
+            "note: {} planned branch(es) no longer exist, so there's nothing left to do for \
+             them",
+            group_digits(missing.len())
+        );
+    }
+    if drift.is_empty() {
+        return Ok(());
     }
+    eprint!("{}", format_drift(&drift));
+    anyhow::bail!(
+        "{} planned branch(es) changed since the plan was made, so nothing was deleted; run \
+         `plan` again to review the current state",
+        group_digits(drift.len())
+    )
+}
+
+/// Which remotes the fetch covered (nothing with a plan, `--stdin`, or `--no-fetch`), and which
+/// remote decided each branch's gone state, for `--list-remotes-checked`
+fn remotes_checked(
+    all_branches: &[BranchLine],
+    gone: &[BranchLine],
+    args: &Args,
+    options: &Options,
+    planned: bool,
+) -> Result<Vec<RemoteCheck>> {
+    let remotes = list_remotes()?;
+    let branch_remotes = branch_remotes()?;
+    let fetched = if planned || args.stdin || args.no_fetch {
+        Fetched::Nothing
+    } else if options.fetch_command.is_some() {
+        Fetched::Unknown
+    } else {
+        let fetch_all = config_get_all("fetch.all")?.pop().is_some_and(|value| {
+            matches!(
+                value.to_ascii_lowercase().as_str(),
+                "true" | "yes" | "on" | "1"
+            )
+        });
+        let current_remote = all_branches
+            .iter()
+            .find(|branch| branch.is_current)
+            .and_then(|branch| branch_remotes.get(&branch.name))
+            .map(String::as_str);
+        Fetched::Remotes {
+            remotes: fetched_remotes(
+                options.fetch_remote.as_deref(),
+                &options.fetch_args,
+                fetch_all,
+                current_remote,
+                &remotes,
+            ),
+            pruned: !options.fetch_args.iter().any(|arg| arg == "--no-prune"),
+        }
+    };
+    let gone: HashSet<String> = gone.iter().map(|branch| branch.name.clone()).collect();
+    Ok(remote_checks(
+        all_branches,
+        &gone,
+        &branch_remotes,
+        &remotes,
+        &fetched,
+    ))
+}
 
-    let stdout =
-        String::from_utf8(output.stdout).context("Failed to parse git branch output as UTF-8")?;
+/// Looks up the plan's branches in the current branch listing, warning about any that
+/// no longer exist locally (e.g. deleted since the plan was made)
+fn planned_branches(plan: &Plan, branches: &[BranchLine]) -> Vec<BranchLine> {
+    plan.branches
+        .iter()
+        .filter_map(|planned| {
+            let branch = branches.iter().find(|branch| branch.name == planned.name);
+            if branch.is_none() {
+                eprintln!(
+                    "warning: planned branch {} no longer exists; skipping",
+                    planned.name
+                );
+            }
+            branch.cloned()
+        })
+        .collect()
+}
 
-    if verbose {
-        println!("\nBranch output:");
-        println!("{stdout}");
+/// Why `branch`'s current tip doesn't satisfy `--expect-shas`, if it doesn't
+fn expected_sha_reason(expected: &HashMap<String, String>, branch: &BranchLine) -> Option<String> {
+    match branch_tip(&branch.name) {
+        Ok(actual) => expected_sha_mismatch(expected, &branch.name, actual.as_deref()),
+        Err(err) => Some(format!("{err:#}")),
     }
+}
 
-    parse_gone_branches(&stdout)
+/// Checks that a branch still points where the plan recorded, warning if it moved
+fn tip_matches_plan(plan: &Plan, branch: &BranchLine) -> bool {
+    let expected = plan.expected_sha(&branch.name);
+    match branch_tip(&branch.name) {
+        Ok(Some(actual)) if Some(actual.as_str()) == expected => true,
+        Ok(actual) => {
+            eprintln!(
+                "warning: refusing to delete {}: tip is {} but the plan expected {}",
+                branch.name,
+                actual.as_deref().unwrap_or("missing"),
+                expected.unwrap_or("nothing")
+            );
+            false
+        }
+        Err(err) => {
+            eprintln!("warning: refusing to delete {}: {err:#}", branch.name);
+            false
+        }
+    }
 }
 
-/// Parses the output of `git branch -vv` to find branches with ": gone]" or ", gone]"
-#[allow(clippy::unnecessary_wraps)]
-fn parse_gone_branches(branch_output: &str) -> Result<Vec<String>> {
-    let gone_regex = Regex::new(r": gone\]").unwrap();
-    let current_branch_regex = Regex::new(r"^\*").unwrap();
+/// Shows the gone branches grouped by risk and asks which to delete, returning the names
+/// of the branches cleared for deletion.
+///
+/// Dry runs only print the grouping, and `--yes` skips the questions. Branches matching an
+/// `--auto-yes` pattern are cleared without asking. Branches tracking a remote that has no
+/// branches left are confirmed together per remote, and the rest in one batch. In
+/// machine-readable formats the summary and prompts go to stderr so stdout only carries the
+/// report.
+fn confirm_deletion(
+    gone_branches: &[BranchLine],
+    args: &Args,
+    options: &Options,
+) -> Result<HashSet<String>> {
+    let all = || gone_branches.iter().map(|b| b.name.clone()).collect();
+    let (verb, verbing, past) = if args.rename_to_archive {
+        ("Archive", "Archiving", "archived")
+    } else {
+        ("Delete", "Deleting", "deleted")
+    };
+    let machine = args.format.is_machine();
+    if machine && (args.dry_run || args.yes) {
+        return Ok(all());
+    }
 
-    let branches: Vec<String> = branch_output
-        .lines()
-        .filter(|line| gone_regex.is_match(line)) // Contains ": gone]"
-        .filter(|line| !current_branch_regex.is_match(line)) // Not current branch
-        .filter_map(|line| {
-            // Extract branch name (first non-whitespace token, possibly after '*')
-            line.split_whitespace()
-                .next()
-                .map(std::string::ToString::to_string)
+    // `--include-no-upstream` candidates are already known to be merged, so they're listed
+    // apart from the truly gone branches
+    let (upstreamless, truly_gone): (Vec<BranchLine>, Vec<BranchLine>) = gone_branches
+        .iter()
+        .cloned()
+        .partition(|branch| args.include_no_upstream && branch.upstream.is_none());
+    let (assessments, base) = assess_risk(&truly_gone, args, options)?;
+    let vanished = find_vanished_remotes(&truly_gone)?;
+    let mut out: Box<dyn Write> = if machine {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    };
+    // Listed under display names, as the assessments themselves still check the full ones
+    let shown: Vec<Assessment> = assessments
+        .iter()
+        .map(|assessment| Assessment {
+            name: truncate_name(&assessment.name, args.truncate_names).into_owned(),
+            ..assessment.clone()
+        })
+        .collect();
+    let shown_upstreamless: Vec<BranchLine> = upstreamless
+        .iter()
+        .map(|branch| BranchLine {
+            name: truncate_name(&branch.name, args.truncate_names).into_owned(),
+            ..branch.clone()
         })
         .collect();
+    match args.sort {
+        SortOrder::Name => write!(out, "\n{}", format_risk_summary(&shown, &base))?,
+        SortOrder::UniqueCommits => write!(out, "\n{}", format_risk_ranking(&shown, &base))?,
+    }
+    write!(out, "{}", format_upstreamless_summary(&shown_upstreamless))?;
+    write!(out, "{}", format_vanished_remotes(&vanished))?;
+    if let Some(by) = args.group_by() {
+        // Grouped under the full names, which the commit times and branch config are keyed by
+        let mut groups = group_branches(gone_branches, by)?;
+        for name in groups.iter_mut().flat_map(|group| &mut group.branches) {
+            *name = truncate_name(name, args.truncate_names).into_owned();
+        }
+        write!(out, "{}", format_groups(by, &groups))?;
+    }
+    if args.show_diffstat && !args.yes {
+        write_diffstats(&mut out, &assessments, args, options)?;
+    }
+
+    if args.dry_run {
+        writeln!(
+            out,
+            "\n[DRY RUN] Would {} {} branch(es)",
+            verb.to_lowercase(),
+            group_digits(gone_branches.len())
+        )?;
+        return Ok(all());
+    }
+    if args.yes {
+        writeln!(out, "\n{verbing} gone branches...")?;
+        return Ok(all());
+    }
+
+    let confirmed = if args.edit {
+        edit_selection(gone_branches, verb, args.select_invert, &mut out)?
+    } else {
+        let empty: HashSet<String> = assessments
+            .iter()
+            .filter(|assessment| {
+                args.assume_yes_for_empty_branches && assessment.unique_commits == 0
+            })
+            .map(|assessment| assessment.name.clone())
+            .collect();
+        ask_for_confirmation(gone_branches, &vanished, &empty, args, verb, &mut out)?
+    };
+    if confirmed.is_empty() {
+        writeln!(out, "Aborted; no branches {past}.")?;
+    } else if confirmed.len() == gone_branches.len() {
+        writeln!(out, "\n{verbing} gone branches...")?;
+    } else {
+        writeln!(
+            out,
+            "\n{verbing} {} of {} gone branch(es)...",
+            group_digits(confirmed.len()),
+            group_digits(gone_branches.len())
+        )?;
+    }
 
-    Ok(branches)
+    Ok(confirmed)
 }
 
-/// Deletes the specified branches using `git branch -D`
-fn delete_branches(branches: &[String], verbose: bool) -> Result<()> {
-    if branches.is_empty() {
-        return Ok(());
+/// Writes the diffstat of each risky branch in `assessments` since it forked from the base,
+/// for `--show-diffstat`
+fn write_diffstats<W: Write>(
+    out: &mut W,
+    assessments: &[Assessment],
+    args: &Args,
+    options: &Options,
+) -> Result<()> {
+    let base = match args.merged_base.first() {
+        Some(base) => base.clone(),
+        None => match options.base()? {
+            Base::Ref(base) => base,
+            Base::AllRemotes => {
+                eprintln!("warning: --show-diffstat needs a single base, and none resolves");
+                return Ok(());
+            }
+        },
+    };
+    for assessment in assessments.iter().filter(|a| a.risk() == Risk::Risky) {
+        writeln!(
+            out,
+            "\nChanges on {} since it forked from {base}:",
+            assessment.name
+        )?;
+        let stat = diffstat(&base, &assessment.name)?;
+        if stat.is_empty() {
+            writeln!(out, "  (no file changes)")?;
+        } else {
+            write!(out, "{stat}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Asks which branches to delete: those matching `--auto-yes` and the `empty` ones (with no
+/// unique commits, for `--assume-yes-for-empty-branches`) without asking, then those tracking
+/// each vanished remote together, then the rest in one batch
+fn ask_for_confirmation<W: Write>(
+    gone_branches: &[BranchLine],
+    vanished: &[VanishedRemote],
+    empty: &HashSet<String>,
+    args: &Args,
+    verb: &str,
+    out: &mut W,
+) -> Result<HashSet<String>> {
+    let mut confirmed: HashSet<String> = gone_branches
+        .iter()
+        .filter(|branch| matches_any(&args.auto_yes, &branch.name))
+        .map(|branch| branch.name.clone())
+        .collect();
+    if !confirmed.is_empty() {
+        writeln!(
+            out,
+            "\nAuto-confirmed by --auto-yes: {} branch(es)",
+            group_digits(confirmed.len())
+        )?;
+    }
+    let empty: Vec<&String> = gone_branches
+        .iter()
+        .map(|branch| &branch.name)
+        .filter(|name| empty.contains(*name) && !confirmed.contains(*name))
+        .collect();
+    if !empty.is_empty() {
+        writeln!(
+            out,
+            "\nAuto-confirmed by --assume-yes-for-empty-branches (no unique commits): {}",
+            group_digits(empty.len())
+        )?;
+        for name in &empty {
+            writeln!(out, "  - {name}")?;
+        }
+        confirmed.extend(empty.into_iter().cloned());
+    }
+
+    let mut input = args.prompt_input();
+    let mut asked: HashSet<String> = confirmed.clone();
+    for group in vanished {
+        let pending: Vec<&String> = group
+            .branches
+            .iter()
+            .filter(|name| !asked.contains(*name))
+            .collect();
+        if pending.is_empty() {
+            continue;
+        }
+        let question = format!(
+            "\nRemote '{}' appears fully removed; {} branch(es) track it. Delete all?",
+            group.remote,
+            group_digits(pending.len())
+        );
+        asked.extend(pending.iter().map(|name| (*name).clone()));
+        if confirm_with_style(
+            &mut input,
+            out,
+            &question,
+            args.prompt_style(),
+            pending.len(),
+        )? {
+            confirmed.extend(pending.into_iter().cloned());
+        }
+    }
+
+    let rest: Vec<&BranchLine> = gone_branches
+        .iter()
+        .filter(|branch| !asked.contains(&branch.name))
+        .collect();
+    if !rest.is_empty() {
+        let question = if rest.len() == gone_branches.len() {
+            format!("\n{verb} {} branch(es)?", group_digits(rest.len()))
+        } else {
+            format!(
+                "\n{verb} the other {} branch(es)?",
+                group_digits(rest.len())
+            )
+        };
+        if confirm_with_style(&mut input, out, &question, args.prompt_style(), rest.len())? {
+            confirmed.extend(rest.into_iter().map(|branch| branch.name.clone()));
+        }
     }
 
-    let mut cmd = Command::new("git");
-    cmd.arg("branch").arg("-D");
+    Ok(confirmed)
+}
 
-    for branch in branches {
-        cmd.arg(branch);
+/// Lets the user pick the branches to delete by editing the list in their editor (`--edit`), or
+/// the branches to keep with `--select-invert`
+fn edit_selection<W: Write>(
+    gone_branches: &[BranchLine],
+    verb: &str,
+    invert: bool,
+    out: &mut W,
+) -> Result<HashSet<String>> {
+    let list = format_edit_list(gone_branches, verb, invert);
+    let edited = edit_in_editor(&git_editor()?, &git_path("CLEAN_GONE_EDIT")?, &list)?;
+    if edited == list {
+        writeln!(out, "\nList unchanged; keeping every branch on it.")?;
     }
+    Ok(select_from_edit_list(&edited, gone_branches, invert)?
+        .into_iter()
+        .collect())
+}
+
+/// Warns about a dangling remote HEAD, re-resolving it when `fix` is set
+fn check_remote_head(remote: &str, fix: bool) -> Result<()> {
+    let Some(target) = dangling_remote_head(remote)? else {
+        return Ok(());
+    };
 
-    let status = if verbose {
-        cmd.status()
+    eprintln!("warning: refs/remotes/{remote}/HEAD points to {target}, which no longer exists");
+    if fix {
+        fix_remote_head(remote)?;
+        eprintln!("Re-resolved refs/remotes/{remote}/HEAD from {remote}");
     } else {
-        cmd.stdout(Stdio::inherit()).status()
+        eprintln!("  (the remote's default branch was likely renamed; re-run with --fix-head)");
     }
-    .context("Failed to execute git branch -D")?;
 
-    if !status.success() {
-        anyhow::bail!("Failed to delete some branches");
+    Ok(())
+}
+
+/// Lists the branches with orphaned tracking config and, unless `dry_run`, unsets it, for
+/// `--clean-orphan-tracking`
+fn clean_orphan_tracking(dry_run: bool, machine: bool) -> Result<()> {
+    let orphaned = find_orphaned_tracking(&branch_remotes()?, &list_remotes()?);
+    if orphaned.is_empty() {
+        return Ok(());
+    }
+
+    say(machine, format_orphaned_tracking(&orphaned).trim_end());
+    if dry_run {
+        say(machine, "[DRY RUN] Would unset their tracking config");
+        return Ok(());
+    }
+    let mut unset = 0;
+    for entry in &orphaned {
+        match unset_upstream_config(&entry.branch) {
+            Ok(()) => unset += 1,
+            Err(err) => eprintln!("warning: {err:#}"),
+        }
+    }
+    say(
+        machine,
+        &format!(
+            "Unset the tracking config of {} branch(es)",
+            group_digits(unset)
+        ),
+    );
+    Ok(())
+}
+
+/// Deletes local tags missing from the remote, asking for confirmation unless `--yes` is set
+fn clean_tags(args: &Args) -> Result<()> {
+    println!("Listing tags on {}...", args.remote());
+    let gone_tags = find_gone_tags(args.remote())?;
+
+    if gone_tags.is_empty() {
+        println!("No gone tags found.");
+        return Ok(());
+    }
+
+    println!(
+        "\nFound {} local tag(s) not on {}:",
+        group_digits(gone_tags.len()),
+        args.remote()
+    );
+    for tag in &gone_tags {
+        println!("  - {tag}");
+    }
+
+    if args.dry_run {
+        println!(
+            "\n[DRY RUN] Would delete {} tag(s)",
+            group_digits(gone_tags.len())
+        );
+        return Ok(());
+    }
+
+    // Tags often mark releases, so deleting them always needs an explicit yes
+    let question = format!("\nDelete {} tag(s)?", group_digits(gone_tags.len()));
+    if !args.yes && !args.confirm(&mut io::stdout(), &question, gone_tags.len())? {
+        println!("Aborted; no tags deleted.");
+        return Ok(());
+    }
+
+    let mut failed = false;
+    for tag in &gone_tags {
+        if let Err(err) = delete_tag(tag) {
+            eprintln!("error: {err:#}");
+            failed = true;
+        }
+    }
+
+    if failed {
+        anyhow::bail!("Failed to delete some tags");
     }
 
     Ok(())
 }
 
-/// Shows all branches (local and remote)
-fn show_all_branches() -> Result<()> {
-    let status = Command::new("git")
-        .args(["branch", "-a"])
-        .status()
-        .context("Failed to execute git branch -a")?;
+/// Finds remote-tracking refs whose branch is gone from their remote, confirms, and deletes them
+fn clean_remote_tracking(args: &Args) -> Result<()> {
+    println!("Listing branches on each remote...");
+    let stale_refs = find_stale_tracking_refs()?;
+    delete_stale_refs(
+        args,
+        &stale_refs,
+        "remote-tracking",
+        "whose branch is gone from the remote",
+    )
+}
+
+/// Lists the linked worktrees whose branch is gone, with how to remove those that can go, for
+/// `--include-orphan-worktrees`; with `--remove-orphan-worktrees`, removes those instead
+fn clean_orphan_worktrees(args: &Args) -> Result<()> {
+    let orphans = orphan_worktrees(list_worktrees()?, &local_branches(false)?);
+    let mut out: Box<dyn Write> = if args.format.is_machine() {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    };
+    if orphans.is_empty() {
+        writeln!(out, "\nNo worktrees with a gone branch found.")?;
+        return Ok(());
+    }
+
+    writeln!(
+        out,
+        "\nWorktrees whose branch is gone: {}",
+        group_digits(orphans.len())
+    )?;
+    let mut removable = Vec::new();
+    for worktree in &orphans {
+        let branch = worktree.branch.as_deref().unwrap_or_default();
+        let path = worktree.path.display();
+        if let Some(blocker) = worktree_blocker(worktree)? {
+            writeln!(out, "  - {path} ({branch}; kept: {})", blocker.as_str())?;
+        } else {
+            writeln!(out, "  - {path} ({branch})")?;
+            removable.push(worktree);
+        }
+    }
+    if removable.is_empty() {
+        return Ok(());
+    }
 
-    if !status.success() {
-        anyhow::bail!("git branch -a failed");
+    let count = group_digits(removable.len());
+    if !args.remove_orphan_worktrees || args.dry_run {
+        writeln!(
+            out,
+            "\nTo remove {count} of them, so the next run can delete their branches:"
+        )?;
+        for worktree in &removable {
+            let path = shell_words::quote(&worktree.path.to_string_lossy()).into_owned();
+            writeln!(out, "  git worktree remove {path}")?;
+        }
+        return Ok(());
+    }
+    let question = format!("\nRemove {count} worktree(s)?");
+    if !args.yes && !args.confirm(&mut out, &question, removable.len())? {
+        writeln!(out, "Aborted; no worktrees removed.")?;
+        return Ok(());
     }
 
+    let mut removed = 0;
+    for worktree in &removable {
+        match remove_worktree(&worktree.path) {
+            Ok(()) => removed += 1,
+            Err(err) => eprintln!("error: {err:#}"),
+        }
+    }
+    writeln!(
+        out,
+        "Removed {} of {count} worktree(s); the next run deletes their branches.",
+        group_digits(removed)
+    )?;
+    if removed < removable.len() {
+        anyhow::bail!("Failed to remove some worktrees");
+    }
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Finds refs under the `--prune-stale-pr-refs` namespace whose pull/merge request ref is gone
+/// from the remote, confirms, and deletes them
+fn clean_pr_refs(args: &Args, namespace: &str) -> Result<()> {
+    println!("Listing pull request refs on {}...", args.remote());
+    let stale_refs = find_stale_pr_refs(args.remote(), namespace)?;
+    delete_stale_refs(
+        args,
+        &stale_refs,
+        "PR",
+        "whose pull request is gone from the remote",
+    )
+}
+
+/// Cleans up after `remote` was removed, for `--removed-remote`: the local branches still
+/// configured to track it go through the usual cleanup, then the refs and upstream config it
+/// left behind are confirmed and removed together
+fn clean_removed_remote(args: &Args, options: &Options, remote: &str) -> Result<()> {
+    if list_remotes()?.iter().any(|r| r == remote) {
+        anyhow::bail!(
+            "{remote} is still a configured remote; remove it first (git remote remove {remote})"
+        );
+    }
+    let (_, would_delete) = clean_branches_planned(args, options, false)?;
+
+    // Deleting a branch removes its config, so what's listed now is only what was left behind,
+    // once a dry run leaves out the branches it would have deleted
+    let refs = list_refs_under(&format!("refs/remotes/{remote}/"))?;
+    let mut configs = branches_configured_for(&branch_remotes()?, remote);
+    configs.retain(|branch| !would_delete.contains(branch));
+    if refs.is_empty() && configs.is_empty() {
+        println!("\nNo refs or branch config of {remote} are left.");
+        return Ok(());
+    }
+
+    println!("\nLeft behind by {remote}:");
+    for refname in &refs {
+        println!("  - {refname}");
+    }
+    for branch in &configs {
+        println!("  - branch.{branch}.remote = {remote}");
+    }
+    let summary = format!(
+        "{} ref(s) and the upstream config of {} branch(es)",
+        group_digits(refs.len()),
+        group_digits(configs.len())
+    );
+    if args.dry_run {
+        println!("\n[DRY RUN] Would remove {summary}");
+        return Ok(());
+    }
+    let count = refs.len() + configs.len();
+    let question = format!("\nRemove {summary}?");
+    if !args.yes && !args.confirm(&mut io::stdout(), &question, count)? {
+        println!("Aborted; nothing else of {remote} removed.");
+        return Ok(());
+    }
+
+    let failed = refs
+        .iter()
+        .map(|refname| delete_ref(refname))
+        .chain(configs.iter().map(|branch| unset_upstream_config(branch)))
+        .filter_map(Result::err)
+        .inspect(|err| eprintln!("error: {err:#}"))
+        .count();
+    println!(
+        "Removed {} of {} ref(s) and upstream config entries of {remote}.",
+        group_digits(count - failed),
+        group_digits(count)
+    );
+    if failed > 0 {
+        anyhow::bail!("Failed to remove some of what {remote} left behind");
+    }
+    Ok(())
+}
 
-    #[test]
-    fn test_parse_gone_branches_empty() {
-        let output = "";
-        let branches = parse_gone_branches(output).unwrap();
-        assert_eq!(branches.len(), 0);
+/// Lists `stale_refs`, then deletes them after asking (`--yes` skips the question), reporting
+/// counts as "{kind} ref(s)"
+fn delete_stale_refs(args: &Args, stale_refs: &[String], kind: &str, why: &str) -> Result<()> {
+    if stale_refs.is_empty() {
+        println!("No stale {kind} refs found.");
+        return Ok(());
     }
 
-    #[test]
-    fn test_parse_gone_branches_no_gone() {
-        let output = r"
-  feature-1    abc1234 [origin/feature-1] Some commit
-  feature-2    def5678 [origin/feature-2] Another commit
-* main         ghi9012 [origin/main] Latest commit
-";
-        let branches = parse_gone_branches(output).unwrap();
-        assert_eq!(branches.len(), 0);
+    println!(
+        "\nFound {} {kind} ref(s) {why}:",
+        group_digits(stale_refs.len())
+    );
+    for refname in stale_refs {
+        println!("  - {refname}");
     }
 
-    #[test]
-    fn test_parse_gone_branches_with_gone() {
-        let output = r"
-  feature-1    abc1234 [origin/feature-1: gone] Some commit
-  feature-2    def5678 [origin/feature-2] Another commit
-  old-feature  ghi9012 [origin/old-feature: gone] Old commit
-* main         jkl3456 [origin/main] Latest commit
-";
-        let branches = parse_gone_branches(output).unwrap();
-        assert_eq!(branches.len(), 2);
-        assert!(branches.contains(&"feature-1".to_string()));
-        assert!(branches.contains(&"old-feature".to_string()));
-        assert!(!branches.contains(&"main".to_string()));
+    if args.dry_run {
+        println!(
+            "\n[DRY RUN] Would delete {} {kind} ref(s)",
+            group_digits(stale_refs.len())
+        );
+        return Ok(());
     }
 
-    #[test]
-    fn test_parse_gone_branches_excludes_current() {
-        let output = r"
-  feature-1    abc1234 [origin/feature-1: gone] Some commit
-* current      def5678 [origin/current: gone] Current branch
-";
-        let branches = parse_gone_branches(output).unwrap();
-        assert_eq!(branches.len(), 1);
-        assert_eq!(branches[0], "feature-1");
+    let question = format!("\nDelete {} {kind} ref(s)?", group_digits(stale_refs.len()));
+    if !args.yes && !args.confirm(&mut io::stdout(), &question, stale_refs.len())? {
+        println!("Aborted; no {kind} refs deleted.");
+        return Ok(());
     }
 
-    #[test]
-    fn test_parse_gone_branches_with_ahead_behind() {
-        let output = r"
-  feature-1    abc1234 [origin/feature-1: ahead 2, gone] Some commit
-  feature-2    def5678 [origin/feature-2: behind 3] Another commit
-  feature-3    ghi9012 [origin/feature-3: ahead 1, behind 2, gone] Mixed commit
-";
-        let branches = parse_gone_branches(output).unwrap();
-        assert_eq!(branches.len(), 0);
-        // We don't want these cases to show up for removal (especially ahead cases) because
-        // then they'd get removed, and we'd lose the local unpushed changes.
+    let mut deleted = 0;
+    for refname in stale_refs {
+        match delete_ref(refname) {
+            Ok(()) => deleted += 1,
+            Err(err) => eprintln!("error: {err:#}"),
+        }
+    }
+    println!(
+        "Deleted {} of {} {kind} ref(s).",
+        group_digits(deleted),
+        group_digits(stale_refs.len())
+    );
 
-        // assert!(branches.contains(&"feature-1".to_string()));
-        // assert!(branches.contains(&"feature-3".to_string()));
+    if deleted < stale_refs.len() {
+        anyhow::bail!("Failed to delete some {kind} refs");
     }
 
-    #[test]
-    fn test_parse_gone_branches_complex_names() {
-        let output = r"
-  feature/JIRA-123    abc1234 [origin/feature/JIRA-123: gone] Ticket work
-  bugfix/fix-thing    def5678 [origin/bugfix/fix-thing: gone] Bug fix
-* main                ghi9012 [origin/main] Latest
-";
-        let branches = parse_gone_branches(output).unwrap();
-        assert_eq!(branches.len(), 2);
-        assert!(branches.contains(&"feature/JIRA-123".to_string()));
-        assert!(branches.contains(&"bugfix/fix-thing".to_string()));
+    Ok(())
+}
+
+/// Prints the duration of each phase to stderr, keeping stdout clean for reports
+fn print_timings(timings: &[(&str, Duration)]) {
+    eprintln!("\nTimings:");
+    for (phase, duration) in timings {
+        eprintln!("  {phase:<12} {:>8.3}s", duration.as_secs_f64());
     }
 }
@@ -0,0 +1,64 @@
+//! The "last clean" marker: when a cleanup last completed, so `--skip-if-cleaned-within` can
+//! make frequent calls (e.g. from a shell prompt hook) skip the whole run, fetch included.
+//!
+//! The marker is `.git/git-clean-gone-last-clean`, holding the Unix time of the last
+//! successful run that wasn't a dry run.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// The marker file's name inside the git directory
+pub const MARKER_FILE: &str = "git-clean-gone-last-clean";
+
+/// Reads when the last clean finished (Unix seconds); `None` if none is recorded, or the
+/// marker is unreadable, in which case the next run just goes ahead
+#[must_use]
+pub fn last_clean(path: &Path) -> Option<i64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Records `now` (Unix seconds) as the last clean
+///
+/// # Errors
+///
+/// Fails if the marker can't be written.
+pub fn record(path: &Path, now: i64) -> Result<()> {
+    fs::write(path, format!("{now}\n"))
+        .with_context(|| format!("Failed to write the last-clean marker {}", path.display()))
+}
+
+/// How long ago the last clean was, if it was within `window` of `now`
+#[must_use]
+pub fn cleaned_within(last: Option<i64>, window: Duration, now: i64) -> Option<Duration> {
+    let ago = u64::try_from(now.saturating_sub(last?)).unwrap_or(0);
+    (ago < window.as_secs()).then(|| Duration::from_secs(ago))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_clean_marker() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(MARKER_FILE);
+        assert_eq!(last_clean(&path), None);
+
+        record(&path, 1_000).unwrap();
+        let last = last_clean(&path);
+        assert_eq!(last, Some(1_000));
+
+        let hour = Duration::from_hours(1);
+        assert_eq!(
+            cleaned_within(last, hour, 1_600),
+            Some(Duration::from_mins(10))
+        );
+        assert_eq!(cleaned_within(last, hour, 1_000 + 3600), None);
+        assert_eq!(cleaned_within(None, hour, 1_600), None);
+
+        fs::write(&path, "garbage").unwrap();
+        assert_eq!(last_clean(&path), None);
+    }
+}
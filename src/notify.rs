@@ -0,0 +1,150 @@
+//! Desktop notifications summarizing a run, for `--notify` on long or unattended runs (such as
+//! `watch`).
+//!
+//! They're sent with the platform's own notifier when the `notify` feature is enabled (the
+//! default): `notify-send` on Linux and the BSDs, `osascript` on macOS, and a PowerShell toast
+//! on Windows. Without the feature, or without a notifier, [`send`] fails and callers just warn.
+
+use crate::report::{Action, Report, group_digits};
+use anyhow::Result;
+
+/// The title every notification is sent under
+pub const TITLE: &str = "git-clean-gone";
+
+/// The notification body for `report`, e.g. `Deleted 3, kept 2` (or, in a dry run,
+/// `Would delete 3, kept 2`), with the archived and failed counts when there are any
+#[must_use]
+pub fn summary(report: &Report) -> String {
+    let (deleted, archived, verb) = if report.dry_run {
+        (
+            report.count(Action::WouldDelete),
+            report.count(Action::WouldArchive),
+            "Would delete",
+        )
+    } else {
+        (
+            report.count(Action::Deleted),
+            report.count(Action::Archived),
+            "Deleted",
+        )
+    };
+    let mut parts = vec![format!("{verb} {}", group_digits(deleted))];
+    if archived > 0 {
+        parts.push(format!("archived {}", group_digits(archived)));
+    }
+    parts.push(format!(
+        "kept {}",
+        group_digits(report.count(Action::Skipped))
+    ));
+    let failed = report.count(Action::Failed);
+    if failed > 0 {
+        parts.push(format!("failed {}", group_digits(failed)));
+    }
+    parts.join(", ")
+}
+
+/// Shows a desktop notification with `body` under [`TITLE`]
+///
+/// # Errors
+///
+/// Fails if the `notify` feature is disabled, or the platform's notifier is missing or
+/// reports an error.
+pub fn send(body: &str) -> Result<()> {
+    #[cfg(feature = "notify")]
+    {
+        let mut command = notifier(TITLE, body);
+        let output = command.output().map_err(|err| {
+            anyhow::anyhow!(
+                "Desktop notifications aren't available: can't run {}: {err}",
+                command.get_program().to_string_lossy()
+            )
+        })?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Desktop notification failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+    #[cfg(not(feature = "notify"))]
+    {
+        let _ = body;
+        anyhow::bail!("Desktop notifications need the `notify` feature, which this build lacks")
+    }
+}
+
+/// The platform notifier's command for a notification; the title and body go in as
+/// arguments or environment variables, never into a script, so they need no quoting
+#[cfg(feature = "notify")]
+fn notifier(title: &str, body: &str) -> std::process::Command {
+    use std::process::Command;
+
+    if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.args([
+            "-e",
+            "on run argv",
+            "-e",
+            "display notification (item 2 of argv) with title (item 1 of argv)",
+            "-e",
+            "end run",
+            title,
+            body,
+        ]);
+        command
+    } else if cfg!(windows) {
+        const TOAST: &str = "\
+            $manager = [Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime]; \
+            $xml = $manager::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+            $text = $xml.GetElementsByTagName('text'); \
+            $null = $text.Item(0).AppendChild($xml.CreateTextNode($env:GIT_CLEAN_GONE_NOTIFY_TITLE)); \
+            $null = $text.Item(1).AppendChild($xml.CreateTextNode($env:GIT_CLEAN_GONE_NOTIFY_BODY)); \
+            $manager::CreateToastNotifier($env:GIT_CLEAN_GONE_NOTIFY_TITLE).Show([Windows.UI.Notifications.ToastNotification]::new($xml))";
+        let mut command = Command::new("powershell");
+        command
+            .args(["-NoProfile", "-NonInteractive", "-Command", TOAST])
+            .env("GIT_CLEAN_GONE_NOTIFY_TITLE", title)
+            .env("GIT_CLEAN_GONE_NOTIFY_BODY", body);
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.args(["--app-name", title, title, body]);
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_gone_branches;
+    use crate::report::BranchReport;
+
+    fn report(dry_run: bool, actions: &[Action]) -> Report {
+        let branches =
+            parse_gone_branches("  feature-1    abc1234 [origin/feature-1: gone] One\n").unwrap();
+        Report {
+            dry_run,
+            branches: actions
+                .iter()
+                .map(|&action| BranchReport::new(&branches[0], action))
+                .collect(),
+            age_histogram: None,
+            remotes_checked: None,
+        }
+    }
+
+    #[test]
+    fn test_summary_counts_deleted_and_kept() {
+        use Action::{Archived, Deleted, Failed, Skipped, WouldDelete};
+
+        let run = report(false, &[Deleted, Deleted, Skipped]);
+        assert_eq!(summary(&run), "Deleted 2, kept 1");
+
+        let dry_run = report(true, &[WouldDelete, Skipped, Skipped]);
+        assert_eq!(summary(&dry_run), "Would delete 1, kept 2");
+
+        let mixed = report(false, &[Deleted, Archived, Failed]);
+        assert_eq!(summary(&mixed), "Deleted 1, archived 1, kept 0, failed 1");
+    }
+}
@@ -0,0 +1,162 @@
+//! Detection of git operations in progress (rebase, `git am`, bisect, merge, cherry-pick,
+//! revert), so the branches they involve aren't deleted out from under them.
+//!
+//! Each operation leaves marker files in the git directory until it finishes or is aborted,
+//! such as `rebase-merge/head-name` (the branch being rebased) or `MERGE_HEAD` (the commits
+//! being merged), which is what's read here.
+
+use crate::parse::BranchLine;
+use std::fs;
+use std::path::Path;
+
+/// An operation in progress, and the branches and commits it involves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InProgress {
+    /// The operation, e.g. `rebase` or `bisect`
+    pub operation: &'static str,
+    /// The branches it involves by name, e.g. the branch being rebased
+    pub branches: Vec<String>,
+    /// The commits it involves, e.g. those being merged or cherry-picked
+    pub commits: Vec<String>,
+}
+
+impl InProgress {
+    /// Whether deleting `branch` could break the operation: it's named by the operation, or
+    /// its tip is one of the operation's commits
+    #[must_use]
+    pub fn involves(&self, branch: &BranchLine) -> bool {
+        self.branches.contains(&branch.name)
+            || (!branch.sha.is_empty()
+                && self
+                    .commits
+                    .iter()
+                    .any(|commit| commit.starts_with(&branch.sha)))
+    }
+}
+
+/// Lists the operations in progress in the repository whose git directory is `git_dir`, from
+/// their marker files; markers that can't be read count as absent
+#[must_use]
+pub fn in_progress_operations(git_dir: &Path) -> Vec<InProgress> {
+    let read = |name: &str| fs::read_to_string(git_dir.join(name)).ok();
+    let mut operations = Vec::new();
+
+    for dir in ["rebase-merge", "rebase-apply"] {
+        if !git_dir.join(dir).is_dir() {
+            continue;
+        }
+        // `git am` uses rebase-apply too, marking it with `applying`
+        let operation = if git_dir.join(dir).join("applying").exists() {
+            "am"
+        } else {
+            "rebase"
+        };
+        operations.push(InProgress {
+            operation,
+            branches: read(&format!("{dir}/head-name"))
+                .and_then(|head| head.trim().strip_prefix("refs/heads/").map(str::to_string))
+                .into_iter()
+                .collect(),
+            commits: [
+                read(&format!("{dir}/onto")),
+                read(&format!("{dir}/orig-head")),
+            ]
+            .into_iter()
+            .flatten()
+            .map(|commit| commit.trim().to_string())
+            .collect(),
+        });
+    }
+
+    // BISECT_START holds what was checked out when the bisect started: a branch name, or a
+    // commit if HEAD was detached
+    if let Some(start) = read("BISECT_START") {
+        let start = start.trim().to_string();
+        operations.push(InProgress {
+            operation: "bisect",
+            branches: vec![start.clone()],
+            commits: vec![start],
+        });
+    }
+
+    for (marker, operation) in [
+        ("MERGE_HEAD", "merge"),
+        ("CHERRY_PICK_HEAD", "cherry-pick"),
+        ("REVERT_HEAD", "revert"),
+    ] {
+        if let Some(heads) = read(marker) {
+            operations.push(InProgress {
+                operation,
+                branches: Vec::new(),
+                commits: heads.lines().map(|line| line.trim().to_string()).collect(),
+            });
+        }
+    }
+    operations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_branch_lines;
+
+    #[test]
+    fn test_no_markers_means_nothing_in_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(in_progress_operations(dir.path()), []);
+    }
+
+    #[test]
+    fn test_in_progress_operations_read_marker_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("rebase-merge")).unwrap();
+        fs::write(
+            dir.path().join("rebase-merge/head-name"),
+            "refs/heads/topic\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("rebase-merge/onto"),
+            "def5678aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("BISECT_START"), "bisected\n").unwrap();
+        fs::write(
+            dir.path().join("MERGE_HEAD"),
+            "ghi9012bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb\n",
+        )
+        .unwrap();
+
+        let operations = in_progress_operations(dir.path());
+        let names: Vec<&str> = operations.iter().map(|op| op.operation).collect();
+        assert_eq!(names, ["rebase", "bisect", "merge"]);
+        assert_eq!(operations[0].branches, ["topic"]);
+
+        let branches = parse_branch_lines(
+            r"
+  topic        abc1234 [origin/topic: gone] Being rebased
+  base         def5678 [origin/base: gone] Rebased onto
+  bisected     jkl3456 [origin/bisected: gone] Being bisected
+  merged-in    ghi9012 [origin/merged-in: gone] Being merged
+  unrelated    mno7890 [origin/unrelated: gone] Not involved
+",
+        );
+        let involved: Vec<&str> = branches
+            .iter()
+            .filter(|branch| operations.iter().any(|op| op.involves(branch)))
+            .map(|branch| branch.name.as_str())
+            .collect();
+        assert_eq!(involved, ["topic", "base", "bisected", "merged-in"]);
+    }
+
+    #[test]
+    fn test_am_is_told_apart_from_rebase() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("rebase-apply")).unwrap();
+        fs::write(dir.path().join("rebase-apply/applying"), "").unwrap();
+        let operations = in_progress_operations(dir.path());
+        assert_eq!(operations.len(), 1);
+        assert_eq!(operations[0].operation, "am");
+        assert!(operations[0].branches.is_empty());
+    }
+}
@@ -0,0 +1,170 @@
+//! Running git for several remotes, branches, or repositories at once (`--max-parallel-git`).
+//!
+//! Every parallel code path takes its slots from the same process-wide counter, so the bound
+//! holds for the whole run, even with several of them going at the same time.
+
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+/// The slots of the process-wide semaphore
+struct Slots {
+    /// At most this many at once, or [`default_limit`] until [`set_limit`] is called
+    limit: Option<NonZeroUsize>,
+    taken: usize,
+}
+
+static SLOTS: Mutex<Slots> = Mutex::new(Slots {
+    limit: None,
+    taken: 0,
+});
+static FREED: Condvar = Condvar::new();
+
+/// The default `--max-parallel-git`: the available parallelism, or 1 if it can't be told
+#[must_use]
+pub fn default_limit() -> NonZeroUsize {
+    thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+}
+
+/// Sets the most git processes [`map`] runs at once, for the rest of the process
+pub fn set_limit(limit: NonZeroUsize) {
+    lock().limit = Some(limit);
+    FREED.notify_all();
+}
+
+/// The most git processes [`map`] runs at once
+#[must_use]
+pub fn limit() -> NonZeroUsize {
+    lock().limit.unwrap_or_else(default_limit)
+}
+
+fn lock() -> MutexGuard<'static, Slots> {
+    // The counter stays consistent whatever panicked while holding the lock
+    SLOTS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Gives its slot back when dropped, even if the work in it panicked
+struct Taken;
+
+impl Drop for Taken {
+    fn drop(&mut self) {
+        lock().taken -= 1;
+        FREED.notify_one();
+    }
+}
+
+/// Waits for a free slot, then runs `f` in it
+fn in_slot<R>(f: impl FnOnce() -> R) -> R {
+    let mut slots = lock();
+    while slots.taken >= slots.limit.unwrap_or_else(default_limit).get() {
+        slots = FREED.wait(slots).unwrap_or_else(PoisonError::into_inner);
+    }
+    slots.taken += 1;
+    drop(slots);
+    let _taken = Taken;
+    f()
+}
+
+/// Calls `f` on each of `items` on up to [`limit`] threads, each call holding a slot while it
+/// runs its git, and returns the results in the order of `items`.
+///
+/// `f` must not call `map` itself: with every slot held by an outer call, the inner one would
+/// wait forever.
+pub fn map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = limit().get().min(items.len());
+    if workers <= 1 {
+        return items.iter().map(|item| in_slot(|| f(item))).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let work = || {
+        let mut done = Vec::new();
+        loop {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(item) = items.get(index) else {
+                return done;
+            };
+            done.push((index, in_slot(|| f(item))));
+        }
+    };
+    // The fixture a unit test points git at is per thread
+    #[cfg(test)]
+    let repo = crate::git::fixture::current();
+    let mut results: Vec<Option<R>> = items.iter().map(|_| None).collect();
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    #[cfg(test)]
+                    if let Some(repo) = &repo {
+                        return crate::git::fixture::in_repo(repo, work);
+                    }
+                    work()
+                })
+            })
+            .collect();
+        for handle in handles {
+            let done = handle
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+            for (index, result) in done {
+                results[index] = Some(result);
+            }
+        }
+    });
+    results.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_map_keeps_the_order_of_items() {
+        let items: Vec<usize> = (0..100).collect();
+        let doubled: Vec<usize> = items.iter().map(|i| i * 2).collect();
+        assert_eq!(map(&items, |i| i * 2), doubled);
+        assert!(map(&[] as &[usize], |i| *i).is_empty());
+    }
+
+    /// Held by the tests that set the limit, which is process-wide
+    static LIMIT: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn test_map_stays_within_the_limit() {
+        let _limit = LIMIT.lock().unwrap_or_else(PoisonError::into_inner);
+        let previous = limit();
+        set_limit(NonZeroUsize::new(2).unwrap());
+
+        let running = AtomicUsize::new(0);
+        let most = AtomicUsize::new(0);
+        let items: Vec<usize> = (0..32).collect();
+        map(&items, |i| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            most.fetch_max(now, Ordering::SeqCst);
+            // The first item waits for the second worker, which may wait for a slot another
+            // test's git holds
+            if *i == 0 {
+                for _ in 0..1000 {
+                    if most.load(Ordering::SeqCst) >= 2 {
+                        break;
+                    }
+                    thread::sleep(Duration::from_millis(5));
+                }
+            }
+            thread::sleep(Duration::from_millis(5));
+            running.fetch_sub(1, Ordering::SeqCst);
+        });
+        set_limit(previous);
+
+        assert_eq!(most.load(Ordering::SeqCst), 2);
+        assert_eq!(running.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,561 @@
+//! Parsing of `git branch -vv` output.
+
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::hash::BuildHasher;
+use std::sync::LazyLock;
+
+/// Matches one branch line: marker (`*` for the current branch, `+` for one checked out in
+/// another worktree), name, tip, the other worktree's path in parentheses for `+` lines,
+/// optional `[upstream: tracking]`, and subject
+static BRANCH_LINE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?:(?P<marker>[* ]) (?P<name>[^\s(]\S*)\s+(?P<sha>\S+)|\+ (?P<wt_name>[^\s(]\S*)\s+(?P<wt_sha>\S+)\s+\((?P<worktree>[^)]*)\))(?:\s+\[(?P<upstream>[^:\]]+)(?:: (?P<tracking>[^\]]+))?\])?\s?(?P<subject>.*)$",
+    )
+    .unwrap()
+});
+
+/// Matches an ANSI escape sequence that sets colors or other attributes (e.g. `ESC[32m`)
+static ANSI_ESCAPE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\x1b\[[0-9;]*[A-Za-z]").unwrap());
+
+/// A single branch entry from the output of `git branch -vv`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BranchLine {
+    pub name: String,
+    pub sha: String,
+    pub upstream: Option<String>,
+    /// Tracking state inside the brackets after the upstream (e.g. "gone", "ahead 2, behind 1")
+    pub tracking: Option<String>,
+    pub subject: String,
+    pub is_current: bool,
+    /// Checked out in another linked worktree (marked `+` by `git branch`)
+    pub in_other_worktree: bool,
+}
+
+impl BranchLine {
+    /// Whether the upstream is gone with no local-only commits, and the branch isn't checked out
+    #[must_use]
+    pub fn is_deletable_gone(&self) -> bool {
+        !self.is_checked_out() && self.tracking.as_deref() == Some("gone")
+    }
+
+    /// Whether the branch is checked out here or in another worktree, so git won't delete it
+    #[must_use]
+    pub fn is_checked_out(&self) -> bool {
+        self.is_current || self.in_other_worktree
+    }
+
+    /// The branch's ahead/behind counts and gone state, if it has a tracking segment
+    #[must_use]
+    pub fn divergence(&self) -> Option<Divergence> {
+        self.tracking.as_deref().and_then(parse_tracking)
+    }
+}
+
+/// How a branch relates to its upstream, per the tracking segment of `git branch -vv`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Divergence {
+    /// Commits on the branch that aren't on its upstream
+    pub ahead: u32,
+    /// Commits on the upstream that aren't on the branch
+    pub behind: u32,
+    /// Whether the upstream no longer exists
+    pub gone: bool,
+}
+
+impl Divergence {
+    /// Whether either count is nonzero
+    #[must_use]
+    pub fn has_counts(&self) -> bool {
+        self.ahead > 0 || self.behind > 0
+    }
+
+    /// The counts as git words them (e.g. "ahead 2, behind 1"), or "up to date" without any
+    #[must_use]
+    pub fn summary(&self) -> String {
+        match (self.ahead, self.behind) {
+            (0, 0) => "up to date".to_string(),
+            (ahead, 0) => format!("ahead {ahead}"),
+            (0, behind) => format!("behind {behind}"),
+            (ahead, behind) => format!("ahead {ahead}, behind {behind}"),
+        }
+    }
+}
+
+/// Parses a tracking segment such as "ahead 2, behind 1" or "ahead 1, gone".
+///
+/// Returns `None` if any part isn't one git writes, so an unfamiliar (e.g. localized) segment
+/// isn't mistaken for an up-to-date branch.
+#[must_use]
+pub fn parse_tracking(segment: &str) -> Option<Divergence> {
+    let mut divergence = Divergence::default();
+    for part in segment.split(", ") {
+        match part.split_once(' ') {
+            Some(("ahead", count)) => divergence.ahead = count.parse().ok()?,
+            Some(("behind", count)) => divergence.behind = count.parse().ok()?,
+            None if part == "gone" => divergence.gone = true,
+            _ => return None,
+        }
+    }
+    Some(divergence)
+}
+
+/// Parses each line of `git branch -vv` into its name, tip, upstream, and commit subject.
+///
+/// Lines that don't describe a named branch (e.g. a detached HEAD) are skipped, as are refs
+/// outside `refs/heads` that a hand-made listing (e.g. for `--stdin`) might include, such as
+/// `refs/stash` or `stash@{0}` (see [`is_internal_ref_name`]).
+///
+/// Colors (e.g. from `color.branch=always` in a listing piped to `--stdin`) are stripped first
+/// (see [`strip_ansi`]).
+#[must_use]
+pub fn parse_branch_lines(branch_output: &str) -> Vec<BranchLine> {
+    strip_ansi(branch_output)
+        .lines()
+        .filter_map(|line| BRANCH_LINE_REGEX.captures(line))
+        .filter_map(|caps| {
+            let in_other_worktree = caps.name("worktree").is_some();
+            let (name, sha) = if in_other_worktree {
+                ("wt_name", "wt_sha")
+            } else {
+                ("name", "sha")
+            };
+            let name = caps[name].to_string();
+            let sha = caps[sha].to_string();
+            (!is_internal_ref_name(&name)).then_some((caps, name, sha, in_other_worktree))
+        })
+        .map(|(caps, name, sha, in_other_worktree)| BranchLine {
+            name,
+            sha,
+            upstream: caps.name("upstream").map(|m| m.as_str().to_string()),
+            tracking: caps.name("tracking").map(|m| m.as_str().to_string()),
+            subject: caps["subject"].to_string(),
+            is_current: caps.name("marker").is_some_and(|m| m.as_str() == "*"),
+            in_other_worktree,
+        })
+        .collect()
+}
+
+/// Removes the ANSI escape sequences git colors its output with
+#[must_use]
+pub fn strip_ansi(text: &str) -> Cow<'_, str> {
+    ANSI_ESCAPE_REGEX.replace_all(text, "")
+}
+
+/// Whether `name` is a fully qualified ref (e.g. `refs/stash`) or a reflog entry (e.g.
+/// `stash@{0}`) rather than a branch name.
+///
+/// Neither is ever a branch to delete: `@{` is invalid in branch names, and `git branch -D` on a
+/// `refs/...` name would resolve under `refs/heads` anyway, so a real branch named like that is
+/// left alone too.
+#[must_use]
+pub fn is_internal_ref_name(name: &str) -> bool {
+    name.starts_with("refs/") || name.contains("@{")
+}
+
+/// Adjusts parsed branches for a bare repository.
+///
+/// A bare repository has no working tree, so the `*` in `git branch -vv` only shows where
+/// `HEAD` points; no branch is checked out, and that one can be cleaned up like any other.
+#[must_use]
+pub fn in_bare_repository(branches: Vec<BranchLine>) -> Vec<BranchLine> {
+    branches
+        .into_iter()
+        .map(|branch| BranchLine {
+            is_current: false,
+            ..branch
+        })
+        .collect()
+}
+
+/// Marks the named branches' upstreams as gone, whatever their real tracking state, so the
+/// cleanup can be exercised without deleting anything on a remote (a testing aid)
+#[must_use]
+pub fn simulate_gone(branches: Vec<BranchLine>, names: &[String]) -> Vec<BranchLine> {
+    branches
+        .into_iter()
+        .map(|branch| {
+            if names.contains(&branch.name) {
+                BranchLine {
+                    tracking: Some("gone".to_string()),
+                    ..branch
+                }
+            } else {
+                branch
+            }
+        })
+        .collect()
+}
+
+/// Selects the branches that can be deleted because their upstream is gone
+#[must_use]
+pub fn gone_branches(branches: &[BranchLine]) -> Vec<BranchLine> {
+    branches
+        .iter()
+        .filter(|branch| branch.is_deletable_gone())
+        .cloned()
+        .collect()
+}
+
+/// Selects the branches that never had an upstream set and aren't checked out, the
+/// candidates for `--include-no-upstream`
+#[must_use]
+pub fn upstreamless_branches(branches: &[BranchLine]) -> Vec<BranchLine> {
+    branches
+        .iter()
+        .filter(|branch| !branch.is_checked_out() && branch.upstream.is_none())
+        .cloned()
+        .collect()
+}
+
+/// Looks up the names in `listing` (one per line, with blank lines and `#` comments ignored)
+/// among `branches`, returning the branches found, in listing order without duplicates, and the
+/// names that aren't there
+#[must_use]
+pub fn select_listed(branches: &[BranchLine], listing: &str) -> (Vec<BranchLine>, Vec<String>) {
+    let mut seen = HashSet::new();
+    let (mut found, mut missing) = (Vec::new(), Vec::new());
+    for name in listing
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+    {
+        if !seen.insert(name) {
+            continue;
+        }
+        match branches.iter().find(|branch| branch.name == name) {
+            Some(branch) => found.push(branch.clone()),
+            None => missing.push(name.to_string()),
+        }
+    }
+    (found, missing)
+}
+
+/// Keeps the branches whose configured remote (per `branch_remotes`, branch name to remote) is
+/// `remote`, for when only one remote is authoritative about what's gone
+#[must_use]
+pub fn tracking_remote<S: BuildHasher>(
+    branches: Vec<BranchLine>,
+    remote: &str,
+    branch_remotes: &HashMap<String, String, S>,
+) -> Vec<BranchLine> {
+    branches
+        .into_iter()
+        .filter(|branch| {
+            branch_remotes
+                .get(&branch.name)
+                .is_some_and(|r| r == remote)
+        })
+        .collect()
+}
+
+/// Parses the output of `git branch -vv` to find branches with ": gone]" or ", gone]"
+///
+/// # Errors
+///
+/// Currently infallible; the `Result` is kept so stricter parsing can report problems.
+#[allow(clippy::unnecessary_wraps)]
+pub fn parse_gone_branches(branch_output: &str) -> Result<Vec<BranchLine>> {
+    Ok(gone_branches(&parse_branch_lines(branch_output)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(branches: Vec<BranchLine>) -> Vec<String> {
+        branches.into_iter().map(|branch| branch.name).collect()
+    }
+
+    #[test]
+    fn test_upstreamless_branches() {
+        let branches = parse_branch_lines(
+            r"
+  feature-1    abc1234 [origin/feature-1: gone] Feature
+  scratch      def5678 Local experiment
+* wip          1234567 Checked out
+  keep-me      89abcde [origin/keep-me] Live
+",
+        );
+        assert_eq!(names(upstreamless_branches(&branches)), ["scratch"]);
+    }
+
+    #[test]
+    fn test_parse_gone_branches_empty() {
+        let output = "";
+        let branches = names(parse_gone_branches(output).unwrap());
+        assert_eq!(branches.len(), 0);
+    }
+
+    #[test]
+    fn test_parse_gone_branches_no_gone() {
+        let output = r"
+  feature-1    abc1234 [origin/feature-1] Some commit
+  feature-2    def5678 [origin/feature-2] Another commit
+* main         ghi9012 [origin/main] Latest commit
+";
+        let branches = names(parse_gone_branches(output).unwrap());
+        assert_eq!(branches.len(), 0);
+    }
+
+    #[test]
+    fn test_parse_gone_branches_with_gone() {
+        let output = r"
+  feature-1    abc1234 [origin/feature-1: gone] Some commit
+  feature-2    def5678 [origin/feature-2] Another commit
+  old-feature  ghi9012 [origin/old-feature: gone] Old commit
+* main         jkl3456 [origin/main] Latest commit
+";
+        let branches = names(parse_gone_branches(output).unwrap());
+        assert_eq!(branches.len(), 2);
+        assert!(branches.contains(&"feature-1".to_string()));
+        assert!(branches.contains(&"old-feature".to_string()));
+        assert!(!branches.contains(&"main".to_string()));
+    }
+
+    #[test]
+    fn test_parse_gone_branches_with_colors() {
+        // As `git -c color.branch=always branch -vv` writes it
+        let output = "\
+* \x1b[32mmain     \x1b[m abc1234 [\x1b[34morigin/main\x1b[m] Latest commit
+  feature-1\x1b[m def5678 [\x1b[34morigin/feature-1\x1b[m: \x1b[31mgone\x1b[m] Some commit
+  \x1b[1;33mwip\x1b[m      ghi9012 [\x1b[34morigin/wip\x1b[m: ahead 1] Work in progress
+";
+        let branches = parse_branch_lines(output);
+        assert_eq!(names(branches.clone()), ["main", "feature-1", "wip"]);
+        assert!(branches[0].is_current);
+        assert_eq!(branches[1].upstream.as_deref(), Some("origin/feature-1"));
+        assert_eq!(branches[1].tracking.as_deref(), Some("gone"));
+        assert_eq!(branches[1].subject, "Some commit");
+        assert_eq!(names(parse_gone_branches(output).unwrap()), ["feature-1"]);
+    }
+
+    #[test]
+    fn test_parse_gone_branches_excludes_current() {
+        let output = r"
+  feature-1    abc1234 [origin/feature-1: gone] Some commit
+* current      def5678 [origin/current: gone] Current branch
+";
+        let branches = names(parse_gone_branches(output).unwrap());
+        assert_eq!(branches.len(), 1);
+        assert_eq!(branches[0], "feature-1");
+    }
+
+    #[test]
+    fn test_parse_gone_branches_with_ahead_behind() {
+        let output = r"
+  feature-1    abc1234 [origin/feature-1: ahead 2, gone] Some commit
+  feature-2    def5678 [origin/feature-2: behind 3] Another commit
+  feature-3    ghi9012 [origin/feature-3: ahead 1, behind 2, gone] Mixed commit
+";
+        let branches = names(parse_gone_branches(output).unwrap());
+        assert_eq!(branches.len(), 0);
+        // We don't want these cases to show up for removal (especially ahead cases) because
+        // then they'd get removed, and we'd lose the local unpushed changes.
+
+        // assert!(branches.contains(&"feature-1".to_string()));
+        // assert!(branches.contains(&"feature-3".to_string()));
+    }
+
+    #[test]
+    fn test_parse_tracking_counts() {
+        let parse = |segment| parse_tracking(segment).unwrap();
+        assert_eq!(
+            parse("ahead 2"),
+            Divergence {
+                ahead: 2,
+                behind: 0,
+                gone: false
+            }
+        );
+        assert_eq!(
+            parse("behind 3"),
+            Divergence {
+                ahead: 0,
+                behind: 3,
+                gone: false
+            }
+        );
+        assert_eq!(
+            parse("ahead 1, behind 2"),
+            Divergence {
+                ahead: 1,
+                behind: 2,
+                gone: false
+            }
+        );
+        assert_eq!(
+            parse("gone"),
+            Divergence {
+                ahead: 0,
+                behind: 0,
+                gone: true
+            }
+        );
+        assert_eq!(
+            parse("ahead 2, gone"),
+            Divergence {
+                ahead: 2,
+                behind: 0,
+                gone: true
+            }
+        );
+        assert_eq!(
+            parse("ahead 1, behind 2, gone"),
+            Divergence {
+                ahead: 1,
+                behind: 2,
+                gone: true
+            }
+        );
+        assert_eq!(parse("ahead 1, behind 2").summary(), "ahead 1, behind 2");
+        assert_eq!(parse("gone").summary(), "up to date");
+    }
+
+    #[test]
+    fn test_parse_tracking_rejects_unknown_segments() {
+        assert_eq!(parse_tracking("disparu"), None);
+        assert_eq!(parse_tracking("ahead two"), None);
+        assert_eq!(parse_tracking("ahead 1, behind"), None);
+    }
+
+    #[test]
+    fn test_parse_branch_lines_ignores_stash_refs() {
+        let output = r"
+  feature-1    abc1234 [origin/feature-1: gone] Some commit
+  refs/stash   def5678 [origin/stash: gone] WIP on main
+  stash@{0}    def5678 [origin/stash: gone] WIP on main
+";
+        assert_eq!(names(parse_gone_branches(output).unwrap()), ["feature-1"]);
+        assert!(is_internal_ref_name("refs/stash"));
+        assert!(!is_internal_ref_name("stash"));
+    }
+
+    #[test]
+    fn test_parse_branch_lines_worktree_marker() {
+        let output = r"
++ in-worktree  abc1234 (/tmp/worktree) [origin/in-worktree: gone] Checked out elsewhere
++ wt-local     def5678 (/tmp/other) (wip) No upstream
+* main         ghi9012 [origin/main] Latest
+  feature-1    jkl3456 [origin/feature-1: gone] Some commit
+";
+        let branches = parse_branch_lines(output);
+        assert_eq!(branches.len(), 4);
+        assert_eq!(branches[0].name, "in-worktree");
+        assert!(branches[0].in_other_worktree);
+        assert!(!branches[0].is_current);
+        assert_eq!(branches[0].tracking.as_deref(), Some("gone"));
+        assert_eq!(branches[0].subject, "Checked out elsewhere");
+        assert_eq!(branches[1].name, "wt-local");
+        assert_eq!(branches[1].subject, "(wip) No upstream");
+        assert!(!branches[2].in_other_worktree);
+
+        assert_eq!(names(gone_branches(&branches)), ["feature-1"]);
+        assert!(upstreamless_branches(&branches).is_empty());
+    }
+
+    #[test]
+    fn test_parse_gone_branches_complex_names() {
+        let output = r"
+  feature/JIRA-123    abc1234 [origin/feature/JIRA-123: gone] Ticket work
+  bugfix/fix-thing    def5678 [origin/bugfix/fix-thing: gone] Bug fix
+* main                ghi9012 [origin/main] Latest
+";
+        let branches = names(parse_gone_branches(output).unwrap());
+        assert_eq!(branches.len(), 2);
+        assert!(branches.contains(&"feature/JIRA-123".to_string()));
+        assert!(branches.contains(&"bugfix/fix-thing".to_string()));
+    }
+
+    #[test]
+    fn test_parse_branch_lines_metadata() {
+        let output = r"
+  feature-1    abc1234 [origin/feature-1: gone] Fix: the <thing> & more
+  local-only   def5678 No upstream here
+* main         ghi9012 [origin/main] Latest commit
+* (HEAD detached at abc1234) abc1234 Detached
+";
+        let branches = parse_branch_lines(output);
+        assert_eq!(branches.len(), 3);
+
+        assert_eq!(branches[0].name, "feature-1");
+        assert_eq!(branches[0].sha, "abc1234");
+        assert_eq!(branches[0].upstream.as_deref(), Some("origin/feature-1"));
+        assert_eq!(branches[0].tracking.as_deref(), Some("gone"));
+        assert_eq!(branches[0].subject, "Fix: the <thing> & more");
+
+        assert_eq!(branches[1].name, "local-only");
+        assert_eq!(branches[1].upstream, None);
+        assert_eq!(branches[1].subject, "No upstream here");
+
+        assert!(branches[2].is_current);
+        assert_eq!(branches[2].tracking, None);
+    }
+
+    #[test]
+    fn test_bare_repository_has_no_current_branch() {
+        let output = r"
+  feature-1    abc1234 [origin/feature-1: gone] Some commit
+* main         def5678 [origin/main: gone] HEAD of a bare mirror
+";
+        let branches = in_bare_repository(parse_branch_lines(output));
+        assert!(branches.iter().all(|branch| !branch.is_current));
+        assert_eq!(names(gone_branches(&branches)), ["feature-1", "main"]);
+
+        // The same listing from a working tree keeps the checked-out branch
+        assert_eq!(names(parse_gone_branches(output).unwrap()), ["feature-1"]);
+    }
+
+    #[test]
+    fn test_simulate_gone() {
+        let output = r"
+  feature-1    abc1234 [origin/feature-1] Still on the remote
+  local-only   def5678 Never pushed
+* main         ghi9012 [origin/main] Latest
+";
+        let simulated = ["feature-1", "local-only", "main"].map(String::from);
+        let branches = simulate_gone(parse_branch_lines(output), &simulated);
+
+        // The checked-out branch still can't be deleted
+        assert_eq!(names(gone_branches(&branches)), ["feature-1", "local-only"]);
+    }
+
+    #[test]
+    fn test_tracking_remote() {
+        let gone = parse_gone_branches(
+            r"
+  feature-1    abc1234 [origin/feature-1: gone] On origin
+  feature-2    def5678 [upstream/feature-2: gone] On upstream
+",
+        )
+        .unwrap();
+        let remotes: HashMap<String, String> = [("feature-1", "origin"), ("feature-2", "upstream")]
+            .map(|(branch, remote)| (branch.to_string(), remote.to_string()))
+            .into();
+
+        assert_eq!(
+            names(tracking_remote(gone, "upstream", &remotes)),
+            ["feature-2"]
+        );
+    }
+
+    #[test]
+    fn test_select_listed() {
+        let branches = parse_branch_lines(
+            r"
+  feature-1    abc1234 [origin/feature-1] Still on the remote
+  feature-2    def5678 Never pushed
+",
+        );
+        let (found, missing) = select_listed(
+            &branches,
+            "feature-2\n\n# a comment\n  typo  \nfeature-1\nfeature-2\n",
+        );
+        assert_eq!(names(found), ["feature-2", "feature-1"]);
+        assert_eq!(missing, ["typo"]);
+    }
+}
@@ -0,0 +1,240 @@
+//! Glob patterns for selecting branches by name, the global protect file that lists some for
+//! every repository, and `--upstream-map`'s regex mapping of names to remote branches.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Where the global protect file lives under the user's config directory
+pub const GLOBAL_PROTECT_FILE: &str = "git-clean-gone/protect";
+
+/// A branch-name glob: `*` matches any run of characters (including `/`), `?` matches one
+/// character, and everything else matches literally.
+#[derive(Debug, Clone)]
+pub struct BranchPattern {
+    glob: String,
+    regex: Regex,
+}
+
+impl BranchPattern {
+    /// Compiles a glob such as `dependabot/*`
+    ///
+    /// # Errors
+    ///
+    /// Fails if the glob is empty.
+    pub fn new(glob: &str) -> Result<Self> {
+        if glob.is_empty() {
+            anyhow::bail!("Branch pattern may not be empty");
+        }
+
+        let mut source = String::from("^");
+        for c in glob.chars() {
+            match c {
+                '*' => source.push_str(".*"),
+                '?' => source.push('.'),
+                _ => source.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        source.push('$');
+
+        let regex = Regex::new(&source)
+            .with_context(|| format!("Failed to compile branch pattern {glob}"))?;
+        Ok(Self {
+            glob: glob.to_string(),
+            regex,
+        })
+    }
+
+    #[must_use]
+    pub fn matches(&self, name: &str) -> bool {
+        self.regex.is_match(name)
+    }
+}
+
+impl FromStr for BranchPattern {
+    type Err = anyhow::Error;
+
+    fn from_str(glob: &str) -> Result<Self> {
+        Self::new(glob)
+    }
+}
+
+impl fmt::Display for BranchPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.glob)
+    }
+}
+
+/// A `--upstream-map`: a regex over local branch names, and the replacement (with `$1`,
+/// `${name}`, ... for its capture groups) that turns a matching name into the
+/// remote-tracking branch it stands for, e.g. `^user/(.+)$` and `origin/$1`
+#[derive(Debug, Clone)]
+pub struct UpstreamMap {
+    regex: Regex,
+    replacement: String,
+}
+
+impl UpstreamMap {
+    /// Compiles the mapping
+    ///
+    /// # Errors
+    ///
+    /// Fails if `regex` isn't a valid regular expression.
+    pub fn new(regex: &str, replacement: &str) -> Result<Self> {
+        Ok(Self {
+            regex: Regex::new(regex)
+                .with_context(|| format!("Failed to compile --upstream-map regex {regex}"))?,
+            replacement: replacement.to_string(),
+        })
+    }
+
+    /// The remote-tracking branch (e.g. `origin/feature`) that `branch` maps to, or `None` if
+    /// the regex doesn't match it
+    #[must_use]
+    pub fn map(&self, branch: &str) -> Option<String> {
+        self.regex
+            .is_match(branch)
+            .then(|| self.regex.replace(branch, &self.replacement).into_owned())
+    }
+}
+
+/// Whether any of `patterns` matches `name`
+#[must_use]
+pub fn matches_any(patterns: &[BranchPattern], name: &str) -> bool {
+    patterns.iter().any(|pattern| pattern.matches(name))
+}
+
+/// The user's config directory: `$XDG_CONFIG_HOME` if it's set (and not empty), otherwise
+/// `$HOME/.config`, like git's own global ignore file. `None` if neither is set.
+#[must_use]
+pub fn user_config_dir(
+    xdg_config_home: Option<OsString>,
+    home: Option<OsString>,
+) -> Option<PathBuf> {
+    match xdg_config_home.filter(|dir| !dir.is_empty()) {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => Some(PathBuf::from(home.filter(|dir| !dir.is_empty())?).join(".config")),
+    }
+}
+
+/// The global protect file's path, under [`user_config_dir`]
+#[must_use]
+pub fn global_protect_path(
+    xdg_config_home: Option<OsString>,
+    home: Option<OsString>,
+) -> Option<PathBuf> {
+    Some(user_config_dir(xdg_config_home, home)?.join(GLOBAL_PROTECT_FILE))
+}
+
+/// Parses a list of globs, one per line; blank lines and lines starting with `#` are ignored
+///
+/// # Errors
+///
+/// Fails, naming the line, on an invalid glob.
+pub fn parse_pattern_list(text: &str) -> Result<Vec<BranchPattern>> {
+    text.lines()
+        .enumerate()
+        .map(|(index, line)| (index, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(index, line)| {
+            BranchPattern::new(line).with_context(|| format!("Line {}", index + 1))
+        })
+        .collect()
+}
+
+/// Loads the globs listed in the file at `path`, or `None` if there isn't one
+///
+/// # Errors
+///
+/// Fails if the file exists but can't be read or parsed.
+pub fn load_pattern_list(path: &Path) -> Result<Option<Vec<BranchPattern>>> {
+    match fs::read_to_string(path) {
+        Ok(text) => parse_pattern_list(&text)
+            .map(Some)
+            .with_context(|| format!("Invalid protect file {}", path.display())),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => {
+            Err(err).with_context(|| format!("Failed to read protect file {}", path.display()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_matching() {
+        let pattern = BranchPattern::new("dependabot/*").unwrap();
+        assert!(pattern.matches("dependabot/npm_and_yarn/lodash-4.17.21"));
+        assert!(pattern.matches("dependabot/"));
+        assert!(!pattern.matches("feature/dependabot/x"));
+        assert!(!pattern.matches("dependabot"));
+
+        let pattern = BranchPattern::new("release-?.x").unwrap();
+        assert!(pattern.matches("release-1.x"));
+        assert!(!pattern.matches("release-10.x"));
+        assert!(!pattern.matches("release-1-x"));
+    }
+
+    #[test]
+    fn test_glob_escapes_regex_syntax() {
+        let pattern = BranchPattern::new("fix(ci)+[wip]").unwrap();
+        assert!(pattern.matches("fix(ci)+[wip]"));
+        assert!(!pattern.matches("fixci"));
+    }
+
+    #[test]
+    fn test_matches_any() {
+        let patterns = ["renovate/*", "dependabot/*"].map(|glob| glob.parse().unwrap());
+        assert!(matches_any(&patterns, "renovate/serde-1.x"));
+        assert!(!matches_any(&patterns, "feature/x"));
+        assert!(!matches_any(&[], "anything"));
+        assert!(BranchPattern::new("").is_err());
+    }
+
+    #[test]
+    fn test_upstream_map_expands_capture_groups() {
+        let map = UpstreamMap::new("^user/(.+)$", "origin/$1").unwrap();
+        assert_eq!(
+            map.map("user/feature-1").as_deref(),
+            Some("origin/feature-1")
+        );
+        assert_eq!(map.map("feature-1"), None);
+
+        let named =
+            UpstreamMap::new(r"^(?P<team>\w+)-(?P<rest>.+)$", "upstream/${team}/${rest}").unwrap();
+        assert_eq!(named.map("core-fix").as_deref(), Some("upstream/core/fix"));
+        assert!(UpstreamMap::new("(", "x").is_err());
+    }
+
+    #[test]
+    fn test_global_protect_path_prefers_xdg_config_home() {
+        let path = |xdg: Option<&str>, home: Option<&str>| {
+            global_protect_path(xdg.map(OsString::from), home.map(OsString::from))
+        };
+        assert_eq!(
+            path(Some("/xdg"), Some("/home/me")),
+            Some(PathBuf::from("/xdg/git-clean-gone/protect"))
+        );
+        assert_eq!(
+            path(Some(""), Some("/home/me")),
+            Some(PathBuf::from("/home/me/.config/git-clean-gone/protect"))
+        );
+        assert_eq!(path(None, None), None);
+    }
+
+    #[test]
+    fn test_parse_pattern_list() {
+        let patterns =
+            parse_pattern_list("# Everywhere\npersonal-notes\n\n  scratch/*  \n").unwrap();
+        let globs: Vec<String> = patterns.iter().map(ToString::to_string).collect();
+        assert_eq!(globs, ["personal-notes", "scratch/*"]);
+        assert!(matches_any(&patterns, "scratch/idea"));
+    }
+}
@@ -0,0 +1,316 @@
+//! The cleanup run as explicit phases: Fetch → Enumerate → Filter → Confirm → Delete → Verify.
+//!
+//! Each phase consumes the [`State`] the previous one produced and returns the next, so a
+//! frontend only supplies what each phase does (see [`Phases`]) and [`run`] strings them
+//! together. The states are serializable, which lets a run be inspected, saved, or resumed
+//! between phases, and lets tests drive a single phase with [`State::advance`].
+
+use crate::parse::BranchLine;
+use crate::report::Report;
+use crate::safety::Held;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+
+/// A step of a cleanup run, in the order they run
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Phase {
+    /// Fetch and prune the remotes
+    Fetch,
+    /// List the local branches and pick out the gone ones
+    Enumerate,
+    /// Hold back the gone branches a safety check flags
+    Filter,
+    /// Decide (or ask) which of the rest to delete
+    Confirm,
+    /// Delete (or, in a dry run, assess) the confirmed branches
+    Delete,
+    /// Check the outcome, e.g. that deleted branches are really gone
+    Verify,
+}
+
+impl Phase {
+    /// The phase's name, as used in messages
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Fetch => "fetch",
+            Self::Enumerate => "enumerate",
+            Self::Filter => "filter",
+            Self::Confirm => "confirm",
+            Self::Delete => "delete",
+            Self::Verify => "verify",
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What the Enumerate phase found
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Enumerated {
+    /// Every local branch
+    pub all_branches: Vec<BranchLine>,
+    /// The candidates for deletion among them
+    pub gone: Vec<BranchLine>,
+}
+
+/// What's left once the Filter phase held branches back
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Filtered {
+    pub all_branches: Vec<BranchLine>,
+    /// The candidates that passed every safety check
+    pub deletable: Vec<BranchLine>,
+    pub held: Vec<Held>,
+}
+
+/// The Confirm phase's verdict on the deletable branches
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Confirmed {
+    pub deletable: Vec<BranchLine>,
+    pub held: Vec<Held>,
+    /// The names of the deletable branches cleared for deletion; the rest are kept
+    pub confirmed: HashSet<String>,
+}
+
+/// Where a run stands: the output of the last phase, and so the input of the next
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "state", content = "data", rename_all = "kebab-case")]
+pub enum State {
+    /// Nothing has run yet
+    Start,
+    Fetched,
+    Enumerated(Enumerated),
+    Filtered(Filtered),
+    Confirmed(Confirmed),
+    Deleted(Report),
+    /// The run is complete
+    Verified(Report),
+}
+
+impl State {
+    /// The phase that runs next from this state, or `None` once the run is complete
+    #[must_use]
+    pub fn next_phase(&self) -> Option<Phase> {
+        match self {
+            Self::Start => Some(Phase::Fetch),
+            Self::Fetched => Some(Phase::Enumerate),
+            Self::Enumerated(_) => Some(Phase::Filter),
+            Self::Filtered(_) => Some(Phase::Confirm),
+            Self::Confirmed(_) => Some(Phase::Delete),
+            Self::Deleted(_) => Some(Phase::Verify),
+            Self::Verified(_) => None,
+        }
+    }
+
+    /// Runs the next phase with `phases`, returning the state it produces (a complete run's
+    /// state is returned as is)
+    ///
+    /// # Errors
+    ///
+    /// Fails if the phase does.
+    pub fn advance<P: Phases + ?Sized>(self, phases: &mut P) -> Result<Self> {
+        Ok(match self {
+            Self::Start => {
+                phases.fetch()?;
+                Self::Fetched
+            }
+            Self::Fetched => Self::Enumerated(phases.enumerate()?),
+            Self::Enumerated(enumerated) => Self::Filtered(phases.filter(enumerated)?),
+            Self::Filtered(filtered) => Self::Confirmed(phases.confirm(filtered)?),
+            Self::Confirmed(confirmed) => Self::Deleted(phases.delete(confirmed)?),
+            Self::Deleted(report) => Self::Verified(phases.verify(report)?),
+            Self::Verified(report) => Self::Verified(report),
+        })
+    }
+}
+
+/// What each phase does, for one frontend. The defaults let a frontend skip the phases it has
+/// no use for: confirming clears every deletable branch, and verifying changes nothing.
+pub trait Phases {
+    /// Fetches and prunes the remotes
+    ///
+    /// # Errors
+    ///
+    /// Fails if the fetch does.
+    fn fetch(&mut self) -> Result<()>;
+
+    /// Lists the local branches and the gone ones among them
+    ///
+    /// # Errors
+    ///
+    /// Fails if the branches can't be listed.
+    fn enumerate(&mut self) -> Result<Enumerated>;
+
+    /// Runs the safety checks over the gone branches
+    ///
+    /// # Errors
+    ///
+    /// Fails if a check can't be run.
+    fn filter(&mut self, enumerated: Enumerated) -> Result<Filtered>;
+
+    /// Decides which deletable branches to delete
+    ///
+    /// # Errors
+    ///
+    /// Fails if the decision can't be made, e.g. the prompt can't be read.
+    fn confirm(&mut self, filtered: Filtered) -> Result<Confirmed> {
+        Ok(Confirmed {
+            confirmed: filtered
+                .deletable
+                .iter()
+                .map(|branch| branch.name.clone())
+                .collect(),
+            deletable: filtered.deletable,
+            held: filtered.held,
+        })
+    }
+
+    /// Deletes the confirmed branches, reporting on every candidate
+    ///
+    /// # Errors
+    ///
+    /// Fails if the run can't go on; failures to delete single branches go in the report.
+    fn delete(&mut self, confirmed: Confirmed) -> Result<Report>;
+
+    /// Checks the outcome of the deletions
+    ///
+    /// # Errors
+    ///
+    /// Fails if the check can't be run.
+    fn verify(&mut self, report: Report) -> Result<Report> {
+        Ok(report)
+    }
+}
+
+/// Runs the phases from `state` until the run is complete, returning its report
+///
+/// # Errors
+///
+/// Fails if any phase does.
+pub fn run<P: Phases + ?Sized>(phases: &mut P, mut state: State) -> Result<Report> {
+    loop {
+        if let State::Verified(report) = state {
+            return Ok(report);
+        }
+        state = state.advance(phases)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_branch_lines;
+    use crate::report::{Action, BranchReport};
+    use crate::safety::HoldKind;
+
+    /// Phases over a canned listing, recording which ran
+    struct Canned {
+        ran: Vec<Phase>,
+    }
+
+    impl Phases for Canned {
+        fn fetch(&mut self) -> Result<()> {
+            self.ran.push(Phase::Fetch);
+            Ok(())
+        }
+
+        fn enumerate(&mut self) -> Result<Enumerated> {
+            self.ran.push(Phase::Enumerate);
+            let all_branches = parse_branch_lines(
+                "* main       abc1234 [origin/main] Init\n  \
+                 feature-1  def5678 [origin/feature-1: gone] One\n  \
+                 release    aaa1111 [origin/release: gone] Keep\n",
+            );
+            Ok(Enumerated {
+                gone: all_branches[1..].to_vec(),
+                all_branches,
+            })
+        }
+
+        fn filter(&mut self, enumerated: Enumerated) -> Result<Filtered> {
+            self.ran.push(Phase::Filter);
+            let (held, deletable) = enumerated
+                .gone
+                .into_iter()
+                .partition(|branch| branch.name == "release");
+            Ok(Filtered {
+                all_branches: enumerated.all_branches,
+                deletable,
+                held: held
+                    .into_iter()
+                    .map(|branch| Held {
+                        branch,
+                        kind: HoldKind::Protected,
+                        reason: "protected".to_string(),
+                    })
+                    .collect(),
+            })
+        }
+
+        fn delete(&mut self, confirmed: Confirmed) -> Result<Report> {
+            self.ran.push(Phase::Delete);
+            Ok(Report {
+                dry_run: true,
+                branches: confirmed
+                    .deletable
+                    .iter()
+                    .map(|branch| BranchReport::new(branch, Action::WouldDelete))
+                    .chain(confirmed.held.iter().map(BranchReport::held))
+                    .collect(),
+                age_histogram: None,
+                remotes_checked: None,
+            })
+        }
+    }
+
+    #[test]
+    fn test_run_goes_through_every_phase() {
+        let mut phases = Canned { ran: Vec::new() };
+        let report = run(&mut phases, State::Start).unwrap();
+        assert_eq!(
+            phases.ran,
+            [Phase::Fetch, Phase::Enumerate, Phase::Filter, Phase::Delete]
+        );
+        let actions: Vec<(&str, Action)> = report
+            .branches
+            .iter()
+            .map(|branch| (branch.name.as_str(), branch.action))
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                ("feature-1", Action::WouldDelete),
+                ("release", Action::Skipped)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_states_round_trip_and_resume() {
+        let mut phases = Canned { ran: Vec::new() };
+        let mut state = State::Start;
+        while state.next_phase() != Some(Phase::Confirm) {
+            state = state.advance(&mut phases).unwrap();
+        }
+        let json = serde_json::to_string(&state).unwrap();
+        let restored: State = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, state);
+
+        // Resuming from the saved state runs only the remaining phases
+        let mut resumed = Canned { ran: Vec::new() };
+        let report = run(&mut resumed, restored).unwrap();
+        assert_eq!(resumed.ran, [Phase::Delete]);
+        assert_eq!(report.branches.len(), 2);
+        let saved: State =
+            serde_json::from_str(&serde_json::to_string(&State::Verified(report.clone())).unwrap())
+                .unwrap();
+        assert_eq!(saved.next_phase(), None);
+    }
+}
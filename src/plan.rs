@@ -0,0 +1,493 @@
+//! Deletion plans that separate reviewing a cleanup from executing it.
+//!
+//! A dry run can write the branches it would delete, with their tip SHAs, to a plan file.
+//! Replaying the plan later deletes exactly those branches, refusing any whose tip has
+//! moved since the plan was made. Each entry also carries its [`Recovery`], so the plan alone
+//! is enough to bring the branches back.
+//!
+//! The `plan` and `apply` subcommands are the same workflow as first-class steps: `apply`
+//! first compares the plan with the repository (see [`Plan::drift`]) and refuses to delete
+//! anything if a planned branch moved or disappeared.
+//!
+//! For a lighter check, a run prints a plan hash of its target set, and `--expect-plan-hash`
+//! makes a later run refuse to go on if its own hash differs (see [`plan_hash`]).
+
+use crate::git::{branch_tip, branch_tips, hash_object};
+use crate::parse::BranchLine;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::fs;
+use std::hash::BuildHasher;
+use std::path::Path;
+
+/// Format version written to new plans
+const PLAN_VERSION: u32 = 1;
+
+/// How many hex digits of the hash [`plan_hash`] keeps
+pub const PLAN_HASH_LEN: usize = 16;
+
+/// A branch scheduled for deletion, pinned to the tip it had when planned
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PlannedBranch {
+    pub name: String,
+    pub sha: String,
+    /// Missing from plans written before recovery was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery: Option<Recovery>,
+}
+
+/// How to recreate a planned branch after it's deleted. This only works while its commits are
+/// still in the repository: `git gc` prunes them once no ref or reflog reaches them.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Recovery {
+    /// The full tip SHA, captured when the plan was made
+    pub sha: String,
+    /// The exact command, e.g. `git branch feature-1 abc1234...`
+    pub command: String,
+}
+
+impl Recovery {
+    #[must_use]
+    pub fn new(name: &str, sha: &str) -> Self {
+        Self {
+            sha: sha.to_string(),
+            // Quoted, since characters such as `&` are valid in branch names
+            command: format!("git branch {} {sha}", shell_words::quote(name)),
+        }
+    }
+}
+
+/// How a planned branch changed since the plan was made
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Drift {
+    /// Its tip is now `current`, not the planned `planned`
+    Moved {
+        name: String,
+        planned: String,
+        current: String,
+    },
+    /// It no longer exists
+    Missing { name: String },
+}
+
+/// A reviewable list of branches to delete
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Plan {
+    pub version: u32,
+    pub branches: Vec<PlannedBranch>,
+}
+
+impl Plan {
+    /// Builds a plan from the given branches, recording each one's full tip SHA
+    ///
+    /// # Errors
+    ///
+    /// Fails if a branch's tip can't be resolved.
+    pub fn from_branches(branches: &[BranchLine]) -> Result<Self> {
+        let branches = branches
+            .iter()
+            .map(|branch| {
+                let sha = branch_tip(&branch.name)?
+                    .with_context(|| format!("Branch {} no longer exists", branch.name))?;
+                Ok(PlannedBranch {
+                    recovery: Some(Recovery::new(&branch.name, &sha)),
+                    name: branch.name.clone(),
+                    sha,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            version: PLAN_VERSION,
+            branches,
+        })
+    }
+
+    /// Looks up the planned tip SHA for `name`
+    #[must_use]
+    pub fn expected_sha(&self, name: &str) -> Option<&str> {
+        self.branches
+            .iter()
+            .find(|branch| branch.name == name)
+            .map(|branch| branch.sha.as_str())
+    }
+
+    /// Compares the planned tips with `tips` (every local branch's full tip SHA), listing the
+    /// planned branches that moved or disappeared, in plan order
+    #[must_use]
+    pub fn drift<S: BuildHasher>(&self, tips: &HashMap<String, String, S>) -> Vec<Drift> {
+        self.branches
+            .iter()
+            .filter_map(|planned| match tips.get(&planned.name) {
+                Some(current) if *current == planned.sha => None,
+                Some(current) => Some(Drift::Moved {
+                    name: planned.name.clone(),
+                    planned: planned.sha.clone(),
+                    current: current.clone(),
+                }),
+                None => Some(Drift::Missing {
+                    name: planned.name.clone(),
+                }),
+            })
+            .collect()
+    }
+
+    /// Serializes the plan as pretty-printed JSON
+    ///
+    /// # Errors
+    ///
+    /// Fails if the plan can't be serialized.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map(|json| json + "\n")
+            .context("Failed to serialize plan")
+    }
+
+    /// Parses a plan from JSON, rejecting unknown format versions
+    ///
+    /// # Errors
+    ///
+    /// Fails if the JSON is malformed or the version is unsupported.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let plan: Self = serde_json::from_str(json).context("Failed to parse plan")?;
+        if plan.version != PLAN_VERSION {
+            anyhow::bail!(
+                "Unsupported plan version {} (expected {PLAN_VERSION})",
+                plan.version
+            );
+        }
+        Ok(plan)
+    }
+
+    /// Writes the plan to `path`
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be written.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_json()?)
+            .with_context(|| format!("Failed to write plan to {}", path.display()))
+    }
+
+    /// Reads a plan from `path`
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be read or parsed.
+    pub fn read(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read plan from {}", path.display()))?;
+        Self::from_json(&json)
+    }
+}
+
+/// Formats `drift` as a short diff, one line per changed branch with abbreviated SHAs, or
+/// nothing if nothing changed
+#[must_use]
+pub fn format_drift(drift: &[Drift]) -> String {
+    if drift.is_empty() {
+        return String::new();
+    }
+
+    let short = |sha: &str| sha.chars().take(7).collect::<String>();
+    let mut diff = format!("Changed since the plan was made: {}\n", drift.len());
+    for entry in drift {
+        let _ = match entry {
+            Drift::Moved {
+                name,
+                planned,
+                current,
+            } => writeln!(diff, "  ~ {name}: {} -> {}", short(planned), short(current)),
+            Drift::Missing { name } => writeln!(diff, "  - {name}: no longer exists"),
+        };
+    }
+    diff
+}
+
+/// Parses an `--expect-shas` file: one `<branch> <sha>` pair per line, with blank lines and
+/// `#` comments ignored. SHAs may be abbreviated to no fewer than 7 hex digits.
+///
+/// # Errors
+///
+/// Fails on a malformed line or a branch listed twice.
+pub fn parse_expected_shas(text: &str) -> Result<HashMap<String, String>> {
+    let mut expected = HashMap::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, sha) = line
+            .split_once(char::is_whitespace)
+            .map(|(name, sha)| (name, sha.trim()))
+            .with_context(|| format!("Line {}: expected `<branch> <sha>`", number + 1))?;
+        if sha.len() < 7 || !sha.chars().all(|c| c.is_ascii_hexdigit()) {
+            anyhow::bail!("Line {}: {sha:?} isn't a commit SHA", number + 1);
+        }
+        if expected
+            .insert(name.to_string(), sha.to_ascii_lowercase())
+            .is_some()
+        {
+            anyhow::bail!("Line {}: {name} is listed more than once", number + 1);
+        }
+    }
+    Ok(expected)
+}
+
+/// Reads an `--expect-shas` file from `path` (see [`parse_expected_shas`])
+///
+/// # Errors
+///
+/// Fails if the file can't be read or parsed.
+pub fn read_expected_shas(path: &Path) -> Result<HashMap<String, String>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read expected SHAs from {}", path.display()))?;
+    parse_expected_shas(&text).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Parses a list of branch names, one per line, with blank lines and `#` comments ignored
+#[must_use]
+pub fn parse_branch_list(text: &str) -> HashSet<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Reads a list of branch names from `path` (see [`parse_branch_list`])
+///
+/// # Errors
+///
+/// Fails if the file can't be read.
+pub fn read_branch_list(path: &Path) -> Result<HashSet<String>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read branch list from {}", path.display()))?;
+    Ok(parse_branch_list(&text))
+}
+
+/// The target set a plan hash covers: one `<branch> <tip>` line per branch, sorted by name,
+/// with the full tip from `tips` (or, for a branch missing there, its listed tip)
+#[must_use]
+pub fn target_set<S: BuildHasher>(
+    branches: &[BranchLine],
+    tips: &HashMap<String, String, S>,
+) -> String {
+    let mut lines: Vec<String> = branches
+        .iter()
+        .map(|branch| {
+            let tip = tips.get(&branch.name).unwrap_or(&branch.sha);
+            format!("{} {tip}\n", branch.name)
+        })
+        .collect();
+    lines.sort();
+    lines.concat()
+}
+
+/// The plan hash of deleting `branches`: the first [`PLAN_HASH_LEN`] hex digits of git's hash
+/// of their [`target_set`]. It's the same for any two runs that target the same branches at the
+/// same tips, in whatever order they list them.
+///
+/// # Errors
+///
+/// Fails if the tips can't be listed or git can't hash them.
+pub fn plan_hash(branches: &[BranchLine]) -> Result<String> {
+    let mut hash = hash_object(&target_set(branches, &branch_tips()?))?;
+    hash.truncate(PLAN_HASH_LEN);
+    Ok(hash)
+}
+
+/// Why `name`, whose tip is `actual`, doesn't match its entry in `expected`, if it doesn't
+#[must_use]
+pub fn expected_sha_mismatch<S: BuildHasher>(
+    expected: &HashMap<String, String, S>,
+    name: &str,
+    actual: Option<&str>,
+) -> Option<String> {
+    match (expected.get(name), actual) {
+        (None, _) => Some("not listed in --expect-shas".to_string()),
+        (Some(sha), Some(actual)) if actual.starts_with(sha.as_str()) => None,
+        (Some(sha), actual) => Some(format!(
+            "tip is {} but --expect-shas expected {sha}",
+            actual.unwrap_or("missing")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_plan() -> Plan {
+        Plan {
+            version: PLAN_VERSION,
+            branches: vec![
+                PlannedBranch {
+                    name: "feature-1".to_string(),
+                    sha: "abc1234abc1234abc1234abc1234abc1234abc12".to_string(),
+                    recovery: Some(Recovery::new(
+                        "feature-1",
+                        "abc1234abc1234abc1234abc1234abc1234abc12",
+                    )),
+                },
+                PlannedBranch {
+                    name: "feature/JIRA-123".to_string(),
+                    sha: "def5678def5678def5678def5678def5678def56".to_string(),
+                    recovery: None,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_target_set_is_sorted_with_full_tips() {
+        let branches = crate::parse::parse_branch_lines(
+            "  zeta   abc1234 [origin/zeta: gone] Z\n  alpha  def5678 [origin/alpha: gone] A\n",
+        );
+        let tips = HashMap::from([("zeta".to_string(), "abc1234ffff".to_string())]);
+        let set = target_set(&branches, &tips);
+        assert_eq!(set, "alpha def5678\nzeta abc1234ffff\n");
+        let reversed: Vec<BranchLine> = branches.into_iter().rev().collect();
+        assert_eq!(target_set(&reversed, &tips), set);
+    }
+
+    #[test]
+    fn test_plan_json_round_trip() {
+        let plan = sample_plan();
+        assert_eq!(Plan::from_json(&plan.to_json().unwrap()).unwrap(), plan);
+    }
+
+    #[test]
+    fn test_plan_recovery() {
+        let json = sample_plan().to_json().unwrap();
+        let plan: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            plan["branches"][0]["recovery"],
+            serde_json::json!({
+                "sha": "abc1234abc1234abc1234abc1234abc1234abc12",
+                "command": "git branch feature-1 abc1234abc1234abc1234abc1234abc1234abc12"
+            })
+        );
+        // Plans from before recovery was recorded still load
+        assert!(plan["branches"][1].get("recovery").is_none());
+        assert_eq!(
+            Recovery::new("fix&go", "abc1234").command,
+            "git branch 'fix&go' abc1234"
+        );
+    }
+
+    #[test]
+    fn test_plan_drift() {
+        let plan = sample_plan();
+        let mut tips = HashMap::from([
+            (
+                "feature-1".to_string(),
+                "abc1234abc1234abc1234abc1234abc1234abc12".to_string(),
+            ),
+            (
+                "feature/JIRA-123".to_string(),
+                "def5678def5678def5678def5678def5678def56".to_string(),
+            ),
+        ]);
+        assert!(plan.drift(&tips).is_empty());
+        assert_eq!(format_drift(&[]), "");
+
+        tips.insert("feature-1".to_string(), "fff0000".to_string());
+        tips.remove("feature/JIRA-123");
+        let drift = plan.drift(&tips);
+        assert_eq!(
+            drift,
+            [
+                Drift::Moved {
+                    name: "feature-1".to_string(),
+                    planned: "abc1234abc1234abc1234abc1234abc1234abc12".to_string(),
+                    current: "fff0000".to_string(),
+                },
+                Drift::Missing {
+                    name: "feature/JIRA-123".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            format_drift(&drift),
+            "Changed since the plan was made: 2
+  ~ feature-1: abc1234 -> fff0000
+  - feature/JIRA-123: no longer exists
+"
+        );
+    }
+
+    #[test]
+    fn test_plan_expected_sha() {
+        let plan = sample_plan();
+        assert_eq!(
+            plan.expected_sha("feature/JIRA-123"),
+            Some("def5678def5678def5678def5678def5678def56")
+        );
+        assert_eq!(plan.expected_sha("missing"), None);
+    }
+
+    #[test]
+    fn test_plan_rejects_unknown_version() {
+        let json = r#"{"version": 99, "branches": []}"#;
+        assert!(Plan::from_json(json).is_err());
+    }
+
+    #[test]
+    fn test_plan_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plan.json");
+        let plan = sample_plan();
+        plan.write(&path).unwrap();
+        assert_eq!(Plan::read(&path).unwrap(), plan);
+    }
+
+    #[test]
+    fn test_parse_expected_shas() {
+        let expected = parse_expected_shas(
+            "# computed by CI\nfeature-1 ABC1234\n\n  feature/JIRA-123\tdef5678def5678\n",
+        )
+        .unwrap();
+        assert_eq!(expected.len(), 2);
+        assert_eq!(expected["feature-1"], "abc1234");
+        assert_eq!(expected["feature/JIRA-123"], "def5678def5678");
+
+        assert!(parse_expected_shas("feature-1").is_err());
+        assert!(parse_expected_shas("feature-1 abc").is_err());
+        assert!(parse_expected_shas("feature-1 not-a-sha").is_err());
+        assert!(parse_expected_shas("a abc1234\na def5678").is_err());
+    }
+
+    #[test]
+    fn test_parse_branch_list() {
+        let list =
+            parse_branch_list("# exported from the API\nfeature-1\n\n  feature/JIRA-123  \n");
+        assert_eq!(list.len(), 2);
+        assert!(list.contains("feature-1"));
+        assert!(list.contains("feature/JIRA-123"));
+    }
+
+    #[test]
+    fn test_expected_sha_mismatch() {
+        let expected = parse_expected_shas("feature-1 abc1234").unwrap();
+        let full = "abc1234abc1234abc1234abc1234abc1234abc12";
+        assert_eq!(
+            expected_sha_mismatch(&expected, "feature-1", Some(full)),
+            None
+        );
+        assert_eq!(
+            expected_sha_mismatch(&expected, "feature-1", Some("def5678")).as_deref(),
+            Some("tip is def5678 but --expect-shas expected abc1234")
+        );
+        assert_eq!(
+            expected_sha_mismatch(&expected, "feature-1", None).as_deref(),
+            Some("tip is missing but --expect-shas expected abc1234")
+        );
+        assert_eq!(
+            expected_sha_mismatch(&expected, "other", Some(full)).as_deref(),
+            Some("not listed in --expect-shas")
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! Progress indicators for the slow phases: a spinner while fetching and a bar while deleting.
+//!
+//! They're drawn with `indicatif` when the `progress` feature is enabled (the default). Without
+//! it, or when they're not wanted, [`Progress`] does nothing and costs nothing.
+
+#[cfg(feature = "progress")]
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::{self, IsTerminal};
+
+/// Whether progress indicators should be shown: never for machine-readable formats or with
+/// verbose git output, and only when both stdout and stderr are terminals that render ANSI
+/// escapes
+#[must_use]
+pub fn wanted(machine: bool, verbose: bool) -> bool {
+    cfg!(feature = "progress")
+        && !machine
+        && !verbose
+        && io::stdout().is_terminal()
+        && io::stderr().is_terminal()
+        && enable_ansi()
+}
+
+/// Turns on ANSI escape handling for the console stderr is attached to, returning whether the
+/// escapes will render.
+///
+/// Windows consoles print escapes as garbage until virtual terminal processing is enabled;
+/// terminals that aren't consoles (e.g. mintty, which Git for Windows ships) handle them already.
+#[cfg(windows)]
+fn enable_ansi() -> bool {
+    use windows_sys::Win32::System::Console::{
+        ENABLE_VIRTUAL_TERMINAL_PROCESSING, GetConsoleMode, GetStdHandle, STD_ERROR_HANDLE,
+        SetConsoleMode,
+    };
+
+    // SAFETY: the handle comes straight from `GetStdHandle` and `mode` outlives both calls
+    unsafe {
+        let handle = GetStdHandle(STD_ERROR_HANDLE);
+        let mut mode = 0;
+        if GetConsoleMode(handle, &raw mut mode) == 0 {
+            return true;
+        }
+        mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0
+            || SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+    }
+}
+
+/// Terminals outside Windows render ANSI escapes without being asked
+#[cfg(not(windows))]
+fn enable_ansi() -> bool {
+    true
+}
+
+/// A spinner or progress bar drawn on stderr, or nothing when hidden
+#[derive(Debug)]
+pub struct Progress {
+    #[cfg(feature = "progress")]
+    bar: Option<ProgressBar>,
+}
+
+impl Progress {
+    /// An indicator that draws nothing
+    #[must_use]
+    pub fn hidden() -> Self {
+        Self {
+            #[cfg(feature = "progress")]
+            bar: None,
+        }
+    }
+
+    /// A spinner showing `message`, if `show` is set
+    #[must_use]
+    pub fn spinner(show: bool, message: &str) -> Self {
+        #[cfg(feature = "progress")]
+        if show {
+            let bar = ProgressBar::new_spinner().with_message(message.to_string());
+            bar.enable_steady_tick(std::time::Duration::from_millis(100));
+            return Self { bar: Some(bar) };
+        }
+        let _ = (show, message);
+        Self::hidden()
+    }
+
+    /// A bar counting up to `len` items, if `show` is set
+    #[must_use]
+    pub fn bar(show: bool, len: usize, message: &str) -> Self {
+        #[cfg(feature = "progress")]
+        if show {
+            let bar = ProgressBar::new(len as u64)
+                .with_style(
+                    ProgressStyle::with_template("{msg} [{bar:30}] {pos}/{len}")
+                        .unwrap_or_else(|_| ProgressStyle::default_bar())
+                        .progress_chars("=> "),
+                )
+                .with_message(message.to_string());
+            return Self { bar: Some(bar) };
+        }
+        let _ = (show, len, message);
+        Self::hidden()
+    }
+
+    /// Advances the bar by one item
+    pub fn inc(&self) {
+        #[cfg(feature = "progress")]
+        if let Some(bar) = &self.bar {
+            bar.inc(1);
+        }
+    }
+
+    /// Runs `f` (e.g. printing a line) with the indicator temporarily cleared, so the output
+    /// doesn't get mixed into it
+    pub fn suspend<R>(&self, f: impl FnOnce() -> R) -> R {
+        #[cfg(feature = "progress")]
+        if let Some(bar) = &self.bar {
+            return bar.suspend(f);
+        }
+        f()
+    }
+
+    /// Removes the indicator from the terminal
+    pub fn finish(&self) {
+        #[cfg(feature = "progress")]
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+    }
+}
@@ -0,0 +1,529 @@
+//! Interactive confirmation prompts.
+//!
+//! Prompts take their input and output streams as parameters so they can be driven from tests.
+//! `--edit` instead hands the branch list to the user's editor, like `git rebase -i`.
+//! With `--confirm-timeout`, stdin is read through [`TimedInput`], so an unanswered prompt
+//! takes its default instead of waiting forever.
+
+use crate::parse::BranchLine;
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::Path;
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+/// Asks a yes/no question, defaulting to "no".
+///
+/// Only `y` or `yes` (case-insensitive) confirm; anything else, including end of input
+/// (e.g. stdin isn't a terminal), declines.
+///
+/// # Errors
+///
+/// Fails if the prompt can't be written or the answer can't be read.
+pub fn confirm<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    question: &str,
+) -> Result<bool> {
+    write!(output, "{question} [y/N] ").context("Failed to write prompt")?;
+    output.flush().context("Failed to flush prompt")?;
+
+    let mut answer = String::new();
+    input
+        .read_line(&mut answer)
+        .context("Failed to read confirmation")?;
+
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+/// Input for a prompt that stops waiting for an answer after a timeout (`--confirm-timeout`).
+/// Once the time runs out, it says so on stderr and reads as the end of input, so the prompt
+/// takes its default answer: no.
+pub struct TimedInput<'a> {
+    lines: &'a Mutex<Receiver<io::Result<String>>>,
+    timeout: Duration,
+    line: Vec<u8>,
+    pos: usize,
+    timed_out: bool,
+}
+
+impl<'a> TimedInput<'a> {
+    /// Reads the lines `lines` receives, waiting up to `timeout` for each
+    #[must_use]
+    pub fn new(lines: &'a Mutex<Receiver<io::Result<String>>>, timeout: Duration) -> Self {
+        Self {
+            lines,
+            timeout,
+            line: Vec::new(),
+            pos: 0,
+            timed_out: false,
+        }
+    }
+
+    /// Whether the answer didn't come in time
+    #[must_use]
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+}
+
+impl TimedInput<'static> {
+    /// Reads stdin, waiting up to `timeout` for each line. Stdin is read by a thread started
+    /// on first use, so a line typed after one prompt timed out goes to the next prompt.
+    #[must_use]
+    pub fn stdin(timeout: Duration) -> Self {
+        static LINES: OnceLock<Mutex<Receiver<io::Result<String>>>> = OnceLock::new();
+        let lines = LINES.get_or_init(|| {
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                let mut input = io::stdin().lock();
+                loop {
+                    let mut line = String::new();
+                    match input.read_line(&mut line) {
+                        // Ending the thread drops the sender, which the prompt reads as the
+                        // end of input too
+                        Ok(0) => break,
+                        Ok(_) => {
+                            if sender.send(Ok(line)).is_err() {
+                                break;
+                            }
+                        }
+                        Err(err) => {
+                            let _ = sender.send(Err(err));
+                            break;
+                        }
+                    }
+                }
+            });
+            Mutex::new(receiver)
+        });
+        Self::new(lines, timeout)
+    }
+}
+
+impl Read for TimedInput<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for TimedInput<'_> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.line.len() && !self.timed_out {
+            let lines = self.lines.lock().unwrap_or_else(PoisonError::into_inner);
+            match lines.recv_timeout(self.timeout) {
+                Ok(line) => {
+                    self.line = line?.into_bytes();
+                    self.pos = 0;
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    self.timed_out = true;
+                    eprintln!(
+                        "\nNo answer within {}s (--confirm-timeout), so taking the default: no",
+                        self.timeout.as_secs()
+                    );
+                }
+                // The end of input
+                Err(RecvTimeoutError::Disconnected) => {}
+            }
+        }
+        Ok(&self.line[self.pos..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.pos = (self.pos + amount).min(self.line.len());
+    }
+}
+
+/// How the confirmation before deleting is asked (`--prompt-style`)
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PromptStyle {
+    /// A `[y/N]` question
+    #[default]
+    Simple,
+    /// A numbered menu of yes and no
+    Menu,
+    /// Typing the number of items about to be deleted
+    Count,
+}
+
+/// Asks `question` about deleting `count` items in the given `style`, declining by default
+/// like [`confirm`]. With [`PromptStyle::Count`], only typing `count` itself (with or without
+/// thousands separators) confirms, which guards against answering on autopilot.
+///
+/// # Errors
+///
+/// Fails if the prompt can't be written or the answer can't be read.
+pub fn confirm_with_style<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    question: &str,
+    style: PromptStyle,
+    count: usize,
+) -> Result<bool> {
+    match style {
+        PromptStyle::Simple => return confirm(input, output, question),
+        PromptStyle::Menu => write!(
+            output,
+            "{question}\n  1) Yes\n  2) No\nChoose [1-2, default 2]: "
+        ),
+        PromptStyle::Count => write!(output, "{question} Type {count} to confirm: "),
+    }
+    .context("Failed to write prompt")?;
+    output.flush().context("Failed to flush prompt")?;
+
+    let mut answer = String::new();
+    input
+        .read_line(&mut answer)
+        .context("Failed to read confirmation")?;
+    let answer = answer.trim();
+    Ok(match style {
+        PromptStyle::Menu => answer == "1",
+        _ => answer.replace(',', "") == count.to_string(),
+    })
+}
+
+/// Asks `question`, confirming only if `word` itself is typed back, for the few actions that
+/// can't be undone and shouldn't be answered on autopilot
+///
+/// # Errors
+///
+/// Fails if the prompt can't be written or the answer can't be read.
+pub fn confirm_word<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    question: &str,
+    word: &str,
+) -> Result<bool> {
+    write!(output, "{question} Type {word} to confirm: ").context("Failed to write prompt")?;
+    output.flush().context("Failed to flush prompt")?;
+
+    let mut answer = String::new();
+    input
+        .read_line(&mut answer)
+        .context("Failed to read confirmation")?;
+    Ok(answer.trim() == word)
+}
+
+/// Writes the branches as an editable list, one `<name> <sha> <subject>` line per branch
+/// under a commented explanation. With `invert`, the header explains that the lines left on the
+/// list are the ones kept (see [`select_from_edit_list`]).
+#[must_use]
+pub fn format_edit_list(branches: &[BranchLine], verb: &str, invert: bool) -> String {
+    let mut list = String::new();
+    let verb_lower = verb.to_lowercase();
+    if invert {
+        let _ = writeln!(
+            list,
+            "# KEEP LIST (--select-invert): these {} branch(es) are kept. Remove a line to \
+             {verb_lower} that branch.",
+            branches.len()
+        );
+        let _ = writeln!(
+            list,
+            "# Saving the list unchanged keeps them all; removing every line {verb_lower}s them all.\n"
+        );
+    } else {
+        let _ = writeln!(
+            list,
+            "# {verb} these {} branch(es)? Remove a line to keep that branch.",
+            branches.len()
+        );
+        let _ = writeln!(
+            list,
+            "# Saving the list unchanged {verb_lower}s them all; removing every line {verb_lower}s none.\n"
+        );
+    }
+    for branch in branches {
+        let line = format!("{} {} {}", branch.name, branch.sha, branch.subject);
+        let _ = writeln!(list, "{}", line.trim_end());
+    }
+    list
+}
+
+/// Reads back an edited list, returning the branch names still on it (the first word of each
+/// line that isn't blank or a `#` comment)
+///
+/// # Errors
+///
+/// Fails if a line names a branch that wasn't on the list, so a typo can't go unnoticed.
+pub fn parse_edit_list(text: &str, branches: &[BranchLine]) -> Result<Vec<String>> {
+    text.lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .filter(|name| !name.starts_with('#'))
+        .map(|name| {
+            if branches.iter().any(|branch| branch.name == name) {
+                Ok(name.to_string())
+            } else {
+                anyhow::bail!("{name} isn't one of the listed branches")
+            }
+        })
+        .collect()
+}
+
+/// The branches an edited list selects: those still on it, or with `invert`, those removed
+/// from it
+///
+/// # Errors
+///
+/// See [`parse_edit_list`].
+pub fn select_from_edit_list(
+    text: &str,
+    branches: &[BranchLine],
+    invert: bool,
+) -> Result<Vec<String>> {
+    let listed = parse_edit_list(text, branches)?;
+    if !invert {
+        return Ok(listed);
+    }
+    Ok(branches
+        .iter()
+        .filter(|branch| !listed.contains(&branch.name))
+        .map(|branch| branch.name.clone())
+        .collect())
+}
+
+/// Writes `text` to `path`, opens it in `editor` (a shell command such as `vim` or
+/// `code --wait`, as from [`crate::git::git_editor`]), and returns the saved contents.
+///
+/// # Errors
+///
+/// Fails if the file can't be written or read, or the editor can't be started or exits with
+/// an error.
+pub fn edit_in_editor(editor: &str, path: &Path, text: &str) -> Result<String> {
+    fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))?;
+    // The same invocation git uses, so editor commands with arguments work. Windows has no
+    // `sh` on `PATH` outside Git Bash, so the command goes through `cmd` there instead.
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", &format!("{editor} \"{}\"", path.display())]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command
+            .args(["-c", &format!("{editor} \"$@\""), editor])
+            .arg(path);
+        command
+    };
+    let status = command
+        .status()
+        .with_context(|| format!("Failed to start editor {editor}"))?;
+    if !status.success() {
+        anyhow::bail!("Editor {editor} exited with {status}; no branches were deleted");
+    }
+    let edited =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    // Best effort, like git leaves a stale COMMIT_EDITMSG behind
+    let _ = fs::remove_file(path);
+    Ok(edited)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(input: &str) -> bool {
+        let mut output = Vec::new();
+        confirm(&mut input.as_bytes(), &mut output, "Delete?").unwrap()
+    }
+
+    #[test]
+    fn test_timed_input() {
+        let (sender, receiver) = mpsc::channel();
+        let lines = Mutex::new(receiver);
+        let mut output = Vec::new();
+
+        sender.send(Ok("yes\n".to_string())).unwrap();
+        let mut input = TimedInput::new(&lines, Duration::from_secs(5));
+        assert!(confirm(&mut input, &mut output, "Delete?").unwrap());
+        assert!(!input.timed_out());
+
+        // Nothing typed in time declines
+        let mut input = TimedInput::new(&lines, Duration::from_millis(10));
+        assert!(!confirm(&mut input, &mut output, "Delete?").unwrap());
+        assert!(input.timed_out());
+
+        // A late answer goes to the next prompt
+        sender.send(Ok("y\n".to_string())).unwrap();
+        let mut input = TimedInput::new(&lines, Duration::from_secs(5));
+        assert!(confirm(&mut input, &mut output, "Delete?").unwrap());
+
+        drop(sender);
+        let mut input = TimedInput::new(&lines, Duration::from_secs(5));
+        assert!(!confirm(&mut input, &mut output, "Delete?").unwrap());
+        assert!(!input.timed_out());
+    }
+
+    #[test]
+    fn test_confirm_accepts_yes() {
+        assert!(answer("y\n"));
+        assert!(answer("YES\n"));
+        assert!(answer("  yes  \n"));
+    }
+
+    #[test]
+    fn test_confirm_defaults_to_no() {
+        assert!(!answer("\n"));
+        assert!(!answer("n\n"));
+        assert!(!answer("sure\n"));
+        assert!(!answer(""));
+    }
+
+    #[test]
+    fn test_confirm_writes_question() {
+        let mut output = Vec::new();
+        confirm(&mut "y\n".as_bytes(), &mut output, "Delete 2 tags?").unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "Delete 2 tags? [y/N] ");
+    }
+
+    fn styled_answer(input: &str, style: PromptStyle) -> (bool, String) {
+        let mut output = Vec::new();
+        let confirmed =
+            confirm_with_style(&mut input.as_bytes(), &mut output, "Delete?", style, 1234).unwrap();
+        (confirmed, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_confirm_with_style_simple() {
+        assert_eq!(
+            styled_answer("y\n", PromptStyle::Simple),
+            (true, "Delete? [y/N] ".to_string())
+        );
+        assert!(!styled_answer("1234\n", PromptStyle::Simple).0);
+    }
+
+    #[test]
+    fn test_confirm_with_style_menu() {
+        let (confirmed, output) = styled_answer("1\n", PromptStyle::Menu);
+        assert!(confirmed);
+        assert_eq!(
+            output,
+            "Delete?\n  1) Yes\n  2) No\nChoose [1-2, default 2]: "
+        );
+        assert!(!styled_answer("2\n", PromptStyle::Menu).0);
+        assert!(!styled_answer("\n", PromptStyle::Menu).0);
+        assert!(!styled_answer("y\n", PromptStyle::Menu).0);
+    }
+
+    #[test]
+    fn test_confirm_with_style_count() {
+        let (confirmed, output) = styled_answer("1234\n", PromptStyle::Count);
+        assert!(confirmed);
+        assert_eq!(output, "Delete? Type 1234 to confirm: ");
+        assert!(styled_answer("1,234\n", PromptStyle::Count).0);
+        assert!(!styled_answer("y\n", PromptStyle::Count).0);
+        assert!(!styled_answer("123\n", PromptStyle::Count).0);
+        assert!(!styled_answer("", PromptStyle::Count).0);
+    }
+
+    #[test]
+    fn test_confirm_word() {
+        let answer = |input: &str| {
+            let mut output = Vec::new();
+            let confirmed =
+                confirm_word(&mut input.as_bytes(), &mut output, "Expire?", "expire").unwrap();
+            (confirmed, String::from_utf8(output).unwrap())
+        };
+        assert_eq!(
+            answer(
+                "expire
+"
+            ),
+            (true, "Expire? Type expire to confirm: ".to_string())
+        );
+        assert!(
+            !answer(
+                "y
+"
+            )
+            .0
+        );
+        assert!(
+            !answer(
+                "EXPIRE
+"
+            )
+            .0
+        );
+        assert!(!answer("").0);
+    }
+
+    fn branches() -> Vec<BranchLine> {
+        crate::parse::parse_branch_lines(
+            r"
+  feature-1    abc1234 [origin/feature-1: gone] Add the thing
+  keep-me      def5678 [origin/keep-me: gone] Another commit
+",
+        )
+    }
+
+    #[test]
+    fn test_edit_list_round_trip() {
+        let branches = branches();
+        let list = format_edit_list(&branches, "Delete", false);
+        assert!(list.starts_with("# Delete these 2 branch(es)?"));
+        assert!(
+            list.ends_with("\nfeature-1 abc1234 Add the thing\nkeep-me def5678 Another commit\n")
+        );
+        assert_eq!(
+            parse_edit_list(&list, &branches).unwrap(),
+            ["feature-1", "keep-me"]
+        );
+
+        let edited = list.replace("keep-me def5678 Another commit\n", "");
+        assert_eq!(parse_edit_list(&edited, &branches).unwrap(), ["feature-1"]);
+        assert!(
+            parse_edit_list("# all gone\n\n", &branches)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(parse_edit_list("feature-2 abc1234\n", &branches).is_err());
+    }
+
+    #[test]
+    fn test_inverted_edit_list_selects_removed_lines() {
+        let branches = branches();
+        let list = format_edit_list(&branches, "Delete", true);
+        assert!(list.starts_with("# KEEP LIST (--select-invert): these 2 branch(es) are kept."));
+        assert!(
+            select_from_edit_list(&list, &branches, true)
+                .unwrap()
+                .is_empty()
+        );
+
+        let edited = list.replace("feature-1 abc1234 Add the thing\n", "");
+        assert_eq!(
+            select_from_edit_list(&edited, &branches, true).unwrap(),
+            ["feature-1"]
+        );
+        assert_eq!(
+            select_from_edit_list(&edited, &branches, false).unwrap(),
+            ["keep-me"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_edit_in_editor() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("CLEAN_GONE_EDIT");
+        let edited =
+            edit_in_editor("sed -i.bak '/keep-me/d'", &path, "feature-1\nkeep-me\n").unwrap();
+        assert_eq!(edited, "feature-1\n");
+        assert!(!path.exists());
+
+        assert!(edit_in_editor("false", &path, "feature-1\n").is_err());
+    }
+}
@@ -0,0 +1,570 @@
+//! Reading local branches straight from the ref store on disk, without spawning git.
+//!
+//! `git branch -vv` gets slow in repositories with tens of thousands of refs, so this reads
+//! `packed-refs`, the loose refs, and the upstreams in `.git/config` directly. Anything it
+//! doesn't fully understand (reftable, linked worktrees, config includes, custom fetch
+//! refspecs, ...) is an error, so callers can fall back to asking git.
+
+use crate::parse::BranchLine;
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// A branch's configured upstream (`branch.<name>.remote` and `branch.<name>.merge`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Upstream {
+    pub remote: Option<String>,
+    pub merge: Option<String>,
+}
+
+/// The parts of `.git/config` that decide where each branch's upstream lives
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RefConfig {
+    /// Upstream settings by branch name
+    pub branches: HashMap<String, Upstream>,
+    /// `remote.<name>.fetch` refspecs by remote name
+    pub remotes: HashMap<String, Vec<String>>,
+}
+
+/// Lists the local branches of the repository containing `start`, like
+/// [`crate::git::local_branches`] but without a subprocess.
+///
+/// Tips are full SHAs, and subjects are left empty since reading them would mean inflating
+/// commit objects.
+///
+/// # Errors
+///
+/// Fails if the repository uses anything this reader can't be sure to interpret the way git
+/// would, or if its files can't be read.
+pub fn read_branches(start: &Path) -> Result<Vec<BranchLine>> {
+    let git_dir = find_git_dir(start)?;
+    check_layout(&git_dir)?;
+
+    let config = parse_config(&read(&git_dir.join("config"))?.unwrap_or_default())?;
+    let mut refs = parse_packed_refs(&read(&git_dir.join("packed-refs"))?.unwrap_or_default())?;
+    for namespace in ["refs/heads", "refs/remotes"] {
+        read_loose_refs(&git_dir, namespace, &mut refs)?;
+    }
+    let head = read(&git_dir.join("HEAD"))?.context("HEAD is missing")?;
+
+    branches_from_refs(&refs, &head, &config)
+}
+
+/// Checks that the branches in `git_dir` live only in `HEAD`, `config`, `packed-refs`, and the
+/// loose refs, the files this module reads
+///
+/// # Errors
+///
+/// Fails for reftable, linked worktrees, or a `.git` that shares another's refs.
+pub fn check_layout(git_dir: &Path) -> Result<()> {
+    for unsupported in ["commondir", "reftable"] {
+        if git_dir.join(unsupported).exists() {
+            anyhow::bail!("{unsupported} isn't supported");
+        }
+    }
+    if fs::read_dir(git_dir.join("worktrees")).is_ok_and(|mut entries| entries.next().is_some()) {
+        anyhow::bail!("linked worktrees aren't supported");
+    }
+    Ok(())
+}
+
+/// Finds the `.git` directory of the repository containing `start`
+///
+/// # Errors
+///
+/// Fails if there's no `.git` directory above `start`, if `.git` is a file (a linked worktree
+/// or submodule), or if git's location is overridden through the environment.
+pub fn find_git_dir(start: &Path) -> Result<PathBuf> {
+    for var in [
+        "GIT_DIR",
+        "GIT_COMMON_DIR",
+        "GIT_CONFIG_PARAMETERS",
+        "GIT_CONFIG_COUNT",
+    ] {
+        if std::env::var_os(var).is_some() {
+            anyhow::bail!("{var} is set");
+        }
+    }
+    for dir in start.ancestors() {
+        let candidate = dir.join(".git");
+        if candidate.is_dir() {
+            return Ok(candidate);
+        }
+        if candidate.exists() {
+            anyhow::bail!("{} isn't a directory", candidate.display());
+        }
+    }
+    anyhow::bail!("No .git directory above {}", start.display())
+}
+
+/// Parses a `packed-refs` file into a map from ref name to SHA, skipping peeled tag lines
+///
+/// # Errors
+///
+/// Fails on a line that isn't a `<sha> <refname>` pair.
+pub fn parse_packed_refs(text: &str) -> Result<BTreeMap<String, String>> {
+    let mut refs = BTreeMap::new();
+    for line in text.lines() {
+        if line.starts_with('#') || line.starts_with('^') || line.is_empty() {
+            continue;
+        }
+        let (sha, name) = line
+            .split_once(' ')
+            .filter(|(sha, _)| is_sha(sha))
+            .with_context(|| format!("Unexpected packed-refs line {line:?}"))?;
+        refs.insert(name.to_string(), sha.to_string());
+    }
+    Ok(refs)
+}
+
+/// Parses the branch and remote sections of a git config file.
+///
+/// # Errors
+///
+/// Fails on syntax whose meaning would need git's full config parser: includes, line
+/// continuations, quoting or comments in relevant values, old-style `[branch.name]` headers,
+/// repeated upstream keys, and ref storage or per-worktree config extensions.
+pub fn parse_config(text: &str) -> Result<RefConfig> {
+    let mut config = RefConfig::default();
+    let mut section = (String::new(), None::<String>);
+    for line in text.lines() {
+        let line = line.trim();
+        if line.ends_with('\\') {
+            anyhow::bail!("Config line continuations aren't supported");
+        }
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+        if line.starts_with('[') {
+            section = parse_section_header(line)?;
+            continue;
+        }
+
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim().to_ascii_lowercase(), value.trim()),
+            None => (line.to_ascii_lowercase(), ""),
+        };
+        let relevant = match (section.0.as_str(), section.1.as_deref()) {
+            ("extensions", None) => matches!(key.as_str(), "refstorage" | "worktreeconfig"),
+            ("branch", Some(_)) => matches!(key.as_str(), "remote" | "merge"),
+            ("remote", Some(_)) => key == "fetch",
+            _ => false,
+        };
+        if !relevant {
+            continue;
+        }
+        if value.contains(['"', '\\', '#', ';']) {
+            anyhow::bail!("Can't interpret config value {value:?}");
+        }
+
+        match (section.0.as_str(), section.1.clone()) {
+            ("extensions", _) => {
+                if key == "refstorage" && value != "files" {
+                    anyhow::bail!("extensions.refStorage = {value} isn't supported");
+                }
+                if key == "worktreeconfig" && value != "false" {
+                    anyhow::bail!("extensions.worktreeConfig isn't supported");
+                }
+            }
+            ("branch", Some(branch)) => {
+                let upstream = config.branches.entry(branch).or_default();
+                let slot = if key == "remote" {
+                    &mut upstream.remote
+                } else {
+                    &mut upstream.merge
+                };
+                if slot.replace(value.to_string()).is_some() {
+                    anyhow::bail!("Repeated branch.*.{key} isn't supported");
+                }
+            }
+            (_, Some(remote)) => {
+                config
+                    .remotes
+                    .entry(remote)
+                    .or_default()
+                    .push(value.to_string());
+            }
+            _ => {}
+        }
+    }
+    Ok(config)
+}
+
+/// Splits `[section "subsection"]` into its lowercased section name and subsection
+fn parse_section_header(line: &str) -> Result<(String, Option<String>)> {
+    let header = line
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .with_context(|| format!("Can't interpret config section {line:?}"))?;
+    let (name, subsection) = match header.split_once(' ') {
+        Some((name, quoted)) => {
+            let subsection = quoted
+                .trim()
+                .strip_prefix('"')
+                .and_then(|rest| rest.strip_suffix('"'))
+                .filter(|subsection| !subsection.contains(['"', '\\']))
+                .with_context(|| format!("Can't interpret config section {line:?}"))?;
+            (name, Some(subsection.to_string()))
+        }
+        None => (header, None),
+    };
+
+    let name = name.to_ascii_lowercase();
+    if name == "include" || name == "includeif" {
+        anyhow::bail!("Config includes aren't supported");
+    }
+    if name.starts_with("branch.") || name.starts_with("remote.") {
+        anyhow::bail!("Old-style config section {line:?} isn't supported");
+    }
+    Ok((name, subsection))
+}
+
+/// Builds the branch listing from resolved refs, the contents of `HEAD`, and the config
+///
+/// # Errors
+///
+/// Fails if a branch's upstream can't be mapped to a remote-tracking ref.
+pub fn branches_from_refs(
+    refs: &BTreeMap<String, String>,
+    head: &str,
+    config: &RefConfig,
+) -> Result<Vec<BranchLine>> {
+    let current = head.trim().strip_prefix("ref: refs/heads/");
+    refs.range("refs/heads/".to_string()..)
+        .map_while(|(name, sha)| Some((name.strip_prefix("refs/heads/")?, sha)))
+        .map(|(name, sha)| {
+            let tracking_ref = match config.branches.get(name) {
+                Some(Upstream {
+                    remote: Some(remote),
+                    merge: Some(merge),
+                }) => Some(tracking_ref(remote, merge, config)?),
+                _ => None,
+            };
+            let upstream = tracking_ref
+                .as_deref()
+                .map(|tracking| {
+                    tracking
+                        .strip_prefix("refs/remotes/")
+                        .or_else(|| tracking.strip_prefix("refs/heads/"))
+                        .map(ToString::to_string)
+                        .with_context(|| format!("Can't shorten upstream {tracking}"))
+                })
+                .transpose()?;
+            let gone = tracking_ref.is_some_and(|tracking| !refs.contains_key(&tracking));
+            Ok(BranchLine {
+                name: name.to_string(),
+                sha: sha.clone(),
+                upstream,
+                tracking: gone.then(|| "gone".to_string()),
+                subject: String::new(),
+                is_current: current == Some(name),
+                // `check_layout` refuses repositories with linked worktrees
+                in_other_worktree: false,
+            })
+        })
+        .collect()
+}
+
+/// Maps a branch's upstream to the ref that tracks it locally, using the remote's fetch
+/// refspecs the way git does
+fn tracking_ref(remote: &str, merge: &str, config: &RefConfig) -> Result<String> {
+    if remote == "." {
+        return Ok(merge.to_string());
+    }
+    let refspecs = config
+        .remotes
+        .get(remote)
+        .with_context(|| format!("Remote {remote} has no fetch refspecs"))?;
+    for refspec in refspecs {
+        let refspec = refspec.strip_prefix('+').unwrap_or(refspec);
+        if refspec.starts_with('^') {
+            anyhow::bail!("Negative refspec {refspec} isn't supported");
+        }
+        let Some((src, dst)) = refspec.split_once(':') else {
+            continue;
+        };
+        match (src.split_once('*'), dst.split_once('*')) {
+            (None, None) if src == merge => return Ok(dst.to_string()),
+            (Some((src_prefix, src_suffix)), Some((dst_prefix, dst_suffix))) => {
+                if let Some(matched) = merge
+                    .strip_prefix(src_prefix)
+                    .and_then(|rest| rest.strip_suffix(src_suffix))
+                {
+                    return Ok(format!("{dst_prefix}{matched}{dst_suffix}"));
+                }
+            }
+            _ => {}
+        }
+    }
+    anyhow::bail!("No fetch refspec of {remote} maps {merge}")
+}
+
+/// Adds the loose refs under `namespace` (e.g. `refs/heads`) to `refs`, overriding packed ones
+fn read_loose_refs(
+    git_dir: &Path,
+    namespace: &str,
+    refs: &mut BTreeMap<String, String>,
+) -> Result<()> {
+    let mut pending = vec![namespace.to_string()];
+    while let Some(prefix) = pending.pop() {
+        let dir = git_dir.join(&prefix);
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => continue,
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read {}", dir.display()));
+            }
+        };
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name().into_string().map_err(|name| {
+                anyhow::anyhow!("Ref name {} isn't valid UTF-8", name.to_string_lossy())
+            })?;
+            let refname = format!("{prefix}/{name}");
+            if entry.file_type()?.is_dir() {
+                pending.push(refname);
+                continue;
+            }
+            // Refs can't end in `.lock`, so these are in-progress writes
+            #[allow(clippy::case_sensitive_file_extension_comparisons)] // So is git's check
+            if name.ends_with(".lock") {
+                continue;
+            }
+            let contents = fs::read_to_string(entry.path())
+                .with_context(|| format!("Failed to read {refname}"))?;
+            let contents = contents.trim_end();
+            if is_sha(contents) {
+                refs.insert(refname, contents.to_string());
+            } else if namespace == "refs/remotes" && contents.starts_with("ref: ") {
+                // e.g. `refs/remotes/origin/HEAD`; only its existence matters
+                refs.insert(refname, contents.to_string());
+            } else {
+                anyhow::bail!("Can't interpret {refname}: {contents:?}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads a file, or `None` if it doesn't exist
+fn read(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// Whether `text` is a full SHA-1 or SHA-256 object name
+fn is_sha(text: &str) -> bool {
+    matches!(text.len(), 40 | 64) && text.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::git_command;
+    use crate::parse::{gone_branches, parse_branch_lines};
+    use std::fmt::Write as _;
+    use std::time::Instant;
+
+    const SHA: &str = "abc1234abc1234abc1234abc1234abc1234abc12";
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = git_command()
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .env_remove("GIT_DIR")
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?} failed");
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    /// Makes a repository with `count` tracked branches, every other one of them gone
+    fn repo_with_branches(count: usize) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        git(path, &["init", "-q", "-b", "main"]);
+        git(path, &["commit", "-q", "--allow-empty", "-m", "init"]);
+        git(
+            path,
+            &["remote", "add", "origin", "https://example.com/repo.git"],
+        );
+        let sha = git(path, &["rev-parse", "HEAD"]);
+
+        let mut updates = String::new();
+        let mut config = String::new();
+        for i in 0..count {
+            writeln!(updates, "create refs/heads/b-{i} {}", sha.trim()).unwrap();
+            if i % 2 == 0 {
+                writeln!(updates, "create refs/remotes/origin/b-{i} {}", sha.trim()).unwrap();
+            }
+            write!(
+                config,
+                "[branch \"b-{i}\"]\n\tremote = origin\n\tmerge = refs/heads/b-{i}\n"
+            )
+            .unwrap();
+        }
+        let mut child = git_command()
+            .args(["update-ref", "--stdin"])
+            .current_dir(path)
+            .stdin(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        std::io::Write::write_all(child.stdin.as_mut().unwrap(), updates.as_bytes()).unwrap();
+        assert!(child.wait().unwrap().success());
+        git(path, &["pack-refs", "--all"]);
+        // A loose ref on top of the packed ones
+        git(path, &["branch", "loose"]);
+
+        let config_path = path.join(".git/config");
+        let existing = fs::read_to_string(&config_path).unwrap();
+        fs::write(&config_path, existing + &config).unwrap();
+        dir
+    }
+
+    fn gone_names(branches: &[BranchLine]) -> Vec<String> {
+        gone_branches(branches)
+            .into_iter()
+            .map(|branch| branch.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_packed_refs() {
+        let refs = parse_packed_refs(&format!(
+            "# pack-refs with: peeled fully-peeled sorted \n{SHA} refs/heads/feature-1\n{SHA} refs/tags/v1\n^{SHA}\n"
+        ))
+        .unwrap();
+        assert_eq!(refs.len(), 2);
+        assert_eq!(refs["refs/heads/feature-1"], SHA);
+        assert!(parse_packed_refs("garbage").is_err());
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = parse_config(
+            r#"
+[core]
+	bare = false
+[remote "origin"]
+	url = https://example.com/repo.git
+	fetch = +refs/heads/*:refs/remotes/origin/*
+[Branch "feature/x"]
+	Remote = origin
+	merge = refs/heads/feature/x
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.remotes["origin"],
+            ["+refs/heads/*:refs/remotes/origin/*"]
+        );
+        assert_eq!(
+            config.branches["feature/x"],
+            Upstream {
+                remote: Some("origin".to_string()),
+                merge: Some("refs/heads/feature/x".to_string()),
+            }
+        );
+
+        for unsupported in [
+            "[include]\n\tpath = other",
+            "[includeIf \"gitdir:~/work/\"]\n\tpath = work",
+            "[branch.x]\n\tremote = origin",
+            "[branch \"x\"]\n\tremote = origin\n\tremote = upstream",
+            "[branch \"x\"]\n\tmerge = \"refs/heads/x\"",
+            "[core]\n\tpager = less \\\n\t-R",
+            "[extensions]\n\trefStorage = reftable",
+        ] {
+            assert!(parse_config(unsupported).is_err(), "{unsupported}");
+        }
+    }
+
+    #[test]
+    fn test_branches_from_refs() {
+        let refs: BTreeMap<String, String> = [
+            "refs/heads/feature-1",
+            "refs/heads/keep-me",
+            "refs/heads/local",
+            "refs/heads/main",
+            "refs/remotes/origin/keep-me",
+            "refs/stash",
+            "refs/tags/v1",
+        ]
+        .into_iter()
+        .map(|name| (name.to_string(), SHA.to_string()))
+        .collect();
+        let config = parse_config(
+            r#"
+[remote "origin"]
+	fetch = +refs/heads/*:refs/remotes/origin/*
+[branch "feature-1"]
+	remote = origin
+	merge = refs/heads/feature-1
+[branch "keep-me"]
+	remote = origin
+	merge = refs/heads/keep-me
+[branch "local"]
+	remote = .
+	merge = refs/heads/main
+"#,
+        )
+        .unwrap();
+
+        let branches = branches_from_refs(&refs, "ref: refs/heads/main\n", &config).unwrap();
+        let names: Vec<_> = branches.iter().map(|branch| branch.name.as_str()).collect();
+        assert_eq!(names, ["feature-1", "keep-me", "local", "main"]);
+        assert_eq!(branches[0].upstream.as_deref(), Some("origin/feature-1"));
+        assert_eq!(branches[0].tracking.as_deref(), Some("gone"));
+        assert_eq!(branches[1].tracking, None);
+        assert_eq!(branches[2].upstream.as_deref(), Some("main"));
+        assert_eq!(branches[2].tracking, None);
+        assert!(branches[3].is_current);
+        assert_eq!(gone_names(&branches), ["feature-1"]);
+
+        let no_refspec = parse_config(
+            "[branch \"feature-1\"]\n\tremote = elsewhere\n\tmerge = refs/heads/feature-1",
+        )
+        .unwrap();
+        assert!(branches_from_refs(&refs, "", &no_refspec).is_err());
+    }
+
+    #[test]
+    fn test_read_branches_matches_git() {
+        let repo = repo_with_branches(10);
+        let fast = read_branches(repo.path()).unwrap();
+        let slow = parse_branch_lines(&git(repo.path(), &["branch", "-vv"]));
+
+        assert_eq!(gone_names(&fast), gone_names(&slow));
+        assert_eq!(fast.len(), slow.len());
+        for (fast, slow) in fast.iter().zip(&slow) {
+            assert_eq!(fast.name, slow.name);
+            assert!(fast.sha.starts_with(&slow.sha));
+            assert_eq!(fast.upstream, slow.upstream);
+            assert_eq!(fast.is_current, slow.is_current);
+        }
+    }
+
+    #[test]
+    #[ignore = "benchmark; run with `cargo test --release -- --ignored --nocapture`"]
+    fn bench_read_branches_against_git_branch() {
+        let repo = repo_with_branches(20_000);
+
+        let start = Instant::now();
+        let fast = read_branches(repo.path()).unwrap();
+        let fast_elapsed = start.elapsed();
+
+        let start = Instant::now();
+        let slow = parse_branch_lines(&git(repo.path(), &["branch", "-vv"]));
+        let slow_elapsed = start.elapsed();
+
+        println!("ref store: {fast_elapsed:?}, git branch -vv: {slow_elapsed:?}");
+        assert_eq!(gone_names(&fast), gone_names(&slow));
+        assert_eq!(gone_names(&fast).len(), 10_000);
+    }
+}
@@ -0,0 +1,81 @@
+//! Tidying of the loose ref directories git leaves under `refs/heads`.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Removes empty directories below `root` (but never `root` itself), deepest first, so
+/// directories left empty by their removed children go too.
+///
+/// Returns the removed directories.
+///
+/// # Errors
+///
+/// Fails if a directory can't be read or removed.
+pub fn prune_empty_dirs(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+    for entry in fs::read_dir(root).with_context(|| format!("Failed to read {}", root.display()))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            prune_dir(&path, &mut removed)?;
+        }
+    }
+    Ok(removed)
+}
+
+/// Prunes below `dir`, then removes `dir` itself if that left it empty
+fn prune_dir(dir: &Path, removed: &mut Vec<PathBuf>) -> Result<()> {
+    let mut is_empty = true;
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            prune_dir(&path, removed)?;
+        }
+        // Still there if it held refs (or wasn't a directory)
+        is_empty &= !path.exists();
+    }
+
+    if is_empty {
+        fs::remove_dir(dir).with_context(|| format!("Failed to remove {}", dir.display()))?;
+        removed.push(dir.to_path_buf());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prune_empty_dirs() {
+        let root = tempfile::tempdir().unwrap();
+        let heads = root.path();
+        fs::create_dir_all(heads.join("feature/nested/deeper")).unwrap();
+        fs::create_dir_all(heads.join("empty")).unwrap();
+        fs::create_dir_all(heads.join("bugfix")).unwrap();
+        fs::write(heads.join("bugfix/live"), "abc\n").unwrap();
+        fs::write(heads.join("main"), "abc\n").unwrap();
+
+        let mut removed = prune_empty_dirs(heads).unwrap();
+        removed.sort();
+
+        assert_eq!(
+            removed,
+            [
+                heads.join("empty"),
+                heads.join("feature"),
+                heads.join("feature/nested"),
+                heads.join("feature/nested/deeper"),
+            ]
+        );
+        assert!(heads.join("bugfix/live").exists());
+        assert!(heads.join("main").exists());
+    }
+
+    #[test]
+    fn test_prune_empty_dirs_keeps_root() {
+        let root = tempfile::tempdir().unwrap();
+        assert!(prune_empty_dirs(root.path()).unwrap().is_empty());
+        assert!(root.path().exists());
+    }
+}
@@ -0,0 +1,482 @@
+//! Detection of remotes whose branches have all vanished, and of branches configured to
+//! track a remote that no longer exists.
+//!
+//! When every branch of a remote disappears at once, the remote was most likely removed or
+//! emptied on purpose (or by accident), so its tracking branches are confirmed as a group
+//! instead of one by one.
+//!
+//! When the remote itself is removed from the config, `git branch -vv` shows the branches that
+//! tracked it with no upstream at all, so they aren't gone; their leftover
+//! `branch.<name>.remote` is reported as orphaned tracking config instead.
+//!
+//! `--list-remotes-checked` shows which remotes a run fetched and pruned, and which remote
+//! decided each branch's gone state, for working out why a branch wasn't found gone.
+
+use crate::git::{branch_remotes, remote_has_branches};
+use crate::grouping::upstream_remote;
+use crate::parse::BranchLine;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+use std::hash::BuildHasher;
+
+/// A remote with no remote-tracking branches left, and the gone branches that track it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VanishedRemote {
+    pub remote: String,
+    pub branches: Vec<String>,
+}
+
+/// Groups the gone branches by remote, keeping the remotes for which `has_branches` reports
+/// no remote-tracking branches left.
+///
+/// `branch_remotes` maps each branch to its configured remote; branches tracking another
+/// local branch (remote `.`) are ignored.
+///
+/// # Errors
+///
+/// Fails if `has_branches` does.
+pub fn group_vanished_remotes<S, F>(
+    gone_branches: &[BranchLine],
+    branch_remotes: &HashMap<String, String, S>,
+    mut has_branches: F,
+) -> Result<Vec<VanishedRemote>>
+where
+    S: BuildHasher,
+    F: FnMut(&str) -> Result<bool>,
+{
+    let mut by_remote: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for branch in gone_branches {
+        if let Some(remote) = branch_remotes
+            .get(&branch.name)
+            .filter(|remote| *remote != ".")
+        {
+            by_remote
+                .entry(remote)
+                .or_default()
+                .push(branch.name.clone());
+        }
+    }
+
+    let mut vanished = Vec::new();
+    for (remote, branches) in by_remote {
+        if !has_branches(remote)? {
+            vanished.push(VanishedRemote {
+                remote: remote.to_string(),
+                branches,
+            });
+        }
+    }
+    Ok(vanished)
+}
+
+/// Finds the remotes among the gone branches' upstreams that have no branches left
+///
+/// # Errors
+///
+/// Fails if git can't be run.
+pub fn find_vanished_remotes(gone_branches: &[BranchLine]) -> Result<Vec<VanishedRemote>> {
+    if gone_branches.is_empty() {
+        return Ok(Vec::new());
+    }
+    group_vanished_remotes(gone_branches, &branch_remotes()?, remote_has_branches)
+}
+
+/// Lists the branches whose configured remote (per `branch_remotes`, branch name to remote) is
+/// `remote`, sorted by name, e.g. the config a removed remote left behind
+#[must_use]
+pub fn branches_configured_for<S: BuildHasher>(
+    branch_remotes: &HashMap<String, String, S>,
+    remote: &str,
+) -> Vec<String> {
+    let mut branches: Vec<String> = branch_remotes
+        .iter()
+        .filter(|(_, r)| *r == remote)
+        .map(|(branch, _)| branch.clone())
+        .collect();
+    branches.sort();
+    branches
+}
+
+/// A branch whose configured remote (`branch.<name>.remote`) is no longer a remote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrphanedTracking {
+    pub branch: String,
+    pub remote: String,
+}
+
+/// Finds the branches whose configured remote (per `branch_remotes`, branch name to remote)
+/// isn't among `remotes`, sorted by name. Remotes given as a URL or path (containing `:` or
+/// `/`) and another local branch (`.`) are valid without being configured, so they're skipped.
+#[must_use]
+pub fn find_orphaned_tracking<S: BuildHasher>(
+    branch_remotes: &HashMap<String, String, S>,
+    remotes: &[String],
+) -> Vec<OrphanedTracking> {
+    let mut orphaned: Vec<OrphanedTracking> = branch_remotes
+        .iter()
+        .filter(|(_, remote)| {
+            *remote != "." && !remote.contains([':', '/']) && !remotes.contains(remote)
+        })
+        .map(|(branch, remote)| OrphanedTracking {
+            branch: branch.clone(),
+            remote: remote.clone(),
+        })
+        .collect();
+    orphaned.sort_by(|a, b| a.branch.cmp(&b.branch));
+    orphaned
+}
+
+/// Lists the branches with orphaned tracking config, or nothing if there are none
+#[must_use]
+pub fn format_orphaned_tracking(orphaned: &[OrphanedTracking]) -> String {
+    if orphaned.is_empty() {
+        return String::new();
+    }
+
+    let mut summary = format!(
+        "\nBranches with orphaned tracking config: {}\n",
+        orphaned.len()
+    );
+    for entry in orphaned {
+        let _ = writeln!(
+            summary,
+            "  - {} (remote '{}' no longer exists)",
+            entry.branch, entry.remote
+        );
+    }
+    summary
+}
+
+/// What a run's fetch covered, for [`remote_checks`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fetched {
+    /// Nothing was fetched (`--no-fetch`, `--stdin`, or a plan)
+    Nothing,
+    /// `git fetch` fetched these remotes, pruning them unless `pruned` is false
+    Remotes { remotes: Vec<String>, pruned: bool },
+    /// A `--fetch-command`, which could have fetched and pruned anything
+    Unknown,
+}
+
+/// The remotes `git fetch -ap [remote] [extra_args]` fetches: all of them with `--all` (or
+/// `fetch.all`, unless one is named), else the remote named by `remote` or the first argument
+/// in `extra_args` that's one of `remotes`, else the current branch's remote, else `origin`
+#[must_use]
+pub fn fetched_remotes(
+    remote: Option<&str>,
+    extra_args: &[String],
+    fetch_all: bool,
+    current_remote: Option<&str>,
+    remotes: &[String],
+) -> Vec<String> {
+    let named = remote.or_else(|| {
+        extra_args
+            .iter()
+            .map(String::as_str)
+            .find(|arg| remotes.iter().any(|remote| remote == arg))
+    });
+    if extra_args.iter().any(|arg| arg == "--all") || (fetch_all && named.is_none()) {
+        return remotes.to_vec();
+    }
+    match named.or(current_remote.filter(|remote| *remote != ".")) {
+        Some(remote) => vec![remote.to_string()],
+        None if remotes.iter().any(|remote| remote == "origin") => vec!["origin".to_string()],
+        None => Vec::new(),
+    }
+}
+
+/// A remote as this run checked it, and the local branches whose gone state it decided
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RemoteCheck {
+    pub remote: String,
+    /// Whether it's a configured remote, rather than one only a branch's config still names
+    pub configured: bool,
+    /// Whether it was fetched, or `None` if a `--fetch-command` did the fetching
+    pub fetched: Option<bool>,
+    /// Whether its deleted branches were pruned, or `None` if a `--fetch-command` did it
+    pub pruned: Option<bool>,
+    /// The local branches whose upstream is on this remote
+    pub branches: Vec<String>,
+    /// Those of `branches` found gone
+    pub gone: Vec<String>,
+}
+
+/// Lists each of `remotes`, and each remote a branch's upstream is on, with what `fetched`
+/// did to it and the branches it decided (per `branch_remotes`, branch name to remote, or
+/// else the upstream's name). Branches tracking another local branch (remote `.`) are left
+/// out.
+#[must_use]
+pub fn remote_checks<S: BuildHasher, T: BuildHasher>(
+    all_branches: &[BranchLine],
+    gone: &HashSet<String, T>,
+    branch_remotes: &HashMap<String, String, S>,
+    remotes: &[String],
+    fetched: &Fetched,
+) -> Vec<RemoteCheck> {
+    let mut by_remote: BTreeMap<&str, Vec<&str>> = remotes
+        .iter()
+        .map(|remote| (remote.as_str(), Vec::new()))
+        .collect();
+    for branch in all_branches {
+        let remote = match (branch_remotes.get(&branch.name), &branch.upstream) {
+            (Some(remote), _) => remote.as_str(),
+            (None, Some(upstream)) => upstream_remote(upstream, remotes),
+            (None, None) => continue,
+        };
+        if branch.upstream.is_some() && remote != "." {
+            by_remote.entry(remote).or_default().push(&branch.name);
+        }
+    }
+
+    by_remote
+        .into_iter()
+        .map(|(remote, branches)| {
+            let configured = remotes.iter().any(|r| r == remote);
+            let (fetched, pruned) = match fetched {
+                Fetched::Nothing => (Some(false), Some(false)),
+                Fetched::Remotes {
+                    remotes: fetched,
+                    pruned,
+                } => {
+                    let fetched = fetched.iter().any(|r| r == remote);
+                    (Some(fetched), Some(fetched && *pruned))
+                }
+                Fetched::Unknown => (None, None),
+            };
+            RemoteCheck {
+                remote: remote.to_string(),
+                configured,
+                fetched,
+                pruned,
+                gone: branches
+                    .iter()
+                    .filter(|name| gone.contains(**name))
+                    .map(ToString::to_string)
+                    .collect(),
+                branches: branches.into_iter().map(ToString::to_string).collect(),
+            }
+        })
+        .collect()
+}
+
+/// Formats the `--list-remotes-checked` section, or nothing if there are no remotes
+#[must_use]
+pub fn format_remote_checks(checks: &[RemoteCheck]) -> String {
+    if checks.is_empty() {
+        return String::new();
+    }
+
+    let mut out = String::from("\nRemotes checked:\n");
+    for check in checks {
+        let state = match (check.configured, check.fetched, check.pruned) {
+            (false, _, _) => "not a configured remote, so its branches can't be found gone",
+            (true, None, _) => "fetched by --fetch-command, which may or may not have pruned it",
+            (true, Some(true), Some(true)) => "fetched and pruned",
+            (true, Some(true), _) => "fetched, not pruned, so deleted branches aren't gone yet",
+            (true, Some(false), _) => "not fetched, so its branches' gone state may be stale",
+        };
+        let _ = writeln!(out, "  {} ({state}):", check.remote);
+        if check.branches.is_empty() {
+            let _ = writeln!(out, "    no local branch tracks it");
+        }
+        for name in &check.branches {
+            if check.gone.contains(name) {
+                let _ = writeln!(out, "    - {name} (gone)");
+            } else {
+                let _ = writeln!(out, "    - {name}");
+            }
+        }
+    }
+    out
+}
+
+/// Formats each vanished remote with the gone branches tracking it
+#[must_use]
+pub fn format_vanished_remotes(vanished: &[VanishedRemote]) -> String {
+    let mut summary = String::new();
+    for group in vanished {
+        let _ = writeln!(
+            summary,
+            "\nRemote '{}' appears fully removed; {} branch(es) track it:",
+            group.remote,
+            group.branches.len()
+        );
+        for name in &group.branches {
+            let _ = writeln!(summary, "  - {name}");
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{parse_branch_lines, parse_gone_branches};
+
+    #[test]
+    fn test_group_vanished_remotes() {
+        let gone = parse_gone_branches(
+            r"
+  fork-a       abc1234 [fork/a: gone] A
+  feature-1    def5678 [origin/feature-1: gone] One
+  fork-b       ghi9012 [fork/b: gone] B
+  local        jkl3456 [main: gone] Tracks a local branch
+",
+        )
+        .unwrap();
+        let branch_remotes = HashMap::from(
+            [
+                ("fork-a", "fork"),
+                ("feature-1", "origin"),
+                ("fork-b", "fork"),
+                ("local", "."),
+            ]
+            .map(|(branch, remote)| (branch.to_string(), remote.to_string())),
+        );
+
+        let vanished =
+            group_vanished_remotes(&gone, &branch_remotes, |remote| Ok(remote == "origin"))
+                .unwrap();
+
+        assert_eq!(
+            vanished,
+            [VanishedRemote {
+                remote: "fork".to_string(),
+                branches: vec!["fork-a".to_string(), "fork-b".to_string()],
+            }]
+        );
+        assert_eq!(
+            format_vanished_remotes(&vanished),
+            "\nRemote 'fork' appears fully removed; 2 branch(es) track it:\n  - fork-a\n  - fork-b\n"
+        );
+    }
+
+    #[test]
+    fn test_find_orphaned_tracking() {
+        let branch_remotes = HashMap::from(
+            [
+                ("removed-b", "old-fork"),
+                ("feature-1", "origin"),
+                ("removed-a", "old-fork"),
+                ("local", "."),
+                ("by-url", "https://example.com/repo.git"),
+                ("by-path", "../mirror"),
+            ]
+            .map(|(branch, remote)| (branch.to_string(), remote.to_string())),
+        );
+        let orphaned = find_orphaned_tracking(&branch_remotes, &["origin".to_string()]);
+        assert_eq!(
+            orphaned
+                .iter()
+                .map(|entry| (entry.branch.as_str(), entry.remote.as_str()))
+                .collect::<Vec<_>>(),
+            [("removed-a", "old-fork"), ("removed-b", "old-fork")]
+        );
+        assert_eq!(
+            format_orphaned_tracking(&orphaned[..1]),
+            "\nBranches with orphaned tracking config: 1\n  - removed-a (remote 'old-fork' no longer exists)\n"
+        );
+        assert_eq!(format_orphaned_tracking(&[]), "");
+    }
+
+    #[test]
+    fn test_fetched_remotes() {
+        let remotes = ["fork".to_string(), "origin".to_string()];
+        let args = |args: &[&str]| args.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            fetched_remotes(None, &[], false, None, &remotes),
+            ["origin"]
+        );
+        assert_eq!(
+            fetched_remotes(None, &[], false, Some("fork"), &remotes),
+            ["fork"]
+        );
+        assert_eq!(
+            fetched_remotes(None, &[], true, Some("fork"), &remotes),
+            remotes
+        );
+        assert_eq!(
+            fetched_remotes(None, &args(&["--all"]), false, None, &remotes),
+            remotes
+        );
+        assert_eq!(
+            fetched_remotes(None, &args(&["--tags", "fork"]), true, None, &remotes),
+            ["fork"]
+        );
+        assert_eq!(
+            fetched_remotes(Some("origin"), &[], true, Some("fork"), &remotes),
+            ["origin"]
+        );
+        assert!(fetched_remotes(None, &[], false, Some("."), &["fork".to_string()]).is_empty());
+    }
+
+    #[test]
+    fn test_remote_checks() {
+        let branches = parse_branch_lines(
+            r"
+  feature-1    abc1234 [origin/feature-1: gone] One
+  fork-work    def5678 [fork/fork-work] Still there
+  leftover     ghi9012 [old/leftover] Tracks a removed remote
+  local        jkl3456 [main] Tracks a local branch
+  scratch      mno7890 No upstream
+",
+        );
+        let branch_remotes = HashMap::from([("local".to_string(), ".".to_string())]);
+        let remotes = [
+            "fork".to_string(),
+            "origin".to_string(),
+            "quiet".to_string(),
+        ];
+        let gone = HashSet::from(["feature-1".to_string()]);
+        let fetched = Fetched::Remotes {
+            remotes: vec!["origin".to_string()],
+            pruned: true,
+        };
+
+        let checks = remote_checks(&branches, &gone, &branch_remotes, &remotes, &fetched);
+        assert_eq!(
+            format_remote_checks(&checks),
+            "
+Remotes checked:
+  fork (not fetched, so its branches' gone state may be stale):
+    - fork-work
+  old (not a configured remote, so its branches can't be found gone):
+    - leftover
+  origin (fetched and pruned):
+    - feature-1 (gone)
+  quiet (not fetched, so its branches' gone state may be stale):
+    no local branch tracks it
+"
+        );
+
+        let checks = remote_checks(
+            &branches,
+            &gone,
+            &branch_remotes,
+            &remotes,
+            &Fetched::Unknown,
+        );
+        assert_eq!((checks[2].fetched, checks[2].pruned), (None, None));
+        assert_eq!(checks[2].gone, ["feature-1"]);
+    }
+
+    #[test]
+    fn test_branches_configured_for() {
+        let branch_remotes = HashMap::from(
+            [
+                ("fork-b", "fork"),
+                ("feature-1", "origin"),
+                ("fork-a", "fork"),
+                ("local", "."),
+            ]
+            .map(|(branch, remote)| (branch.to_string(), remote.to_string())),
+        );
+        assert_eq!(
+            branches_configured_for(&branch_remotes, "fork"),
+            ["fork-a", "fork-b"]
+        );
+        assert!(branches_configured_for(&branch_remotes, "upstream").is_empty());
+    }
+}
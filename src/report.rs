@@ -0,0 +1,1562 @@
+//! Structured report of the gone branches found and what was done with them.
+//!
+//! The same `Report` backs every machine-readable `--format`, so each format
+//! carries identical metadata.
+
+use crate::age::AgeBucket;
+use crate::git::GitFailure;
+use crate::grouping::Group;
+use crate::parse::{BranchLine, Divergence};
+use crate::remotes::RemoteCheck;
+use crate::risk::{Assessment, Risk};
+use crate::safety::{Held, HoldKind};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::de::{self, IntoDeserializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::fs;
+use std::hash::BuildHasher;
+use std::num::NonZeroUsize;
+use std::path::Path;
+
+/// Output format for the final report
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable progress and listing (default)
+    #[default]
+    Human,
+    /// JSON document
+    Json,
+    /// XML document
+    Xml,
+    /// Markdown table, e.g. for pasting into a PR or issue
+    Markdown,
+    /// Just the number of gone branches, e.g. for shell prompts
+    Count,
+    /// SARIF 2.1.0 log with a warning for each unmerged gone branch, for code-scanning
+    /// dashboards
+    Sarif,
+    /// JUnit-format XML with a test case per gone branch, failing for each unmerged one, for CI
+    /// test reports
+    Junit,
+}
+
+impl OutputFormat {
+    /// Whether this format is meant to be consumed by another program
+    #[must_use]
+    pub fn is_machine(self) -> bool {
+        self != Self::Human
+    }
+}
+
+/// What happened (or would happen) to a gone branch
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Deleted,
+    WouldDelete,
+    /// Renamed to `archive/<name>` by `--rename-to-archive`
+    Archived,
+    WouldArchive,
+    Skipped,
+    Failed,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Action {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Deleted => "deleted",
+            Self::WouldDelete => "would-delete",
+            Self::Archived => "archived",
+            Self::WouldArchive => "would-archive",
+            Self::Skipped => "skipped",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// What happened to a deleted branch's counterpart on `--delete-remote`'s remote
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RemoteDeletion {
+    pub remote: String,
+    pub outcome: RemoteOutcome,
+    /// Why the remote deletion failed, for `RemoteOutcome::Failed`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Outcome of deleting a branch on a remote
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RemoteOutcome {
+    Deleted,
+    /// The remote has no branch of that name, so there was nothing to delete
+    Missing,
+    /// Left alone because the remote deletion wasn't confirmed
+    Declined,
+    Failed,
+}
+
+impl fmt::Display for RemoteOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Deleted => "deleted",
+            Self::Missing => "missing",
+            Self::Declined => "declined",
+            Self::Failed => "failed",
+        })
+    }
+}
+
+/// What a branch's deletion failed on, classified from git's error output so automation can
+/// react to the cause
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FailureCause {
+    /// Not a valid branch name, so never handed to git
+    InvalidName,
+    /// Checked out in a worktree
+    CheckedOut,
+    /// Another git process holds the ref's lock file
+    LockHeld,
+    /// `git branch -d` refused it as not fully merged
+    NotMerged,
+    /// Still listed after git reported it deleted
+    Survived,
+    /// Its archive is missing or doesn't point at the tip the branch had
+    ArchiveInvalid,
+    Other,
+}
+
+impl FailureCause {
+    /// Classifies the error `message` (git's stderr, or the tool's own error)
+    #[must_use]
+    pub fn classify(message: &str) -> Self {
+        let message = message.to_lowercase();
+        if message.contains("refusing to delete") {
+            Self::InvalidName
+        } else if message.contains("checked out at") || message.contains("used by worktree") {
+            Self::CheckedOut
+        } else if message.contains(".lock") || message.contains("cannot lock ref") {
+            Self::LockHeld
+        } else if message.contains("not fully merged") {
+            Self::NotMerged
+        } else {
+            Self::Other
+        }
+    }
+
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::InvalidName => "invalid-name",
+            Self::CheckedOut => "checked-out",
+            Self::LockHeld => "lock-held",
+            Self::NotMerged => "not-merged",
+            Self::Survived => "survived",
+            Self::ArchiveInvalid => "archive-invalid",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Metadata about a single gone branch
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BranchReport {
+    pub name: String,
+    pub sha: String,
+    pub upstream: Option<String>,
+    pub subject: String,
+    /// Commits on the branch that aren't on its upstream, when `git branch -vv` reports them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ahead: Option<u32>,
+    /// Commits on the upstream that aren't on the branch, when `git branch -vv` reports them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub behind: Option<u32>,
+    /// Commits the base doesn't contain, for a branch assessed as risky to delete (see
+    /// [`Report::fill_unmerged_commits`])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unmerged_commits: Option<usize>,
+    pub action: Action,
+    /// Why the deletion failed, for `Action::Failed`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// What the deletion failed on, for `Action::Failed`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cause: Option<FailureCause>,
+    /// git's own error output, for `Action::Failed` when git ran and refused
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stderr: Option<String>,
+    /// The branch's description (`git branch --edit-description`), if it has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The branch's group with `--group-by`: its upstream's remote, its name's prefix, or
+    /// the age of its last commit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Why a safety check kept the branch, for `Action::Skipped`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// The branch's new name, for `Action::Archived`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived_as: Option<String>,
+    /// The same branch's deletion on another remote, with `--delete-remote`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_deletion: Option<RemoteDeletion>,
+    /// What a real run would do with the branch, in a dry run (see
+    /// [`Report::fill_planned_actions`])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub planned_action: Option<PlannedAction>,
+    /// Why the branch was kept, for `Action::Skipped` branches a check (rather than the
+    /// user) held back
+    #[serde(skip)]
+    pub hold: Option<HoldKind>,
+}
+
+/// What a dry run plans for a branch. Serialized as one of a fixed set of strings: `delete`,
+/// `archive`, `skip-<kind>` for each [`HoldKind`] (e.g. `skip-protected`, `skip-unmerged`),
+/// `skip-declined` for a branch left out at the prompt, and `fail` for one that can't be
+/// deleted at all.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlannedAction {
+    Delete,
+    Archive,
+    Skip(HoldKind),
+    SkipDeclined,
+    Fail,
+}
+
+impl fmt::Display for PlannedAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Delete => f.write_str("delete"),
+            Self::Archive => f.write_str("archive"),
+            Self::Skip(kind) => write!(f, "skip-{}", kind.as_str()),
+            Self::SkipDeclined => f.write_str("skip-declined"),
+            Self::Fail => f.write_str("fail"),
+        }
+    }
+}
+
+impl Serialize for PlannedAction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PlannedAction {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        match text.as_str() {
+            "delete" => Ok(Self::Delete),
+            "archive" => Ok(Self::Archive),
+            "skip-declined" => Ok(Self::SkipDeclined),
+            "fail" => Ok(Self::Fail),
+            _ => match text.strip_prefix("skip-") {
+                Some(kind) => HoldKind::deserialize(kind.into_deserializer()).map(Self::Skip),
+                None => Err(de::Error::custom(format!(
+                    "unknown planned action {text:?}"
+                ))),
+            },
+        }
+    }
+}
+
+impl BranchReport {
+    #[must_use]
+    pub fn new(branch: &BranchLine, action: Action) -> Self {
+        let divergence = branch.divergence().filter(Divergence::has_counts);
+        Self {
+            name: branch.name.clone(),
+            sha: branch.sha.clone(),
+            upstream: branch.upstream.clone(),
+            subject: branch.subject.clone(),
+            ahead: divergence.map(|d| d.ahead),
+            behind: divergence.map(|d| d.behind),
+            unmerged_commits: None,
+            action,
+            error: None,
+            cause: None,
+            stderr: None,
+            description: None,
+            group: None,
+            reason: None,
+            archived_as: None,
+            remote_deletion: None,
+            planned_action: None,
+            hold: None,
+        }
+    }
+
+    #[must_use]
+    pub fn held(held: &Held) -> Self {
+        Self::skipped(&held.branch, held.kind, held.reason.clone())
+    }
+
+    #[must_use]
+    pub fn skipped(branch: &BranchLine, kind: HoldKind, reason: String) -> Self {
+        Self {
+            reason: Some(reason),
+            hold: Some(kind),
+            ..Self::new(branch, Action::Skipped)
+        }
+    }
+
+    #[must_use]
+    pub fn archived(branch: &BranchLine, archived_as: String) -> Self {
+        Self {
+            archived_as: Some(archived_as),
+            ..Self::new(branch, Action::Archived)
+        }
+    }
+
+    /// A failed branch, with the cause and git's stderr when `error` is (or wraps) a
+    /// [`GitFailure`]
+    #[must_use]
+    pub fn failed(branch: &BranchLine, error: &anyhow::Error) -> Self {
+        let message = format!("{error:#}");
+        let stderr = error
+            .downcast_ref::<GitFailure>()
+            .map(|failure| failure.stderr.clone());
+        Self {
+            cause: Some(FailureCause::classify(
+                stderr.as_deref().unwrap_or(&message),
+            )),
+            error: Some(message),
+            stderr,
+            ..Self::new(branch, Action::Failed)
+        }
+    }
+}
+
+/// The full outcome of a run
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    pub dry_run: bool,
+    pub branches: Vec<BranchReport>,
+    /// The gone branches by age, with `--report-age-histogram`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age_histogram: Option<Vec<AgeBucket>>,
+    /// The remotes fetched and pruned, and the branches each decided, with `--format json`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remotes_checked: Option<Vec<RemoteCheck>>,
+}
+
+impl Report {
+    /// Whether any branch failed to delete, locally or on `--delete-remote`'s remote
+    #[must_use]
+    pub fn has_failures(&self) -> bool {
+        self.branches.iter().any(|branch| {
+            branch.action == Action::Failed
+                || branch
+                    .remote_deletion
+                    .as_ref()
+                    .is_some_and(|deletion| deletion.outcome == RemoteOutcome::Failed)
+        })
+    }
+
+    /// Marks each reportedly deleted (or archived) branch that's still in `remaining` as
+    /// failed, returning the names of those survivors
+    pub fn verify_removed(&mut self, remaining: &[BranchLine]) -> Vec<String> {
+        let mut survivors = Vec::new();
+        for branch in &mut self.branches {
+            if matches!(branch.action, Action::Deleted | Action::Archived)
+                && remaining.iter().any(|line| line.name == branch.name)
+            {
+                branch.action = Action::Failed;
+                branch.error = Some("branch still exists after deletion".to_string());
+                branch.cause = Some(FailureCause::Survived);
+                survivors.push(branch.name.clone());
+            }
+        }
+        survivors
+    }
+
+    /// Checks each archived branch's archive against `tips` (branch name to full tip SHA): it
+    /// must exist and point at the tip the branch had. A branch whose archive doesn't is marked
+    /// failed, so it isn't reported as safely archived. Returns the verified archives with
+    /// their tips, and an error message for each failed one.
+    pub fn verify_archived<S: BuildHasher>(
+        &mut self,
+        tips: &HashMap<String, String, S>,
+    ) -> (Vec<(String, String)>, Vec<String>) {
+        let (mut verified, mut invalid) = (Vec::new(), Vec::new());
+        for branch in &mut self.branches {
+            let Some(archived_as) = branch
+                .archived_as
+                .as_ref()
+                .filter(|_| branch.action == Action::Archived)
+            else {
+                continue;
+            };
+            let error = match tips.get(archived_as) {
+                Some(tip) if tip.starts_with(&branch.sha) => {
+                    verified.push((archived_as.clone(), tip.clone()));
+                    continue;
+                }
+                Some(tip) => format!(
+                    "archive {archived_as} points at {}, not {}",
+                    &tip[..branch.sha.len().min(tip.len())],
+                    branch.sha
+                ),
+                None => format!("archive {archived_as} doesn't exist"),
+            };
+            invalid.push(format!("{}: {error}", branch.name));
+            branch.action = Action::Failed;
+            branch.error = Some(error);
+            branch.cause = Some(FailureCause::ArchiveInvalid);
+        }
+        (verified, invalid)
+    }
+
+    /// Renders the report in the given machine-readable format.
+    ///
+    /// Returns `None` for `OutputFormat::Human`, which is printed incrementally instead.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the report can't be serialized.
+    pub fn render(&self, format: OutputFormat) -> Result<Option<String>> {
+        match format {
+            OutputFormat::Human => Ok(None),
+            OutputFormat::Json => self.to_json().map(Some),
+            OutputFormat::Xml => Ok(Some(self.to_xml())),
+            OutputFormat::Markdown => Ok(Some(self.to_markdown())),
+            OutputFormat::Count => Ok(Some(format!("{}\n", self.branches.len()))),
+            OutputFormat::Sarif => self.to_sarif().map(Some),
+            OutputFormat::Junit => Ok(Some(self.to_junit())),
+        }
+    }
+
+    /// Renders the report as JSON on a single line, e.g. for logs that store one object per
+    /// line. It carries the same fields as the pretty-printed form.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the report can't be serialized.
+    pub fn to_compact_json(&self) -> Result<String> {
+        serde_json::to_string(self)
+            .map(|json| json + "\n")
+            .context("Failed to serialize report as JSON")
+    }
+
+    fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map(|json| json + "\n")
+            .context("Failed to serialize report as JSON")
+    }
+
+    fn to_xml(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            xml,
+            "<git-clean-gone dry-run=\"{}\" count=\"{}\">",
+            self.dry_run,
+            self.branches.len()
+        );
+
+        for branch in &self.branches {
+            let _ = write!(
+                xml,
+                "  <branch name=\"{}\" sha=\"{}\" action=\"{}\"",
+                escape_xml(&branch.name),
+                escape_xml(&branch.sha),
+                branch.action.as_str()
+            );
+            if let Some(upstream) = &branch.upstream {
+                let _ = write!(xml, " upstream=\"{}\"", escape_xml(upstream));
+            }
+            if let Some(archived_as) = &branch.archived_as {
+                let _ = write!(xml, " archived-as=\"{}\"", escape_xml(archived_as));
+            }
+            if let Some(cause) = branch.cause {
+                let _ = write!(xml, " cause=\"{}\"", cause.as_str());
+            }
+            if let (Some(ahead), Some(behind)) = (branch.ahead, branch.behind) {
+                let _ = write!(xml, " ahead=\"{ahead}\" behind=\"{behind}\"");
+            }
+            if let Some(planned) = branch.planned_action {
+                let _ = write!(xml, " planned-action=\"{planned}\"");
+            }
+            let _ = writeln!(xml, ">");
+            let _ = writeln!(
+                xml,
+                "    <subject>{}</subject>",
+                escape_xml(&branch.subject)
+            );
+            if let Some(error) = &branch.error {
+                let _ = writeln!(xml, "    <error>{}</error>", escape_xml(error));
+            }
+            if let Some(stderr) = &branch.stderr {
+                let _ = writeln!(xml, "    <stderr>{}</stderr>", escape_xml(stderr));
+            }
+            if let Some(reason) = &branch.reason {
+                let _ = writeln!(xml, "    <reason>{}</reason>", escape_xml(reason));
+            }
+            if let Some(description) = &branch.description {
+                let _ = writeln!(
+                    xml,
+                    "    <description>{}</description>",
+                    escape_xml(description)
+                );
+            }
+            if let Some(group) = &branch.group {
+                let _ = writeln!(xml, "    <group>{}</group>", escape_xml(group));
+            }
+            if let Some(deletion) = &branch.remote_deletion {
+                let _ = write!(
+                    xml,
+                    "    <remote-deletion remote=\"{}\" outcome=\"{}\"",
+                    escape_xml(&deletion.remote),
+                    deletion.outcome
+                );
+                let _ = match &deletion.error {
+                    Some(error) => writeln!(xml, ">{}</remote-deletion>", escape_xml(error)),
+                    None => writeln!(xml, "/>"),
+                };
+            }
+            let _ = writeln!(xml, "  </branch>");
+        }
+
+        xml.push_str("</git-clean-gone>\n");
+        xml
+    }
+
+    /// Renders the unmerged branches (those with [`BranchReport::unmerged_commits`]) as
+    /// warning-level results of a SARIF 2.1.0 log. Branches are logical locations, as they
+    /// have no file to point at.
+    fn to_sarif(&self) -> Result<String> {
+        let results: Vec<serde_json::Value> = self
+            .branches
+            .iter()
+            .filter_map(|branch| {
+                let unmerged = branch.unmerged_commits?;
+                Some(serde_json::json!({
+                    "ruleId": SARIF_RULE_ID,
+                    "ruleIndex": 0,
+                    "level": "warning",
+                    "message": {
+                        "text": format!(
+                            "Branch {} is gone from {} but has {} unmerged commit(s) ({})",
+                            branch.name,
+                            branch.upstream.as_deref().unwrap_or("its remote"),
+                            group_digits(unmerged),
+                            branch.action
+                        ),
+                    },
+                    "locations": [{
+                        "logicalLocations": [{
+                            "name": branch.name,
+                            "fullyQualifiedName": format!("refs/heads/{}", branch.name),
+                            "kind": "branch",
+                        }],
+                    }],
+                    "partialFingerprints": {
+                        "branchTip/v1": format!("{}@{}", branch.name, branch.sha),
+                    },
+                    "properties": {
+                        "sha": branch.sha,
+                        "upstream": branch.upstream,
+                        "action": branch.action,
+                        "unmergedCommits": unmerged,
+                    },
+                }))
+            })
+            .collect();
+        let log = serde_json::json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                        "rules": [{
+                            "id": SARIF_RULE_ID,
+                            "name": "UnmergedGoneBranch",
+                            "shortDescription": {
+                                "text": "Gone branch with unmerged commits",
+                            },
+                            "fullDescription": {
+                                "text": "The branch's upstream was deleted, but it has commits \
+                                         its base doesn't contain, which deleting it would lose.",
+                            },
+                            "defaultConfiguration": { "level": "warning" },
+                        }],
+                    },
+                },
+                "properties": { "dryRun": self.dry_run },
+                "results": results,
+            }],
+        });
+        serde_json::to_string_pretty(&log)
+            .map(|json| json + "\n")
+            .context("Failed to serialize report as SARIF")
+    }
+
+    /// Renders each branch as a `JUnit` test case, failing for the unmerged ones (those with
+    /// [`BranchReport::unmerged_commits`]) and passing for the rest
+    fn to_junit(&self) -> String {
+        let failures = self
+            .branches
+            .iter()
+            .filter(|branch| branch.unmerged_commits.is_some())
+            .count();
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let counts = format!(
+            "tests=\"{}\" failures=\"{failures}\" errors=\"0\" skipped=\"0\"",
+            self.branches.len()
+        );
+        let _ = writeln!(
+            xml,
+            "<testsuites name=\"{}\" {counts}>",
+            env!("CARGO_PKG_NAME")
+        );
+        let _ = writeln!(xml, "  <testsuite name=\"gone-branches\" {counts}>");
+        for branch in &self.branches {
+            let name = escape_xml(&branch.name);
+            let Some(unmerged) = branch.unmerged_commits else {
+                let _ = writeln!(
+                    xml,
+                    "    <testcase classname=\"gone-branches\" name=\"{name}\"/>"
+                );
+                continue;
+            };
+            let message = format!(
+                "Branch {} is gone from {} but has {} unmerged commit(s) ({})",
+                branch.name,
+                branch.upstream.as_deref().unwrap_or("its remote"),
+                group_digits(unmerged),
+                branch.action
+            );
+            let _ = writeln!(
+                xml,
+                "    <testcase classname=\"gone-branches\" name=\"{name}\">"
+            );
+            let _ = writeln!(
+                xml,
+                "      <failure type=\"{SARIF_RULE_ID}\" message=\"{}\">{}@{}</failure>",
+                escape_xml(&message),
+                name,
+                escape_xml(&branch.sha)
+            );
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        if self.branches.is_empty() {
+            md.push_str("No gone branches found.\n");
+            return md;
+        }
+
+        md.push_str("| Branch | SHA | Upstream | Subject | Action | Note |\n");
+        md.push_str("| --- | --- | --- | --- | --- | --- |\n");
+        for branch in &self.branches {
+            let _ = writeln!(
+                md,
+                "| {} | {} | {} | {} | {} | {} |",
+                escape_markdown_cell(&branch.name),
+                escape_markdown_cell(&branch.sha),
+                escape_markdown_cell(branch.upstream.as_deref().unwrap_or("")),
+                escape_markdown_cell(&branch.subject),
+                branch.action.as_str(),
+                escape_markdown_cell(&branch_note(branch)),
+            );
+        }
+
+        let (heading, counts) = self.summary();
+        let _ = writeln!(md, "\n**{heading}:** {counts}");
+        md
+    }
+}
+
+impl Report {
+    /// Renders the report as plain text, one branch per line followed by a summary line, for
+    /// writing a human-format report to a file
+    #[must_use]
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        if self.branches.is_empty() {
+            text.push_str("No gone branches found.\n");
+            return text;
+        }
+
+        for branch in &self.branches {
+            let _ = write!(text, "{} {} ({})", branch.action, branch.name, branch.sha);
+            let note = branch_note(branch);
+            if !note.is_empty() {
+                let _ = write!(text, ": {note}");
+            }
+            text.push('\n');
+        }
+        let (heading, counts) = self.summary();
+        let _ = writeln!(text, "\n{heading}: {counts}");
+        text
+    }
+
+    /// Sets [`BranchReport::unmerged_commits`] for each branch `assessments` found risky, for
+    /// `--format sarif` and `--format junit`
+    pub fn fill_unmerged_commits(&mut self, assessments: &[Assessment]) {
+        for branch in &mut self.branches {
+            branch.unmerged_commits = assessments
+                .iter()
+                .find(|assessment| assessment.name == branch.name)
+                .filter(|assessment| assessment.risk() == Risk::Risky)
+                .map(|assessment| assessment.unique_commits);
+        }
+    }
+
+    /// Sets each branch's [`BranchReport::description`] from `descriptions`
+    pub fn fill_descriptions<S: BuildHasher>(&mut self, descriptions: &HashMap<String, String, S>) {
+        for branch in &mut self.branches {
+            branch.description = descriptions.get(&branch.name).cloned();
+        }
+    }
+
+    /// Sets each branch's [`BranchReport::group`] from `groups`
+    pub fn fill_groups(&mut self, groups: &[Group]) {
+        for branch in &mut self.branches {
+            branch.group = groups
+                .iter()
+                .find(|group| group.branches.contains(&branch.name))
+                .map(|group| group.key.clone());
+        }
+    }
+
+    /// Sets each branch's [`BranchReport::planned_action`] from what the dry run found, so a
+    /// machine-readable dry run records the decision for every branch
+    pub fn fill_planned_actions(&mut self) {
+        for branch in &mut self.branches {
+            branch.planned_action = Some(match (branch.action, branch.hold) {
+                (Action::Deleted | Action::WouldDelete, _) => PlannedAction::Delete,
+                (Action::Archived | Action::WouldArchive, _) => PlannedAction::Archive,
+                (Action::Skipped, Some(kind)) => PlannedAction::Skip(kind),
+                (Action::Skipped, None) => PlannedAction::SkipDeclined,
+                (Action::Failed, _) => PlannedAction::Fail,
+            });
+        }
+    }
+
+    /// Gives every skipped branch that has no reason (one declined at the prompt or left out
+    /// in `--edit`) the reason "not confirmed", so each kept branch carries one
+    pub fn fill_kept_reasons(&mut self) {
+        for branch in &mut self.branches {
+            if branch.action == Action::Skipped && branch.reason.is_none() {
+                branch.reason = Some("not confirmed".to_string());
+            }
+        }
+    }
+
+    /// A "Kept:" section listing each gone branch that wasn't deleted (or archived) with the
+    /// reason, or nothing if every branch went
+    #[must_use]
+    pub fn kept_reasons(&self) -> String {
+        let kept: Vec<&BranchReport> = self
+            .branches
+            .iter()
+            .filter(|branch| matches!(branch.action, Action::Skipped | Action::Failed))
+            .collect();
+        if kept.is_empty() {
+            return String::new();
+        }
+
+        let mut section = String::from("\nKept:\n");
+        let width = kept
+            .iter()
+            .map(|branch| branch.name.len())
+            .max()
+            .unwrap_or(0);
+        for branch in kept {
+            let reason = match (&branch.error, &branch.reason) {
+                (Some(error), _) => format!("failed: {error}"),
+                (None, Some(reason)) => reason.clone(),
+                (None, None) => "not confirmed".to_string(),
+            };
+            let _ = writeln!(section, "  {:width$}  {reason}", branch.name);
+        }
+        section
+    }
+
+    /// Copy-pasteable commands that recreate each deleted branch at the commit it pointed to,
+    /// under a "To undo:" header, or nothing if no branch was deleted
+    #[must_use]
+    pub fn undo_commands(&self) -> String {
+        let deleted: Vec<&BranchReport> = self
+            .branches
+            .iter()
+            .filter(|branch| branch.action == Action::Deleted)
+            .collect();
+        if deleted.is_empty() {
+            return String::new();
+        }
+
+        let mut undo = String::from("\nTo undo:\n");
+        // Names are quoted, since characters such as `&` are valid in branch names
+        let names: Vec<_> = deleted
+            .iter()
+            .map(|branch| shell_words::quote(&branch.name))
+            .collect();
+        let width = names.iter().map(|name| name.len()).max().unwrap_or(0);
+        for (branch, name) in deleted.iter().zip(&names) {
+            let _ = writeln!(
+                undo,
+                "  git branch {name:width$} {}  # to restore",
+                branch.sha
+            );
+        }
+        undo
+    }
+
+    /// The branches that failed, with their causes under a "Failed:" header, or nothing if none
+    /// did
+    #[must_use]
+    pub fn failures(&self) -> String {
+        let failed: Vec<&BranchReport> = self
+            .branches
+            .iter()
+            .filter(|branch| branch.action == Action::Failed)
+            .collect();
+        if failed.is_empty() {
+            return String::new();
+        }
+
+        let mut section = format!("\nFailed: {}\n", group_digits(failed.len()));
+        let width = failed
+            .iter()
+            .map(|branch| branch.name.len())
+            .max()
+            .unwrap_or(0);
+        for branch in failed {
+            let cause = branch.cause.unwrap_or(FailureCause::Other).as_str();
+            // git's first line says why; any after it are hints
+            let detail = branch
+                .stderr
+                .as_deref()
+                .or(branch.error.as_deref())
+                .and_then(|text| text.lines().next())
+                .unwrap_or_default();
+            let _ = writeln!(section, "  {:width$}  {cause}: {detail}", branch.name);
+        }
+        section
+    }
+
+    /// How many branches ended up with `action`
+    #[must_use]
+    pub fn count(&self, action: Action) -> usize {
+        self.branches.iter().filter(|b| b.action == action).count()
+    }
+
+    /// The summary line's heading and counts, e.g. `3 gone branch(es) (dry run)` (or
+    /// `2 gone branch(es) and 1 without an upstream (dry run)`) and
+    /// `2 would-delete, 1 skipped`
+    fn summary(&self) -> (String, String) {
+        let counts: Vec<String> = [
+            Action::Deleted,
+            Action::WouldDelete,
+            Action::Archived,
+            Action::WouldArchive,
+            Action::Skipped,
+            Action::Failed,
+        ]
+        .into_iter()
+        .filter_map(|action| {
+            let count = self.count(action);
+            (count > 0).then(|| format!("{} {action}", group_digits(count)))
+        })
+        .collect();
+        // Only `--include-no-upstream` candidates lack an upstream
+        let upstreamless = self
+            .branches
+            .iter()
+            .filter(|b| b.upstream.is_none())
+            .count();
+        let heading = format!(
+            "{} gone branch(es){}{}",
+            group_digits(self.branches.len() - upstreamless),
+            if upstreamless > 0 {
+                format!(" and {} without an upstream", group_digits(upstreamless))
+            } else {
+                String::new()
+            },
+            if self.dry_run { " (dry run)" } else { "" },
+        );
+        (heading, counts.join(", "))
+    }
+}
+
+/// The SARIF rule every unmerged gone branch is reported under, and the `JUnit` failure type
+const SARIF_RULE_ID: &str = "unmerged-gone-branch";
+
+/// Writes a rendered report to `path` atomically (via a temporary file and a rename), so
+/// readers never see a partial report
+///
+/// # Errors
+///
+/// Fails if the file can't be written.
+pub fn write_report(path: &Path, rendered: &str) -> Result<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    fs::write(&temp_path, rendered)
+        .with_context(|| format!("Failed to write report {}", temp_path.display()))?;
+    fs::rename(&temp_path, path)
+        .with_context(|| format!("Failed to write report {}", path.display()))
+}
+
+/// Formats `count` with thousands separators (e.g. `1,234`), for human-readable output; the
+/// machine-readable formats keep raw integers
+#[must_use]
+pub fn group_digits(count: usize) -> String {
+    let digits = count.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Shortens `name` to at most `max` characters, ending it with `…` when cut, for
+/// `--truncate-names`; display only, as deletion and the machine-readable formats need the
+/// full name
+#[must_use]
+pub fn truncate_name(name: &str, max: Option<NonZeroUsize>) -> Cow<'_, str> {
+    match max {
+        Some(max) if name.chars().count() > max.get() => {
+            let kept: String = name.chars().take(max.get() - 1).collect();
+            Cow::Owned(format!("{kept}…"))
+        }
+        _ => Cow::Borrowed(name),
+    }
+}
+
+/// The Markdown "Note" cell (and plain-text note): why the branch was kept or failed, and any remote deletion
+fn branch_note(branch: &BranchReport) -> String {
+    let mut notes: Vec<String> = branch.reason.iter().chain(&branch.error).cloned().collect();
+    if branch.upstream.is_none() {
+        notes.push("no upstream; merged".to_string());
+    }
+    if let (Some(ahead), Some(behind)) = (branch.ahead, branch.behind) {
+        let divergence = Divergence {
+            ahead,
+            behind,
+            gone: false,
+        };
+        notes.push(divergence.summary());
+    }
+    if let Some(archived_as) = &branch.archived_as {
+        notes.push(format!("archived as {archived_as}"));
+    }
+    if let Some(deletion) = &branch.remote_deletion {
+        notes.push(format!("on {}: {}", deletion.remote, deletion.outcome));
+    }
+    notes.join("; ")
+}
+
+/// Escapes text for use in a Markdown table cell, where a bare `|` would start a new column
+fn escape_markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+/// Escapes text for use in XML element content and attribute values. Characters XML 1.0 can't
+/// carry at all, even as character references (most control characters, such as the escape
+/// sequences a colored commit subject may hold), are replaced with U+FFFD.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            '\0'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => escaped.push(char::REPLACEMENT_CHARACTER),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_report() -> Report {
+        Report {
+            dry_run: true,
+            branches: vec![BranchReport {
+                name: "feature/a&b".to_string(),
+                sha: "abc1234".to_string(),
+                upstream: Some("origin/feature/a&b".to_string()),
+                subject: "Fix <script> \"quotes\" & 'apostrophes'".to_string(),
+                ahead: None,
+                behind: None,
+                unmerged_commits: None,
+                action: Action::WouldDelete,
+                error: None,
+                cause: None,
+                stderr: None,
+                description: None,
+                group: None,
+                reason: None,
+                archived_as: None,
+                remote_deletion: None,
+                planned_action: None,
+                hold: None,
+            }],
+            age_histogram: None,
+            remotes_checked: None,
+        }
+    }
+
+    #[test]
+    fn test_verify_removed_flags_survivors() {
+        let mut report = sample_report();
+        report.dry_run = false;
+        report.branches[0].action = Action::Deleted;
+        let mut survivor = report.branches[0].clone();
+        survivor.name = "survivor".to_string();
+        report.branches.push(survivor);
+        let remaining = crate::parse::parse_branch_lines(
+            "  survivor abc1234 [origin/survivor: gone] Subject\n",
+        );
+
+        assert_eq!(report.verify_removed(&remaining), ["survivor"]);
+        assert_eq!(report.branches[0].action, Action::Deleted);
+        assert_eq!(report.branches[1].action, Action::Failed);
+        assert_eq!(report.branches[1].cause, Some(FailureCause::Survived));
+        assert!(report.has_failures());
+    }
+
+    #[test]
+    fn test_verify_archived() {
+        let mut report = sample_report();
+        report.dry_run = false;
+        report.branches = ["kept", "moved", "missing"]
+            .iter()
+            .map(|name| {
+                let line = format!("  {name} abc1234 [origin/{name}: gone] Subject\n");
+                let branch = &crate::parse::parse_branch_lines(&line)[0];
+                BranchReport::archived(branch, format!("archive/{name}"))
+            })
+            .collect();
+        let tips = HashMap::from(
+            [
+                ("archive/kept", "abc1234abc1234abc1234abc1234abc1234abc12"),
+                ("archive/moved", "fff0000fff0000fff0000fff0000fff0000fff00"),
+            ]
+            .map(|(name, tip)| (name.to_string(), tip.to_string())),
+        );
+
+        let (verified, invalid) = report.verify_archived(&tips);
+        assert_eq!(
+            verified,
+            [(
+                "archive/kept".to_string(),
+                "abc1234abc1234abc1234abc1234abc1234abc12".to_string()
+            )]
+        );
+        assert_eq!(
+            invalid,
+            [
+                "moved: archive archive/moved points at fff0000, not abc1234",
+                "missing: archive archive/missing doesn't exist"
+            ]
+        );
+        assert_eq!(report.branches[0].action, Action::Archived);
+        assert_eq!(report.branches[1].action, Action::Failed);
+        assert_eq!(report.branches[2].cause, Some(FailureCause::ArchiveInvalid));
+        assert!(report.has_failures());
+    }
+
+    #[test]
+    fn test_failed_branches_carry_cause_and_stderr() {
+        let branches = crate::parse::parse_branch_lines(
+            r"
+  in-worktree  abc1234 [origin/in-worktree: gone] Checked out
+  locked       def5678 [origin/locked: gone] Lock held
+  bad          ghi9012 [origin/bad: gone] Invalid
+",
+        );
+        let checked_out = anyhow::Error::new(GitFailure {
+            command: "git branch -D in-worktree".to_string(),
+            stderr: "error: cannot delete branch 'in-worktree' used by worktree at '/wt'"
+                .to_string(),
+        });
+        let locked = anyhow::Error::new(GitFailure {
+            command: "git branch -D locked".to_string(),
+            stderr: "error: cannot lock ref 'refs/heads/locked': Unable to create \
+                     '/r/.git/refs/heads/locked.lock': File exists.\n\nAnother git process \
+                     seems to be running"
+                .to_string(),
+        });
+        let report = Report {
+            dry_run: false,
+            branches: vec![
+                BranchReport::failed(&branches[0], &checked_out),
+                BranchReport::failed(&branches[1], &locked.context("Deleting locked")),
+                BranchReport::failed(
+                    &branches[2],
+                    &anyhow::anyhow!("refusing to delete invalid branch name \"bad\""),
+                ),
+                BranchReport::new(&branches[0], Action::Deleted),
+            ],
+            age_histogram: None,
+            remotes_checked: None,
+        };
+
+        let causes: Vec<_> = report.branches.iter().map(|branch| branch.cause).collect();
+        assert_eq!(
+            causes,
+            [
+                Some(FailureCause::CheckedOut),
+                Some(FailureCause::LockHeld),
+                Some(FailureCause::InvalidName),
+                None
+            ]
+        );
+        assert_eq!(report.branches[2].stderr, None);
+        let json: serde_json::Value =
+            serde_json::from_str(&report.render(OutputFormat::Json).unwrap().unwrap()).unwrap();
+        assert_eq!(json["branches"][0]["cause"], "checked-out");
+        assert_eq!(
+            json["branches"][0]["stderr"],
+            "error: cannot delete branch 'in-worktree' used by worktree at '/wt'"
+        );
+        assert!(json["branches"][3].get("cause").is_none());
+
+        assert_eq!(
+            report.failures(),
+            "
+Failed: 3
+  in-worktree  checked-out: error: cannot delete branch 'in-worktree' used by worktree at '/wt'
+  locked       lock-held: error: cannot lock ref 'refs/heads/locked': Unable to create \
+             '/r/.git/refs/heads/locked.lock': File exists.
+  bad          invalid-name: refusing to delete invalid branch name \"bad\"
+"
+        );
+    }
+
+    #[test]
+    fn test_to_text_and_write_report() {
+        let mut report = sample_report();
+        report.branches[0].action = Action::Skipped;
+        report.branches[0].reason = Some("kept".to_string());
+        let text = report.to_text();
+        assert_eq!(
+            text,
+            "skipped feature/a&b (abc1234): kept\n\n1 gone branch(es) (dry run): 1 skipped\n"
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.txt");
+        write_report(&path, &text).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), text);
+        assert!(!dir.path().join("report.txt.tmp").exists());
+    }
+
+    #[test]
+    fn test_to_text_separates_upstreamless_branches() {
+        let mut report = sample_report();
+        let mut scratch = report.branches[0].clone();
+        scratch.name = "scratch".to_string();
+        scratch.upstream = None;
+        report.branches.push(scratch);
+        assert_eq!(
+            report.to_text(),
+            "\
+would-delete feature/a&b (abc1234)
+would-delete scratch (abc1234): no upstream; merged
+
+1 gone branch(es) and 1 without an upstream (dry run): 2 would-delete
+"
+        );
+    }
+
+    #[test]
+    fn test_kept_reasons() {
+        let mut report = sample_report();
+        assert_eq!(report.kept_reasons(), "");
+
+        report.branches[0].action = Action::Skipped;
+        let mut protected = report.branches[0].clone();
+        protected.name = "release".to_string();
+        protected.reason = Some("matches --protect release".to_string());
+        let mut failed = report.branches[0].clone();
+        failed.name = "wt".to_string();
+        failed.action = Action::Failed;
+        failed.error = Some("checked out in a worktree".to_string());
+        report.branches.extend([protected, failed]);
+        assert_eq!(
+            report.kept_reasons(),
+            "\
+\nKept:
+  feature/a&b  not confirmed
+  release      matches --protect release
+  wt           failed: checked out in a worktree
+"
+        );
+
+        report.fill_kept_reasons();
+        assert_eq!(report.branches[0].reason.as_deref(), Some("not confirmed"));
+        assert_eq!(report.branches[2].reason, None);
+    }
+
+    #[test]
+    fn test_fill_planned_actions() {
+        let mut report = sample_report();
+        let mut protected = report.branches[0].clone();
+        protected.action = Action::Skipped;
+        protected.hold = Some(HoldKind::Protected);
+        let mut declined = report.branches[0].clone();
+        declined.action = Action::Skipped;
+        report.branches.extend([protected, declined]);
+
+        report.fill_planned_actions();
+        let planned: Vec<String> = report
+            .branches
+            .iter()
+            .map(|branch| branch.planned_action.unwrap().to_string())
+            .collect();
+        assert_eq!(planned, ["delete", "skip-protected", "skip-declined"]);
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["branches"][1]["planned_action"], "skip-protected");
+        assert!(
+            report
+                .to_xml()
+                .contains("action=\"skipped\" upstream=\"origin/feature/a&amp;b\" planned-action=\"skip-protected\">")
+        );
+    }
+
+    #[test]
+    fn test_undo_commands() {
+        let mut report = sample_report();
+        assert_eq!(report.undo_commands(), "");
+
+        report.branches[0].action = Action::Deleted;
+        let mut other = report.branches[0].clone();
+        other.name = "x".to_string();
+        other.sha = "def5678".to_string();
+        report.branches.push(other);
+        assert_eq!(
+            report.undo_commands(),
+            "\
+\nTo undo:
+  git branch 'feature/a&b' abc1234  # to restore
+  git branch x             def5678  # to restore
+"
+        );
+    }
+
+    #[test]
+    fn test_render_count() {
+        let mut report = sample_report();
+        assert_eq!(report.render(OutputFormat::Count).unwrap().unwrap(), "1\n");
+        report.branches.clear();
+        assert_eq!(report.render(OutputFormat::Count).unwrap().unwrap(), "0\n");
+    }
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(
+            escape_xml(r#"<a href="x">Tom & Jerry's</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&apos;s&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn test_escape_xml_replaces_chars_xml_cannot_carry() {
+        assert_eq!(
+            escape_xml("\u{1b}[31mred\u{1b}[0m\0\u{ffff}"),
+            "\u{fffd}[31mred\u{fffd}[0m\u{fffd}\u{fffd}"
+        );
+        assert_eq!(escape_xml("tab\there\r\n"), "tab\there\r\n");
+    }
+
+    #[test]
+    fn test_render_xml_replaces_control_chars_in_subjects() {
+        let mut report = sample_report();
+        report.branches[0].subject = "Fix \u{1b}[1mbold\u{7} bug".to_string();
+        let xml = report.render(OutputFormat::Xml).unwrap().unwrap();
+        assert!(xml.contains("<subject>Fix \u{fffd}[1mbold\u{fffd} bug</subject>"));
+        assert!(!xml.contains('\u{1b}'));
+    }
+
+    #[test]
+    fn test_group_digits() {
+        assert_eq!(group_digits(0), "0");
+        assert_eq!(group_digits(999), "999");
+        assert_eq!(group_digits(1_234), "1,234");
+        assert_eq!(group_digits(100_000), "100,000");
+        assert_eq!(group_digits(1_234_567), "1,234,567");
+    }
+
+    #[test]
+    fn test_truncate_name() {
+        let max = |max| NonZeroUsize::new(max);
+        assert_eq!(truncate_name("feature-1", None), "feature-1");
+        assert_eq!(truncate_name("feature-1", max(9)), "feature-1");
+        assert_eq!(
+            truncate_name("ci/renovate-lockfile-maintenance", max(12)),
+            "ci/renovate…"
+        );
+        // Counted in characters, so a cut never splits one
+        assert_eq!(truncate_name("fix/ünïcödé", max(6)), "fix/ü…");
+    }
+
+    #[test]
+    fn test_report_includes_divergence_counts() {
+        let line = &crate::parse::parse_branch_lines(
+            "  feature-1  abc1234 [origin/feature-1: ahead 2, behind 1] Work\n",
+        )[0];
+        let report = Report {
+            dry_run: true,
+            branches: vec![
+                BranchReport::new(line, Action::WouldDelete),
+                sample_report().branches.remove(0),
+            ],
+            age_histogram: None,
+            remotes_checked: None,
+        };
+        let json = report.render(OutputFormat::Json).unwrap().unwrap();
+        assert!(json.contains("\"ahead\": 2,\n      \"behind\": 1,"));
+        assert_eq!(json.matches("\"ahead\"").count(), 1);
+        let xml = report.render(OutputFormat::Xml).unwrap().unwrap();
+        assert!(xml.contains(" ahead=\"2\" behind=\"1\""));
+        assert_eq!(branch_note(&report.branches[0]), "ahead 2, behind 1");
+        assert_eq!(branch_note(&report.branches[1]), "");
+    }
+
+    #[test]
+    fn test_render_xml_escapes_names_and_subjects() {
+        let xml = sample_report().render(OutputFormat::Xml).unwrap().unwrap();
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+        assert!(xml.contains("<git-clean-gone dry-run=\"true\" count=\"1\">"));
+        assert!(xml.contains("name=\"feature/a&amp;b\""));
+        assert!(xml.contains("upstream=\"origin/feature/a&amp;b\""));
+        assert!(xml.contains("action=\"would-delete\""));
+        assert!(xml.contains(
+            "<subject>Fix &lt;script&gt; &quot;quotes&quot; &amp; &apos;apostrophes&apos;</subject>"
+        ));
+        assert!(xml.trim_end().ends_with("</git-clean-gone>"));
+    }
+
+    #[test]
+    fn test_render_json_matches_metadata() {
+        let json = sample_report().render(OutputFormat::Json).unwrap().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["dry_run"], true);
+        assert_eq!(value["branches"][0]["name"], "feature/a&b");
+        assert_eq!(value["branches"][0]["action"], "would-delete");
+    }
+
+    #[test]
+    fn test_render_human_is_none() {
+        assert!(
+            sample_report()
+                .render(OutputFormat::Human)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_render_markdown_table() {
+        let mut report = sample_report();
+        report.branches[0].name = "fix|pipe".to_string();
+        report.branches.push(BranchReport {
+            reason: Some("kept".to_string()),
+            ..BranchReport::new(
+                &crate::parse::parse_branch_lines("  other  def5678 [origin/other: gone] Other\n")
+                    [0],
+                Action::Skipped,
+            )
+        });
+
+        let md = report.render(OutputFormat::Markdown).unwrap().unwrap();
+        let lines: Vec<&str> = md.lines().collect();
+        assert_eq!(
+            lines[0],
+            "| Branch | SHA | Upstream | Subject | Action | Note |"
+        );
+        assert!(lines[2].starts_with("| fix\\|pipe | abc1234 | origin/feature/a&b |"));
+        assert!(lines[2].ends_with("| would-delete |  |"));
+        assert_eq!(
+            lines[3],
+            "| other | def5678 | origin/other | Other | skipped | kept |"
+        );
+        assert_eq!(
+            lines.last().unwrap(),
+            &"**2 gone branch(es) (dry run):** 1 would-delete, 1 skipped"
+        );
+    }
+
+    #[test]
+    fn test_render_markdown_empty() {
+        let report = Report {
+            dry_run: false,
+            branches: Vec::new(),
+            age_histogram: None,
+            remotes_checked: None,
+        };
+        assert_eq!(
+            report.render(OutputFormat::Markdown).unwrap().unwrap(),
+            "No gone branches found.\n"
+        );
+    }
+
+    #[test]
+    fn test_render_remote_deletion() {
+        let mut report = sample_report();
+        report.branches[0].action = Action::Deleted;
+        report.branches[0].remote_deletion = Some(RemoteDeletion {
+            remote: "fork".to_string(),
+            outcome: RemoteOutcome::Failed,
+            error: Some("permission <denied>".to_string()),
+        });
+
+        let xml = report.render(OutputFormat::Xml).unwrap().unwrap();
+        assert!(xml.contains(
+            "<remote-deletion remote=\"fork\" outcome=\"failed\">permission &lt;denied&gt;</remote-deletion>"
+        ));
+
+        let json = report.render(OutputFormat::Json).unwrap().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let deletion = &value["branches"][0]["remote_deletion"];
+        assert_eq!(deletion["remote"], "fork");
+        assert_eq!(deletion["outcome"], "failed");
+
+        let md = report.render(OutputFormat::Markdown).unwrap().unwrap();
+        assert!(md.contains("| deleted | on fork: failed |"));
+    }
+
+    #[test]
+    fn test_compact_json_is_one_line_with_all_fields() {
+        let report = sample_report();
+        let compact = report.to_compact_json().unwrap();
+        assert_eq!(compact.lines().count(), 1);
+
+        let pretty = report.render(OutputFormat::Json).unwrap().unwrap();
+        let compact: serde_json::Value = serde_json::from_str(&compact).unwrap();
+        let pretty: serde_json::Value = serde_json::from_str(&pretty).unwrap();
+        assert_eq!(compact, pretty);
+    }
+
+    #[test]
+    fn test_render_sarif_reports_only_unmerged_branches() {
+        let mut report = sample_report();
+        let mut merged = report.branches[0].clone();
+        merged.name = "merged".to_string();
+        report.branches.push(merged);
+        let assessment = |name: &str, unique_commits, merged_upstream| Assessment {
+            name: name.to_string(),
+            unique_commits,
+            merged_into: None,
+            merged_upstream,
+        };
+        report.fill_unmerged_commits(&[
+            assessment("feature/a&b", 2, false),
+            assessment("merged", 3, true),
+        ]);
+        assert_eq!(report.branches[0].unmerged_commits, Some(2));
+        assert_eq!(report.branches[1].unmerged_commits, None);
+
+        let sarif = report.render(OutputFormat::Sarif).unwrap().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&sarif).unwrap();
+        assert_eq!(value["version"], "2.1.0");
+        let run = &value["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], SARIF_RULE_ID);
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["level"], "warning");
+        assert_eq!(
+            results[0]["message"]["text"],
+            "Branch feature/a&b is gone from origin/feature/a&b but has 2 unmerged commit(s) \
+             (would-delete)"
+        );
+        assert_eq!(
+            results[0]["locations"][0]["logicalLocations"][0]["fullyQualifiedName"],
+            "refs/heads/feature/a&b"
+        );
+    }
+
+    #[test]
+    fn test_render_junit_fails_unmerged_branches() {
+        let mut report = sample_report();
+        let mut merged = report.branches[0].clone();
+        merged.name = "merged".to_string();
+        report.branches.push(merged);
+        report.branches[0].unmerged_commits = Some(2);
+
+        assert_eq!(
+            report.render(OutputFormat::Junit).unwrap().unwrap(),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="git-clean-gone" tests="2" failures="1" errors="0" skipped="0">
+  <testsuite name="gone-branches" tests="2" failures="1" errors="0" skipped="0">
+    <testcase classname="gone-branches" name="feature/a&amp;b">
+      <failure type="unmerged-gone-branch" message="Branch feature/a&amp;b is gone from origin/feature/a&amp;b but has 2 unmerged commit(s) (would-delete)">feature/a&amp;b@abc1234</failure>
+    </testcase>
+    <testcase classname="gone-branches" name="merged"/>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+
+    #[test]
+    fn test_render_junit_replaces_control_chars() {
+        let mut report = sample_report();
+        report.branches[0].name = "bad\u{1b}name".to_string();
+        report.branches[0].upstream = Some("origin/bad\u{8}name".to_string());
+        report.branches[0].unmerged_commits = Some(1);
+
+        let junit = report.render(OutputFormat::Junit).unwrap().unwrap();
+        assert!(junit.contains("<testcase classname=\"gone-branches\" name=\"bad\u{fffd}name\">"));
+        assert!(
+            junit.contains("message=\"Branch bad\u{fffd}name is gone from origin/bad\u{fffd}name")
+        );
+        assert!(!junit.chars().any(|c| c < ' ' && c != '\n'));
+    }
+
+    #[test]
+    fn test_render_archived() {
+        let branch =
+            &crate::parse::parse_branch_lines("  wip  abc1234 [origin/wip: gone] Work\n")[0];
+        let report = Report {
+            dry_run: false,
+            branches: vec![BranchReport::archived(branch, "archive/wip-2".to_string())],
+            age_histogram: None,
+            remotes_checked: None,
+        };
+
+        let xml = report.render(OutputFormat::Xml).unwrap().unwrap();
+        assert!(
+            xml.contains(
+                "action=\"archived\" upstream=\"origin/wip\" archived-as=\"archive/wip-2\""
+            )
+        );
+
+        let md = report.render(OutputFormat::Markdown).unwrap().unwrap();
+        assert!(md.contains("| archived | archived as archive/wip-2 |"));
+        assert!(md.ends_with("**1 gone branch(es):** 1 archived\n"));
+    }
+}
@@ -0,0 +1,1178 @@
+//! Risk assessment of gone branches, based on the commits that deleting them would lose.
+
+use crate::git::{git_command, output_with_stdin};
+use crate::parse::BranchLine;
+use crate::report::group_digits;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Write};
+use std::hash::BuildHasher;
+use std::process::Stdio;
+
+/// How risky deleting a branch is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Risk {
+    /// Merged into the base, so no commits are lost
+    Safe,
+    /// Has commits the base doesn't contain
+    Risky,
+}
+
+/// The unique-commit count of one gone branch relative to the base
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assessment {
+    pub name: String,
+    /// Commits on the branch that aren't reachable from the base (the fewest across bases,
+    /// with `--merged-base`)
+    pub unique_commits: usize,
+    /// The first of several bases the branch is merged into, with `--merged-base`
+    pub merged_into: Option<String>,
+    /// Listed as merged upstream (e.g. a squash-merged PR) in `--merged-prs-file`, which
+    /// counts as merged whatever the commits say
+    pub merged_upstream: bool,
+}
+
+impl Assessment {
+    #[must_use]
+    pub fn risk(&self) -> Risk {
+        if self.unique_commits == 0 || self.merged_upstream {
+            Risk::Safe
+        } else {
+            Risk::Risky
+        }
+    }
+
+    /// The commits deleting the branch would lose: none when it's safe, otherwise its unique
+    /// commits
+    #[must_use]
+    pub fn commits_lost(&self) -> usize {
+        match self.risk() {
+            Risk::Safe => 0,
+            Risk::Risky => self.unique_commits,
+        }
+    }
+}
+
+/// How the gone branches are ordered, for `--sort`
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    /// By name, as git lists them, grouped into safe and risky
+    #[default]
+    Name,
+    /// By the commits deleting them would lose, most first, so the riskiest come first
+    UniqueCommits,
+}
+
+/// What gone branches are compared against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Base {
+    /// A single ref, such as `origin/HEAD`
+    Ref(String),
+    /// Every remote-tracking branch, when no single ref resolves (e.g. a bare repository
+    /// whose `HEAD` is unborn)
+    AllRemotes,
+}
+
+impl fmt::Display for Base {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ref(name) => f.write_str(name),
+            Self::AllRemotes => f.write_str("all remote-tracking branches"),
+        }
+    }
+}
+
+/// Whether `name` resolves to a commit
+fn resolves(name: &str) -> bool {
+    git_command()
+        .args(["rev-parse", "--verify", "-q", name])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Picks what branches are compared against: the remote's default branch (`<remote>/HEAD`)
+/// when it resolves, otherwise the current `HEAD`, otherwise every remote-tracking branch.
+#[must_use]
+pub fn resolve_base(remote: &str) -> Base {
+    let remote_head = format!("{remote}/HEAD");
+    if resolves(&remote_head) {
+        Base::Ref(remote_head)
+    } else if resolves("HEAD") {
+        Base::Ref("HEAD".to_string())
+    } else {
+        Base::AllRemotes
+    }
+}
+
+/// Which step of [`fallback_base`] picked the base
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaseSource {
+    CurrentBranch,
+    RemoteHead,
+    ConventionalName,
+    OnlyRemainingBranch,
+    AllRemotes,
+}
+
+impl fmt::Display for BaseSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::CurrentBranch => "the current branch",
+            Self::RemoteHead => "the remote's default branch",
+            Self::ConventionalName => "named main or master",
+            Self::OnlyRemainingBranch => "the only local branch that isn't gone",
+            Self::AllRemotes => "no single base resolves",
+        })
+    }
+}
+
+/// Picks the base for `--remote-head-fallback`, trying in order: the `current` branch, the
+/// remote's default branch (`<remote>/HEAD`), `main` or `master` (local, then the remote's),
+/// and the only branch among `remaining` (the local branches that aren't gone), and finally
+/// every remote-tracking branch. `resolves` says whether a ref resolves to a commit.
+#[must_use]
+pub fn fallback_base(
+    remote: &str,
+    current: Option<&str>,
+    remaining: &[String],
+    resolves: impl Fn(&str) -> bool,
+) -> (Base, BaseSource) {
+    if let Some(current) = current.filter(|current| resolves(&format!("refs/heads/{current}"))) {
+        return (Base::Ref(current.to_string()), BaseSource::CurrentBranch);
+    }
+    let remote_head = format!("{remote}/HEAD");
+    if resolves(&remote_head) {
+        return (Base::Ref(remote_head), BaseSource::RemoteHead);
+    }
+    let conventional = ["main", "master"]
+        .into_iter()
+        .map(|name| (format!("refs/heads/{name}"), name.to_string()))
+        .chain(["main", "master"].into_iter().map(|name| {
+            (
+                format!("refs/remotes/{remote}/{name}"),
+                format!("{remote}/{name}"),
+            )
+        }));
+    for (full, name) in conventional {
+        if resolves(&full) {
+            return (Base::Ref(name), BaseSource::ConventionalName);
+        }
+    }
+    if let [only] = remaining {
+        return (Base::Ref(only.clone()), BaseSource::OnlyRemainingBranch);
+    }
+    (Base::AllRemotes, BaseSource::AllRemotes)
+}
+
+/// Runs [`fallback_base`] against the repository
+///
+/// # Errors
+///
+/// Fails if the local branches can't be listed.
+pub fn resolve_fallback_base(remote: &str) -> Result<(Base, BaseSource)> {
+    // Detached or unborn, `HEAD` picks no current branch
+    let current = git_command()
+        .args(["symbolic-ref", "-q", "--short", "HEAD"])
+        .output()
+        .context("Failed to execute git symbolic-ref")?;
+    let current = String::from_utf8_lossy(&current.stdout).trim().to_string();
+
+    let output = git_command()
+        .args([
+            "for-each-ref",
+            "--format=%(refname:short) %(upstream:track)",
+            "refs/heads",
+        ])
+        .output()
+        .context("Failed to execute git for-each-ref")?;
+    if !output.status.success() {
+        anyhow::bail!("git for-each-ref failed");
+    }
+    let remaining: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (name, track) = line.split_once(' ').unwrap_or((line, ""));
+            (track != "[gone]").then(|| name.to_string())
+        })
+        .collect();
+
+    Ok(fallback_base(
+        remote,
+        Some(current.as_str()).filter(|current| !current.is_empty()),
+        &remaining,
+        resolves,
+    ))
+}
+
+/// Counts the commits on the local branch `branch` that aren't reachable from `base`, even if
+/// a tag shares its name
+///
+/// # Errors
+///
+/// Fails if `git rev-list` can't be run or either ref doesn't resolve.
+pub fn unique_commit_count(branch: &str, base: &Base) -> Result<usize> {
+    let mut cmd = git_command();
+    cmd.args([
+        "rev-list",
+        "--count",
+        &format!("refs/heads/{branch}"),
+        "--not",
+    ]);
+    match base {
+        Base::Ref(name) => cmd.arg(name),
+        Base::AllRemotes => cmd.arg("--remotes"),
+    };
+    let output = cmd
+        .output()
+        .context("Failed to execute git rev-list --count")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git rev-list --count failed for {branch} against {base}");
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .context("Failed to parse git rev-list --count output")
+}
+
+/// Counts the commits on `branch` that no other ref reaches (no other branch, tag,
+/// remote-tracking branch, or `HEAD`), i.e. the commits deleting it would lose
+///
+/// # Errors
+///
+/// Fails if `git rev-list` can't be run or `branch` doesn't exist.
+pub fn graph_unique_commit_count(branch: &str) -> Result<usize> {
+    let refname = format!("refs/heads/{branch}");
+    let output = git_command()
+        .args(["rev-list", "--count", &refname, "--not"])
+        // `--exclude` only applies to the `--all` after it
+        .arg(format!("--exclude={refname}"))
+        .arg("--all")
+        .output()
+        .context("Failed to execute git rev-list --count")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git rev-list --count failed for {branch} against every other ref");
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .context("Failed to parse git rev-list --count output")
+}
+
+/// The committer date (as a Unix timestamp) of the commit where `branch` forked from `base`
+/// (their merge base), or `None` if they share no history or there's no single base
+///
+/// # Errors
+///
+/// Fails if `git merge-base` or `git show` can't be run.
+pub fn fork_point_time(branch: &str, base: &Base) -> Result<Option<i64>> {
+    let Base::Ref(base) = base else {
+        return Ok(None);
+    };
+    let output = git_command()
+        .args(["merge-base", &format!("refs/heads/{branch}"), base])
+        .output()
+        .context("Failed to execute git merge-base")?;
+    // Exits 1 without output when there's no common ancestor
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let fork_point = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let output = git_command()
+        .args(["show", "-s", "--format=%ct", &fork_point])
+        .output()
+        .context("Failed to execute git show")?;
+    if !output.status.success() {
+        anyhow::bail!("git show failed for {fork_point}");
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().parse().ok())
+}
+
+/// Assesses each branch against `base`
+///
+/// # Errors
+///
+/// Fails if any branch's unique commits can't be counted.
+pub fn assess_branches(branches: &[BranchLine], base: &Base) -> Result<Vec<Assessment>> {
+    branches
+        .iter()
+        .map(|branch| {
+            Ok(Assessment {
+                name: branch.name.clone(),
+                unique_commits: unique_commit_count(&branch.name, base)?,
+                merged_into: None,
+                merged_upstream: false,
+            })
+        })
+        .collect()
+}
+
+/// Assesses each branch against several bases: it's safe if merged into at least one of them,
+/// and otherwise counts the commits that the closest base lacks
+///
+/// # Errors
+///
+/// Fails if any branch's unique commits can't be counted, e.g. because a base doesn't resolve.
+pub fn assess_against_bases(branches: &[BranchLine], bases: &[Base]) -> Result<Vec<Assessment>> {
+    branches
+        .iter()
+        .map(|branch| {
+            let mut fewest = usize::MAX;
+            for base in bases {
+                let unique_commits = unique_commit_count(&branch.name, base)?;
+                if unique_commits == 0 {
+                    return Ok(Assessment {
+                        name: branch.name.clone(),
+                        unique_commits,
+                        merged_into: Some(base.to_string()),
+                        merged_upstream: false,
+                    });
+                }
+                fewest = fewest.min(unique_commits);
+            }
+            Ok(Assessment {
+                name: branch.name.clone(),
+                unique_commits: fewest,
+                merged_into: None,
+                merged_upstream: false,
+            })
+        })
+        .collect()
+}
+
+/// The commit graph of a set of branches and bases, loaded with a single `git rev-list`, so
+/// unique commits can be counted in memory instead of with one `git rev-list` per branch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitGraph {
+    /// Each commit's parents
+    parents: HashMap<String, Vec<String>>,
+    /// The tip commit of each branch
+    tips: HashMap<String, String>,
+    /// For each base, every commit reachable from it
+    base_sets: Vec<HashSet<String>>,
+}
+
+impl CommitGraph {
+    /// Loads the history of `branches` and `bases`: `git rev-parse` resolves the tips and
+    /// `git rev-list --parents` reads the graph, plus `git for-each-ref` for
+    /// [`Base::AllRemotes`]
+    ///
+    /// # Errors
+    ///
+    /// Fails if a branch or base doesn't resolve or git can't be run.
+    pub fn load(branches: &[BranchLine], bases: &[Base]) -> Result<Self> {
+        let refnames: Vec<String> = branches
+            .iter()
+            .map(|branch| format!("refs/heads/{}", branch.name))
+            .collect();
+        let base_refs: Vec<&str> = bases
+            .iter()
+            .filter_map(|base| match base {
+                Base::Ref(name) => Some(name.as_str()),
+                Base::AllRemotes => None,
+            })
+            .collect();
+        let mut resolved = rev_parse(
+            refnames
+                .iter()
+                .map(String::as_str)
+                .chain(base_refs.iter().copied()),
+        )?
+        .into_iter();
+        let tips: HashMap<String, String> = branches
+            .iter()
+            .map(|branch| branch.name.clone())
+            .zip(resolved.by_ref())
+            .collect();
+
+        let mut base_heads = Vec::new();
+        for base in bases {
+            base_heads.push(match base {
+                Base::Ref(_) => resolved.next().into_iter().collect(),
+                Base::AllRemotes => remote_tracking_tips()?,
+            });
+        }
+
+        let mut input = String::new();
+        for revision in tips.values().chain(base_heads.iter().flatten()) {
+            input.push_str(revision);
+            input.push('\n');
+        }
+        let output = output_with_stdin(
+            git_command()
+                .args(["rev-list", "--parents", "--stdin"])
+                .stderr(Stdio::piped()),
+            input,
+        )
+        .context("Failed to execute git rev-list --parents")?;
+        if !output.status.success() {
+            anyhow::bail!("git rev-list --parents failed");
+        }
+
+        Ok(Self::from_rev_list(
+            &String::from_utf8_lossy(&output.stdout),
+            tips,
+            &base_heads,
+        ))
+    }
+
+    /// Builds the graph from `git rev-list --parents` output, given each branch's tip and the
+    /// head commits of each base
+    #[must_use]
+    pub fn from_rev_list(
+        rev_list_output: &str,
+        tips: HashMap<String, String>,
+        base_heads: &[Vec<String>],
+    ) -> Self {
+        let parents = rev_list_output
+            .lines()
+            .filter_map(|line| {
+                let mut shas = line.split_whitespace().map(ToString::to_string);
+                Some((shas.next()?, shas.collect()))
+            })
+            .collect();
+        let mut graph = Self {
+            parents,
+            tips,
+            base_sets: Vec::new(),
+        };
+        graph.base_sets = base_heads
+            .iter()
+            .map(|heads| graph.ancestors(heads, &HashSet::new()))
+            .collect();
+        graph
+    }
+
+    /// Every commit reachable from `heads`, not walking past commits in `stop`
+    fn ancestors(&self, heads: &[String], stop: &HashSet<String>) -> HashSet<String> {
+        let mut seen = HashSet::new();
+        let mut pending: Vec<&String> = heads.iter().collect();
+        while let Some(commit) = pending.pop() {
+            if stop.contains(commit) || !seen.insert(commit.clone()) {
+                continue;
+            }
+            pending.extend(self.parents.get(commit).into_iter().flatten());
+        }
+        seen
+    }
+
+    /// Counts the commits on `branch` that aren't reachable from the `base`-th base, like
+    /// [`unique_commit_count`]
+    #[must_use]
+    pub fn unique_commits(&self, branch: &str, base: usize) -> usize {
+        let Some(tip) = self.tips.get(branch) else {
+            return 0;
+        };
+        self.ancestors(std::slice::from_ref(tip), &self.base_sets[base])
+            .len()
+    }
+}
+
+/// Resolves each revision to its commit SHA with one `git cat-file --batch-check`, fed the
+/// revisions on stdin however many there are
+fn rev_parse<'a>(revisions: impl Iterator<Item = &'a str>) -> Result<Vec<String>> {
+    let revisions: Vec<&str> = revisions.collect();
+    let mut input = String::new();
+    for revision in &revisions {
+        input.push_str(revision);
+        input.push_str("^{commit}\n");
+    }
+    let output = output_with_stdin(
+        git_command()
+            .args(["cat-file", "--batch-check=%(objectname)", "--buffer"])
+            .stderr(Stdio::piped()),
+        input,
+    )
+    .context("Failed to execute git cat-file --batch-check")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git cat-file --batch-check failed: {}", stderr.trim());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let resolved: Vec<String> = stdout.lines().map(ToString::to_string).collect();
+    // Revisions that don't resolve are echoed back with " missing" (or " ambiguous")
+    for (revision, line) in revisions.iter().zip(&resolved) {
+        if line.contains(' ') {
+            anyhow::bail!("{revision} doesn't resolve to a commit");
+        }
+    }
+    Ok(resolved)
+}
+
+/// The commits every remote-tracking branch points to
+fn remote_tracking_tips() -> Result<Vec<String>> {
+    let output = git_command()
+        .args(["for-each-ref", "--format=%(objectname)", "refs/remotes"])
+        .output()
+        .context("Failed to execute git for-each-ref")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git for-each-ref failed");
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(ToString::to_string)
+        .collect())
+}
+
+/// Like [`assess_branches`], but counting in a [`CommitGraph`] loaded once
+///
+/// # Errors
+///
+/// Fails if the commit graph can't be loaded.
+pub fn assess_branches_in_graph(branches: &[BranchLine], base: &Base) -> Result<Vec<Assessment>> {
+    let graph = CommitGraph::load(branches, std::slice::from_ref(base))?;
+    Ok(branches
+        .iter()
+        .map(|branch| Assessment {
+            name: branch.name.clone(),
+            unique_commits: graph.unique_commits(&branch.name, 0),
+            merged_into: None,
+            merged_upstream: false,
+        })
+        .collect())
+}
+
+/// Like [`assess_against_bases`], but counting in a [`CommitGraph`] loaded once
+///
+/// # Errors
+///
+/// Fails if the commit graph can't be loaded.
+pub fn assess_against_bases_in_graph(
+    branches: &[BranchLine],
+    bases: &[Base],
+) -> Result<Vec<Assessment>> {
+    let graph = CommitGraph::load(branches, bases)?;
+    Ok(branches
+        .iter()
+        .map(|branch| {
+            let counts =
+                (0..bases.len()).map(|base| (base, graph.unique_commits(&branch.name, base)));
+            let merged = counts
+                .clone()
+                .find(|&(_, unique_commits)| unique_commits == 0);
+            Assessment {
+                name: branch.name.clone(),
+                unique_commits: counts
+                    .map(|(_, unique_commits)| unique_commits)
+                    .min()
+                    .unwrap_or(usize::MAX),
+                merged_into: merged.map(|(base, _)| bases[base].to_string()),
+                merged_upstream: false,
+            }
+        })
+        .collect())
+}
+
+/// Formats the assessed branches grouped into safe and risky, with counts per group
+#[must_use]
+pub fn format_risk_summary(assessments: &[Assessment], base: &str) -> String {
+    let (safe, risky): (Vec<&Assessment>, Vec<&Assessment>) = assessments
+        .iter()
+        .partition(|assessment| assessment.risk() == Risk::Safe);
+
+    let mut summary = String::new();
+    let _ = writeln!(
+        summary,
+        "Found {} gone branch(es), compared against {base}:",
+        group_digits(assessments.len())
+    );
+
+    if !safe.is_empty() {
+        let _ = writeln!(
+            summary,
+            "\nSafe (merged, no unique commits): {}",
+            group_digits(safe.len())
+        );
+        for assessment in safe {
+            let _ = match &assessment.merged_into {
+                Some(base) => writeln!(summary, "  - {} (merged into {base})", assessment.name),
+                None if assessment.unique_commits > 0 => writeln!(
+                    summary,
+                    "  - {} (merged upstream per --merged-prs-file)",
+                    assessment.name
+                ),
+                None => writeln!(summary, "  - {}", assessment.name),
+            };
+        }
+    }
+
+    if !risky.is_empty() {
+        let _ = writeln!(
+            summary,
+            "\nRisky (unmerged or ahead): {}",
+            group_digits(risky.len())
+        );
+        for assessment in risky {
+            let _ = writeln!(
+                summary,
+                "  - {} ({} unique commit(s))",
+                assessment.name,
+                group_digits(assessment.unique_commits)
+            );
+        }
+    }
+
+    summary
+}
+
+/// Orders `branches` by the commits deleting them would lose per `assessments`, most first;
+/// ties keep their order, and branches without an assessment go last
+#[must_use]
+pub fn sort_by_unique_commits(
+    mut branches: Vec<BranchLine>,
+    assessments: &[Assessment],
+) -> Vec<BranchLine> {
+    let lost: HashMap<&str, usize> = assessments
+        .iter()
+        .map(|assessment| (assessment.name.as_str(), assessment.commits_lost()))
+        .collect();
+    branches.sort_by_key(|branch| std::cmp::Reverse(lost.get(branch.name.as_str()).copied()));
+    branches
+}
+
+/// Formats the assessed branches as one list ordered by the commits deleting them would lose,
+/// most first, with the count next to each, for `--sort unique-commits`
+#[must_use]
+pub fn format_risk_ranking(assessments: &[Assessment], base: &str) -> String {
+    let mut ranked: Vec<&Assessment> = assessments.iter().collect();
+    ranked.sort_by_key(|assessment| std::cmp::Reverse(assessment.commits_lost()));
+
+    let mut summary = String::new();
+    let _ = writeln!(
+        summary,
+        "Found {} gone branch(es), compared against {base}, most unique commits first:\n",
+        group_digits(assessments.len())
+    );
+    for assessment in ranked {
+        let lost = assessment.commits_lost();
+        let _ = writeln!(
+            summary,
+            "  - {} ({} unique commit(s){})",
+            assessment.name,
+            group_digits(lost),
+            if lost == 0 && assessment.unique_commits > 0 {
+                ", merged upstream per --merged-prs-file"
+            } else {
+                ""
+            }
+        );
+    }
+    summary
+}
+
+/// Marks the assessments of branches listed in `merged_upstream` (see [`Assessment::merged_upstream`])
+pub fn apply_merged_upstream<S: BuildHasher>(
+    assessments: &mut [Assessment],
+    merged_upstream: &HashSet<String, S>,
+) {
+    for assessment in assessments {
+        assessment.merged_upstream = merged_upstream.contains(&assessment.name);
+    }
+}
+
+/// Groups branches by `key`, keeping only the groups with more than one member, in order of
+/// each group's first branch; branches without a key are left out
+fn shared_groups<'a>(
+    branches: &'a [BranchLine],
+    key: impl Fn(&'a BranchLine) -> Option<&'a str>,
+) -> Vec<(&'a str, Vec<&'a str>)> {
+    let mut groups: Vec<(&str, Vec<&str>)> = Vec::new();
+    for branch in branches {
+        let Some(key) = key(branch) else { continue };
+        match groups.iter_mut().find(|(shared, _)| *shared == key) {
+            Some((_, names)) => names.push(&branch.name),
+            None => groups.push((key, vec![&branch.name])),
+        }
+    }
+    groups.retain(|(_, names)| names.len() > 1);
+    groups
+}
+
+/// Groups branches pointing at the same tip, keeping only the groups with more than one
+/// member, in order of each group's first branch
+#[must_use]
+pub fn identical_tip_groups(branches: &[BranchLine]) -> Vec<(&str, Vec<&str>)> {
+    shared_groups(branches, |branch| Some(&branch.sha))
+}
+
+/// Groups branches tracking the same upstream, keeping only the groups with more than one
+/// member, in order of each group's first branch
+#[must_use]
+pub fn shared_upstream_groups(branches: &[BranchLine]) -> Vec<(&str, Vec<&str>)> {
+    shared_groups(branches, |branch| branch.upstream.as_deref())
+}
+
+/// Warns about each group of gone branches tracking the same upstream, as one of them may be
+/// a mistaken duplicate of the other; nothing if every upstream is distinct
+#[must_use]
+pub fn format_shared_upstreams(branches: &[BranchLine]) -> String {
+    let mut warnings = String::new();
+    for (upstream, names) in shared_upstream_groups(branches) {
+        let _ = writeln!(
+            warnings,
+            "warning: {} gone branches track the same upstream {upstream}: {}; one may be a \
+             mistaken duplicate, so check that deleting all of them is intended",
+            names.len(),
+            names.join(", ")
+        );
+    }
+    warnings
+}
+
+/// Describes the groups of gone branches sharing a tip, or nothing if every tip is distinct
+#[must_use]
+pub fn format_identical_tips(branches: &[BranchLine]) -> String {
+    let groups = identical_tip_groups(branches);
+    if groups.is_empty() {
+        return String::new();
+    }
+
+    let mut summary = String::new();
+    let _ = writeln!(
+        summary,
+        "\nGone branches with identical tips: {} group(s)",
+        groups.len()
+    );
+    for (sha, names) in groups {
+        let _ = writeln!(summary, "  - {sha}: {}", names.join(", "));
+    }
+    summary
+}
+
+/// The default glyph `--compact-summary-line` starts with (U+2387, the alternative key symbol
+/// prompts commonly use for branches)
+pub const SUMMARY_GLYPH: &str = "\u{2387}";
+
+/// Formats `assessments` as one line for a shell prompt, e.g. `⎇ 3 gone (2 safe, 1 risky)`,
+/// or nothing if there are none. With `color`, the glyph is green when every branch is safe,
+/// and yellow otherwise.
+#[must_use]
+pub fn format_compact_summary(assessments: &[Assessment], glyph: &str, color: bool) -> String {
+    if assessments.is_empty() {
+        return String::new();
+    }
+
+    let risky = assessments
+        .iter()
+        .filter(|assessment| assessment.risk() == Risk::Risky)
+        .count();
+    let glyph = match (color, risky) {
+        (false, _) => glyph.to_string(),
+        (true, 0) => format!("\x1b[32m{glyph}\x1b[0m"),
+        (true, _) => format!("\x1b[33m{glyph}\x1b[0m"),
+    };
+    format!(
+        "{glyph} {} gone ({} safe, {} risky)\n",
+        group_digits(assessments.len()),
+        group_digits(assessments.len() - risky),
+        group_digits(risky)
+    )
+}
+
+/// Lists the merged branches without an upstream picked up by `--include-no-upstream`, or
+/// nothing if there are none
+#[must_use]
+pub fn format_upstreamless_summary(branches: &[BranchLine]) -> String {
+    if branches.is_empty() {
+        return String::new();
+    }
+
+    let mut summary = String::new();
+    let _ = writeln!(
+        summary,
+        "\nMerged, never had an upstream (--include-no-upstream): {}",
+        branches.len()
+    );
+    for branch in branches {
+        let _ = writeln!(summary, "  - {}", branch.name);
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fixture::{self, git};
+
+    fn assessment(name: &str, unique_commits: usize) -> Assessment {
+        Assessment {
+            name: name.to_string(),
+            unique_commits,
+            merged_into: None,
+            merged_upstream: false,
+        }
+    }
+
+    #[test]
+    fn test_risk_from_unique_commits() {
+        assert_eq!(assessment("a", 0).risk(), Risk::Safe);
+        assert_eq!(assessment("b", 3).risk(), Risk::Risky);
+    }
+
+    #[test]
+    fn test_identical_tip_groups() {
+        let branches = crate::parse::parse_branch_lines(
+            r"
+  feature-1    abc1234 [origin/feature-1: gone] Same
+  feature-2    def5678 [origin/feature-2: gone] Different
+  feature-1b   abc1234 [origin/feature-1b: gone] Same
+  feature-1c   abc1234 [origin/feature-1c: gone] Same
+",
+        );
+        assert_eq!(
+            identical_tip_groups(&branches),
+            [("abc1234", vec!["feature-1", "feature-1b", "feature-1c"])]
+        );
+        assert_eq!(
+            format_identical_tips(&branches),
+            "\nGone branches with identical tips: 1 group(s)\n  - abc1234: feature-1, feature-1b, feature-1c\n"
+        );
+        assert_eq!(format_identical_tips(&branches[..2]), "");
+    }
+
+    #[test]
+    fn test_fallback_base() {
+        let fallback = |current: Option<&str>, remaining: &[&str], refs: &[&str]| {
+            let remaining: Vec<String> = remaining.iter().map(ToString::to_string).collect();
+            fallback_base("origin", current, &remaining, |name| refs.contains(&name))
+        };
+        let base = |name: &str| Base::Ref(name.to_string());
+
+        assert_eq!(
+            fallback(Some("dev"), &[], &["refs/heads/dev", "origin/HEAD"]),
+            (base("dev"), BaseSource::CurrentBranch)
+        );
+        // An unborn current branch doesn't resolve
+        assert_eq!(
+            fallback(Some("dev"), &[], &["origin/HEAD", "refs/heads/main"]),
+            (base("origin/HEAD"), BaseSource::RemoteHead)
+        );
+        assert_eq!(
+            fallback(
+                None,
+                &[],
+                &["refs/heads/master", "refs/remotes/origin/main"]
+            ),
+            (base("master"), BaseSource::ConventionalName)
+        );
+        assert_eq!(
+            fallback(None, &[], &["refs/remotes/origin/main"]),
+            (base("origin/main"), BaseSource::ConventionalName)
+        );
+        assert_eq!(
+            fallback(None, &["trunk"], &[]),
+            (base("trunk"), BaseSource::OnlyRemainingBranch)
+        );
+        assert_eq!(
+            fallback(None, &["trunk", "dev"], &[]),
+            (Base::AllRemotes, BaseSource::AllRemotes)
+        );
+    }
+
+    #[test]
+    fn test_format_compact_summary() {
+        let assessments = [
+            assessment("merged", 0),
+            assessment("risky", 2),
+            assessment("merged-2", 0),
+        ];
+        assert_eq!(
+            format_compact_summary(&assessments, SUMMARY_GLYPH, false),
+            "\u{2387} 3 gone (2 safe, 1 risky)\n"
+        );
+        assert_eq!(
+            format_compact_summary(&assessments, "git:", true),
+            "\x1b[33mgit:\x1b[0m 3 gone (2 safe, 1 risky)\n"
+        );
+        assert_eq!(
+            format_compact_summary(&assessments[..1], "*", true),
+            "\x1b[32m*\x1b[0m 1 gone (1 safe, 0 risky)\n"
+        );
+        assert_eq!(format_compact_summary(&[], SUMMARY_GLYPH, true), "");
+    }
+
+    #[test]
+    fn test_shared_upstream_groups() {
+        let branches = crate::parse::parse_branch_lines(
+            r"
+  feature      abc1234 [origin/feature: gone] Original
+  feature-copy def5678 [origin/feature: gone] Copy
+  other        ghi9012 [origin/other: gone] Other
+  local        jkl3456 Local
+",
+        );
+        assert_eq!(
+            shared_upstream_groups(&branches),
+            [("origin/feature", vec!["feature", "feature-copy"])]
+        );
+        assert_eq!(
+            format_shared_upstreams(&branches),
+            "warning: 2 gone branches track the same upstream origin/feature: feature, \
+             feature-copy; one may be a mistaken duplicate, so check that deleting all of them \
+             is intended\n"
+        );
+        assert_eq!(format_shared_upstreams(&branches[1..]), "");
+    }
+
+    #[test]
+    fn test_apply_merged_upstream() {
+        let mut assessments = [assessment("squashed", 3), assessment("unmerged", 2)];
+        apply_merged_upstream(&mut assessments, &HashSet::from(["squashed".to_string()]));
+        assert_eq!(assessments[0].risk(), Risk::Safe);
+        assert_eq!(assessments[1].risk(), Risk::Risky);
+        assert_eq!(
+            format_risk_summary(&assessments, "origin/HEAD"),
+            "\
+Found 2 gone branch(es), compared against origin/HEAD:
+
+Safe (merged, no unique commits): 1
+  - squashed (merged upstream per --merged-prs-file)
+
+Risky (unmerged or ahead): 1
+  - unmerged (2 unique commit(s))
+"
+        );
+    }
+
+    #[test]
+    fn test_sort_and_rank_by_unique_commits() {
+        let mut assessments = [
+            assessment("merged", 0),
+            assessment("small", 2),
+            assessment("squashed", 9),
+            assessment("large", 5),
+        ];
+        apply_merged_upstream(&mut assessments, &HashSet::from(["squashed".to_string()]));
+        let branches = crate::parse::parse_branch_lines(
+            r"
+  merged       abc1234 [origin/merged: gone] A
+  unassessed   def5678 [origin/unassessed: gone] B
+  small        ghi9012 [origin/small: gone] C
+  squashed     jkl3456 [origin/squashed: gone] D
+  large        mno7890 [origin/large: gone] E
+",
+        );
+        let names: Vec<String> = sort_by_unique_commits(branches, &assessments)
+            .into_iter()
+            .map(|branch| branch.name)
+            .collect();
+        assert_eq!(
+            names,
+            ["large", "small", "merged", "squashed", "unassessed"]
+        );
+
+        assert_eq!(
+            format_risk_ranking(&assessments, "origin/HEAD"),
+            "\
+Found 4 gone branch(es), compared against origin/HEAD, most unique commits first:
+
+  - large (5 unique commit(s))
+  - small (2 unique commit(s))
+  - merged (0 unique commit(s))
+  - squashed (0 unique commit(s), merged upstream per --merged-prs-file)
+"
+        );
+    }
+
+    #[test]
+    fn test_format_upstreamless_summary() {
+        assert_eq!(format_upstreamless_summary(&[]), "");
+        let branches = crate::parse::parse_branch_lines("  scratch abc1234 Local experiment\n");
+        assert_eq!(
+            format_upstreamless_summary(&branches),
+            "\nMerged, never had an upstream (--include-no-upstream): 1\n  - scratch\n"
+        );
+    }
+
+    #[test]
+    fn test_format_risk_summary_groups_and_counts() {
+        let summary = format_risk_summary(
+            &[
+                assessment("merged-1", 0),
+                assessment("unmerged", 2),
+                assessment("merged-2", 0),
+            ],
+            "origin/HEAD",
+        );
+        assert_eq!(
+            summary,
+            "\
+Found 3 gone branch(es), compared against origin/HEAD:
+
+Safe (merged, no unique commits): 2
+  - merged-1
+  - merged-2
+
+Risky (unmerged or ahead): 1
+  - unmerged (2 unique commit(s))
+"
+        );
+    }
+
+    #[test]
+    fn test_commit_graph_counts_unique_commits() {
+        // a <- b <- c (base), and b <- d <- e (branch), plus a merge m of c and e
+        let rev_list = "\
+m c e
+e d
+d b
+c b
+b a
+a
+";
+        let tips: HashMap<String, String> = [("feature", "e"), ("merged", "m"), ("old", "b")]
+            .map(|(branch, tip)| (branch.to_string(), tip.to_string()))
+            .into();
+        let graph = CommitGraph::from_rev_list(rev_list, tips, &[vec!["c".to_string()]]);
+
+        assert_eq!(graph.unique_commits("feature", 0), 2);
+        assert_eq!(graph.unique_commits("merged", 0), 3);
+        assert_eq!(graph.unique_commits("old", 0), 0);
+    }
+
+    fn branch_named(name: &str) -> BranchLine {
+        BranchLine {
+            name: name.to_string(),
+            sha: String::new(),
+            upstream: None,
+            tracking: None,
+            subject: String::new(),
+            is_current: false,
+            in_other_worktree: false,
+        }
+    }
+
+    #[test]
+    fn test_commit_graph_matches_per_branch_rev_list() {
+        // main: init - c1, with origin/main there too
+        // feature: c1 - f1 - f2, side: init - s1 - s2 - s3, old: init
+        let repo = fixture::repo();
+        let dir = repo.path();
+        git(dir, &["branch", "old"]);
+        git(dir, &["checkout", "-q", "-b", "side"]);
+        for message in ["s1", "s2", "s3"] {
+            git(dir, &["commit", "-q", "--allow-empty", "-m", message]);
+        }
+        git(dir, &["checkout", "-q", "main"]);
+        git(dir, &["commit", "-q", "--allow-empty", "-m", "c1"]);
+        git(dir, &["update-ref", "refs/remotes/origin/main", "main"]);
+        git(dir, &["checkout", "-q", "-b", "feature"]);
+        for message in ["f1", "f2"] {
+            git(dir, &["commit", "-q", "--allow-empty", "-m", message]);
+        }
+
+        let branches = ["feature", "side", "old"].map(branch_named).to_vec();
+        let counts = |assessments: Vec<Assessment>| -> Vec<usize> {
+            assessments.iter().map(|a| a.unique_commits).collect()
+        };
+        fixture::in_repo(dir, || {
+            for base in [Base::Ref("main".to_string()), Base::AllRemotes] {
+                let in_graph = assess_branches_in_graph(&branches, &base).unwrap();
+                assert_eq!(in_graph, assess_branches(&branches, &base).unwrap());
+                assert_eq!(counts(in_graph), [2, 3, 0], "against {base}");
+            }
+            let bases = [Base::Ref("side".to_string()), Base::Ref("main".to_string())];
+            let in_graph = assess_against_bases_in_graph(&branches, &bases).unwrap();
+            assert_eq!(in_graph, assess_against_bases(&branches, &bases).unwrap());
+            assert_eq!(counts(in_graph.clone()), [2, 0, 0]);
+            assert_eq!(in_graph[1].merged_into.as_deref(), Some("side"));
+        });
+    }
+
+    #[test]
+    fn test_unique_commits_count_the_branch_not_a_same_named_tag() {
+        let repo = fixture::repo();
+        let dir = repo.path();
+        git(dir, &["tag", "v1"]);
+        git(dir, &["checkout", "-q", "-b", "v1"]);
+        git(dir, &["commit", "-q", "--allow-empty", "-m", "unmerged"]);
+        git(dir, &["checkout", "-q", "main"]);
+
+        let base = Base::Ref("main".to_string());
+        let branches = [branch_named("v1")];
+        fixture::in_repo(dir, || {
+            assert_eq!(unique_commit_count("v1", &base).unwrap(), 1);
+            // There are no remote-tracking branches to reach `main` either
+            assert_eq!(unique_commit_count("v1", &Base::AllRemotes).unwrap(), 2);
+            let in_graph = assess_branches_in_graph(&branches, &base).unwrap();
+            assert_eq!(in_graph[0].unique_commits, 1);
+        });
+    }
+
+    #[test]
+    fn test_commit_graph_handles_more_branches_than_fit_on_a_command_line() {
+        let repo = fixture::repo();
+        let sha = git(repo.path(), &["rev-parse", "HEAD"]);
+        // As arguments, the 60,000 tips' full SHAs alone would take 2.4 MB, past Linux's 2 MiB
+        // limit
+        let mut updates = String::new();
+        for i in 0..60_000 {
+            writeln!(updates, "create refs/heads/b-{i:05} {sha}").unwrap();
+        }
+        let status = output_with_stdin(
+            git_command()
+                .args(["update-ref", "--stdin"])
+                .current_dir(repo.path()),
+            updates,
+        )
+        .unwrap()
+        .status;
+        assert!(status.success());
+
+        let branches: Vec<BranchLine> = (0..60_000)
+            .map(|i| branch_named(&format!("b-{i:05}")))
+            .collect();
+        let graph = fixture::in_repo(repo.path(), || {
+            CommitGraph::load(
+                &branches,
+                &[Base::Ref("main".to_string()), Base::AllRemotes],
+            )
+            .unwrap()
+        });
+        for name in ["b-00000", "b-59999"] {
+            assert_eq!(graph.unique_commits(name, 0), 0);
+            // There are no remote-tracking branches to reach the one commit
+            assert_eq!(graph.unique_commits(name, 1), 1);
+        }
+    }
+
+    #[test]
+    fn test_rev_parse_rejects_unknown_revisions() {
+        let repo = fixture::repo();
+        let err = fixture::in_repo(repo.path(), || {
+            rev_parse(["main", "no-such-branch-here"].into_iter()).unwrap_err()
+        });
+        assert_eq!(
+            err.to_string(),
+            "no-such-branch-here doesn't resolve to a commit"
+        );
+    }
+
+    #[test]
+    fn test_format_risk_summary_names_merged_base() {
+        let merged = Assessment {
+            merged_into: Some("release/1.x".to_string()),
+            ..assessment("hotfix", 0)
+        };
+        let summary = format_risk_summary(&[merged], "main, release/1.x");
+        assert!(summary.contains("compared against main, release/1.x:"));
+        assert!(summary.contains("  - hotfix (merged into release/1.x)\n"));
+    }
+
+    #[test]
+    fn test_format_risk_summary_omits_empty_groups() {
+        let summary = format_risk_summary(&[assessment("merged", 0)], "HEAD");
+        assert!(summary.contains("Safe (merged, no unique commits): 1"));
+        assert!(!summary.contains("Risky"));
+    }
+}
@@ -0,0 +1,206 @@
+//! Per-branch cleanup rules from a file committed to the repository, so a team can codify
+//! which branches are kept, how carefully the others are deleted, and which are archived.
+//!
+//! The file (by default `.git-clean-gone-rules` at the top of the working tree) has one rule
+//! per line: a branch glob (see [`BranchPattern`]) and an action, separated by whitespace.
+//! Blank lines and lines starting with `#` are ignored:
+//!
+//! ```text
+//! release/*      keep
+//! dependabot/*   force-delete
+//! feature/*      safe-delete
+//! spike/*        archive
+//! ```
+//!
+//! When several globs match a branch, the most specific one wins: the one with the most
+//! literal (non-wildcard) characters, and among equally specific ones, the one further down.
+
+use crate::pattern::BranchPattern;
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The rules file's default name, at the top of the working tree
+pub const RULES_FILE: &str = ".git-clean-gone-rules";
+
+/// What a rule does with the gone branches it matches
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuleAction {
+    /// Never delete the branch
+    Keep,
+    /// Delete with `git branch -d`, which refuses branches not merged into `HEAD`
+    SafeDelete,
+    /// Delete with `git branch -D`, even over `--safe` or `--rename-to-archive`
+    ForceDelete,
+    /// Rename to `archive/<name>` instead of deleting, as with `--rename-to-archive`
+    Archive,
+}
+
+impl RuleAction {
+    /// The action's name in the rules file
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Keep => "keep",
+            Self::SafeDelete => "safe-delete",
+            Self::ForceDelete => "force-delete",
+            Self::Archive => "archive",
+        }
+    }
+}
+
+impl FromStr for RuleAction {
+    type Err = anyhow::Error;
+
+    fn from_str(action: &str) -> Result<Self> {
+        match action {
+            "keep" => Ok(Self::Keep),
+            "safe-delete" => Ok(Self::SafeDelete),
+            "force-delete" => Ok(Self::ForceDelete),
+            "archive" => Ok(Self::Archive),
+            _ => anyhow::bail!(
+                "Unknown action {action:?} (expected keep, safe-delete, force-delete, or archive)"
+            ),
+        }
+    }
+}
+
+/// One line of the rules file
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub pattern: BranchPattern,
+    pub action: RuleAction,
+}
+
+impl Rule {
+    /// How many literal characters the glob has, which decides between overlapping rules
+    fn specificity(&self) -> usize {
+        self.pattern
+            .to_string()
+            .chars()
+            .filter(|c| !matches!(c, '*' | '?'))
+            .count()
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.pattern, self.action.as_str())
+    }
+}
+
+/// The rules of a rules file, in file order
+#[derive(Debug, Clone, Default)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    /// Parses the contents of a rules file
+    ///
+    /// # Errors
+    ///
+    /// Fails, naming the line, on a line without exactly a glob and an action, or with an
+    /// unknown action or an invalid glob.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut rules = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [glob, action] = fields[..] else {
+                anyhow::bail!(
+                    "Line {}: expected `<glob> <action>`, got {line:?}",
+                    index + 1
+                );
+            };
+            rules.push(Rule {
+                pattern: glob
+                    .parse()
+                    .with_context(|| format!("Line {}", index + 1))?,
+                action: action
+                    .parse()
+                    .with_context(|| format!("Line {}", index + 1))?,
+            });
+        }
+        Ok(Self { rules })
+    }
+
+    /// Loads the rules file at `path`, or `None` if there isn't one
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file exists but can't be read or parsed.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text)
+                .map(Some)
+                .with_context(|| format!("Invalid rules file {}", path.display())),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => {
+                Err(err).with_context(|| format!("Failed to read rules file {}", path.display()))
+            }
+        }
+    }
+
+    /// The rule that decides what happens to `branch`, if any matches
+    #[must_use]
+    pub fn rule_for(&self, branch: &str) -> Option<&Rule> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.pattern.matches(branch))
+            // `max_by_key` keeps the last of equal keys, so later lines win ties
+            .max_by_key(|rule| rule.specificity())
+    }
+
+    /// The action of the rule for `branch`, if any
+    #[must_use]
+    pub fn action_for(&self, branch: &str) -> Option<RuleAction> {
+        self.rule_for(branch).map(|rule| rule.action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_most_specific_rule_wins() {
+        let rules = Rules::parse(
+            "# Team policy\n\
+             *              safe-delete\n\
+             release/*      keep\n\
+             release/old-*  force-delete\n\
+             \n\
+             spike/*        archive\n\
+             spike/?        keep\n\
+             spike/*        force-delete\n",
+        )
+        .unwrap();
+        let action = |branch| rules.action_for(branch);
+        assert_eq!(action("feature/x"), Some(RuleAction::SafeDelete));
+        assert_eq!(action("release/1.0"), Some(RuleAction::Keep));
+        assert_eq!(action("release/old-1"), Some(RuleAction::ForceDelete));
+        // Equally specific: the later line wins
+        assert_eq!(action("spike/long"), Some(RuleAction::ForceDelete));
+        assert_eq!(action("spike/a"), Some(RuleAction::ForceDelete));
+        assert_eq!(
+            rules.rule_for("release/1.0").unwrap().to_string(),
+            "release/* keep"
+        );
+        assert_eq!(Rules::default().action_for("feature/x"), None);
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_lines() {
+        let err = Rules::parse("release/* keep\nfeature/* delete\n").unwrap_err();
+        assert!(format!("{err:#}").contains("Line 2"));
+        assert!(Rules::parse("release/*\n").is_err());
+        assert!(Rules::parse("release/* keep now\n").is_err());
+    }
+}
@@ -0,0 +1,1619 @@
+//! Safety checks that hold gone branches back from deletion.
+//!
+//! Each check splits the candidates into those still safe to delete and those [`Held`]
+//! with a reason, so held branches can be reported instead of silently dropped.
+
+use crate::Options;
+use crate::git::{
+    branch_commit_times, branch_descriptions, git_dir, is_ancestor, last_reflog_time,
+    merged_parents, run_open_pr_command, symbolic_branches, tracking_last_seen,
+};
+use crate::operations::{InProgress, in_progress_operations};
+use crate::parse::BranchLine;
+use crate::pattern::BranchPattern;
+use crate::risk::{
+    Assessment, Base, Risk, fork_point_time, graph_unique_commit_count, unique_commit_count,
+};
+use crate::rules::{RuleAction, Rules};
+use crate::tags::tags_containing;
+use crate::tracking::list_all_tracking_branches;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Write as _;
+use std::hash::BuildHasher;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Branch patterns `--safe` protects, the usual names of long-lived branches
+pub const DEFAULT_PROTECTED: &[&str] = &["main", "master", "develop", "release/*"];
+
+/// A gone branch kept because a safety check flagged it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Held {
+    pub branch: BranchLine,
+    pub kind: HoldKind,
+    pub reason: String,
+}
+
+/// Why a branch was kept, as a stable machine-readable category (see
+/// [`crate::report::PlannedAction`]); the [`Held`] reason carries the details
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum HoldKind {
+    /// Upstream outside `--match-upstream-prefix`
+    OutOfScope,
+    /// Matches a `--protect` pattern
+    Protected,
+    /// Matches a `keep` rule in the rules file
+    Rule,
+    /// Has a description, with `--protect-described`
+    Described,
+    SymbolicRef,
+    /// Involved in a rebase, bisect, merge, or the like that's in progress
+    InProgress,
+    /// Name differs only in case from another branch's
+    CaseCollision,
+    /// `--skip-if-exists-elsewhere`
+    ExistsElsewhere,
+    /// `--protect-tagged-ancestors`
+    Tagged,
+    /// Branched off before `--since`
+    BranchedBefore,
+    /// `--exclude-recent-activity`
+    RecentActivity,
+    /// Its upstream was seen on the remote within `--gone-for`
+    GoneRecently,
+    /// Has commits not on the base, with `--prune-merged-remote-tracking` or `--merged-prs-file`
+    Unmerged,
+    /// Has commits no other ref reaches, with `--auto-safe`
+    UniqueCommits,
+    /// Has commits no remote-tracking ref reaches, with `--protect-unpushed`
+    Unpushed,
+    /// Ahead of its upstream per `git branch -vv`, with `--protect-ahead`
+    Ahead,
+    /// `--keep-last-merged`
+    LastMerged,
+    /// Has an open pull request, per `--open-pr-command`
+    OpenPr,
+    /// `--keep-recent`
+    KeepRecent,
+    /// Beyond `--limit`
+    Limit,
+    /// The tip moved since listing, or doesn't match the plan or `--expect-shas`
+    TipMismatch,
+    /// The upstream was re-created on the remote since the fetch, with `--verify-remote-gone`
+    Reappeared,
+    /// Not reached before `--max-runtime` ran out
+    Deadline,
+    /// Not reached before the run was interrupted (Ctrl-C or SIGTERM)
+    Interrupted,
+}
+
+impl HoldKind {
+    /// The category's name in machine-readable output
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::OutOfScope => "out-of-scope",
+            Self::Protected => "protected",
+            Self::Rule => "rule",
+            Self::Described => "described",
+            Self::SymbolicRef => "symbolic-ref",
+            Self::InProgress => "in-progress",
+            Self::CaseCollision => "case-collision",
+            Self::ExistsElsewhere => "exists-elsewhere",
+            Self::Tagged => "tagged",
+            Self::BranchedBefore => "branched-before",
+            Self::RecentActivity => "recent-activity",
+            Self::GoneRecently => "gone-recently",
+            Self::Unmerged => "unmerged",
+            Self::UniqueCommits => "unique-commits",
+            Self::Unpushed => "unpushed",
+            Self::Ahead => "ahead",
+            Self::LastMerged => "last-merged",
+            Self::OpenPr => "open-pr",
+            Self::KeepRecent => "keep-recent",
+            Self::Limit => "limit",
+            Self::TipMismatch => "tip-mismatch",
+            Self::Reappeared => "reappeared",
+            Self::Deadline => "deadline",
+            Self::Interrupted => "interrupted",
+        }
+    }
+}
+
+/// The point `--since` measures branches against, to leave long-lived ones alone
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Since {
+    /// A commit: branches that contain it branched off after it
+    Commit(String),
+    /// A Unix timestamp: branches whose fork point from the base was committed at or after it
+    Time(i64),
+}
+
+impl Since {
+    /// Parses a date (`2026-01-31` or RFC 3339, in UTC), a duration before now (e.g. `30days`,
+    /// `2weeks`), or else a commit. Dates and durations win if a ref has the same name.
+    #[must_use]
+    pub fn parse(text: &str) -> Self {
+        let date = humantime::parse_rfc3339_weak(text)
+            .or_else(|_| humantime::parse_rfc3339_weak(&format!("{text} 00:00:00")));
+        if let Ok(date) = date {
+            let secs = date.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+            return Self::Time(i64::try_from(secs).unwrap_or(i64::MAX));
+        }
+        match humantime::parse_duration(text) {
+            Ok(ago) => Self::Time(
+                unix_now().saturating_sub(i64::try_from(ago.as_secs()).unwrap_or(i64::MAX)),
+            ),
+            Err(_) => Self::Commit(text.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Since {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Commit(commit) => f.write_str(commit),
+            Self::Time(time) => {
+                let time = UNIX_EPOCH + Duration::from_secs(u64::try_from(*time).unwrap_or(0));
+                write!(f, "{}", humantime::format_rfc3339_seconds(time))
+            }
+        }
+    }
+}
+
+/// Runs every enabled safety check over the gone branches, in order.
+///
+/// Checks see only the branches earlier checks left deletable, so a branch is held for
+/// the first reason that applies.
+///
+/// # Errors
+///
+/// Fails if a check can't gather the git metadata it needs.
+pub fn apply_safety_checks(
+    gone_branches: Vec<BranchLine>,
+    all_branches: &[BranchLine],
+    options: &Options,
+) -> Result<(Vec<BranchLine>, Vec<Held>)> {
+    let (deletable, mut held) = match &options.upstream_prefix {
+        Some(prefix) => hold_outside_upstream_prefix(gone_branches, prefix),
+        None => (gone_branches, Vec::new()),
+    };
+    let (deletable, protected) = hold_protected(deletable, &options.protect);
+    held.extend(protected);
+    let (deletable, kept) = hold_kept_by_rules(deletable, &options.rules);
+    held.extend(kept);
+    let deletable = if options.protect_described && !deletable.is_empty() {
+        let (deletable, described) = hold_described(deletable, &branch_descriptions()?);
+        held.extend(described);
+        deletable
+    } else {
+        deletable
+    };
+    let deletable = if deletable.is_empty() {
+        deletable
+    } else {
+        let (deletable, symbolic) = hold_symbolic_refs(deletable, &symbolic_branches()?);
+        held.extend(symbolic);
+        let (deletable, involved) =
+            hold_in_progress(deletable, &in_progress_operations(&git_dir()?));
+        held.extend(involved);
+        deletable
+    };
+    let (deletable, collisions) = hold_case_collisions(deletable, all_branches);
+    held.extend(collisions);
+
+    let deletable = if options.skip_if_exists_elsewhere && !deletable.is_empty() {
+        let (deletable, elsewhere) =
+            hold_existing_elsewhere(deletable, &list_all_tracking_branches()?);
+        held.extend(elsewhere);
+        deletable
+    } else {
+        deletable
+    };
+
+    let deletable = match &options.since {
+        Some(since) if !deletable.is_empty() => {
+            let base = options.base()?;
+            let (deletable, old) = hold_branched_before(deletable, since, |branch| match since {
+                Since::Commit(commit) => is_ancestor(commit, &format!("refs/heads/{branch}")),
+                Since::Time(time) => {
+                    Ok(fork_point_time(branch, &base)?.is_some_and(|fork| fork >= *time))
+                }
+            })?;
+            held.extend(old);
+            deletable
+        }
+        _ => deletable,
+    };
+
+    let deletable = if options.protect_tagged_ancestors {
+        let (deletable, tagged) = hold_tagged_ancestors(deletable, |branch| {
+            tags_containing(&format!("refs/heads/{branch}"))
+        })?;
+        held.extend(tagged);
+        deletable
+    } else {
+        deletable
+    };
+
+    let deletable = match options.exclude_recent_activity {
+        Some(window) if !deletable.is_empty() => {
+            let (deletable, active) =
+                hold_recent_activity(deletable, window, unix_now(), last_reflog_time)?;
+            held.extend(active);
+            deletable
+        }
+        _ => deletable,
+    };
+
+    let deletable = match options.gone_for {
+        Some(min_gone) if !deletable.is_empty() => {
+            let (deletable, recent, unknown) =
+                hold_gone_recently(deletable, min_gone, unix_now(), |branch| {
+                    branch
+                        .upstream
+                        .as_deref()
+                        .map_or(Ok(None), tracking_last_seen)
+                })?;
+            held.extend(recent);
+            for branch in unknown {
+                eprintln!(
+                    "note: no reflog shows when {}'s upstream was last seen, so --gone-for \
+                     doesn't apply to it",
+                    branch.name
+                );
+            }
+            deletable
+        }
+        _ => deletable,
+    };
+
+    let deletable = apply_commit_checks(deletable, &mut held, options)?;
+    let deletable = apply_final_checks(deletable, &mut held, options)?;
+    Ok((deletable, held))
+}
+
+/// The checks that count commits, in order: `--protect-ahead`, `--protect-unpushed`,
+/// `--prune-merged-remote-tracking`, and `--auto-safe`, adding the branches they keep to `held`
+fn apply_commit_checks(
+    deletable: Vec<BranchLine>,
+    held: &mut Vec<Held>,
+    options: &Options,
+) -> Result<Vec<BranchLine>> {
+    // Just reads the listing, so it goes before the checks that ask git
+    let deletable = if options.protect_ahead {
+        let (deletable, ahead) = hold_ahead(deletable);
+        held.extend(ahead);
+        deletable
+    } else {
+        deletable
+    };
+
+    // Before the merge checks, so an unpushed branch is reported with its unpushed count
+    let deletable = if options.protect_unpushed && !deletable.is_empty() {
+        let (deletable, unpushed) = hold_unpushed(deletable, |branch| {
+            unique_commit_count(branch, &Base::AllRemotes)
+        })?;
+        held.extend(unpushed);
+        deletable
+    } else {
+        deletable
+    };
+
+    let deletable = if options.merged_only && !deletable.is_empty() {
+        let base = options.base()?;
+        let (deletable, unmerged) =
+            hold_unmerged(deletable, |branch| unique_commit_count(branch, &base))?;
+        held.extend(unmerged);
+        deletable
+    } else {
+        deletable
+    };
+
+    let deletable = if options.auto_safe && !deletable.is_empty() {
+        let (deletable, unique) = hold_unique_commits(deletable, graph_unique_commit_count)?;
+        held.extend(unique);
+        deletable
+    } else {
+        deletable
+    };
+    Ok(deletable)
+}
+
+/// The checks that run after the rest, in order: `--keep-last-merged`, `--open-pr-command`,
+/// `--keep-recent`, and `--limit`, adding the branches they keep to `held`
+fn apply_final_checks(
+    deletable: Vec<BranchLine>,
+    held: &mut Vec<Held>,
+    options: &Options,
+) -> Result<Vec<BranchLine>> {
+    let base = (options.keep_last_merged && !deletable.is_empty())
+        .then(|| options.base())
+        .transpose()?;
+    // With no single base there's no merge history to consult
+    let deletable = match base {
+        Some(Base::Ref(base)) => {
+            let (deletable, last) = hold_last_merged(deletable, &merged_parents(&base)?, &base);
+            held.extend(last);
+            deletable
+        }
+        _ => deletable,
+    };
+
+    // Runs a (possibly slow) command per branch, so only for those the other checks let through
+    let deletable = match &options.open_pr_command {
+        Some(command) if !deletable.is_empty() => {
+            let (deletable, open) =
+                hold_open_prs(deletable, |branch| run_open_pr_command(command, branch))?;
+            held.extend(open);
+            deletable
+        }
+        _ => deletable,
+    };
+
+    // Runs late, so it spares recent branches among those the other checks let through
+    let deletable = match options.keep_recent {
+        Some(count) if count > 0 && !deletable.is_empty() => {
+            let (deletable, recent) = hold_most_recent(deletable, count, &branch_commit_times()?);
+            held.extend(recent);
+            deletable
+        }
+        _ => deletable,
+    };
+
+    // After everything else, so the limit counts only branches that would really go
+    let deletable = match options.limit {
+        Some(limit) => {
+            let (deletable, over) = hold_over_limit(deletable, limit);
+            held.extend(over);
+            deletable
+        }
+        None => deletable,
+    };
+
+    Ok(deletable)
+}
+
+/// Holds back the candidates `assessments` rate risky, for when `--merged-prs-file` is the
+/// authority on what was merged and anything else with unmerged commits needs a closer look
+#[must_use]
+pub fn hold_unlisted_risky(
+    candidates: Vec<BranchLine>,
+    assessments: &[Assessment],
+) -> (Vec<BranchLine>, Vec<Held>) {
+    let mut deletable = Vec::new();
+    let mut held = Vec::new();
+    for branch in candidates {
+        match assessments
+            .iter()
+            .find(|assessment| assessment.name == branch.name && assessment.risk() == Risk::Risky)
+        {
+            Some(assessment) => held.push(Held {
+                kind: HoldKind::Unmerged,
+                reason: format!(
+                    "{} unmerged commit(s) and not in --merged-prs-file",
+                    assessment.unique_commits
+                ),
+                branch,
+            }),
+            None => deletable.push(branch),
+        }
+    }
+    (deletable, held)
+}
+
+/// Holds back the candidates with commits `unique_commits_for` counts as not merged into the
+/// base, for [`Options::merged_only`]
+///
+/// # Errors
+///
+/// Fails if `unique_commits_for` does.
+pub fn hold_unmerged<F>(
+    candidates: Vec<BranchLine>,
+    mut unique_commits_for: F,
+) -> Result<(Vec<BranchLine>, Vec<Held>)>
+where
+    F: FnMut(&str) -> Result<usize>,
+{
+    let mut deletable = Vec::new();
+    let mut held = Vec::new();
+    for branch in candidates {
+        match unique_commits_for(&branch.name)? {
+            0 => deletable.push(branch),
+            n => held.push(Held {
+                kind: HoldKind::Unmerged,
+                reason: format!("{n} unmerged commit(s) (--prune-merged-remote-tracking)"),
+                branch,
+            }),
+        }
+    }
+    Ok((deletable, held))
+}
+
+/// Holds back the candidates with commits `unique_commits_for` counts as reached by no other
+/// ref, for [`Options::auto_safe`]
+///
+/// # Errors
+///
+/// Fails if `unique_commits_for` does.
+pub fn hold_unique_commits<F>(
+    candidates: Vec<BranchLine>,
+    mut unique_commits_for: F,
+) -> Result<(Vec<BranchLine>, Vec<Held>)>
+where
+    F: FnMut(&str) -> Result<usize>,
+{
+    let mut deletable = Vec::new();
+    let mut held = Vec::new();
+    for branch in candidates {
+        match unique_commits_for(&branch.name)? {
+            0 => deletable.push(branch),
+            n => held.push(Held {
+                kind: HoldKind::UniqueCommits,
+                reason: format!("{n} commit(s) on no other ref (--auto-safe)"),
+                branch,
+            }),
+        }
+    }
+    Ok((deletable, held))
+}
+
+/// Holds back the candidates whose `git branch -vv` tracking state says they're ahead of their
+/// upstream, for [`Options::protect_ahead`]
+#[must_use]
+pub fn hold_ahead(candidates: Vec<BranchLine>) -> (Vec<BranchLine>, Vec<Held>) {
+    let mut deletable = Vec::new();
+    let mut held = Vec::new();
+    for branch in candidates {
+        match branch.divergence().map_or(0, |divergence| divergence.ahead) {
+            0 => deletable.push(branch),
+            n => held.push(Held {
+                kind: HoldKind::Ahead,
+                reason: format!("ahead of its upstream by {n} commit(s) (--protect-ahead)"),
+                branch,
+            }),
+        }
+    }
+    (deletable, held)
+}
+
+/// Holds back the candidates with commits `unpushed_for` counts as on no remote-tracking ref,
+/// for [`Options::protect_unpushed`]
+///
+/// # Errors
+///
+/// Fails if `unpushed_for` does.
+pub fn hold_unpushed<F>(
+    candidates: Vec<BranchLine>,
+    mut unpushed_for: F,
+) -> Result<(Vec<BranchLine>, Vec<Held>)>
+where
+    F: FnMut(&str) -> Result<usize>,
+{
+    let mut deletable = Vec::new();
+    let mut held = Vec::new();
+    for branch in candidates {
+        match unpushed_for(&branch.name)? {
+            0 => deletable.push(branch),
+            n => held.push(Held {
+                kind: HoldKind::Unpushed,
+                reason: format!("{n} commit(s) on no remote (--protect-unpushed)"),
+                branch,
+            }),
+        }
+    }
+    Ok((deletable, held))
+}
+
+/// Holds back the candidates `has_open_pr` says have an open pull request, for
+/// [`Options::open_pr_command`]. It's asked once per branch name, however many candidates
+/// share it.
+///
+/// # Errors
+///
+/// Fails if `has_open_pr` does.
+pub fn hold_open_prs<F>(
+    candidates: Vec<BranchLine>,
+    mut has_open_pr: F,
+) -> Result<(Vec<BranchLine>, Vec<Held>)>
+where
+    F: FnMut(&str) -> Result<bool>,
+{
+    let mut answers: HashMap<String, bool> = HashMap::new();
+    let mut deletable = Vec::new();
+    let mut held = Vec::new();
+    for branch in candidates {
+        let open = if let Some(&open) = answers.get(&branch.name) {
+            open
+        } else {
+            let open = has_open_pr(&branch.name)?;
+            answers.insert(branch.name.clone(), open);
+            open
+        };
+        if open {
+            held.push(Held {
+                kind: HoldKind::OpenPr,
+                reason: "has an open pull request (--open-pr-command)".to_string(),
+                branch,
+            });
+        } else {
+            deletable.push(branch);
+        }
+    }
+    Ok((deletable, held))
+}
+
+/// Holds back the candidates whose rule (see [`Rules::rule_for`]) is `keep`
+#[must_use]
+pub fn hold_kept_by_rules(
+    candidates: Vec<BranchLine>,
+    rules: &Rules,
+) -> (Vec<BranchLine>, Vec<Held>) {
+    let mut deletable = Vec::new();
+    let mut held = Vec::new();
+    for branch in candidates {
+        match rules.rule_for(&branch.name) {
+            Some(rule) if rule.action == RuleAction::Keep => held.push(Held {
+                kind: HoldKind::Rule,
+                reason: format!("kept by rule `{rule}`"),
+                branch,
+            }),
+            _ => deletable.push(branch),
+        }
+    }
+    (deletable, held)
+}
+
+/// Holds back the candidates with a description in `descriptions`, for
+/// [`Options::protect_described`], on the theory that describing a branch was deliberate
+#[must_use]
+pub fn hold_described<S: BuildHasher>(
+    candidates: Vec<BranchLine>,
+    descriptions: &HashMap<String, String, S>,
+) -> (Vec<BranchLine>, Vec<Held>) {
+    let mut deletable = Vec::new();
+    let mut held = Vec::new();
+    for branch in candidates {
+        match descriptions.get(&branch.name) {
+            Some(description) => held.push(Held {
+                kind: HoldKind::Described,
+                reason: format!(
+                    "has a description (--protect-described): {}",
+                    description.lines().next().unwrap_or_default()
+                ),
+                branch,
+            }),
+            None => deletable.push(branch),
+        }
+    }
+    (deletable, held)
+}
+
+/// Lists the gone branches among `branches` with a description in `descriptions`, with its
+/// first line, or nothing if none has one
+#[must_use]
+pub fn format_described<S: BuildHasher>(
+    branches: &[BranchLine],
+    descriptions: &HashMap<String, String, S>,
+) -> String {
+    let described: Vec<(&str, &str)> = branches
+        .iter()
+        .filter_map(|branch| {
+            let description = descriptions.get(&branch.name)?;
+            Some((
+                branch.name.as_str(),
+                description.lines().next().unwrap_or_default(),
+            ))
+        })
+        .collect();
+    if described.is_empty() {
+        return String::new();
+    }
+
+    let mut summary = format!("\nGone branches with a description: {}\n", described.len());
+    for (name, description) in described {
+        let _ = writeln!(summary, "  - {name}: {description}");
+    }
+    summary
+}
+
+/// Holds back the candidates `branched_after` says branched off before `since`, for
+/// [`Options::since`]
+///
+/// # Errors
+///
+/// Fails if `branched_after` does.
+pub fn hold_branched_before<F>(
+    candidates: Vec<BranchLine>,
+    since: &Since,
+    mut branched_after: F,
+) -> Result<(Vec<BranchLine>, Vec<Held>)>
+where
+    F: FnMut(&str) -> Result<bool>,
+{
+    let mut deletable = Vec::new();
+    let mut held = Vec::new();
+    for branch in candidates {
+        if branched_after(&branch.name)? {
+            deletable.push(branch);
+        } else {
+            held.push(Held {
+                kind: HoldKind::BranchedBefore,
+                reason: format!("too old: branched before --since {since}"),
+                branch,
+            });
+        }
+    }
+    Ok((deletable, held))
+}
+
+/// Holds back the candidates after the first `limit`, so a run deletes in batches
+#[must_use]
+pub fn hold_over_limit(
+    mut candidates: Vec<BranchLine>,
+    limit: usize,
+) -> (Vec<BranchLine>, Vec<Held>) {
+    let over = candidates
+        .split_off(limit.min(candidates.len()))
+        .into_iter()
+        .map(|branch| Held {
+            kind: HoldKind::Limit,
+            branch,
+            reason: format!("beyond --limit {limit}"),
+        })
+        .collect();
+    (candidates, over)
+}
+
+/// Holds back the candidate most recently merged into `base`, given the commits its merges
+/// brought in, newest first (see [`crate::git::merged_parents`])
+#[must_use]
+pub fn hold_last_merged(
+    candidates: Vec<BranchLine>,
+    merged_parents: &[String],
+    base: &str,
+) -> (Vec<BranchLine>, Vec<Held>) {
+    let last = merged_parents.iter().find_map(|parent| {
+        candidates
+            .iter()
+            .position(|branch| parent.starts_with(&branch.sha))
+    });
+    let mut deletable = candidates;
+    let held = last
+        .map(|index| Held {
+            kind: HoldKind::LastMerged,
+            branch: deletable.remove(index),
+            reason: format!("most recently merged into {base} (--keep-last-merged)"),
+        })
+        .into_iter()
+        .collect();
+    (deletable, held)
+}
+
+/// Holds back candidates whose upstream doesn't start with `prefix` (e.g. `origin/team-a/`),
+/// scoping a cleanup to one namespace on the remote
+#[must_use]
+pub fn hold_outside_upstream_prefix(
+    candidates: Vec<BranchLine>,
+    prefix: &str,
+) -> (Vec<BranchLine>, Vec<Held>) {
+    let mut deletable = Vec::new();
+    let mut held = Vec::new();
+    for branch in candidates {
+        let reason = match &branch.upstream {
+            Some(upstream) if upstream.starts_with(prefix) => None,
+            Some(upstream) => Some(format!(
+                "upstream {upstream} is outside --match-upstream-prefix {prefix}"
+            )),
+            None => Some(format!(
+                "no upstream to match --match-upstream-prefix {prefix}"
+            )),
+        };
+        match reason {
+            Some(reason) => held.push(Held {
+                branch,
+                kind: HoldKind::OutOfScope,
+                reason,
+            }),
+            None => deletable.push(branch),
+        }
+    }
+    (deletable, held)
+}
+
+/// Holds back candidates matching any of the `protected` patterns
+#[must_use]
+pub fn hold_protected(
+    candidates: Vec<BranchLine>,
+    protected: &[BranchPattern],
+) -> (Vec<BranchLine>, Vec<Held>) {
+    let mut deletable = Vec::new();
+    let mut held = Vec::new();
+    for branch in candidates {
+        match protected
+            .iter()
+            .find(|pattern| pattern.matches(&branch.name))
+        {
+            Some(pattern) => held.push(Held {
+                kind: HoldKind::Protected,
+                reason: format!("matches protected pattern {pattern}"),
+                branch,
+            }),
+            None => deletable.push(branch),
+        }
+    }
+    (deletable, held)
+}
+
+/// Holds back candidates that are symbolic refs (per `symbolic`, branch name to target ref),
+/// since `git branch -D` on one doesn't do what removing a gone branch should
+#[must_use]
+pub fn hold_symbolic_refs<S: BuildHasher>(
+    candidates: Vec<BranchLine>,
+    symbolic: &HashMap<String, String, S>,
+) -> (Vec<BranchLine>, Vec<Held>) {
+    let mut deletable = Vec::new();
+    let mut held = Vec::new();
+    for branch in candidates {
+        match symbolic.get(&branch.name) {
+            Some(target) => held.push(Held {
+                kind: HoldKind::SymbolicRef,
+                reason: format!("symbolic ref to {target}"),
+                branch,
+            }),
+            None => deletable.push(branch),
+        }
+    }
+    (deletable, held)
+}
+
+/// Holds back candidates involved in one of the `operations` in progress, since deleting
+/// e.g. the branch being rebased would leave the rebase unable to finish
+#[must_use]
+pub fn hold_in_progress(
+    candidates: Vec<BranchLine>,
+    operations: &[InProgress],
+) -> (Vec<BranchLine>, Vec<Held>) {
+    let mut deletable = Vec::new();
+    let mut held = Vec::new();
+    for branch in candidates {
+        match operations.iter().find(|op| op.involves(&branch)) {
+            Some(op) => held.push(Held {
+                kind: HoldKind::InProgress,
+                reason: format!("involved in the {} in progress", op.operation),
+                branch,
+            }),
+            None => deletable.push(branch),
+        }
+    }
+    (deletable, held)
+}
+
+/// Holds back gone branches whose names differ only in case from a branch that's being kept.
+///
+/// On case-insensitive filesystems (the macOS and Windows defaults) such names can share a
+/// loose ref file, so deleting one could remove the other.
+#[must_use]
+pub fn hold_case_collisions(
+    candidates: Vec<BranchLine>,
+    all_branches: &[BranchLine],
+) -> (Vec<BranchLine>, Vec<Held>) {
+    let kept: Vec<&BranchLine> = all_branches
+        .iter()
+        .filter(|branch| !candidates.iter().any(|c| c.name == branch.name))
+        .collect();
+
+    let mut deletable = Vec::new();
+    let mut held = Vec::new();
+    for candidate in candidates {
+        let folded = candidate.name.to_lowercase();
+        match kept.iter().find(|k| k.name.to_lowercase() == folded) {
+            Some(other) => held.push(Held {
+                kind: HoldKind::CaseCollision,
+                reason: format!("name collides case-insensitively with {}", other.name),
+                branch: candidate,
+            }),
+            None => deletable.push(candidate),
+        }
+    }
+
+    (deletable, held)
+}
+
+/// The remotes, other than the one `branch`'s upstream is on, that still have a branch of
+/// the same name, according to the `(remote, branch)` pairs in `tracking`
+#[must_use]
+pub fn other_remotes_with<'a>(
+    branch: &BranchLine,
+    tracking: &'a [(String, String)],
+) -> Vec<&'a str> {
+    tracking
+        .iter()
+        .filter(|(remote, name)| {
+            *name == branch.name && branch.upstream.as_deref() != Some(&format!("{remote}/{name}"))
+        })
+        .map(|(remote, _)| remote.as_str())
+        .collect()
+}
+
+/// Describes where a branch still exists, e.g. `upstream (upstream/feature-x)`
+fn describe_elsewhere(branch: &str, remotes: &[&str]) -> String {
+    remotes
+        .iter()
+        .map(|remote| format!("{remote} ({remote}/{branch})"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Warns about each of `branches` that still exists on another remote than its upstream's,
+/// since deleting it may be a mistake. `--skip-if-exists-elsewhere` holds them back instead.
+///
+/// # Errors
+///
+/// Fails if the remote-tracking branches can't be listed.
+pub fn existing_elsewhere_warnings(branches: &[BranchLine]) -> Result<Vec<String>> {
+    if branches.is_empty() {
+        return Ok(Vec::new());
+    }
+    let tracking = list_all_tracking_branches()?;
+    Ok(branches
+        .iter()
+        .filter_map(|branch| {
+            let remotes = other_remotes_with(branch, &tracking);
+            (!remotes.is_empty()).then(|| {
+                format!(
+                    "{} still exists on {}",
+                    branch.name,
+                    describe_elsewhere(&branch.name, &remotes)
+                )
+            })
+        })
+        .collect())
+}
+
+/// Holds back candidates whose name still exists as a branch on another remote than their
+/// upstream's, per the `(remote, branch)` pairs in `tracking`
+#[must_use]
+pub fn hold_existing_elsewhere(
+    candidates: Vec<BranchLine>,
+    tracking: &[(String, String)],
+) -> (Vec<BranchLine>, Vec<Held>) {
+    let mut deletable = Vec::new();
+    let mut held = Vec::new();
+    for branch in candidates {
+        let remotes = other_remotes_with(&branch, tracking);
+        if remotes.is_empty() {
+            deletable.push(branch);
+        } else {
+            held.push(Held {
+                kind: HoldKind::ExistsElsewhere,
+                reason: format!(
+                    "still exists on {} (--skip-if-exists-elsewhere)",
+                    describe_elsewhere(&branch.name, &remotes)
+                ),
+                branch,
+            });
+        }
+    }
+    (deletable, held)
+}
+
+/// Holds back candidates whose tip is contained in (an ancestor of) any tag, as reported by
+/// `tags_for`, since such branches are part of release history.
+///
+/// # Errors
+///
+/// Fails if `tags_for` does.
+pub fn hold_tagged_ancestors<F>(
+    candidates: Vec<BranchLine>,
+    mut tags_for: F,
+) -> Result<(Vec<BranchLine>, Vec<Held>)>
+where
+    F: FnMut(&str) -> Result<Vec<String>>,
+{
+    let mut deletable = Vec::new();
+    let mut held = Vec::new();
+    for branch in candidates {
+        let tags = tags_for(&branch.name)?;
+        match tags.first() {
+            Some(tag) => {
+                let others = match tags.len() - 1 {
+                    0 => String::new(),
+                    n => format!(" and {n} other tag(s)"),
+                };
+                held.push(Held {
+                    kind: HoldKind::Tagged,
+                    reason: format!(
+                        "tip is an ancestor of tag {tag}{others} (--protect-tagged-ancestors)"
+                    ),
+                    branch,
+                });
+            }
+            None => deletable.push(branch),
+        }
+    }
+    Ok((deletable, held))
+}
+
+/// Holds back candidates with reflog activity within `window` before `now` (Unix seconds), as
+/// reported by `last_activity`. This catches branches that were just rebased or amended even
+/// though their commit dates look old.
+///
+/// # Errors
+///
+/// Fails if `last_activity` does.
+pub fn hold_recent_activity<F>(
+    candidates: Vec<BranchLine>,
+    window: Duration,
+    now: i64,
+    mut last_activity: F,
+) -> Result<(Vec<BranchLine>, Vec<Held>)>
+where
+    F: FnMut(&str) -> Result<Option<i64>>,
+{
+    let cutoff = now.saturating_sub(i64::try_from(window.as_secs()).unwrap_or(i64::MAX));
+    let mut deletable = Vec::new();
+    let mut held = Vec::new();
+    for branch in candidates {
+        match last_activity(&branch.name)? {
+            Some(time) if time > cutoff => {
+                let ago = Duration::from_secs(u64::try_from(now - time).unwrap_or(0));
+                held.push(Held {
+                    kind: HoldKind::RecentActivity,
+                    reason: format!(
+                        "reflog activity {} ago (--exclude-recent-activity)",
+                        humantime::format_duration(ago)
+                    ),
+                    branch,
+                });
+            }
+            _ => deletable.push(branch),
+        }
+    }
+    Ok((deletable, held))
+}
+
+/// Holds back candidates whose upstream `last_seen` says was still on the remote within
+/// `min_gone` before `now` (Unix seconds), so only branches gone at least that long are
+/// deleted. Candidates `last_seen` knows nothing about stay deletable, and are returned
+/// separately too so the caller can say the check didn't apply to them.
+///
+/// # Errors
+///
+/// Fails if `last_seen` does.
+pub fn hold_gone_recently<F>(
+    candidates: Vec<BranchLine>,
+    min_gone: Duration,
+    now: i64,
+    mut last_seen: F,
+) -> Result<(Vec<BranchLine>, Vec<Held>, Vec<BranchLine>)>
+where
+    F: FnMut(&BranchLine) -> Result<Option<i64>>,
+{
+    let cutoff = now.saturating_sub(i64::try_from(min_gone.as_secs()).unwrap_or(i64::MAX));
+    let mut deletable = Vec::new();
+    let mut held = Vec::new();
+    let mut unknown = Vec::new();
+    for branch in candidates {
+        match last_seen(&branch)? {
+            Some(time) if time > cutoff => {
+                let ago = Duration::from_secs(u64::try_from(now - time).unwrap_or(0));
+                held.push(Held {
+                    kind: HoldKind::GoneRecently,
+                    reason: format!(
+                        "upstream last seen {} ago (--gone-for)",
+                        humantime::format_duration(ago)
+                    ),
+                    branch,
+                });
+            }
+            Some(_) => deletable.push(branch),
+            None => {
+                unknown.push(branch.clone());
+                deletable.push(branch);
+            }
+        }
+    }
+    Ok((deletable, held, unknown))
+}
+
+/// The current time in Unix seconds
+#[must_use]
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| {
+            i64::try_from(elapsed.as_secs()).unwrap_or(i64::MAX)
+        })
+}
+
+/// Holds back the `count` candidates with the newest last-commit times, as a safety net for
+/// recent experiments. Branches without a known commit time are treated as oldest.
+#[must_use]
+pub fn hold_most_recent<S: BuildHasher>(
+    candidates: Vec<BranchLine>,
+    count: usize,
+    commit_times: &HashMap<String, i64, S>,
+) -> (Vec<BranchLine>, Vec<Held>) {
+    let mut by_recency: Vec<&BranchLine> = candidates.iter().collect();
+    by_recency.sort_by_key(|branch| {
+        std::cmp::Reverse(commit_times.get(&branch.name).copied().unwrap_or(i64::MIN))
+    });
+    let recent: Vec<String> = by_recency
+        .into_iter()
+        .take(count)
+        .map(|branch| branch.name.clone())
+        .collect();
+
+    let (held, deletable): (Vec<BranchLine>, Vec<BranchLine>) = candidates
+        .into_iter()
+        .partition(|branch| recent.contains(&branch.name));
+
+    let held = held
+        .into_iter()
+        .map(|branch| Held {
+            kind: HoldKind::KeepRecent,
+            reason: format!(
+                "one of the {count} most recently committed gone branches (--keep-recent)"
+            ),
+            branch,
+        })
+        .collect();
+
+    (deletable, held)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_branch_lines;
+
+    #[test]
+    fn test_hold_case_collisions() {
+        let all = parse_branch_lines(
+            r"
+  Feature-X    abc1234 [origin/Feature-X] Kept, still on the remote
+  feature-x    def5678 [origin/feature-x: gone] Gone
+  feature-y    ghi9012 [origin/feature-y: gone] Gone, no collision
+* main         jkl3456 [origin/main] Latest
+",
+        );
+        let gone: Vec<BranchLine> = all
+            .iter()
+            .filter(|b| b.is_deletable_gone())
+            .cloned()
+            .collect();
+
+        let (deletable, held) = hold_case_collisions(gone, &all);
+
+        assert_eq!(deletable.len(), 1);
+        assert_eq!(deletable[0].name, "feature-y");
+        assert_eq!(held.len(), 1);
+        assert_eq!(held[0].branch.name, "feature-x");
+        assert!(held[0].reason.contains("Feature-X"));
+    }
+
+    #[test]
+    fn test_hold_case_collisions_between_gone_branches_is_allowed() {
+        let all = parse_branch_lines(
+            r"
+  Feature-X    abc1234 [origin/Feature-X: gone] Gone
+  feature-x    def5678 [origin/feature-x: gone] Also gone
+",
+        );
+        let gone = all.clone();
+
+        let (deletable, held) = hold_case_collisions(gone, &all);
+
+        assert_eq!(deletable.len(), 2);
+        assert!(held.is_empty());
+    }
+
+    #[test]
+    fn test_hold_most_recent() {
+        let candidates = parse_branch_lines(
+            r"
+  old          abc1234 [origin/old: gone] Old
+  newest       def5678 [origin/newest: gone] Newest
+  unknown      aaa1111 [origin/unknown: gone] No commit time
+  newer        ghi9012 [origin/newer: gone] Newer
+",
+        );
+        let commit_times = HashMap::from([
+            ("old".to_string(), 1_000),
+            ("newest".to_string(), 3_000),
+            ("newer".to_string(), 2_000),
+        ]);
+
+        let (deletable, held) = hold_most_recent(candidates, 2, &commit_times);
+
+        let held: Vec<&str> = held.iter().map(|h| h.branch.name.as_str()).collect();
+        let deletable: Vec<&str> = deletable.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(held, ["newest", "newer"]);
+        assert_eq!(deletable, ["old", "unknown"]);
+    }
+
+    #[test]
+    fn test_hold_most_recent_more_than_available() {
+        let candidates = parse_branch_lines("  only    abc1234 [origin/only: gone] Only\n");
+        let (deletable, held) = hold_most_recent(candidates, 5, &HashMap::new());
+        assert!(deletable.is_empty());
+        assert_eq!(held.len(), 1);
+    }
+
+    #[test]
+    fn test_hold_tagged_ancestors() {
+        let candidates = parse_branch_lines(
+            r"
+  released     abc1234 [origin/released: gone] Shipped in v1.0
+  unreleased   def5678 [origin/unreleased: gone] Never tagged
+  hotfix       ghi9012 [origin/hotfix: gone] In two releases
+",
+        );
+
+        let (deletable, held) = hold_tagged_ancestors(candidates, |branch| {
+            Ok(match branch {
+                "released" => vec!["v1.0".to_string()],
+                "hotfix" => vec!["v1.0.1".to_string(), "v1.1".to_string()],
+                _ => Vec::new(),
+            })
+        })
+        .unwrap();
+
+        assert_eq!(deletable.len(), 1);
+        assert_eq!(deletable[0].name, "unreleased");
+        assert_eq!(
+            held.iter().map(|h| h.reason.as_str()).collect::<Vec<_>>(),
+            [
+                "tip is an ancestor of tag v1.0 (--protect-tagged-ancestors)",
+                "tip is an ancestor of tag v1.0.1 and 1 other tag(s) (--protect-tagged-ancestors)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hold_recent_activity() {
+        let gone = parse_branch_lines(
+            r"
+  amended      abc1234 [origin/amended: gone] Old commit, just rebased
+  stale        def5678 [origin/stale: gone] Untouched for days
+  no-reflog    ghi9012 [origin/no-reflog: gone] Reflog disabled
+",
+        );
+        let now = 1_700_000_000;
+
+        let (deletable, held) =
+            hold_recent_activity(gone, Duration::from_hours(1), now, |branch| {
+                Ok(match branch {
+                    "amended" => Some(now - 600),
+                    "stale" => Some(now - 86_400),
+                    _ => None,
+                })
+            })
+            .unwrap();
+
+        let names: Vec<&str> = deletable.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, ["stale", "no-reflog"]);
+        assert_eq!(held.len(), 1);
+        assert_eq!(held[0].branch.name, "amended");
+        assert!(held[0].reason.contains("10m ago"));
+    }
+
+    #[test]
+    fn test_hold_gone_recently() {
+        let gone = parse_branch_lines(
+            r"
+  just-gone    abc1234 [origin/just-gone: gone] Deleted on the remote an hour ago
+  long-gone    def5678 [origin/long-gone: gone] Deleted on the remote last month
+  pruned       ghi9012 [origin/pruned: gone] Reflog went with the ref
+",
+        );
+        let now = 1_700_000_000;
+
+        let (deletable, held, unknown) =
+            hold_gone_recently(gone, Duration::from_hours(24 * 7), now, |branch| {
+                Ok(match branch.name.as_str() {
+                    "just-gone" => Some(now - 3600),
+                    "long-gone" => Some(now - 30 * 86_400),
+                    _ => None,
+                })
+            })
+            .unwrap();
+
+        let names: Vec<&str> = deletable.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, ["long-gone", "pruned"]);
+        assert_eq!(held.len(), 1);
+        assert_eq!(held[0].branch.name, "just-gone");
+        assert_eq!(held[0].kind, HoldKind::GoneRecently);
+        assert!(held[0].reason.contains("1h ago"));
+        assert_eq!(unknown.len(), 1);
+        assert_eq!(unknown[0].name, "pruned");
+    }
+
+    #[test]
+    fn test_since_parses_dates_durations_and_commits() {
+        assert_eq!(Since::parse("2023-11-14"), Since::Time(1_699_920_000));
+        assert_eq!(
+            Since::parse("2023-11-14T22:13:20Z"),
+            Since::Time(1_700_000_000)
+        );
+        assert_eq!(
+            Since::parse("origin/release-1"),
+            Since::Commit("origin/release-1".to_string())
+        );
+        let Since::Time(time) = Since::parse("30days") else {
+            panic!("a duration should parse as a time");
+        };
+        assert!(unix_now() - time >= 30 * 86_400);
+        assert_eq!(
+            Since::Time(1_699_920_000).to_string(),
+            "2023-11-14T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn test_hold_kept_by_rules() {
+        let gone = parse_branch_lines(
+            r"
+  release/1.0  abc1234 [origin/release/1.0: gone] Release
+  feature-1    def5678 [origin/feature-1: gone] Work
+",
+        );
+        let rules = Rules::parse("release/* keep\n* archive\n").unwrap();
+        let (deletable, held) = hold_kept_by_rules(gone, &rules);
+        assert_eq!(deletable[0].name, "feature-1");
+        assert_eq!(held[0].branch.name, "release/1.0");
+        assert_eq!(held[0].kind, HoldKind::Rule);
+        assert_eq!(held[0].reason, "kept by rule `release/* keep`");
+    }
+
+    #[test]
+    fn test_hold_open_prs_asks_once_per_branch() {
+        let mut gone = parse_branch_lines(
+            r"
+  in-review    abc1234 [origin/in-review: gone] Still open
+  merged       def5678 [origin/merged: gone] Done
+",
+        );
+        gone.extend(gone.clone());
+        let mut asked = Vec::new();
+        let (deletable, held) = hold_open_prs(gone, |branch| {
+            asked.push(branch.to_string());
+            Ok(branch == "in-review")
+        })
+        .unwrap();
+        assert_eq!(asked, ["in-review", "merged"]);
+        assert_eq!(deletable.len(), 2);
+        assert!(deletable.iter().all(|branch| branch.name == "merged"));
+        assert_eq!(held.len(), 2);
+        assert_eq!(held[0].kind, HoldKind::OpenPr);
+        assert_eq!(
+            held[0].reason,
+            "has an open pull request (--open-pr-command)"
+        );
+    }
+
+    #[test]
+    fn test_hold_unique_commits() {
+        let gone = parse_branch_lines(
+            r"
+  rebased      abc1234 [origin/rebased: gone] Also on another branch
+  local-only   def5678 [origin/local-only: gone] Nowhere else
+",
+        );
+        let (deletable, held) =
+            hold_unique_commits(gone, |branch| Ok(usize::from(branch == "local-only") * 2))
+                .unwrap();
+        assert_eq!(deletable.len(), 1);
+        assert_eq!(deletable[0].name, "rebased");
+        assert_eq!(held.len(), 1);
+        assert_eq!(held[0].kind, HoldKind::UniqueCommits);
+        assert_eq!(held[0].reason, "2 commit(s) on no other ref (--auto-safe)");
+    }
+
+    #[test]
+    fn test_hold_described() {
+        let gone = parse_branch_lines(
+            r"
+  spike        abc1234 [origin/spike: gone] Deliberate
+  quick-fix    def5678 [origin/quick-fix: gone] Throwaway
+",
+        );
+        let descriptions = HashMap::from([(
+            "spike".to_string(),
+            "Prototype for the new parser\n\nKeep until the RFC lands.".to_string(),
+        )]);
+        assert_eq!(
+            format_described(&gone, &descriptions),
+            "\nGone branches with a description: 1\n  - spike: Prototype for the new parser\n"
+        );
+
+        let (deletable, held) = hold_described(gone, &descriptions);
+        assert_eq!(deletable.len(), 1);
+        assert_eq!(deletable[0].name, "quick-fix");
+        assert_eq!(held[0].kind, HoldKind::Described);
+        assert_eq!(
+            held[0].reason,
+            "has a description (--protect-described): Prototype for the new parser"
+        );
+    }
+
+    #[test]
+    fn test_hold_unpushed() {
+        let gone = parse_branch_lines(
+            r"
+  pushed       abc1234 [origin/pushed: gone] Still on upstream/pushed
+  squashed     def5678 [origin/squashed: gone] Merged, never pushed elsewhere
+",
+        );
+        let (deletable, held) =
+            hold_unpushed(gone, |branch| Ok(usize::from(branch == "squashed") * 3)).unwrap();
+        assert_eq!(deletable.len(), 1);
+        assert_eq!(deletable[0].name, "pushed");
+        assert_eq!(held.len(), 1);
+        assert_eq!(held[0].kind, HoldKind::Unpushed);
+        assert_eq!(
+            held[0].reason,
+            "3 commit(s) on no remote (--protect-unpushed)"
+        );
+    }
+
+    #[test]
+    fn test_hold_ahead() {
+        let candidates = parse_branch_lines(
+            r"
+  ahead-gone   abc1234 [origin/ahead-gone: ahead 2, gone] Local commits on top
+  plain-gone   def5678 [origin/plain-gone: gone] Nothing on top
+  behind       ghi9012 [origin/behind: behind 3] Listed by a command
+  mixed        jkl3456 [origin/mixed: ahead 1, behind 2] Listed by a command
+",
+        );
+        let (deletable, held) = hold_ahead(candidates);
+        assert_eq!(
+            deletable
+                .iter()
+                .map(|branch| branch.name.as_str())
+                .collect::<Vec<_>>(),
+            ["plain-gone", "behind"]
+        );
+        assert_eq!(
+            held.iter()
+                .map(|held| (held.branch.name.as_str(), held.reason.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    "ahead-gone",
+                    "ahead of its upstream by 2 commit(s) (--protect-ahead)"
+                ),
+                (
+                    "mixed",
+                    "ahead of its upstream by 1 commit(s) (--protect-ahead)"
+                )
+            ]
+        );
+        assert!(held.iter().all(|held| held.kind == HoldKind::Ahead));
+    }
+
+    #[test]
+    fn test_hold_branched_before() {
+        let gone = parse_branch_lines(
+            r"
+  new-feature  abc1234 [origin/new-feature: gone] Recent work
+  ancient      def5678 [origin/ancient: gone] Years old
+",
+        );
+        let since = Since::Commit("v2.0".to_string());
+        let (deletable, held) =
+            hold_branched_before(gone, &since, |branch| Ok(branch == "new-feature")).unwrap();
+        assert_eq!(deletable[0].name, "new-feature");
+        assert_eq!(held[0].branch.name, "ancient");
+        assert_eq!(held[0].kind, HoldKind::BranchedBefore);
+        assert_eq!(held[0].reason, "too old: branched before --since v2.0");
+    }
+
+    #[test]
+    fn test_hold_existing_elsewhere() {
+        let gone = parse_branch_lines(
+            r"
+  feature-x    abc1234 [origin/feature-x: gone] Still on upstream
+  feature-y    def5678 [origin/feature-y: gone] Gone everywhere
+",
+        );
+        let tracking: Vec<(String, String)> = [
+            ("origin", "main"),
+            ("upstream", "feature-x"),
+            ("upstream", "main"),
+        ]
+        .map(|(remote, branch)| (remote.to_string(), branch.to_string()))
+        .to_vec();
+
+        assert_eq!(other_remotes_with(&gone[0], &tracking), ["upstream"]);
+
+        let (deletable, held) = hold_existing_elsewhere(gone, &tracking);
+        assert_eq!(deletable.len(), 1);
+        assert_eq!(deletable[0].name, "feature-y");
+        assert_eq!(held[0].branch.name, "feature-x");
+        assert!(held[0].reason.contains("upstream (upstream/feature-x)"));
+    }
+
+    #[test]
+    fn test_hold_protected() {
+        let gone = parse_branch_lines(
+            r"
+  release/1.x  abc1234 [origin/release/1.x: gone] Release line
+  feature-1    def5678 [origin/feature-1: gone] Feature
+",
+        );
+        let protected = ["main", "release/*"].map(|glob| glob.parse().unwrap());
+
+        let (deletable, held) = hold_protected(gone, &protected);
+        assert_eq!(deletable.len(), 1);
+        assert_eq!(deletable[0].name, "feature-1");
+        assert_eq!(held[0].branch.name, "release/1.x");
+        assert_eq!(held[0].reason, "matches protected pattern release/*");
+    }
+
+    #[test]
+    fn test_hold_symbolic_refs() {
+        let gone = parse_branch_lines(
+            r"
+  alias        abc1234 [origin/alias: gone] Points at feature-1
+  feature-1    abc1234 [origin/feature-1: gone] Feature
+",
+        );
+        let symbolic: HashMap<String, String> =
+            [("alias".to_string(), "refs/heads/feature-1".to_string())].into();
+
+        let (deletable, held) = hold_symbolic_refs(gone, &symbolic);
+        assert_eq!(deletable.len(), 1);
+        assert_eq!(deletable[0].name, "feature-1");
+        assert_eq!(held[0].branch.name, "alias");
+        assert_eq!(held[0].reason, "symbolic ref to refs/heads/feature-1");
+    }
+
+    #[test]
+    fn test_hold_in_progress() {
+        let gone = parse_branch_lines(
+            r"
+  topic        abc1234 [origin/topic: gone] Being rebased
+  feature-1    def5678 [origin/feature-1: gone] Feature
+",
+        );
+        let operations = [InProgress {
+            operation: "rebase",
+            branches: vec!["topic".to_string()],
+            commits: Vec::new(),
+        }];
+
+        let (deletable, held) = hold_in_progress(gone, &operations);
+        assert_eq!(deletable.len(), 1);
+        assert_eq!(deletable[0].name, "feature-1");
+        assert_eq!(held[0].branch.name, "topic");
+        assert_eq!(held[0].kind, HoldKind::InProgress);
+        assert_eq!(held[0].reason, "involved in the rebase in progress");
+    }
+
+    #[test]
+    fn test_hold_last_merged() {
+        let gone = parse_branch_lines(
+            r"
+  older        abc1234 [origin/older: gone] Merged first
+  newer        def5678 [origin/newer: gone] Merged last
+  unmerged     1234567 [origin/unmerged: gone] Never merged
+",
+        );
+        let merged_parents = [
+            "def5678def5678def5678def5678def5678def56".to_string(),
+            "abc1234abc1234abc1234abc1234abc1234abc12".to_string(),
+        ];
+
+        let (deletable, held) = hold_last_merged(gone.clone(), &merged_parents, "origin/HEAD");
+        assert_eq!(deletable.len(), 2);
+        assert_eq!(deletable[0].name, "older");
+        assert_eq!(deletable[1].name, "unmerged");
+        assert_eq!(held.len(), 1);
+        assert_eq!(held[0].branch.name, "newer");
+        assert_eq!(
+            held[0].reason,
+            "most recently merged into origin/HEAD (--keep-last-merged)"
+        );
+
+        let (deletable, held) = hold_last_merged(gone, &[], "origin/HEAD");
+        assert_eq!(deletable.len(), 3);
+        assert!(held.is_empty());
+    }
+
+    #[test]
+    fn test_hold_outside_upstream_prefix() {
+        let gone = parse_branch_lines(
+            r"
+  feature      abc1234 [origin/team-a/feature: gone] In scope
+  other        def5678 [origin/team-b/other: gone] Another team
+  team-a-ish   1234567 [origin/team-a-ish: gone] Not under the prefix's slash
+",
+        );
+        let (deletable, held) = hold_outside_upstream_prefix(gone, "origin/team-a/");
+        assert_eq!(deletable.len(), 1);
+        assert_eq!(deletable[0].name, "feature");
+        assert_eq!(held.len(), 2);
+        assert_eq!(
+            held[0].reason,
+            "upstream origin/team-b/other is outside --match-upstream-prefix origin/team-a/"
+        );
+        assert_eq!(held[1].branch.name, "team-a-ish");
+    }
+
+    #[test]
+    fn test_hold_over_limit() {
+        let gone = parse_branch_lines(
+            r"
+  a            abc1234 [origin/a: gone] A
+  b            def5678 [origin/b: gone] B
+  c            1234567 [origin/c: gone] C
+",
+        );
+        let (deletable, held) = hold_over_limit(gone.clone(), 2);
+        assert_eq!(deletable.len(), 2);
+        assert_eq!(held.len(), 1);
+        assert_eq!(held[0].branch.name, "c");
+        assert_eq!(held[0].reason, "beyond --limit 2");
+
+        let (deletable, held) = hold_over_limit(gone, 5);
+        assert_eq!(deletable.len(), 3);
+        assert!(held.is_empty());
+    }
+
+    #[test]
+    fn test_hold_unmerged() {
+        let gone = parse_branch_lines(
+            r"
+  merged       abc1234 [origin/merged: gone] Squashed upstream
+  unmerged     def5678 [origin/unmerged: gone] Local work
+",
+        );
+        let (deletable, held) =
+            hold_unmerged(gone, |branch| Ok(if branch == "unmerged" { 3 } else { 0 })).unwrap();
+        assert_eq!(deletable.len(), 1);
+        assert_eq!(deletable[0].name, "merged");
+        assert_eq!(held.len(), 1);
+        assert_eq!(held[0].branch.name, "unmerged");
+        assert_eq!(
+            held[0].reason,
+            "3 unmerged commit(s) (--prune-merged-remote-tracking)"
+        );
+    }
+
+    #[test]
+    fn test_hold_unlisted_risky() {
+        let gone = parse_branch_lines(
+            r"
+  squashed     abc1234 [origin/squashed: gone] Squash-merged upstream
+  merged       def5678 [origin/merged: gone] Merged
+  unmerged     1234567 [origin/unmerged: gone] Unmerged
+",
+        );
+        let assessment = |name: &str, unique_commits, merged_upstream| Assessment {
+            name: name.to_string(),
+            unique_commits,
+            merged_into: None,
+            merged_upstream,
+        };
+        let assessments = [
+            assessment("squashed", 2, true),
+            assessment("merged", 0, false),
+            assessment("unmerged", 1, false),
+        ];
+
+        let (deletable, held) = hold_unlisted_risky(gone, &assessments);
+        assert_eq!(deletable.len(), 2);
+        assert_eq!(held.len(), 1);
+        assert_eq!(held[0].branch.name, "unmerged");
+        assert_eq!(
+            held[0].reason,
+            "1 unmerged commit(s) and not in --merged-prs-file"
+        );
+    }
+}
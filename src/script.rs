@@ -0,0 +1,114 @@
+//! Shell scripts of the git commands a cleanup would run, for reviewing and running by hand.
+//!
+//! Instead of deleting anything, `--emit-script` turns a dry run's report into the exact
+//! `git branch -D` (or `-d`, rename, and `git push --delete`) commands a real run would use.
+
+use crate::Options;
+use crate::git::archive_name;
+use crate::report::{Action, Report};
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// The git commands a real run would execute for the branches `report` would delete or
+/// archive, each as its arguments after `git`. With `push_remote`, deleted branches are also
+/// deleted there, as with `--delete-remote`.
+///
+/// # Errors
+///
+/// Fails if git can't be run to pick archive names.
+pub fn script_commands(
+    report: &Report,
+    options: &Options,
+    push_remote: Option<&str>,
+) -> Result<Vec<Vec<String>>> {
+    let mut commands = Vec::new();
+    for branch in &report.branches {
+        match branch.action {
+            Action::WouldDelete => {
+                let flag = if options.force_deletes(&branch.name) {
+                    "-D"
+                } else {
+                    "-d"
+                };
+                commands.push(args(&["branch", flag, &branch.name]));
+            }
+            Action::WouldArchive => {
+                let archived = archive_name(&branch.name)?;
+                commands.push(args(&["branch", "-m", &branch.name, &archived]));
+                commands.push(args(&["branch", "--unset-upstream", &archived]));
+            }
+            _ => {}
+        }
+    }
+    if let Some(remote) = push_remote {
+        for branch in &report.branches {
+            if branch.action == Action::WouldDelete {
+                commands.push(args(&["push", remote, "--delete", &branch.name]));
+            }
+        }
+    }
+    Ok(commands)
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(ToString::to_string).collect()
+}
+
+/// Renders `commands` (see [`script_commands`]) as a POSIX shell script that stops at the first
+/// failing command, with every argument quoted
+#[must_use]
+pub fn format_script(commands: &[Vec<String>]) -> String {
+    let mut script = String::from(
+        "#!/bin/sh\n# Generated by git-clean-gone; review before running.\nset -e\n\n",
+    );
+    if commands.is_empty() {
+        script.push_str("# Nothing to do\n");
+    }
+    for command in commands {
+        let _ = writeln!(script, "git {}", shell_words::join(command));
+    }
+    script
+}
+
+/// Writes `script` to `path`, executable on Unix
+///
+/// # Errors
+///
+/// Fails if the file can't be written or made executable.
+pub fn write_script(path: &Path, script: &str) -> Result<()> {
+    fs::write(path, script)
+        .with_context(|| format!("Failed to write script to {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to make {} executable", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_script_quotes_arguments() {
+        let script = format_script(&[
+            args(&["branch", "-D", "feature-1"]),
+            args(&["push", "origin", "--delete", "it's"]),
+        ]);
+        assert_eq!(
+            script,
+            "#!/bin/sh\n# Generated by git-clean-gone; review before running.\nset -e\n\n\
+             git branch -D feature-1\n\
+             git push origin --delete 'it'\\''s'\n"
+        );
+    }
+
+    #[test]
+    fn test_format_script_without_commands() {
+        assert!(format_script(&[]).ends_with("set -e\n\n# Nothing to do\n"));
+    }
+}
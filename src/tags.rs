@@ -0,0 +1,143 @@
+//! Cleanup of local tags that no longer exist on a remote.
+//!
+//! Unlike branches, tags have no upstream tracking, so the remote's tag list from
+//! `git ls-remote --tags` is treated as the authoritative set.
+
+use crate::git::git_command;
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::process::Stdio;
+
+/// Lists the local tags
+///
+/// # Errors
+///
+/// Fails if `git tag` can't be run.
+pub fn list_local_tags() -> Result<Vec<String>> {
+    let output = git_command()
+        .args(["tag", "--list"])
+        .output()
+        .context("Failed to execute git tag --list")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git tag --list failed");
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(ToString::to_string)
+        .collect())
+}
+
+/// Lists the tags present on `remote` via `git ls-remote --tags`
+///
+/// # Errors
+///
+/// Fails if the remote can't be reached.
+pub fn list_remote_tags(remote: &str) -> Result<HashSet<String>> {
+    let output = git_command()
+        .args(["ls-remote", "--tags", remote])
+        .stderr(Stdio::inherit())
+        .output()
+        .context("Failed to execute git ls-remote --tags")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git ls-remote --tags {remote} failed");
+    }
+
+    Ok(parse_ls_remote_tags(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Parses `git ls-remote --tags` output into tag names.
+///
+/// Peeled entries (`refs/tags/v1.0^{}`) are folded into their tag.
+#[must_use]
+pub fn parse_ls_remote_tags(ls_remote_output: &str) -> HashSet<String> {
+    ls_remote_output
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .filter_map(|refname| refname.strip_prefix("refs/tags/"))
+        .map(|tag| tag.strip_suffix("^{}").unwrap_or(tag).to_string())
+        .collect()
+}
+
+/// Finds local tags that don't exist on `remote`, in the order git lists them
+///
+/// # Errors
+///
+/// Fails if the local or remote tags can't be listed.
+pub fn find_gone_tags(remote: &str) -> Result<Vec<String>> {
+    let remote_tags = list_remote_tags(remote)?;
+    Ok(list_local_tags()?
+        .into_iter()
+        .filter(|tag| !remote_tags.contains(tag))
+        .collect())
+}
+
+/// Lists the tags that contain `rev`, i.e. that `rev` is an ancestor of (`git tag --contains`)
+///
+/// # Errors
+///
+/// Fails if `git tag --contains` can't be run or `rev` doesn't resolve.
+pub fn tags_containing(rev: &str) -> Result<Vec<String>> {
+    let output = git_command()
+        .args(["tag", "--contains", rev])
+        .output()
+        .context("Failed to execute git tag --contains")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git tag --contains {rev} failed");
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+/// Deletes a local tag using `git tag -d`
+///
+/// # Errors
+///
+/// Fails with git's error message if the tag couldn't be deleted.
+pub fn delete_tag(tag: &str) -> Result<()> {
+    let output = git_command()
+        .args(["tag", "-d", tag])
+        .stdout(Stdio::inherit())
+        .output()
+        .context("Failed to execute git tag -d")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git tag -d {tag} failed: {}", stderr.trim());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ls_remote_tags() {
+        let output = "\
+abc1234abc1234abc1234abc1234abc1234abc12\trefs/tags/v1.0
+def5678def5678def5678def5678def5678def56\trefs/tags/v1.0^{}
+0123456012345601234560123456012345601234\trefs/tags/release/2024-01
+";
+        let tags = parse_ls_remote_tags(output);
+        assert_eq!(tags.len(), 2);
+        assert!(tags.contains("v1.0"));
+        assert!(tags.contains("release/2024-01"));
+    }
+
+    #[test]
+    fn test_parse_ls_remote_tags_empty() {
+        assert!(parse_ls_remote_tags("").is_empty());
+    }
+}
@@ -0,0 +1,50 @@
+//! The one-time tip on how to run the tool as `git clean-gone`, shown after the first run
+//! unless `--no-tips` is given.
+//!
+//! Whether it was shown is recorded by a marker file in the user's config directory (see
+//! [`crate::pattern::user_config_dir`]), so it's shown once per user rather than per repository.
+
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+
+/// The marker's path under the user's config directory
+pub const TIPS_MARKER: &str = "git-clean-gone/tips-shown";
+
+/// The tip itself, kept to one line
+pub const ALIAS_TIP: &str = "tip: as it's on your PATH, this also runs as `git clean-gone`; \
+                             for a shorter name, `git config --global alias.cg clean-gone` \
+                             (--no-tips hides this)";
+
+/// Records that the tip was shown, returning whether this is the first time. Returns `false`
+/// if the marker can't be created either, so an unwritable config directory never makes the
+/// tip show on every run.
+#[must_use]
+pub fn claim(marker: &Path) -> bool {
+    if let Some(dir) = marker.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    // `create_new` makes the check and the record one step, so concurrent runs show it once
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(marker)
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claim_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join(TIPS_MARKER);
+        assert!(claim(&marker));
+        assert!(!claim(&marker));
+
+        // A file where the config directory should be
+        let blocked = dir.path().join("blocked");
+        fs::write(&blocked, "").unwrap();
+        assert!(!claim(&blocked.join(TIPS_MARKER)));
+    }
+}
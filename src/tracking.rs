@@ -0,0 +1,423 @@
+//! Cleanup of remote-tracking refs whose branch no longer exists on the remote, and of fetched
+//! pull request refs whose pull request is gone.
+//!
+//! `git fetch --prune` only prunes refs matching the remote's fetch refspec, so tracking refs
+//! created by hand (or under an old refspec) can linger. The remote's own ref list from
+//! `git ls-remote` is treated as the authoritative set.
+
+use crate::git::git_command;
+use crate::parallel;
+use crate::parse::{BranchLine, Divergence};
+use crate::report::group_digits;
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::hash::BuildHasher;
+use std::process::Stdio;
+
+/// Lists the configured remotes
+///
+/// # Errors
+///
+/// Fails if `git remote` can't be run.
+pub fn list_remotes() -> Result<Vec<String>> {
+    let output = git_command()
+        .arg("remote")
+        .output()
+        .context("Failed to execute git remote")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git remote failed");
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(ToString::to_string)
+        .collect())
+}
+
+/// Lists the branch names of `remote`'s tracking refs (`refs/remotes/<remote>/*`), except `HEAD`
+///
+/// # Errors
+///
+/// Fails if `git for-each-ref` can't be run.
+pub fn list_tracking_branches(remote: &str) -> Result<Vec<String>> {
+    let prefix = format!("refs/remotes/{remote}/");
+    let output = git_command()
+        .args(["for-each-ref", "--format=%(refname)", &prefix])
+        .output()
+        .context("Failed to execute git for-each-ref")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git for-each-ref failed");
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|refname| refname.strip_prefix(&prefix))
+        .filter(|branch| *branch != "HEAD")
+        .map(ToString::to_string)
+        .collect())
+}
+
+/// Lists every configured remote's tracking branches as `(remote, branch)` pairs
+///
+/// # Errors
+///
+/// Fails if the remotes or their tracking refs can't be listed.
+pub fn list_all_tracking_branches() -> Result<Vec<(String, String)>> {
+    let mut all = Vec::new();
+    for remote in list_remotes()? {
+        for branch in list_tracking_branches(&remote)? {
+            all.push((remote.clone(), branch));
+        }
+    }
+    Ok(all)
+}
+
+/// Lists the branches present on `remote` via `git ls-remote --heads`
+///
+/// # Errors
+///
+/// Fails if the remote can't be reached.
+pub fn list_remote_heads(remote: &str) -> Result<HashSet<String>> {
+    let output = git_command()
+        .args(["ls-remote", "--heads", remote])
+        .stderr(Stdio::inherit())
+        .output()
+        .context("Failed to execute git ls-remote --heads")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git ls-remote --heads {remote} failed");
+    }
+
+    Ok(parse_ls_remote_heads(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Parses `git ls-remote --heads` output into branch names
+#[must_use]
+pub fn parse_ls_remote_heads(ls_remote_output: &str) -> HashSet<String> {
+    ls_remote_output
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .filter_map(|refname| refname.strip_prefix("refs/heads/"))
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Finds the tracking refs of every configured remote whose branch is gone from that remote,
+/// as full ref names (e.g. `refs/remotes/origin/feature-1`). The remotes are asked in
+/// parallel (see [`crate::parallel`]).
+///
+/// # Errors
+///
+/// Fails if a remote can't be reached or its tracking refs can't be listed.
+pub fn find_stale_tracking_refs() -> Result<Vec<String>> {
+    let stale = parallel::map(&list_remotes()?, |remote| -> Result<Vec<String>> {
+        let tracking = list_tracking_branches(remote)?;
+        if tracking.is_empty() {
+            return Ok(Vec::new());
+        }
+        let heads = list_remote_heads(remote)?;
+        Ok(tracking
+            .into_iter()
+            .filter(|branch| !heads.contains(branch))
+            .map(|branch| format!("refs/remotes/{remote}/{branch}"))
+            .collect())
+    });
+    Ok(stale.into_iter().collect::<Result<Vec<_>>>()?.concat())
+}
+
+/// Lists the full names of the local refs under `namespace` (e.g. `refs/remotes/origin/pr/`)
+///
+/// # Errors
+///
+/// Fails if `git for-each-ref` can't be run.
+pub fn list_refs_under(namespace: &str) -> Result<Vec<String>> {
+    let output = git_command()
+        .args(["for-each-ref", "--format=%(refname)", namespace])
+        .output()
+        .context("Failed to execute git for-each-ref")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git for-each-ref failed");
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(ToString::to_string)
+        .collect())
+}
+
+/// Lists the pull/merge request numbers that still have refs on `remote`
+///
+/// # Errors
+///
+/// Fails if the remote can't be reached.
+pub fn list_remote_pr_numbers(remote: &str) -> Result<HashSet<String>> {
+    let output = git_command()
+        .args(["ls-remote", remote, "refs/pull/*", "refs/merge-requests/*"])
+        .stderr(Stdio::inherit())
+        .output()
+        .context("Failed to execute git ls-remote")?;
+
+    if !output.status.success() {
+        anyhow::bail!("git ls-remote {remote} failed");
+    }
+
+    Ok(parse_ls_remote_pr_numbers(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Parses GitHub (`refs/pull/<N>/...`) and GitLab (`refs/merge-requests/<N>/...`) refs in
+/// `git ls-remote` output into their request numbers
+#[must_use]
+pub fn parse_ls_remote_pr_numbers(ls_remote_output: &str) -> HashSet<String> {
+    ls_remote_output
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .filter_map(|refname| {
+            refname
+                .strip_prefix("refs/pull/")
+                .or_else(|| refname.strip_prefix("refs/merge-requests/"))
+        })
+        .filter_map(pr_number)
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// The leading request number of a ref path such as `123/head`, if it has one
+fn pr_number(path: &str) -> Option<&str> {
+    let number = path.split('/').next()?;
+    (!number.is_empty() && number.bytes().all(|b| b.is_ascii_digit())).then_some(number)
+}
+
+/// Picks the refs under `namespace` whose request number isn't among `open_numbers`.
+///
+/// The number is the first path component after the namespace, so both `pr/123` and
+/// `pr/123/head` style layouts work. Refs without a number are left alone.
+#[must_use]
+pub fn stale_pr_refs<S: BuildHasher>(
+    local_refs: &[String],
+    namespace: &str,
+    open_numbers: &HashSet<String, S>,
+) -> Vec<String> {
+    local_refs
+        .iter()
+        .filter(|refname| {
+            refname
+                .strip_prefix(namespace)
+                .and_then(pr_number)
+                .is_some_and(|number| !open_numbers.contains(number))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Finds the refs under `namespace` whose pull/merge request ref is gone from `remote`
+///
+/// # Errors
+///
+/// Fails if `namespace` isn't under `refs/`, or if the refs can't be listed.
+pub fn find_stale_pr_refs(remote: &str, namespace: &str) -> Result<Vec<String>> {
+    if !namespace.starts_with("refs/") {
+        anyhow::bail!("PR ref namespace must start with refs/, got {namespace}");
+    }
+    let namespace = format!("{}/", namespace.trim_end_matches('/'));
+    let local_refs = list_refs_under(&namespace)?;
+    if local_refs.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(stale_pr_refs(
+        &local_refs,
+        &namespace,
+        &list_remote_pr_numbers(remote)?,
+    ))
+}
+
+/// Deletes a ref with `git update-ref -d`
+///
+/// # Errors
+///
+/// Fails with git's error message if the ref couldn't be deleted.
+pub fn delete_ref(refname: &str) -> Result<()> {
+    let output = git_command()
+        .args(["update-ref", "-d", refname])
+        .output()
+        .context("Failed to execute git update-ref -d")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git update-ref -d {refname} failed: {}", stderr.trim());
+    }
+
+    Ok(())
+}
+
+/// What this repository still knows about a gone branch's upstream, for `--diff-upstream`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpstreamState {
+    pub branch: String,
+    pub upstream: String,
+    /// Commits on the upstream that the branch doesn't have, from the remote-tracking ref if it
+    /// lingers, or else from the tracking info `git branch -vv` printed
+    pub behind: Option<usize>,
+    /// The upstream's tip as last fetched: the lingering remote-tracking ref, or else the newest
+    /// entry of its reflog
+    pub last_known_tip: Option<String>,
+}
+
+/// Looks up what's known about `branch`'s upstream, or `None` if it has none
+///
+/// # Errors
+///
+/// Fails if git can't be run.
+pub fn upstream_state(branch: &BranchLine) -> Result<Option<UpstreamState>> {
+    let Some(upstream) = &branch.upstream else {
+        return Ok(None);
+    };
+    let tracking_ref = format!("refs/remotes/{upstream}");
+    let lingering = short_sha(&["rev-parse", "--verify", "-q", "--short", &tracking_ref])?;
+    let (behind, last_known_tip) = match lingering {
+        Some(tip) => {
+            let range = format!("refs/heads/{}..{tracking_ref}", branch.name);
+            (behind_count(&range)?, Some(tip))
+        }
+        None => (
+            branch
+                .divergence()
+                .filter(Divergence::has_counts)
+                .map(|d| d.behind as usize),
+            short_sha(&["reflog", "show", "--format=%h", "-n1", &tracking_ref])?,
+        ),
+    };
+    Ok(Some(UpstreamState {
+        branch: branch.name.clone(),
+        upstream: upstream.clone(),
+        behind,
+        last_known_tip,
+    }))
+}
+
+/// Runs a git command printing one abbreviated SHA, or `None` if it fails or prints nothing
+fn short_sha(args: &[&str]) -> Result<Option<String>> {
+    let output = git_command()
+        .args(args)
+        .stderr(Stdio::null())
+        .output()
+        .with_context(|| format!("Failed to execute git {}", args[0]))?;
+    let sha = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok((output.status.success() && !sha.is_empty()).then_some(sha))
+}
+
+/// Counts the commits in `range`, or `None` if git can't resolve it
+fn behind_count(range: &str) -> Result<Option<usize>> {
+    let output = git_command()
+        .args(["rev-list", "--count", range])
+        .stderr(Stdio::null())
+        .output()
+        .context("Failed to execute git rev-list --count")?;
+    Ok(output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().parse().ok())
+        .flatten())
+}
+
+/// Describes what's known about each gone branch's upstream, or nothing if there are none
+#[must_use]
+pub fn format_upstream_states(states: &[UpstreamState]) -> String {
+    if states.is_empty() {
+        return String::new();
+    }
+
+    let mut summary = String::from("\nUpstreams of the gone branches (--diff-upstream):\n");
+    for state in states {
+        let _ = write!(summary, "  - {} ({}): ", state.branch, state.upstream);
+        let mut known = Vec::new();
+        if let Some(behind) = state.behind {
+            known.push(format!("behind {}", group_digits(behind)));
+        }
+        if let Some(tip) = &state.last_known_tip {
+            known.push(format!("last known tip {tip}"));
+        }
+        if known.is_empty() {
+            summary.push_str("nothing recorded; its remote-tracking ref and reflog are gone\n");
+        } else {
+            let _ = writeln!(summary, "{}", known.join(", "));
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_upstream_states() {
+        let state = |branch: &str, behind, tip: Option<&str>| UpstreamState {
+            branch: branch.to_string(),
+            upstream: format!("origin/{branch}"),
+            behind,
+            last_known_tip: tip.map(ToString::to_string),
+        };
+        assert_eq!(
+            format_upstream_states(&[
+                state("lingering", Some(2), Some("abc1234")),
+                state("reflog", None, Some("def5678")),
+                state("pruned", None, None),
+            ]),
+            "\nUpstreams of the gone branches (--diff-upstream):\n  \
+             - lingering (origin/lingering): behind 2, last known tip abc1234\n  \
+             - reflog (origin/reflog): last known tip def5678\n  \
+             - pruned (origin/pruned): nothing recorded; its remote-tracking ref and reflog \
+             are gone\n"
+        );
+        assert_eq!(format_upstream_states(&[]), "");
+    }
+
+    #[test]
+    fn test_parse_ls_remote_heads() {
+        let output = "\
+abc1234\trefs/heads/main
+def5678\trefs/heads/feature/nested
+ghi9012\trefs/tags/v1.0
+";
+        let heads = parse_ls_remote_heads(output);
+        assert_eq!(heads.len(), 2);
+        assert!(heads.contains("main"));
+        assert!(heads.contains("feature/nested"));
+    }
+
+    #[test]
+    fn test_stale_pr_refs() {
+        let open = parse_ls_remote_pr_numbers(
+            "\
+abc1234\trefs/pull/7/head
+def5678\trefs/pull/7/merge
+ghi9012\trefs/merge-requests/12/head
+jkl3456\trefs/pull/abc/head
+",
+        );
+        assert_eq!(open.len(), 2);
+
+        let local: Vec<String> = [
+            "refs/remotes/origin/pr/7",
+            "refs/remotes/origin/pr/8/head",
+            "refs/remotes/origin/pr/12",
+            "refs/remotes/origin/pr/notes",
+        ]
+        .map(String::from)
+        .to_vec();
+        assert_eq!(
+            stale_pr_refs(&local, "refs/remotes/origin/pr/", &open),
+            ["refs/remotes/origin/pr/8/head"]
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Support for watching a repository for newly gone branches, within one `watch` session or
+//! across runs via a state file.
+
+use crate::report::{BranchReport, Report};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// Remembers which gone branches have already been reported across watch cycles or runs
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct Seen {
+    names: BTreeSet<String>,
+}
+
+impl Seen {
+    /// Loads the branches seen by a previous run; a missing state file means none were
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file exists but can't be read or parsed.
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .with_context(|| format!("Failed to parse state file {}", path.display())),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => {
+                Err(err).with_context(|| format!("Failed to read state file {}", path.display()))
+            }
+        }
+    }
+
+    /// Saves the seen branches for the next run, creating parent directories as needed
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize state")?;
+        fs::write(path, json + "\n")
+            .with_context(|| format!("Failed to write state file {}", path.display()))
+    }
+
+    /// Returns the branches in `report` that weren't gone in the previous cycle, and
+    /// remembers the current set for the next one.
+    ///
+    /// Branches that stop being gone (e.g. deleted, or re-pushed) are forgotten, so they're
+    /// reported again if they later become gone once more.
+    pub fn newly_gone<'a>(&mut self, report: &'a Report) -> Vec<&'a BranchReport> {
+        let new = report
+            .branches
+            .iter()
+            .filter(|branch| !self.names.contains(&branch.name))
+            .collect();
+        self.names = report
+            .branches
+            .iter()
+            .map(|branch| branch.name.clone())
+            .collect();
+        new
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_gone_branches;
+    use crate::report::Action;
+
+    fn report(branch_output: &str) -> Report {
+        Report {
+            dry_run: true,
+            branches: parse_gone_branches(branch_output)
+                .unwrap()
+                .iter()
+                .map(|branch| BranchReport::new(branch, Action::WouldDelete))
+                .collect(),
+            age_histogram: None,
+            remotes_checked: None,
+        }
+    }
+
+    fn names(branches: &[&BranchReport]) -> Vec<String> {
+        branches.iter().map(|branch| branch.name.clone()).collect()
+    }
+
+    #[test]
+    fn test_newly_gone_across_cycles() {
+        let mut seen = Seen::default();
+
+        let first = report("  feature-1    abc1234 [origin/feature-1: gone] One\n");
+        assert_eq!(names(&seen.newly_gone(&first)), ["feature-1"]);
+
+        let second = report(
+            "  feature-1    abc1234 [origin/feature-1: gone] One\n  feature-2    def5678 [origin/feature-2: gone] Two\n",
+        );
+        assert_eq!(names(&seen.newly_gone(&second)), ["feature-2"]);
+
+        // Nothing changed, so nothing is new
+        assert!(seen.newly_gone(&second).is_empty());
+    }
+
+    #[test]
+    fn test_newly_gone_reports_returning_branches_again() {
+        let mut seen = Seen::default();
+        let gone = report("  feature-1    abc1234 [origin/feature-1: gone] One\n");
+
+        assert_eq!(seen.newly_gone(&gone).len(), 1);
+        assert!(seen.newly_gone(&report("")).is_empty());
+        assert_eq!(seen.newly_gone(&gone).len(), 1);
+    }
+
+    #[test]
+    fn test_seen_round_trips_through_state_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("seen.json");
+        assert_eq!(Seen::load(&path).unwrap(), Seen::default());
+
+        let mut seen = Seen::default();
+        seen.newly_gone(&report(
+            "  feature-1    abc1234 [origin/feature-1: gone] One\n",
+        ));
+        seen.save(&path).unwrap();
+
+        let mut loaded = Seen::load(&path).unwrap();
+        assert_eq!(loaded, seen);
+        assert!(
+            loaded
+                .newly_gone(&report(
+                    "  feature-1    abc1234 [origin/feature-1: gone] One\n"
+                ))
+                .is_empty()
+        );
+    }
+}
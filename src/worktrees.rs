@@ -0,0 +1,229 @@
+//! Detection of linked worktrees whose branch is gone ("orphan" worktrees).
+//!
+//! git won't delete a branch checked out in another worktree, so such a branch is kept run after
+//! run until the worktree goes. The worktrees come from `git worktree list --porcelain`; ones
+//! with uncommitted changes, or locked with `git worktree lock`, are never removed.
+
+use crate::git::git_command;
+use crate::parse::BranchLine;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// A linked worktree, per `git worktree list --porcelain`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Worktree {
+    pub path: PathBuf,
+    /// The branch checked out, or `None` for a detached `HEAD`
+    pub branch: Option<String>,
+    /// Locked with `git worktree lock`, so it's meant to stay
+    pub locked: bool,
+    /// Its directory is gone, so `git worktree prune` would drop it anyway
+    pub prunable: bool,
+}
+
+/// Why an orphan worktree can't be removed, if it can't
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Blocker {
+    Locked,
+    /// Has uncommitted changes or untracked files
+    Dirty,
+}
+
+impl Blocker {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Locked => "locked",
+            Self::Dirty => "has uncommitted changes",
+        }
+    }
+}
+
+/// Parses `git worktree list --porcelain`, skipping the main worktree (always listed first)
+#[must_use]
+pub fn parse_worktree_list(porcelain: &str) -> Vec<Worktree> {
+    porcelain
+        .split("\n\n")
+        .filter(|block| !block.trim().is_empty())
+        .skip(1)
+        .filter_map(|block| {
+            let mut lines = block.lines();
+            let path = lines.next()?.strip_prefix("worktree ")?;
+            let mut worktree = Worktree {
+                path: PathBuf::from(path),
+                branch: None,
+                locked: false,
+                prunable: false,
+            };
+            for line in lines {
+                let key = line.split(' ').next().unwrap_or(line);
+                match key {
+                    "branch" => {
+                        worktree.branch = line
+                            .strip_prefix("branch refs/heads/")
+                            .map(ToString::to_string);
+                    }
+                    "locked" => worktree.locked = true,
+                    "prunable" => worktree.prunable = true,
+                    _ => {}
+                }
+            }
+            Some(worktree)
+        })
+        .collect()
+}
+
+/// Lists the repository's linked worktrees
+///
+/// # Errors
+///
+/// Fails with git's error message if `git worktree list` fails.
+pub fn list_worktrees() -> Result<Vec<Worktree>> {
+    let output = git_command()
+        .args(["worktree", "list", "--porcelain"])
+        .output()
+        .context("Failed to execute git worktree list")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git worktree list failed: {}", stderr.trim());
+    }
+    Ok(parse_worktree_list(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Keeps the worktrees whose branch is one of `branches` that's gone
+#[must_use]
+pub fn orphan_worktrees(worktrees: Vec<Worktree>, branches: &[BranchLine]) -> Vec<Worktree> {
+    worktrees
+        .into_iter()
+        .filter(|worktree| {
+            branches.iter().any(|branch| {
+                worktree.branch.as_ref() == Some(&branch.name)
+                    && branch.tracking.as_deref() == Some("gone")
+            })
+        })
+        .collect()
+}
+
+/// Checks whether the worktree at `path` has uncommitted changes or untracked files
+///
+/// # Errors
+///
+/// Fails with git's error message if `git status` fails.
+pub fn is_dirty(path: &Path) -> Result<bool> {
+    let output = git_command()
+        .arg("-C")
+        .arg(path)
+        .args(["status", "--porcelain"])
+        .output()
+        .context("Failed to execute git status")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git status failed in {}: {}", path.display(), stderr.trim());
+    }
+    Ok(!output.stdout.is_empty())
+}
+
+/// What, if anything, keeps `worktree` from being removed
+///
+/// # Errors
+///
+/// Fails if its status can't be checked.
+pub fn blocker(worktree: &Worktree) -> Result<Option<Blocker>> {
+    if worktree.locked {
+        Ok(Some(Blocker::Locked))
+    } else if !worktree.prunable && is_dirty(&worktree.path)? {
+        Ok(Some(Blocker::Dirty))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Removes the worktree at `path` with `git worktree remove`, which itself refuses a worktree
+/// with changes
+///
+/// # Errors
+///
+/// Fails with git's error message if the worktree can't be removed.
+pub fn remove_worktree(path: &Path) -> Result<()> {
+    let output = git_command()
+        .args(["worktree", "remove"])
+        .arg(path)
+        .output()
+        .context("Failed to execute git worktree remove")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!(
+            "git worktree remove {} failed: {}",
+            path.display(),
+            stderr.trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_branch_lines;
+
+    #[test]
+    fn test_orphan_worktrees_from_listing() {
+        let worktrees = parse_worktree_list(
+            "\
+worktree /repo
+HEAD aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa
+branch refs/heads/main
+
+worktree /wt/gone
+HEAD bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb
+branch refs/heads/feature-1
+
+worktree /wt/locked
+HEAD cccccccccccccccccccccccccccccccccccccccc
+branch refs/heads/feature-2
+locked keep for now
+
+worktree /wt/missing
+HEAD dddddddddddddddddddddddddddddddddddddddd
+branch refs/heads/feature-3
+prunable gitdir file points to non-existent location
+
+worktree /wt/live
+HEAD eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee
+branch refs/heads/live
+
+worktree /wt/detached
+HEAD ffffffffffffffffffffffffffffffffffffffff
+detached
+
+",
+        );
+        assert_eq!(worktrees.len(), 5);
+        assert!(worktrees[1].locked);
+        assert!(worktrees[2].prunable);
+        assert_eq!(worktrees[4].branch, None);
+
+        let branches = parse_branch_lines(
+            r"
+* main         aaaaaaa [origin/main] Init
++ feature-1    bbbbbbb (/wt/gone) [origin/feature-1: gone] One
++ feature-2    ccccccc (/wt/locked) [origin/feature-2: gone] Two
++ feature-3    ddddddd (/wt/missing) [origin/feature-3: gone] Three
++ live         eeeeeee (/wt/live) [origin/live] Live
+",
+        );
+        let orphans: Vec<PathBuf> = orphan_worktrees(worktrees, &branches)
+            .into_iter()
+            .map(|worktree| worktree.path)
+            .collect();
+        assert_eq!(
+            orphans,
+            ["/wt/gone", "/wt/locked", "/wt/missing"].map(PathBuf::from)
+        );
+    }
+}
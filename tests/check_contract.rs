@@ -0,0 +1,52 @@
+//! Checks the scripting contract of dry runs: `--dry-run --format json` prints the plan and
+//! exits 0 whatever it holds, while `--check` prints the same plan and exits 4 only when it
+//! would delete something, without deleting anything.
+
+mod common;
+
+use common::{actions, clone_with_gone, git, run_json};
+
+#[test]
+fn test_dry_run_and_check_exit_statuses() {
+    let root = tempfile::tempdir().unwrap();
+    let work = clone_with_gone(root.path(), &[]);
+
+    // Nothing gone: an empty plan either way
+    for args in [&["--dry-run"], &["--check"]] {
+        let (code, report) = run_json(&work, args);
+        assert_eq!(code, Some(0), "{args:?}");
+        assert!(actions(&report).is_empty(), "{args:?}");
+    }
+
+    git(&work, &["checkout", "-q", "-b", "merged", "main"]);
+    git(&work, &["push", "-q", "-u", "origin", "merged"]);
+    git(&work, &["checkout", "-q", "main"]);
+    git(&work, &["push", "-q", "origin", "--delete", "merged"]);
+
+    let plan = [("merged".to_string(), "would-delete".to_string())];
+    // --check is a dry run, whatever else is given
+    for (args, expected) in [
+        (&["--dry-run"][..], Some(0)),
+        (&["--check"], Some(4)),
+        (&["--check", "--yes"], Some(4)),
+    ] {
+        let (code, report) = run_json(&work, args);
+        assert_eq!(code, expected, "{args:?}");
+        assert_eq!(actions(&report), plan, "{args:?}");
+    }
+
+    // A plan with only kept branches is empty
+    let (code, report) = run_json(&work, &["--check", "--protect", "merged"]);
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        actions(&report),
+        [("merged".to_string(), "skipped".to_string())]
+    );
+
+    let (code, report) = run_json(&work, &["--yes"]);
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        actions(&report),
+        [("merged".to_string(), "deleted".to_string())]
+    );
+}
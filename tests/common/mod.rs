@@ -0,0 +1,145 @@
+//! Fixture repositories and helpers the integration tests share.
+
+// Each test crate compiles this module on its own and uses only some of it
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// Keeps the user's environment out of a command run in `dir`: `HOME` and `XDG_CONFIG_HOME` are
+/// the test's tempdir, git reads neither a global nor a system config, and no
+/// `GIT_CLEAN_GONE_*` variable stands in for a flag
+fn isolate<'a>(command: &'a mut Command, dir: &Path) -> &'a mut Command {
+    let temp = std::env::temp_dir();
+    let home = dir
+        .ancestors()
+        .find(|ancestor| ancestor.parent() == Some(&temp))
+        .unwrap_or(dir);
+    for (name, _) in std::env::vars_os() {
+        if name.to_string_lossy().starts_with("GIT_CLEAN_GONE_") {
+            command.env_remove(name);
+        }
+    }
+    command
+        .current_dir(dir)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home)
+        .env("GIT_CONFIG_GLOBAL", "/dev/null")
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env_remove("GIT_DIR")
+}
+
+/// Runs git in `dir`, asserting it succeeds, and returns its trimmed stdout
+pub fn git(dir: &Path, args: &[&str]) -> String {
+    let output = isolate(&mut Command::new("git"), dir)
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .stderr(Stdio::inherit())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {args:?} failed");
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+/// The local branches of the repository in `dir`, by name
+pub fn local_branches(dir: &Path) -> Vec<String> {
+    git(
+        dir,
+        &["for-each-ref", "--format=%(refname:short)", "refs/heads"],
+    )
+    .lines()
+    .map(ToString::to_string)
+    .collect()
+}
+
+/// Runs the tool in `dir` with `args`, keeping no history
+pub fn run(dir: &Path, args: &[&str]) -> Output {
+    run_with_env(dir, &[], args)
+}
+
+/// Like [`run`], with the environment variables `vars` set
+pub fn run_with_env(dir: &Path, vars: &[(&str, &str)], args: &[&str]) -> Output {
+    isolate(&mut Command::new(env!("CARGO_BIN_EXE_git-clean-gone")), dir)
+        .envs(vars.iter().copied())
+        .arg("--no-history")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Runs the tool with `args`, asserting it succeeds
+pub fn run_ok(dir: &Path, args: &[&str]) -> Output {
+    let output = run(dir, args);
+    assert!(
+        output.status.success(),
+        "{args:?}: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+/// Runs the tool with a JSON report and `args`, returning its exit code and the report
+pub fn run_json(dir: &Path, args: &[&str]) -> (Option<i32>, serde_json::Value) {
+    let output = run(dir, &[&["--format", "json"], args].concat());
+    let report = serde_json::from_slice(&output.stdout).unwrap_or_else(|err| {
+        panic!(
+            "{args:?} didn't print a JSON report ({err}): {}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+    });
+    (output.status.code(), report)
+}
+
+/// The branches of a JSON report as `(name, action)`
+pub fn actions(report: &serde_json::Value) -> Vec<(String, String)> {
+    report["branches"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|branch| {
+            (
+                branch["name"].as_str().unwrap().to_string(),
+                branch["action"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect()
+}
+
+/// Sets up a clone of a remote in `root` whose `gone` branches were deleted on the remote,
+/// with `main` checked out
+pub fn clone_with_gone(root: &Path, gone: &[&str]) -> PathBuf {
+    let remote = root.join("remote.git");
+    let work = root.join("work");
+    git(
+        root,
+        &[
+            "init",
+            "-q",
+            "--bare",
+            "-b",
+            "main",
+            remote.to_str().unwrap(),
+        ],
+    );
+    git(
+        root,
+        &[
+            "clone",
+            "-q",
+            remote.to_str().unwrap(),
+            work.to_str().unwrap(),
+        ],
+    );
+    git(&work, &["checkout", "-q", "-b", "main"]);
+    git(&work, &["commit", "-q", "--allow-empty", "-m", "init"]);
+    git(&work, &["push", "-q", "-u", "origin", "main"]);
+    for branch in gone {
+        git(&work, &["checkout", "-q", "-b", branch, "main"]);
+        git(&work, &["push", "-q", "-u", "origin", branch]);
+    }
+    git(&work, &["checkout", "-q", "main"]);
+    for branch in gone {
+        git(&work, &["push", "-q", "origin", "--delete", branch]);
+    }
+    work
+}
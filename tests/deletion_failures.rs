@@ -0,0 +1,54 @@
+//! Checks that a run whose deletions partly fail still deletes the rest, and reports each
+//! failure with its cause and git's stderr.
+
+mod common;
+
+use common::{clone_with_gone, git, run_json};
+use std::fs;
+
+#[test]
+fn test_mixed_success_and_failure() {
+    let root = tempfile::tempdir().unwrap();
+    let work = clone_with_gone(root.path(), &["deletable", "locked"]);
+    git(&work, &["checkout", "-q", "-b", "unmerged", "main"]);
+    git(
+        &work,
+        &["commit", "-q", "--allow-empty", "-m", "unmerged work"],
+    );
+    git(&work, &["push", "-q", "-u", "origin", "unmerged"]);
+    git(&work, &["push", "-q", "origin", "--delete", "unmerged"]);
+    git(&work, &["checkout", "-q", "main"]);
+    // As if another git process were updating the branch
+    fs::write(work.join(".git/refs/heads/locked.lock"), "").unwrap();
+    // `git branch -d` refuses a branch that isn't merged
+    fs::write(work.join(".git-clean-gone-rules"), "unmerged safe-delete\n").unwrap();
+
+    let (code, report) = run_json(&work, &["--yes"]);
+    assert_eq!(code, Some(1));
+    let outcomes: Vec<(&str, &str, &str)> = report["branches"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|branch| {
+            (
+                branch["name"].as_str().unwrap(),
+                branch["action"].as_str().unwrap(),
+                branch["cause"].as_str().unwrap_or("-"),
+            )
+        })
+        .collect();
+    assert_eq!(
+        outcomes,
+        [
+            ("deletable", "deleted", "-"),
+            ("locked", "failed", "lock-held"),
+            ("unmerged", "failed", "not-merged"),
+        ]
+    );
+    for failed in &report["branches"].as_array().unwrap()[1..] {
+        assert!(
+            failed["stderr"].as_str().unwrap().starts_with("error: "),
+            "{failed}"
+        );
+    }
+}
@@ -0,0 +1,55 @@
+//! Checks that a dry run reports exactly the branches a real run then deletes.
+
+mod common;
+
+use common::{actions, clone_with_gone, git, run_json};
+use std::collections::BTreeSet;
+
+#[test]
+fn test_dry_run_targets_what_a_real_run_deletes() {
+    let root = tempfile::tempdir().unwrap();
+    let work = clone_with_gone(root.path(), &["merged"]);
+
+    // The rest have commits of their own
+    let gone = ["unmerged", "protected-release", "in-worktree"];
+    for branch in gone.iter().chain(&["live"]) {
+        git(&work, &["checkout", "-q", "-b", branch, "main"]);
+        git(&work, &["commit", "-q", "--allow-empty", "-m", branch]);
+        git(&work, &["push", "-q", "-u", "origin", branch]);
+    }
+    git(&work, &["checkout", "-q", "main"]);
+    for branch in gone {
+        git(&work, &["push", "-q", "origin", "--delete", branch]);
+    }
+    let worktree = root.path().join("worktree");
+    git(
+        &work,
+        &[
+            "worktree",
+            "add",
+            "-q",
+            worktree.to_str().unwrap(),
+            "in-worktree",
+        ],
+    );
+
+    // The names of the branches a run with `args` reported with `action`
+    let names = |args: &[&str], action: &str| -> BTreeSet<String> {
+        let args = [&["--yes", "--protect", "protected-*"], args].concat();
+        let (code, report) = run_json(&work, &args);
+        assert_eq!(code, Some(0), "{args:?}");
+        actions(&report)
+            .into_iter()
+            .filter(|(_, reported)| reported == action)
+            .map(|(name, _)| name)
+            .collect()
+    };
+    let would_delete = names(&["--dry-run"], "would-delete");
+    let deleted = names(&[], "deleted");
+
+    assert_eq!(would_delete, deleted);
+    assert_eq!(
+        deleted,
+        BTreeSet::from(["merged".to_string(), "unmerged".to_string()])
+    );
+}
@@ -0,0 +1,58 @@
+//! Checks that the `GIT_CLEAN_GONE_*` environment variables stand in for their flags, and that
+//! a flag given on the command line wins over its variable.
+
+mod common;
+
+use common::{actions, clone_with_gone, local_branches, run_with_env};
+
+#[test]
+fn test_variables_set_flags_and_flags_override_them() {
+    let root = tempfile::tempdir().unwrap();
+    let work = clone_with_gone(root.path(), &["a", "b", "c"]);
+    let vars = [
+        ("GIT_CLEAN_GONE_DRY_RUN", "1"),
+        ("GIT_CLEAN_GONE_PROTECT", "a,b"),
+        // Overridden by the --format json of every run
+        ("GIT_CLEAN_GONE_FORMAT", "markdown"),
+    ];
+    // The reported `(name, action)` of a JSON run with `vars` set and `args`
+    let run = |vars: &[(&str, &str)], args: &[&str]| {
+        let output = run_with_env(&work, vars, &[&["--format", "json"], args].concat());
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        actions(&serde_json::from_slice(&output.stdout).unwrap())
+    };
+    let action = |name: &str, action: &str| (name.to_string(), action.to_string());
+
+    // Kept branches are reported after the rest
+    assert_eq!(
+        run(&vars, &[]),
+        [
+            action("c", "would-delete"),
+            action("a", "skipped"),
+            action("b", "skipped"),
+        ]
+    );
+    assert_eq!(
+        run(&vars, &["--protect", "c"]),
+        [
+            action("a", "would-delete"),
+            action("b", "would-delete"),
+            action("c", "skipped"),
+        ]
+    );
+    assert_eq!(local_branches(&work), ["a", "b", "c", "main"]);
+
+    assert_eq!(
+        run(&vars[1..], &["--yes"]),
+        [
+            action("c", "deleted"),
+            action("a", "skipped"),
+            action("b", "skipped"),
+        ]
+    );
+    assert_eq!(local_branches(&work), ["a", "b", "main"]);
+}
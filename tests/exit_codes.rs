@@ -0,0 +1,42 @@
+//! Checks the documented exit codes that tell a script why nothing was deleted.
+
+mod common;
+
+use common::{clone_with_gone, git, local_branches, run};
+
+#[test]
+fn test_fail_on_unmerged_exits_3_only_with_unmerged_branches() {
+    let root = tempfile::tempdir().unwrap();
+    let work = clone_with_gone(root.path(), &["merged"]);
+    git(&work, &["checkout", "-q", "-b", "unmerged", "main"]);
+    git(&work, &["commit", "-q", "--allow-empty", "-m", "unmerged"]);
+    git(&work, &["push", "-q", "-u", "origin", "unmerged"]);
+    git(&work, &["checkout", "-q", "main"]);
+    git(&work, &["push", "-q", "origin", "--delete", "unmerged"]);
+
+    let output = run(&work, &["--fail-on-unmerged", "--yes"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unmerged commits"));
+    assert_eq!(local_branches(&work), ["main", "merged", "unmerged"]);
+
+    git(&work, &["branch", "-q", "-D", "unmerged"]);
+    let output = run(&work, &["--fail-on-unmerged", "--yes"]);
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(local_branches(&work), ["main"]);
+}
+
+#[test]
+fn test_max_runtime_exits_124_without_deleting() {
+    let root = tempfile::tempdir().unwrap();
+    let work = clone_with_gone(root.path(), &["feature-1", "feature-2"]);
+
+    let output = run(&work, &["--max-runtime", "0s", "--yes"]);
+    assert_eq!(output.status.code(), Some(124));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("exceeded --max-runtime"));
+    assert_eq!(local_branches(&work), ["feature-1", "feature-2", "main"]);
+}
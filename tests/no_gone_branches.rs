@@ -0,0 +1,45 @@
+//! Checks that every machine format emits a complete document when no branch is gone, so
+//! parsers downstream never get empty input.
+
+mod common;
+
+use common::{clone_with_gone, run_ok};
+
+#[test]
+fn test_machine_formats_are_complete_without_gone_branches() {
+    let root = tempfile::tempdir().unwrap();
+    let work = clone_with_gone(root.path(), &[]);
+
+    for args in [
+        &["--dry-run", "--format", "json"][..],
+        &["--yes", "--format", "json"],
+        &["--dry-run", "--format", "json", "--json-compact"],
+    ] {
+        let report: serde_json::Value =
+            serde_json::from_slice(&run_ok(&work, args).stdout).unwrap();
+        assert_eq!(report["dry_run"], args[0] == "--dry-run", "{args:?}");
+        assert_eq!(report["branches"], serde_json::json!([]), "{args:?}");
+    }
+
+    let sarif: serde_json::Value =
+        serde_json::from_slice(&run_ok(&work, &["--dry-run", "--format", "sarif"]).stdout).unwrap();
+    assert_eq!(sarif["version"], "2.1.0");
+    assert_eq!(sarif["runs"][0]["results"], serde_json::json!([]));
+
+    let xml = run_ok(&work, &["--dry-run", "--format", "xml"]).stdout;
+    assert_eq!(
+        String::from_utf8_lossy(&xml),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <git-clean-gone dry-run=\"true\" count=\"0\">\n\
+         </git-clean-gone>\n"
+    );
+
+    assert_eq!(
+        String::from_utf8_lossy(&run_ok(&work, &["--dry-run", "--format", "count"]).stdout),
+        "0\n"
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&run_ok(&work, &["--dry-run", "--format", "markdown"]).stdout),
+        "No gone branches found.\n"
+    );
+}
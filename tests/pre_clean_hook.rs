@@ -0,0 +1,39 @@
+//! Checks that a `pre-clean-gone` hook's veto holds for the frontends that delete without
+//! prompting, not just the interactive cleanup.
+#![cfg(unix)]
+
+mod common;
+
+use common::{clone_with_gone, git, local_branches, run};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+/// Installs `script` as the `pre-clean-gone` hook of the repository in `work`
+fn write_hook(work: &Path, script: &str) {
+    let path = work.join(".git/hooks/pre-clean-gone");
+    fs::write(&path, script).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[test]
+fn test_post_merge_honors_the_veto() {
+    let root = tempfile::tempdir().unwrap();
+    let work = clone_with_gone(root.path(), &["feature-1", "feature-2"]);
+    git(&work, &["config", "cleanGone.postMerge", "delete"]);
+
+    write_hook(&work, "#!/bin/sh\ncat >/dev/null\nexit 1\n");
+    let output = run(&work, &["--hook", "post-merge"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("hook rejected the cleanup"));
+    assert_eq!(local_branches(&work), ["feature-1", "feature-2", "main"]);
+
+    write_hook(&work, "#!/bin/sh\ncat >/dev/null\n");
+    let output = run(&work, &["--hook", "post-merge"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(local_branches(&work), ["main"]);
+}
@@ -0,0 +1,43 @@
+//! Checks that `--removed-remote` lists the same leftovers in a dry run as in the real run,
+//! whose deletions take the deleted branches' config with them.
+
+mod common;
+
+use common::{git, run_ok};
+
+/// The "Left behind by" list in the output of a run
+fn left_behind(stdout: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .skip_while(|line| !line.starts_with("Left behind by "))
+        .skip(1)
+        .take_while(|line| line.starts_with("  - "))
+        .map(ToString::to_string)
+        .collect()
+}
+
+#[test]
+fn test_dry_run_leaves_out_the_branches_it_would_delete() {
+    let dir = tempfile::tempdir().unwrap();
+    let work = dir.path();
+    git(work, &["init", "-q", "-b", "main"]);
+    git(work, &["commit", "-q", "--allow-empty", "-m", "init"]);
+    // Both tracked a remote that's been removed; `checked-out` can't be deleted
+    for branch in ["gone", "checked-out"] {
+        git(work, &["branch", branch]);
+        git(work, &["config", &format!("branch.{branch}.remote"), "old"]);
+        let merge = format!("refs/heads/{branch}");
+        git(work, &["config", &format!("branch.{branch}.merge"), &merge]);
+    }
+    git(work, &["update-ref", "refs/remotes/old/stale", "main"]);
+    git(work, &["checkout", "-q", "checked-out"]);
+
+    let expected = [
+        "  - refs/remotes/old/stale",
+        "  - branch.checked-out.remote = old",
+    ];
+    let dry_run = run_ok(work, &["--removed-remote", "old", "--dry-run"]);
+    assert_eq!(left_behind(&dry_run.stdout), expected);
+    let real = run_ok(work, &["--removed-remote", "old", "--yes"]);
+    assert_eq!(left_behind(&real.stdout), expected);
+}
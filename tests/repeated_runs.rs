@@ -0,0 +1,67 @@
+//! Checks that running the cleanup again right after it succeeded is a clean no-op, so
+//! automation can retry it: the second run finds nothing to do instead of tripping over the
+//! branches the first one deleted.
+
+mod common;
+
+use common::{actions, clone_with_gone, run_json, run_ok};
+
+#[test]
+fn test_second_run_is_a_no_op() {
+    let root = tempfile::tempdir().unwrap();
+    let work = clone_with_gone(root.path(), &["feature-1", "feature-2"]);
+
+    let (code, first) = run_json(&work, &["--no-tips", "--yes"]);
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        actions(&first),
+        [
+            ("feature-1".to_string(), "deleted".to_string()),
+            ("feature-2".to_string(), "deleted".to_string()),
+        ]
+    );
+    let (code, second) = run_json(&work, &["--no-tips", "--yes"]);
+    assert_eq!(code, Some(0));
+    assert!(actions(&second).is_empty());
+
+    let output = run_ok(&work, &["--no-tips", "--yes"]);
+    assert!(
+        String::from_utf8_lossy(&output.stdout).contains("No gone branches found."),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
+
+#[test]
+fn test_retried_plan_and_plan_hash_are_no_ops() {
+    let root = tempfile::tempdir().unwrap();
+    let work = clone_with_gone(root.path(), &["feature-1"]);
+    let plan = root.path().join("plan.json");
+    let plan = plan.to_str().unwrap();
+
+    let dry_run = run_ok(&work, &["--no-tips", "plan", plan]);
+    let hash = String::from_utf8_lossy(&dry_run.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("Plan hash: ").map(ToString::to_string))
+        .unwrap();
+
+    let (code, applied) = run_json(&work, &["--no-tips", "--yes", "apply", plan]);
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        actions(&applied),
+        [("feature-1".to_string(), "deleted".to_string())]
+    );
+
+    // The branch the plan deleted is gone now, which is no reason to fail
+    let retry = run_ok(
+        &work,
+        &["--no-tips", "--yes", "--format", "json", "apply", plan],
+    );
+    assert!(String::from_utf8_lossy(&retry.stderr).contains("no longer exist"));
+    let report: serde_json::Value = serde_json::from_slice(&retry.stdout).unwrap();
+    assert_eq!(report["branches"], serde_json::json!([]));
+
+    let (code, report) = run_json(&work, &["--no-tips", "--yes", "--expect-plan-hash", &hash]);
+    assert_eq!(code, Some(0));
+    assert!(actions(&report).is_empty());
+}
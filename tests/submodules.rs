@@ -0,0 +1,49 @@
+//! Checks that `--recurse-submodules` cleans up each submodule as well as the superproject, and
+//! ends with a line per repository.
+
+mod common;
+
+use common::{clone_with_gone, git, local_branches, run_ok};
+
+#[test]
+fn test_submodules_are_cleaned_with_a_breakdown() {
+    let root = tempfile::tempdir().unwrap();
+    let work = clone_with_gone(root.path(), &["super-gone"]);
+    let lib_root = root.path().join("lib");
+    std::fs::create_dir(&lib_root).unwrap();
+    clone_with_gone(&lib_root, &[]);
+    let lib_remote = lib_root.join("remote.git");
+    git(
+        &work,
+        &[
+            "-c",
+            "protocol.file.allow=always",
+            "submodule",
+            "add",
+            "-q",
+            lib_remote.to_str().unwrap(),
+            "lib",
+        ],
+    );
+    git(&work, &["commit", "-q", "-m", "add lib"]);
+    let lib = work.join("lib");
+    git(&lib, &["checkout", "-q", "-b", "lib-gone"]);
+    git(&lib, &["push", "-q", "-u", "origin", "lib-gone"]);
+    git(&lib, &["checkout", "-q", "main"]);
+    git(&lib, &["push", "-q", "origin", "--delete", "lib-gone"]);
+
+    let output = run_ok(&work, &["--recurse-submodules", "--yes"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\nSubmodule lib:\n"), "{stdout}");
+    let breakdown = [
+        "Per repository:",
+        "  .    Deleted 1, kept 0",
+        "  lib  Deleted 1, kept 0",
+    ];
+    assert!(
+        stdout.ends_with(&format!("\n{}\n", breakdown.join("\n"))),
+        "{stdout}"
+    );
+    assert_eq!(local_branches(&work), ["main"]);
+    assert_eq!(local_branches(&lib), ["main"]);
+}
@@ -0,0 +1,26 @@
+//! Checks that a freshly initialized repository, whose branch has no commits yet, ends the run
+//! with a clear message instead of going through the whole cleanup.
+
+mod common;
+
+use common::{git, run};
+
+#[test]
+fn test_unborn_branch() {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "-q", "-b", "trunk"]);
+
+    let output = run(dir.path(), &[]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "No commits yet (HEAD is the unborn branch trunk), so there are no branches to clean up.\n"
+    );
+
+    // Machine formats still get a report
+    let output = run(dir.path(), &["--format", "json"]);
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["branches"], serde_json::json!([]));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("note: No commits yet"));
+}